@@ -12,6 +12,16 @@ impl Vec2u {
     }
 }
 
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Vec2i {
+    pub x: i64,
+    pub y: i64,
+}
+
+impl Vec2i {
+    pub const ZERO: Self = Self { x: 0, y: 0 };
+}
+
 #[derive(Default, Debug, Clone, Copy, PartialEq)]
 pub struct Vec2f {
     pub x: f64,
@@ -33,6 +43,73 @@ impl Bounds2f {
         pos: Vec2f::ZERO,
         size: Vec2f::ZERO,
     };
+
+    pub fn contains(&self, point: Vec2f) -> bool {
+        point.x >= self.pos.x
+            && point.y >= self.pos.y
+            && point.x < self.pos.x + self.size.x
+            && point.y < self.pos.y + self.size.y
+    }
+
+    /// Splits the bounds into a left and right half, with a column in
+    /// between reserved for a separator. Returns the left half, the x
+    /// position of the separator, and the right half.
+    pub fn split_vertically(&self) -> (Self, f64, Self) {
+        let available_width = (self.size.x - 1.0).max(0.0);
+        let left_width = (available_width / 2.0).floor();
+        let separator_x = self.pos.x + left_width;
+
+        (
+            Self {
+                pos: self.pos,
+                size: Vec2f {
+                    x: left_width,
+                    y: self.size.y,
+                },
+            },
+            separator_x,
+            Self {
+                pos: Vec2f {
+                    x: separator_x + 1.0,
+                    y: self.pos.y,
+                },
+                size: Vec2f {
+                    x: available_width - left_width,
+                    y: self.size.y,
+                },
+            },
+        )
+    }
+
+    /// Splits the bounds into a top and bottom half, with a row in
+    /// between reserved for a separator. Returns the top half, the y
+    /// position of the separator, and the bottom half.
+    pub fn split_horizontally(&self) -> (Self, f64, Self) {
+        let available_height = (self.size.y - 1.0).max(0.0);
+        let top_height = (available_height / 2.0).floor();
+        let separator_y = self.pos.y + top_height;
+
+        (
+            Self {
+                pos: self.pos,
+                size: Vec2f {
+                    x: self.size.x,
+                    y: top_height,
+                },
+            },
+            separator_y,
+            Self {
+                pos: Vec2f {
+                    x: self.pos.x,
+                    y: separator_y + 1.0,
+                },
+                size: Vec2f {
+                    x: self.size.x,
+                    y: available_height - top_height,
+                },
+            },
+        )
+    }
 }
 
 pub trait ToU64 {
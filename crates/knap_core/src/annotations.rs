@@ -0,0 +1,156 @@
+use std::ops::Range;
+
+/// How serious the problem that an annotation points out is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+/// A message attached to a line, e.g. a diagnostic from a linter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    pub message: String,
+    pub severity: Severity,
+}
+
+/// The annotations of a buffer, each attached to a line.
+#[derive(Debug, Default)]
+pub(crate) struct Annotations {
+    /// sorted by line, and then by severity (most severe first)
+    lines: Vec<(usize, Annotation)>,
+}
+
+impl Annotations {
+    pub(crate) fn new(mut lines: Vec<(usize, Annotation)>) -> Self {
+        lines.sort_by_key(|(line_idx, annotation)| (*line_idx, annotation.severity));
+        Self { lines }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Returns the annotations of the lines in `line_range`, grouped by line.
+    pub(crate) fn get_lines(&self, line_range: Range<usize>) -> Vec<(usize, Vec<&Annotation>)> {
+        let mut lines: Vec<(usize, Vec<&Annotation>)> = vec![];
+        for (line_idx, annotation) in &self.lines {
+            if !line_range.contains(line_idx) {
+                continue;
+            }
+            match lines.last_mut() {
+                Some((last_line_idx, annotations)) if last_line_idx == line_idx => {
+                    annotations.push(annotation);
+                }
+                _ => lines.push((*line_idx, vec![annotation])),
+            }
+        }
+        lines
+    }
+
+    /// `new_lines` lines were inserted into `line_idx`, shifting
+    /// the annotations after it down by as many.
+    pub(crate) fn split_lines(&mut self, line_idx: usize, new_lines: usize) {
+        for (annotated_line_idx, _) in self
+            .lines
+            .iter_mut()
+            .filter(|(annotated_line_idx, _)| *annotated_line_idx > line_idx)
+        {
+            *annotated_line_idx = annotated_line_idx.saturating_add(new_lines);
+        }
+    }
+
+    /// The `removed_lines` lines after `line_idx` were joined onto it,
+    /// shifting the annotations after those up by as many. The
+    /// annotations of the removed lines move to `line_idx`.
+    pub(crate) fn join_lines(&mut self, line_idx: usize, removed_lines: usize) {
+        for (annotated_line_idx, _) in self
+            .lines
+            .iter_mut()
+            .filter(|(annotated_line_idx, _)| *annotated_line_idx > line_idx)
+        {
+            *annotated_line_idx = annotated_line_idx
+                .saturating_sub(removed_lines)
+                .max(line_idx);
+        }
+        self.lines
+            .sort_by_key(|(line_idx, annotation)| (*line_idx, annotation.severity));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn annotation(message: &str, severity: Severity) -> Annotation {
+        Annotation {
+            message: message.to_string(),
+            severity,
+        }
+    }
+
+    fn get_messages(annotations: &Annotations) -> Vec<(usize, Vec<&str>)> {
+        annotations
+            .get_lines(0..usize::MAX)
+            .into_iter()
+            .map(|(line_idx, annotations)| {
+                (
+                    line_idx,
+                    annotations
+                        .into_iter()
+                        .map(|annotation| annotation.message.as_str())
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_shift_lines() {
+        let mut annotations = Annotations::new(vec![
+            (5, annotation("unused", Severity::Warning)),
+            (1, annotation("first", Severity::Info)),
+            (5, annotation("mismatched types", Severity::Error)),
+            (8, annotation("last", Severity::Info)),
+        ]);
+        assert_eq!(
+            get_messages(&annotations),
+            vec![
+                (1, vec!["first"]),
+                (5, vec!["mismatched types", "unused"]),
+                (8, vec!["last"]),
+            ]
+        );
+        let visible_lines = annotations.get_lines(2..6);
+        assert_eq!(
+            visible_lines
+                .iter()
+                .map(|(line_idx, annotations)| (*line_idx, annotations.len()))
+                .collect::<Vec<_>>(),
+            vec![(5, 2)]
+        );
+
+        // two lines added after line 3
+        annotations.split_lines(3, 2);
+        assert_eq!(
+            get_messages(&annotations),
+            vec![
+                (1, vec!["first"]),
+                (7, vec!["mismatched types", "unused"]),
+                (10, vec!["last"]),
+            ]
+        );
+
+        // lines 5 to 8 removed, after line 4
+        annotations.join_lines(4, 4);
+        assert_eq!(
+            get_messages(&annotations),
+            vec![
+                (1, vec!["first"]),
+                (4, vec!["mismatched types", "unused"]),
+                (6, vec!["last"]),
+            ]
+        );
+    }
+}
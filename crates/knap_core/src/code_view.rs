@@ -1,27 +1,106 @@
-use std::{fs::File, io::Write};
+use std::{
+    collections::HashMap,
+    fs::File,
+    hash::{DefaultHasher, Hash, Hasher},
+    io::{ErrorKind, Read, Write},
+    ops::Range,
+    time::{Duration, Instant, SystemTime},
+};
 
 use anyhow::Result;
-use knap_base::math::Bounds2f;
+use knap_base::math::{Bounds2f, Lossy, ToUsize, Vec2f, Vec2i, Vec2u};
 use knap_ui::{
-    text_box::TextBox,
-    text_buffer::{RopeTextBuffer, SearchDirection},
+    text_box::{
+        Edit, EditError, IndentStyle, LineShift, SelectionGranularity, SelectionMode, TextBox,
+        TextRenderOptions,
+    },
+    text_buffer::{FindResult, RopeTextBuffer, SearchDirection, SearchOptions, TextBufferPos},
 };
 use knap_window::drawer::Drawer;
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
 
 use crate::{
-    command_bar::{CommandBar, CommandBarPrompt},
-    commands::EditorCommand,
-    highlighter::HighlightInfo,
-    message_bar::MessageBar,
-    status_bar::ViewStatus,
+    annotations::{Annotation, Annotations},
+    command_bar::{CommandBar, CommandBarPrompt, get_search_matches_message},
+    commands::{CommandOutcome, EditorCommand},
+    completion::{self, Completion},
+    date_time::{self, DEFAULT_DATE_TIME_FORMAT},
+    git,
+    highlighter::{HighlightInfo, HighlightOptions, SearchPattern, WordUnderCaret},
+    jump_list::JumpList,
+    process,
+    session::FileSession,
+    status_bar::{NO_NAME, ViewStatus},
+    theme::Theme,
+};
+#[cfg(feature = "notify")]
+use {
+    crate::{editor::ExternalCommand, file_watcher::FileWatcher},
+    std::sync::mpsc::Sender,
 };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub(crate) enum FileType {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileType {
     Rust,
     PlainText,
 }
 
+/// How long the word under the caret has to stay the same before
+/// its other occurrences are highlighted, so that moving the caret
+/// quickly does not regenerate the highlights on every step.
+const WORD_UNDER_CARET_DELAY: Duration = Duration::from_millis(200);
+
+/// Shown in the gutter next to the lines with annotations.
+const ANNOTATION_MARKER: &str = "●";
+/// Shown in the gutter next to the lines changed since the last save.
+const DIRTY_LINE_MARKER: &str = "▎";
+/// The width of the gutter, which is only shown if there are
+/// annotations or lines changed since the last save.
+const GUTTER_WIDTH: f64 = 2.0;
+
+pub(crate) fn is_identifier(word: &str) -> bool {
+    word.chars()
+        .next()
+        .is_some_and(|ch| ch.is_alphabetic() || ch == '_')
+        && word.chars().all(|ch| ch.is_alphanumeric() || ch == '_')
+}
+
+impl FileType {
+    /// The prefix of a line comment, or `None` if lines cannot be commented out.
+    fn line_comment_prefix(self) -> Option<&'static str> {
+        match self {
+            FileType::Rust => Some("//"),
+            FileType::PlainText => None,
+        }
+    }
+}
+
+/// Split the newline at the end of `contents` (if any) from the rest, as
+/// it is not kept in the buffer, where it would show up as an empty line.
+/// Empty contents count as ending with a newline, as there is no line
+/// without one.
+fn split_trailing_newline(contents: &str) -> (&str, bool) {
+    match contents.strip_suffix('\n') {
+        Some(contents) => (contents.strip_suffix('\r').unwrap_or(contents), true),
+        None => (contents, contents.is_empty()),
+    }
+}
+
+fn hash_file_contents(file_contents: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    file_contents.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The text box of a code view, which unlike the command bar's pairs
+/// brackets and quotes as they are typed.
+fn new_text_box() -> TextBox<RopeTextBuffer> {
+    let mut text_box = TextBox::new(RopeTextBuffer::new());
+    text_box.set_auto_pair(true);
+    text_box
+}
+
 fn deduce_filetype<T: AsRef<str>>(filename: T) -> FileType {
     if filename.as_ref().to_lowercase().ends_with(".rs") {
         FileType::Rust
@@ -35,10 +114,51 @@ pub(crate) struct CodeView {
 
     filename: Option<String>,
     file_type: FileType,
+    /// the git branch of the repository that the file is in
+    git_branch: Option<String>,
+    /// detected from the contents, and used when indenting
+    indent_style: IndentStyle,
+    /// whether the file ends with a newline, which is written back when
+    /// saving (unless the buffer is empty) as it is not in the buffer
+    ends_with_newline: bool,
+    /// whether the file did not exist when it was opened, and has not
+    /// been saved since, so it is not on disk yet
+    is_new_file: bool,
+    /// the hash of the contents of the file when the view last read or
+    /// wrote it, to tell its own saves apart from changes by other programs
+    file_contents_hash: Option<u64>,
 
     text_box: TextBox<RopeTextBuffer>,
+    search_options: SearchOptions,
+    /// how many commands changed the buffer since it was last saved
+    edits_since_save: usize,
+    /// the command that the buffer is formatted with before saving,
+    /// for each file type (e.g. `rustfmt` for Rust)
+    format_commands: HashMap<FileType, String>,
+    /// whether the trailing spaces and tabs of every line are removed
+    /// from the buffer before saving
+    trim_trailing_whitespace_on_save: bool,
+    /// the format of the date inserted by `EditorCommand::InsertDateTime`
+    date_time_format: String,
+
+    jump_list: JumpList,
+    /// where the caret was when the current search started
+    search_start_pos: Option<TextBufferPos>,
+
+    /// what was selected by the click that started the current drag
+    drag_origin: Option<Range<Vec2u>>,
 
     highlight_info: HighlightInfo<RopeTextBuffer>,
+    /// the word under the caret that is waiting to be highlighted,
+    /// and since when it has been under the caret
+    pending_word_under_caret: Option<(Option<WordUnderCaret>, Instant)>,
+    /// the completions of the word before the caret, if they are shown
+    completion: Option<Completion>,
+    /// the messages attached to lines, e.g. by a linter
+    annotations: Annotations,
+    /// reports the changes made to the file by other programs
+    #[cfg(feature = "notify")]
+    file_watcher: Option<FileWatcher>,
 }
 
 impl CodeView {
@@ -46,37 +166,203 @@ impl CodeView {
         Self {
             filename: None,
             file_type: FileType::PlainText,
-            text_box: TextBox::new(RopeTextBuffer::new()),
+            git_branch: None,
+            indent_style: IndentStyle::default(),
+            ends_with_newline: true,
+            is_new_file: false,
+            file_contents_hash: None,
+            text_box: new_text_box(),
+            search_options: SearchOptions::default(),
+            edits_since_save: 0,
+            format_commands: HashMap::new(),
+            trim_trailing_whitespace_on_save: false,
+            date_time_format: DEFAULT_DATE_TIME_FORMAT.to_string(),
+            jump_list: JumpList::default(),
+            search_start_pos: None,
             bounds: Bounds2f::ZERO,
+            drag_origin: None,
             highlight_info: HighlightInfo::new(),
+            pending_word_under_caret: None,
+            completion: None,
+            annotations: Annotations::default(),
+            #[cfg(feature = "notify")]
+            file_watcher: None,
         }
     }
 
+    /// Create a view of `filename`, which is empty if the file does not
+    /// exist yet, so that it is created when the view is first saved.
     pub(crate) fn new_from_file<T: AsRef<str>>(filename: T) -> Result<Self> {
-        let content = std::fs::read_to_string(filename.as_ref())?;
-        let mut text_box = TextBox::new(RopeTextBuffer::new());
+        let (file_contents, is_new_file) = match std::fs::read_to_string(filename.as_ref()) {
+            Ok(file_contents) => (file_contents, false),
+            Err(err) if err.kind() == ErrorKind::NotFound => (String::new(), true),
+            Err(err) => return Err(err.into()),
+        };
+        let file_contents_hash = (!is_new_file).then(|| hash_file_contents(&file_contents));
+        let (content, ends_with_newline) = split_trailing_newline(&file_contents);
+        let mut text_box = new_text_box();
         text_box.set_contents(content);
         text_box.set_is_dirty(false);
 
-        let filename = Some(filename.as_ref().to_string());
-        let file_type = deduce_filetype(filename.as_ref().expect("filename is not None"));
+        let file_type = deduce_filetype(&filename);
         let mut highlight_info = HighlightInfo::new();
         highlight_info.update_file_type(&text_box, file_type);
 
+        let git_branch = git::find_git_branch(filename.as_ref());
+        let indent_style = text_box.detect_indentation().unwrap_or_default();
+
         Ok(Self {
-            filename,
+            filename: Some(filename.as_ref().to_string()),
             file_type,
+            git_branch,
+            indent_style,
+            ends_with_newline,
+            is_new_file,
+            file_contents_hash,
             text_box,
+            search_options: SearchOptions::default(),
+            edits_since_save: 0,
+            format_commands: HashMap::new(),
+            trim_trailing_whitespace_on_save: false,
+            date_time_format: DEFAULT_DATE_TIME_FORMAT.to_string(),
+            jump_list: JumpList::default(),
+            search_start_pos: None,
             bounds: Bounds2f::ZERO,
+            drag_origin: None,
             highlight_info,
+            pending_word_under_caret: None,
+            completion: None,
+            annotations: Annotations::default(),
+            #[cfg(feature = "notify")]
+            file_watcher: None,
         })
     }
 
+    /// Create an untitled view with the contents read from `reader`
+    /// (e.g. stdin), so saving it prompts for a filename.
+    pub(crate) fn new_from_reader<R: Read>(mut reader: R) -> Result<Self> {
+        let mut content = String::new();
+        reader.read_to_string(&mut content)?;
+
+        let mut view = Self::new();
+        let (content, ends_with_newline) = split_trailing_newline(&content);
+        view.ends_with_newline = ends_with_newline;
+        view.text_box.set_contents(content);
+        view.indent_style = view.text_box.detect_indentation().unwrap_or_default();
+        view.highlight_info
+            .regenerate_on_buffer_change(&view.text_box);
+
+        Ok(view)
+    }
+
+    pub(crate) fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    pub(crate) fn is_new_file(&self) -> bool {
+        self.is_new_file
+    }
+
     pub(crate) fn change_filename<T: AsRef<str>>(&mut self, filename: T) {
         self.filename = Some(filename.as_ref().to_string());
-        self.file_type = deduce_filetype(filename);
+        self.file_type = deduce_filetype(&filename);
+        self.git_branch = git::find_git_branch(filename.as_ref());
         self.highlight_info
             .update_file_type(&self.text_box, self.file_type);
+        // the old file is no longer watched, see `Self::watch_file`
+        #[cfg(feature = "notify")]
+        self.file_watcher.take();
+    }
+
+    /// Report the changes made to the file by other programs through
+    /// `sender`, until the view is closed or saved as another file,
+    /// which is then watched by calling this again.
+    #[cfg(feature = "notify")]
+    pub(crate) fn watch_file(&mut self, sender: Sender<ExternalCommand>) -> Result<()> {
+        let Some(filename) = &self.filename else {
+            return Ok(());
+        };
+        self.file_watcher = Some(FileWatcher::new(filename, sender)?);
+        Ok(())
+    }
+
+    /// Whether the file was changed by another program since the view
+    /// last read or wrote it, which is also the case if it cannot be read.
+    pub(crate) fn is_changed_on_disk(&self) -> bool {
+        let Some(filename) = &self.filename else {
+            return false;
+        };
+        std::fs::read_to_string(filename).map_or(true, |file_contents| {
+            self.file_contents_hash != Some(hash_file_contents(&file_contents))
+        })
+    }
+
+    /// Replace the contents with those of the file, discarding any changes,
+    /// and look up the git branch again. Returns whether the contents were
+    /// different.
+    pub(crate) fn reload_from_disk(&mut self) -> Result<bool> {
+        let Some(filename) = &self.filename else {
+            return Ok(false);
+        };
+        self.git_branch = git::find_git_branch(filename);
+        let file_contents = std::fs::read_to_string(filename)?;
+        self.file_contents_hash = Some(hash_file_contents(&file_contents));
+        let (file_contents, ends_with_newline) = split_trailing_newline(&file_contents);
+        self.ends_with_newline = ends_with_newline;
+        if file_contents == self.text_box.get_entire_contents_as_string() {
+            return Ok(false);
+        }
+
+        self.set_contents(file_contents);
+        self.text_box.set_is_dirty(false);
+        self.edits_since_save = 0;
+        self.update_gutter();
+        self.on_buffer_changed();
+        Ok(true)
+    }
+
+    pub(crate) fn set_render_options(&mut self, render_options: TextRenderOptions) {
+        self.text_box.set_render_options(render_options);
+    }
+
+    pub(crate) fn set_search_options(&mut self, search_options: SearchOptions) {
+        self.search_options = search_options;
+    }
+
+    pub(crate) fn is_read_only(&self) -> bool {
+        self.text_box.is_read_only()
+    }
+
+    pub(crate) fn set_read_only(&mut self, read_only: bool) {
+        self.text_box.set_read_only(read_only);
+    }
+
+    pub(crate) fn set_format_commands(&mut self, format_commands: HashMap<FileType, String>) {
+        self.format_commands = format_commands;
+    }
+
+    pub(crate) fn is_trim_trailing_whitespace_on_save(&self) -> bool {
+        self.trim_trailing_whitespace_on_save
+    }
+
+    pub(crate) fn set_trim_trailing_whitespace_on_save(
+        &mut self,
+        trim_trailing_whitespace_on_save: bool,
+    ) {
+        self.trim_trailing_whitespace_on_save = trim_trailing_whitespace_on_save;
+    }
+
+    pub(crate) fn set_date_time_format<T: AsRef<str>>(&mut self, date_time_format: T) {
+        self.date_time_format = date_time_format.as_ref().to_string();
+    }
+
+    pub(crate) fn set_highlight_options(&mut self, options: HighlightOptions) {
+        self.highlight_info.set_options(&self.text_box, options);
+    }
+
+    pub(crate) fn set_theme(&mut self, theme: Theme) {
+        self.text_box.set_colors(theme.text_box_colors());
+        self.highlight_info.set_theme(&self.text_box, theme);
     }
 
     pub(crate) fn get_status(&self) -> ViewStatus {
@@ -86,6 +372,14 @@ impl CodeView {
             is_dirty: self.text_box.is_dirty(),
             file_type: self.file_type,
             caret_position: self.text_box.caret_pos(),
+            caret_visual_column: self.text_box.caret_visual_column(),
+            is_overwrite_mode: self.text_box.is_overwrite_mode(),
+            edits_since_save: self.edits_since_save,
+            git_branch: self.git_branch.clone(),
+            indent_style: self.indent_style,
+            char_count: self.text_box.char_count(),
+            grapheme_count: self.text_box.grapheme_count(),
+            word_count: self.text_box.word_count(),
         }
     }
 
@@ -95,143 +389,2264 @@ impl CodeView {
 
     pub(crate) fn set_bounds(&mut self, bounds: Bounds2f) {
         self.bounds = bounds;
-        self.text_box.set_bounds(bounds);
+        self.text_box.set_bounds(self.get_text_box_bounds());
+    }
+
+    fn has_gutter(&self) -> bool {
+        !self.annotations.is_empty() || self.text_box.has_dirty_lines()
+    }
+
+    /// The bounds of the text box, which leave room for
+    /// the gutter if there is one.
+    fn get_text_box_bounds(&self) -> Bounds2f {
+        if !self.has_gutter() {
+            return self.bounds;
+        }
+
+        let gutter_width = GUTTER_WIDTH.min(self.bounds.size.x);
+        Bounds2f {
+            pos: Vec2f {
+                x: self.bounds.pos.x + gutter_width,
+                y: self.bounds.pos.y,
+            },
+            size: Vec2f {
+                x: self.bounds.size.x - gutter_width,
+                y: self.bounds.size.y,
+            },
+        }
+    }
+
+    /// Show or hide the gutter, if whether there should be one has changed.
+    fn update_gutter(&mut self) {
+        let text_box_bounds = self.get_text_box_bounds();
+        if self.text_box.bounds() != text_box_bounds {
+            self.text_box.set_bounds(text_box_bounds);
+        }
+    }
+
+    /// Replace the annotations with `annotations`, each of which is attached
+    /// to a line. A gutter is shown for their markers if there are any.
+    pub(crate) fn set_annotations(&mut self, annotations: Vec<(usize, Annotation)>) {
+        self.annotations = Annotations::new(annotations);
+        self.set_bounds(self.bounds);
+    }
+
+    /// Handle a (possibly repeated) click at `screen_pos`. A double click
+    /// selects the word under the click, while a triple click selects the line.
+    pub(crate) fn click(&mut self, screen_pos: Vec2f, click_count: usize) {
+        self.text_box.move_cursor_to_screen_pos(screen_pos);
+
+        match click_count {
+            0 | 1 => {}
+            2 => self.text_box.select_word(),
+            _ => self.text_box.select_line(),
+        }
+
+        let caret_pos = self.text_box.caret_pos();
+        self.drag_origin = Some(
+            self.text_box
+                .selection_range()
+                .unwrap_or(caret_pos..caret_pos),
+        );
+    }
+
+    /// Extend the selection of the click that started the drag to `screen_pos`.
+    /// Drags that started with a double click (or triple click) extend the
+    /// selection by whole words (or whole lines).
+    pub(crate) fn drag(&mut self, screen_pos: Vec2f, click_count: usize) {
+        if let Some(origin) = self.drag_origin.clone() {
+            self.text_box.drag_selection_to_screen_pos(
+                origin,
+                screen_pos,
+                match click_count {
+                    0 | 1 => SelectionGranularity::Character,
+                    2 => SelectionGranularity::Word,
+                    _ => SelectionGranularity::Line,
+                },
+            );
+        }
+    }
+
+    pub(crate) fn end_drag(&mut self) {
+        self.drag_origin.take();
+    }
+
+    /// Scroll without moving the caret, e.g. for the mouse wheel.
+    pub(crate) fn scroll_by(&mut self, delta: Vec2i) {
+        self.text_box.scroll_by(delta);
+    }
+
+    pub(crate) fn copy_selection(&self) -> Option<String> {
+        self.text_box.copy_selection()
+    }
+
+    fn get_word_under_caret(&self) -> Option<WordUnderCaret> {
+        let caret_pos = self.text_box.caret_pos();
+        let line = self.text_box.get_raw_line(caret_pos.y.to_usize())?;
+
+        // the caret could also be right after the word
+        [
+            Some(caret_pos),
+            caret_pos.x.checked_sub(1).map(|x| Vec2u { x, ..caret_pos }),
+        ]
+        .into_iter()
+        .flatten()
+        .filter_map(|pos| self.text_box.get_word_byte_range_at(pos))
+        .find(|range| is_identifier(&line[range.clone()]))
+        .map(|range| WordUnderCaret {
+            word: line[range.clone()].to_string(),
+            line_idx: caret_pos.y.to_usize(),
+            byte_idx: range.start,
+            visible_lines: self.text_box.visible_line_range(),
+        })
+    }
+
+    /// Do any work that was deferred, returns whether the view
+    /// has to be redrawn.
+    pub(crate) fn tick(&mut self, now: Instant) -> bool {
+        let matching_brackets = self.text_box.matching_bracket();
+        let brackets_changed = matching_brackets != self.highlight_info.matching_brackets();
+        if brackets_changed {
+            self.highlight_info
+                .set_matching_brackets(&self.text_box, matching_brackets);
+        }
+
+        self.tick_word_under_caret(now) || brackets_changed
+    }
+
+    /// Highlight the other occurrences of the word under the caret once
+    /// it has stayed there for a while, returns whether it changed.
+    fn tick_word_under_caret(&mut self, now: Instant) -> bool {
+        let word_under_caret = self.get_word_under_caret();
+        if word_under_caret.as_ref() == self.highlight_info.word_under_caret() {
+            self.pending_word_under_caret.take();
+            return false;
+        }
+
+        match &self.pending_word_under_caret {
+            Some((pending_word_under_caret, since))
+                if *pending_word_under_caret == word_under_caret =>
+            {
+                if now.duration_since(*since) < WORD_UNDER_CARET_DELAY {
+                    return false;
+                }
+
+                self.pending_word_under_caret.take();
+                self.highlight_info
+                    .set_word_under_caret(&self.text_box, word_under_caret);
+                true
+            }
+            _ => {
+                self.pending_word_under_caret = Some((word_under_caret, now));
+                false
+            }
+        }
     }
 
     pub(crate) fn render(&self, drawer: &mut Drawer) {
         self.text_box
             .render(drawer, self.highlight_info.text_highlight());
+        self.render_gutter(drawer);
+        if let Some(completion) = &self.completion {
+            self.render_completion(drawer, completion);
+        }
+    }
+
+    /// Draw a marker in the gutter for each annotated line and each line
+    /// changed since the last save, and the messages of the annotations
+    /// after the end of the line if there is room for them.
+    fn render_gutter(&self, drawer: &mut Drawer) {
+        if !self.has_gutter() {
+            return;
+        }
+
+        let gutter_background = self.highlight_info.theme().gutter();
+        if let Some(background) = gutter_background {
+            let gutter_width: usize = GUTTER_WIDTH.min(self.bounds.size.x).lossy();
+            let mut y = self.bounds.pos.y;
+            while y < self.bounds.pos.y + self.bounds.size.y {
+                drawer.draw_colored_text(
+                    Vec2f {
+                        x: self.bounds.pos.x,
+                        y,
+                    },
+                    " ".repeat(gutter_width),
+                    None,
+                    Some(background),
+                );
+                y += 1.0;
+            }
+        }
+
+        let dirty_lines = self.text_box.dirty_lines();
+        for line_idx in self.text_box.visible_line_range() {
+            if dirty_lines.get(line_idx) != Some(&true) {
+                continue;
+            }
+            let Some(line_end) = self.text_box.line_end_screen_pos(line_idx) else {
+                continue;
+            };
+            drawer.draw_colored_text(
+                Vec2f {
+                    x: self.bounds.pos.x + 1.0,
+                    y: line_end.y,
+                },
+                DIRTY_LINE_MARKER,
+                Some(self.highlight_info.theme().dirty_line()),
+                gutter_background,
+            );
+        }
+
+        // not the end of the text box bounds, which could have a scrollbar
+        let visible_columns = self.text_box.visible_columns();
+        let text_end_x = self.get_text_box_bounds().pos.x
+            + visible_columns
+                .end
+                .saturating_sub(visible_columns.start)
+                .lossy();
+
+        for (line_idx, annotations) in self
+            .annotations
+            .get_lines(self.text_box.visible_line_range())
+        {
+            let (Some(line_end), Some(most_severe)) = (
+                self.text_box.line_end_screen_pos(line_idx),
+                annotations.first(),
+            ) else {
+                continue;
+            };
+            let color = self.highlight_info.theme().severity(most_severe.severity);
+            drawer.draw_colored_text(
+                Vec2f {
+                    x: self.bounds.pos.x,
+                    y: line_end.y,
+                },
+                ANNOTATION_MARKER,
+                Some(color),
+                gutter_background,
+            );
+
+            let message_x = line_end.x + 2.0;
+            let room: usize = (text_end_x - message_x).max(0.0).lossy();
+            if room > 0 {
+                let message = annotations
+                    .iter()
+                    .map(|annotation| annotation.message.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" | ");
+                drawer.draw_colored_text(
+                    Vec2f {
+                        x: message_x,
+                        y: line_end.y,
+                    },
+                    message.chars().take(room).collect::<String>(),
+                    Some(color),
+                    None,
+                );
+            }
+        }
+    }
+
+    /// Draw the completions below the caret (or above it, if there is no
+    /// room below), lined up with the start of the word.
+    fn render_completion(&self, drawer: &mut Drawer, completion: &Completion) {
+        let (candidates, selected) = completion.visible_candidates();
+        // the popup pads each candidate with a space on both sides
+        let width = candidates
+            .iter()
+            .map(|candidate| candidate.chars().count())
+            .max()
+            .unwrap_or(0)
+            .saturating_add(2)
+            .lossy();
+        let height = candidates.len().lossy();
+
+        let caret_pos = self.text_box.caret_screen_pos();
+        let prefix_width = completion
+            .prefix()
+            .chars()
+            .count()
+            .saturating_add(1)
+            .lossy();
+        let x = (caret_pos.x - prefix_width)
+            .min(self.bounds.pos.x + self.bounds.size.x - width)
+            .max(self.bounds.pos.x);
+        let y = if caret_pos.y + 1.0 + height <= self.bounds.pos.y + self.bounds.size.y {
+            caret_pos.y + 1.0
+        } else {
+            (caret_pos.y - height).max(self.bounds.pos.y)
+        };
+
+        drawer.draw_popup(
+            Vec2f { x, y },
+            candidates,
+            Some(selected),
+            self.highlight_info.theme().popup(),
+        );
+    }
+
+    /// Show the completions of the word before the caret.
+    fn trigger_completion(&mut self) -> CommandOutcome {
+        let caret = self.text_box.caret_buffer_pos();
+        let line = self.text_box.get_raw_line(caret.line).unwrap_or_default();
+        let before_caret = line.get(..caret.byte).unwrap_or_default();
+        let prefix = before_caret
+            .split_word_bounds()
+            .next_back()
+            .filter(|word| is_identifier(word))
+            .unwrap_or_default()
+            .to_string();
+        if prefix.is_empty() {
+            return CommandOutcome {
+                message: Some("Nothing to complete".to_string()),
+                ..CommandOutcome::handled()
+            };
+        }
+
+        let lines = (0..self.text_box.get_total_lines())
+            .filter_map(|line_idx| self.text_box.get_raw_line(line_idx));
+        let candidates = completion::find_completions(lines, &prefix);
+        self.completion = Completion::new(prefix.clone(), candidates);
+        if self.completion.is_some() {
+            CommandOutcome::handled()
+        } else {
+            CommandOutcome {
+                message: Some(format!("No completions for {prefix}")),
+                ..CommandOutcome::handled()
+            }
+        }
+    }
+
+    /// Returns the outcome of `command` if it is meant for the shown
+    /// completions. Other commands hide the completions.
+    fn execute_completion_command(&mut self, command: EditorCommand) -> Option<CommandOutcome> {
+        let completion = self.completion.as_mut()?;
+        match command {
+            EditorCommand::TriggerCompletion | EditorCommand::MoveCursorDown => {
+                completion.select_next();
+                Some(CommandOutcome::handled())
+            }
+            EditorCommand::MoveCursorUp => {
+                completion.select_previous();
+                Some(CommandOutcome::handled())
+            }
+            EditorCommand::InsertNewline => {
+                let remaining_text = completion.remaining_text().to_string();
+                self.completion = None;
+                Some(self.insert_text(&remaining_text))
+            }
+            EditorCommand::Dismiss => {
+                self.completion = None;
+                Some(CommandOutcome::handled())
+            }
+            _ => {
+                self.completion = None;
+                None
+            }
+        }
     }
 
-    fn start_search(&mut self, command_bar: &mut CommandBar) {
+    fn start_search(&mut self, command_bar: &mut CommandBar, prompt: CommandBarPrompt) {
+        self.search_start_pos = Some(self.text_box.caret_buffer_pos());
         self.text_box.enter_search_mode();
-        command_bar.set_prompt(CommandBarPrompt::Search);
+        command_bar.set_prompt(prompt);
     }
 
     pub(crate) fn abort_search(&mut self) {
         self.text_box.exit_search_mode(false);
         self.highlight_info.clear_search_highlights(&self.text_box);
+        self.search_start_pos.take();
+    }
+
+    /// Go back to where the search started, with no matches
+    /// highlighted, as there is nothing to search for.
+    pub(crate) fn clear_search(&mut self) {
+        self.text_box.restore_before_search_pos();
+        self.highlight_info.clear_search_highlights(&self.text_box);
     }
 
     pub(crate) fn complete_search(&mut self) {
         self.text_box.exit_search_mode(true);
         self.highlight_info.clear_search_highlights(&self.text_box);
+        if let Some(search_start_pos) = self.search_start_pos.take()
+            && search_start_pos != self.text_box.caret_buffer_pos()
+        {
+            self.jump_list.push(search_start_pos);
+        }
+    }
+
+    /// Move the caret with `move_cursor`, remembering where it was
+    /// in the jump list if it moved.
+    fn jump<F: FnOnce(&mut TextBox<RopeTextBuffer>)>(&mut self, move_cursor: F) {
+        let pos = self.text_box.caret_buffer_pos();
+        move_cursor(&mut self.text_box);
+        if self.text_box.caret_buffer_pos() != pos {
+            self.jump_list.push(pos);
+        }
+    }
+
+    /// Select the next (or previous) occurrence of the word under the caret,
+    /// showing which of its occurrences it is.
+    fn jump_to_word_occurrence(&mut self, search_direction: SearchDirection) -> CommandOutcome {
+        let Some(word) = self.text_box.word_under_caret() else {
+            return CommandOutcome {
+                message: Some("No word under caret".to_string()),
+                ..CommandOutcome::handled()
+            };
+        };
+
+        let search_options = self.search_options;
+        let mut found = None;
+        self.jump(|text_box| {
+            found = text_box
+                .find_word_under_caret(search_direction, search_options)
+                .map(|(_, found)| found);
+        });
+        let whole_word = SearchOptions {
+            whole_word: true,
+            ..search_options
+        };
+        CommandOutcome {
+            message: Some(get_search_matches_message(
+                found,
+                self.text_box.get_search_match_ordinal(&word, whole_word),
+            )),
+            ..CommandOutcome::handled()
+        }
+    }
+
+    /// Move the caret to the start of the (1-based) `line_number`,
+    /// or the last line if there are not that many lines.
+    pub(crate) fn goto_line(&mut self, line_number: usize) {
+        let line_idx = line_number
+            .saturating_sub(1)
+            .min(self.text_box.get_total_lines().saturating_sub(1));
+        self.jump(|text_box| {
+            text_box.move_cursor_to_buffer_pos(TextBufferPos {
+                line: line_idx,
+                byte: 0,
+            });
+        });
+    }
+
+    /// Record the caret position as the mark `id`.
+    pub(crate) fn set_mark(&mut self, id: char) -> CommandOutcome {
+        self.text_box.set_mark(id);
+        CommandOutcome {
+            message: Some(format!("Mark {id} set")),
+            ..CommandOutcome::handled()
+        }
+    }
+
+    /// Move the caret back to the position recorded as the mark `id`.
+    pub(crate) fn jump_to_mark(&mut self, id: char) -> CommandOutcome {
+        let mut is_jumped = false;
+        self.jump(|text_box| is_jumped = text_box.jump_to_mark(id));
+        CommandOutcome {
+            message: (!is_jumped).then(|| format!("Mark {id} not set")),
+            ..CommandOutcome::handled()
+        }
     }
 
+    /// Returns the match that the caret moved to, if there was one.
     pub(crate) fn find<T: AsRef<str>>(
         &mut self,
         search: T,
         first_search: bool,
         search_direction: SearchDirection,
-    ) {
-        self.text_box.find(&search, first_search, search_direction);
+    ) -> Option<FindResult> {
+        let found =
+            self.text_box
+                .find(&search, first_search, search_direction, self.search_options);
+        self.highlight_info.regenerate_on_search_change(
+            &self.text_box,
+            SearchPattern::Text {
+                text: search.as_ref().to_string(),
+                whole_word: self.search_options.whole_word,
+            },
+            self.text_box.caret_pos(),
+        );
+        found
+    }
+
+    /// Returns the (1-based) ordinal of the match of `search` that the
+    /// caret is at (if any), and the number of matches.
+    pub(crate) fn get_search_match_ordinal(&self, search: &str) -> (Option<usize>, usize) {
+        self.text_box
+            .get_search_match_ordinal(search, self.search_options)
+    }
+
+    /// Like `Self::find`, but for a match of the regular expression `pattern`.
+    pub(crate) fn find_regex(
+        &mut self,
+        pattern: &Regex,
+        first_search: bool,
+        search_direction: SearchDirection,
+    ) -> Option<FindResult> {
+        let found = self
+            .text_box
+            .find_regex(pattern, first_search, search_direction);
         self.highlight_info.regenerate_on_search_change(
             &self.text_box,
-            search,
+            SearchPattern::Regex(pattern.clone()),
             self.text_box.caret_pos(),
         );
+        found
+    }
+
+    /// Format the buffer with the format command for its file type (if any).
+    /// A read-only buffer is left as it is, without running the command.
+    fn format_buffer(&mut self) -> Result<()> {
+        let Some(format_command) = self.format_commands.get(&self.file_type) else {
+            return Ok(());
+        };
+        if self.text_box.is_read_only() {
+            return Ok(());
+        }
+
+        // the formatter is given exactly what would be written to disk
+        let contents = self.get_contents_to_write();
+        let formatted = process::format(format_command, &contents)?;
+        if formatted != contents {
+            let (formatted, ends_with_newline) = split_trailing_newline(&formatted);
+            self.ends_with_newline = ends_with_newline;
+            // as one change that can be undone, with the caret
+            // staying where it was (clamped to the new contents)
+            let edits = vec![
+                Edit::Remove {
+                    start: TextBufferPos::default(),
+                    end: self.get_end_of_buffer_pos(),
+                },
+                Edit::Insert {
+                    pos: TextBufferPos::default(),
+                    text: formatted.to_string(),
+                },
+            ];
+            if self.apply_edits(edits).is_ok() {
+                self.on_buffer_changed();
+            }
+        }
+        Ok(())
+    }
+
+    /// The position after the last character of the buffer.
+    fn get_end_of_buffer_pos(&self) -> TextBufferPos {
+        let last_line_idx = self.text_box.get_total_lines().saturating_sub(1);
+        TextBufferPos {
+            line: last_line_idx,
+            byte: self
+                .text_box
+                .get_raw_line(last_line_idx)
+                .map_or(0, |line| line.len()),
+        }
+    }
+
+    /// Remove the trailing spaces and tabs of every line from the buffer,
+    /// as one change that can be undone. The caret stays where it was,
+    /// unless it was in the removed whitespace of its line.
+    fn trim_trailing_whitespace(&mut self) {
+        let edits: Vec<_> = (0..self.text_box.get_total_lines())
+            .filter_map(|line_idx| {
+                let line = self.text_box.get_raw_line(line_idx)?;
+                let trimmed_len = line.trim_end_matches([' ', '\t']).len();
+                (trimmed_len < line.len()).then_some(Edit::Remove {
+                    start: TextBufferPos {
+                        line: line_idx,
+                        byte: trimmed_len,
+                    },
+                    end: TextBufferPos {
+                        line: line_idx,
+                        byte: line.len(),
+                    },
+                })
+            })
+            .collect();
+        if edits.is_empty() {
+            return;
+        }
+
+        // removing the end of a line leaves the other lines where they are,
+        // so the edits cannot be invalidated by the ones before them
+        if self.apply_edits(edits).is_ok() {
+            self.on_buffer_changed();
+        }
+    }
+
+    /// The contents as they are written to disk when saving.
+    fn get_contents_to_write(&self) -> String {
+        let mut contents = self.text_box.get_entire_contents_as_string();
+        if self.ends_with_newline && !contents.is_empty() {
+            contents.push('\n');
+        }
+        contents
+    }
+
+    /// Pipe the selection (or the whole buffer, if nothing is selected)
+    /// through `shell_command`, replacing it with what the command prints.
+    /// If the command fails, the buffer is left as it is.
+    pub(crate) fn filter_through_command(&mut self, shell_command: &str) -> CommandOutcome {
+        let range = self
+            .text_box
+            .selection_buffer_range()
+            .unwrap_or_else(|| TextBufferPos::default()..self.get_end_of_buffer_pos());
+        let text = self
+            .text_box
+            .copy_selection()
+            .unwrap_or_else(|| self.text_box.get_entire_contents_as_string());
+
+        // commands like `sort` expect every line to end with a newline
+        let input = if text.ends_with('\n') {
+            text
+        } else {
+            format!("{text}\n")
+        };
+        let output = match process::filter(shell_command, &input) {
+            Ok(output) => output,
+            Err(err) => {
+                return CommandOutcome {
+                    message: Some(format!("Filter failed: {err}")),
+                    ..CommandOutcome::handled()
+                };
+            }
+        };
+
+        let edits = vec![
+            Edit::Remove {
+                start: range.start,
+                end: range.end,
+            },
+            Edit::Insert {
+                pos: range.start,
+                text: output.strip_suffix('\n').unwrap_or(&output).to_string(),
+            },
+        ];
+        match self.apply_edits(edits) {
+            Ok(_) => CommandOutcome::buffer_changed(),
+            Err(err) => CommandOutcome {
+                message: Some(format!("Filter failed: {err}")),
+                ..CommandOutcome::handled()
+            },
+        }
     }
 
     fn write_to_disk<T: AsRef<str>>(&mut self, filename: T) -> Result<()> {
+        // a read-only buffer is saved as it is
+        if self.trim_trailing_whitespace_on_save && !self.text_box.is_read_only() {
+            self.trim_trailing_whitespace();
+        }
+
+        let contents = self.get_contents_to_write();
         let mut file = File::create(filename.as_ref())?;
-        writeln!(file, "{}", self.text_box.get_entire_contents_as_string())?;
+        file.write_all(contents.as_bytes())?;
+        self.file_contents_hash = Some(hash_file_contents(&contents));
         self.text_box.set_is_dirty(false);
+        self.is_new_file = false;
+        self.edits_since_save = 0;
+        self.update_gutter();
 
         Ok(())
     }
 
+    pub(crate) fn execute_command(
+        &mut self,
+        command: EditorCommand,
+        command_bar: &mut CommandBar,
+    ) -> CommandOutcome {
+        let outcome = self.dispatch_command(command, command_bar);
+        if outcome.buffer_changed {
+            self.on_edited();
+        }
+        // the changes made by a command are undone together
+        self.text_box.end_undo_group();
+        outcome
+    }
+
+    /// Keep track of an edit.
+    fn on_edited(&mut self) {
+        self.edits_since_save = self.edits_since_save.saturating_add(1);
+        self.track_line_shifts();
+        self.update_gutter();
+    }
+
+    /// Move the jumps and annotations along with the lines
+    /// added or removed since this was last called.
+    fn track_line_shifts(&mut self) {
+        for line_shift in self.text_box.take_line_shifts() {
+            match line_shift {
+                LineShift::Split {
+                    line_idx,
+                    new_lines,
+                } => {
+                    self.jump_list.split_lines(line_idx, new_lines);
+                    self.annotations.split_lines(line_idx, new_lines);
+                }
+                LineShift::Join {
+                    line_idx,
+                    removed_lines,
+                } => {
+                    self.jump_list.join_lines(line_idx, removed_lines);
+                    self.annotations.join_lines(line_idx, removed_lines);
+                }
+            }
+        }
+    }
+
+    /// Replace the selection with `text`, returning what was selected
+    /// (or `None` if nothing is selected).
+    pub(crate) fn swap_selection(&mut self, text: &str) -> Option<String> {
+        let swapped = self.text_box.replace_selection(text)?;
+        self.on_edited();
+        Some(swapped)
+    }
+
     // splitting the function up doesn't change the readability much
     #[allow(clippy::too_many_lines)]
-    pub(crate) fn execute_command(
+    fn dispatch_command(
         &mut self,
         command: EditorCommand,
-        message_bar: &mut MessageBar,
         command_bar: &mut CommandBar,
-    ) -> bool {
+    ) -> CommandOutcome {
+        if command.is_edit_command() && self.text_box.is_read_only() {
+            return CommandOutcome::read_only();
+        }
+        if let Some(outcome) = self.execute_completion_command(command) {
+            return outcome;
+        }
+
         match command {
+            EditorCommand::Noop => CommandOutcome::handled(),
             EditorCommand::MoveCursorUp => {
                 self.text_box.move_cursor_up();
-                true
+                CommandOutcome::handled()
             }
             EditorCommand::MoveCursorDown => {
                 self.text_box.move_cursor_down();
-                true
+                CommandOutcome::handled()
             }
             EditorCommand::MoveCursorLeft => {
                 self.text_box.move_cursor_left();
-                true
+                CommandOutcome::handled()
+            }
+            EditorCommand::MoveCursorWordLeft => {
+                self.text_box.move_cursor_word_left();
+                CommandOutcome::handled()
+            }
+            EditorCommand::MoveCursorWordRight => {
+                self.text_box.move_cursor_word_right();
+                CommandOutcome::handled()
+            }
+            EditorCommand::ExtendSelectionUp => {
+                self.text_box.extend_selection(TextBox::move_cursor_up);
+                CommandOutcome::handled()
+            }
+            EditorCommand::ExtendSelectionDown => {
+                self.text_box.extend_selection(TextBox::move_cursor_down);
+                CommandOutcome::handled()
+            }
+            EditorCommand::ExtendSelectionLeft => {
+                self.text_box.extend_selection(TextBox::move_cursor_left);
+                CommandOutcome::handled()
+            }
+            EditorCommand::ExtendSelectionRight => {
+                self.text_box.extend_selection(TextBox::move_cursor_right);
+                CommandOutcome::handled()
             }
             EditorCommand::MoveCursorRight => {
                 self.text_box.move_cursor_right();
-                true
+                CommandOutcome::handled()
             }
             EditorCommand::MoveCursorUpOnePage => {
                 self.text_box.move_cursor_up_one_page();
-                true
+                CommandOutcome::handled()
             }
             EditorCommand::MoveCursorDownOnePage => {
                 self.text_box.move_cursor_down_one_page();
-                true
+                CommandOutcome::handled()
             }
             EditorCommand::MoveCursorToStartOfLine => {
-                self.text_box.move_cursor_to_start_of_line();
-                true
+                // the first press skips the indentation, the next one goes
+                // to the start of the line
+                let caret_x = self.text_box.caret_pos().x;
+                self.text_box.move_cursor_to_first_non_whitespace();
+                if self.text_box.caret_pos().x == caret_x {
+                    self.text_box.move_cursor_to_start_of_line();
+                }
+                CommandOutcome::handled()
             }
             EditorCommand::MoveCursorToEndOfLine => {
                 self.text_box.move_cursor_to_end_of_line();
-                true
+                CommandOutcome::handled()
+            }
+            EditorCommand::MoveCursorToTop => {
+                self.jump(TextBox::move_cursor_to_start_of_buffer);
+                CommandOutcome::handled()
+            }
+            EditorCommand::MoveCursorToBottom => {
+                self.jump(TextBox::move_cursor_to_end_of_buffer);
+                CommandOutcome::handled()
+            }
+            EditorCommand::InsertIndent => {
+                if self.text_box.selection_range().is_some() {
+                    let lines = self.text_box.selected_lines();
+                    if self.text_box.indent_lines(lines, self.indent_style) {
+                        CommandOutcome::buffer_changed()
+                    } else {
+                        CommandOutcome::handled()
+                    }
+                } else if self
+                    .text_box
+                    .insert_indent_at_cursor(self.indent_style)
+                    .is_ok()
+                {
+                    CommandOutcome::buffer_changed()
+                } else {
+                    CommandOutcome::not_handled()
+                }
+            }
+            EditorCommand::DedentLines => {
+                let lines = self.text_box.selected_lines();
+                if self.text_box.dedent_lines(lines, self.indent_style) {
+                    CommandOutcome::buffer_changed()
+                } else {
+                    CommandOutcome::handled()
+                }
             }
             EditorCommand::InsertCharacter(ch) => {
                 if self.text_box.insert_character_at_cursor(ch).is_ok() {
-                    self.highlight_info
-                        .regenerate_on_buffer_change(&self.text_box);
-                    true
+                    CommandOutcome::buffer_changed()
                 } else {
-                    false
+                    CommandOutcome::not_handled()
                 }
             }
             EditorCommand::EraseCharacterBeforeCursor => {
-                if self.text_box.erase_character_before_cursor().is_ok() {
-                    self.highlight_info
-                        .regenerate_on_buffer_change(&self.text_box);
-                    true
+                if self.text_box.caret_pos() == (Vec2u { x: 0, y: 0 }) {
+                    // nothing before the start of the buffer to erase
+                    CommandOutcome::not_handled()
+                } else if self.text_box.erase_indent_before_cursor(self.indent_style)
+                    || self.text_box.erase_character_before_cursor().is_ok()
+                {
+                    CommandOutcome::buffer_changed()
                 } else {
-                    false
+                    CommandOutcome::not_handled()
                 }
             }
             EditorCommand::EraseCharacterAfterCursor => {
                 if self.text_box.erase_character_after_cursor().is_ok() {
-                    self.highlight_info
-                        .regenerate_on_buffer_change(&self.text_box);
-                    true
+                    CommandOutcome::buffer_changed()
                 } else {
-                    false
+                    CommandOutcome::not_handled()
+                }
+            }
+            EditorCommand::EraseWordBeforeCursor
+            | EditorCommand::EraseWordAfterCursor
+            | EditorCommand::DeleteToEndOfLine
+            | EditorCommand::DeleteLine
+            | EditorCommand::DeleteToStartOfLine => {
+                let killed = match command {
+                    EditorCommand::EraseWordBeforeCursor => {
+                        self.text_box.erase_word_before_cursor()
+                    }
+                    EditorCommand::EraseWordAfterCursor => self.text_box.erase_word_after_cursor(),
+                    EditorCommand::DeleteToEndOfLine => self.text_box.delete_to_end_of_line(),
+                    EditorCommand::DeleteLine => self.text_box.delete_current_line(),
+                    _ => self.text_box.delete_to_start_of_line(),
+                };
+
+                match killed {
+                    Some(killed) => CommandOutcome {
+                        killed: Some(killed),
+                        ..CommandOutcome::buffer_changed()
+                    },
+                    None => CommandOutcome::handled(),
                 }
             }
 
             EditorCommand::InsertNewline => {
-                self.text_box.insert_newline_at_cursor();
-                self.highlight_info
-                    .regenerate_on_buffer_change(&self.text_box);
-                true
+                self.text_box.insert_newline_with_autoindent();
+                CommandOutcome::buffer_changed()
             }
-            EditorCommand::WriteBufferToDisk => {
-                match &self.filename {
-                    Some(filename) => match self.write_to_disk(filename.clone()) {
-                        Ok(()) => message_bar.set_message("File saved successfully"),
-                        Err(err) => message_bar.set_message(format!("Error writing file: {err:?}")),
+            EditorCommand::InsertDateTime => {
+                match date_time::format_date_time(&self.date_time_format, SystemTime::now()) {
+                    Ok(date_time) => self.insert_text(&date_time),
+                    Err(err) => CommandOutcome {
+                        message: Some(format!("Invalid date format: {err}")),
+                        ..CommandOutcome::handled()
                     },
-                    None => {
-                        command_bar.set_prompt(CommandBarPrompt::SaveAs);
+                }
+            }
+            EditorCommand::TriggerCompletion => self.trigger_completion(),
+            EditorCommand::InsertFileName => {
+                let filename = self.filename.clone();
+                self.insert_text(filename.as_deref().unwrap_or(NO_NAME))
+            }
+            EditorCommand::ToggleOverwriteMode => {
+                self.text_box
+                    .set_overwrite_mode(!self.text_box.is_overwrite_mode());
+                CommandOutcome::handled()
+            }
+            EditorCommand::ToggleBlockSelection => {
+                let is_block = self.text_box.selection_mode() == SelectionMode::Normal;
+                self.text_box.set_selection_mode(if is_block {
+                    SelectionMode::Block
+                } else {
+                    SelectionMode::Normal
+                });
+                CommandOutcome {
+                    message: Some(if is_block {
+                        "Selecting blocks".to_string()
+                    } else {
+                        "Selecting text".to_string()
+                    }),
+                    ..CommandOutcome::handled()
+                }
+            }
+            EditorCommand::RevealWhitespaceErrors => {
+                let is_revealed = self.highlight_info.whitespace_errors().is_none();
+                self.highlight_info.set_whitespace_errors(
+                    &self.text_box,
+                    is_revealed.then_some(self.indent_style),
+                );
+                CommandOutcome {
+                    message: Some(if is_revealed {
+                        "Revealing whitespace errors".to_string()
+                    } else {
+                        "Whitespace errors hidden".to_string()
+                    }),
+                    ..CommandOutcome::handled()
+                }
+            }
+            EditorCommand::WriteBufferToDisk => {
+                if let Some(filename) = self.filename.clone() {
+                    // a buffer that cannot be formatted is still saved
+                    let format_result = self.format_buffer();
+                    CommandOutcome {
+                        message: Some(match (self.write_to_disk(filename), format_result) {
+                            (Ok(()), Ok(())) => "File saved successfully".to_string(),
+                            (Ok(()), Err(err)) => {
+                                format!("File saved without formatting: {err}")
+                            }
+                            (Err(err), _) => format!("Error writing file: {err:?}"),
+                        }),
+                        ..CommandOutcome::handled()
                     }
+                } else {
+                    command_bar.set_prompt(CommandBarPrompt::SaveAs);
+                    CommandOutcome::handled()
                 }
-                true
             }
             EditorCommand::StartSearch => {
-                self.start_search(command_bar);
-                true
+                self.start_search(command_bar, CommandBarPrompt::Search);
+                CommandOutcome::handled()
+            }
+            EditorCommand::StartRegexSearch => {
+                self.start_search(command_bar, CommandBarPrompt::RegexSearch);
+                CommandOutcome::handled()
+            }
+            EditorCommand::StartGotoLine => {
+                command_bar.set_prompt(CommandBarPrompt::GotoLine);
+                CommandOutcome::handled()
+            }
+            EditorCommand::FilterThroughCommand => {
+                command_bar.set_prompt(CommandBarPrompt::FilterCommand);
+                CommandOutcome::handled()
+            }
+            EditorCommand::SelectAll => {
+                self.text_box.select_all();
+                CommandOutcome::handled()
+            }
+            EditorCommand::SelectLine => {
+                self.text_box.select_line();
+                CommandOutcome::handled()
+            }
+            EditorCommand::SelectWord => {
+                self.text_box.select_word();
+                CommandOutcome::handled()
+            }
+            EditorCommand::DuplicateSelection => {
+                if self.text_box.duplicate_selection() {
+                    CommandOutcome::buffer_changed()
+                } else {
+                    CommandOutcome::handled()
+                }
+            }
+            EditorCommand::MoveLineUp => {
+                if self.text_box.move_line_up() {
+                    CommandOutcome::buffer_changed()
+                } else {
+                    CommandOutcome::handled()
+                }
+            }
+            EditorCommand::MoveLineDown => {
+                if self.text_box.move_line_down() {
+                    CommandOutcome::buffer_changed()
+                } else {
+                    CommandOutcome::handled()
+                }
+            }
+            EditorCommand::JoinLines => {
+                if self.text_box.join_with_next_line() {
+                    CommandOutcome::buffer_changed()
+                } else {
+                    CommandOutcome::handled()
+                }
+            }
+            EditorCommand::ToggleComment => self.toggle_comment(),
+            EditorCommand::Undo => {
+                if self.text_box.undo() {
+                    CommandOutcome::buffer_changed()
+                } else {
+                    CommandOutcome {
+                        message: Some("Already at oldest change".to_string()),
+                        ..CommandOutcome::handled()
+                    }
+                }
+            }
+            EditorCommand::Redo => {
+                if self.text_box.redo() {
+                    CommandOutcome::buffer_changed()
+                } else {
+                    CommandOutcome {
+                        message: Some("Already at newest change".to_string()),
+                        ..CommandOutcome::handled()
+                    }
+                }
+            }
+            EditorCommand::ToggleFold => self.toggle_fold(),
+            EditorCommand::JumpBack => {
+                if let Some(pos) = self.jump_list.back(self.text_box.caret_buffer_pos()) {
+                    self.text_box.move_cursor_to_buffer_pos(pos);
+                }
+                CommandOutcome::handled()
+            }
+            EditorCommand::JumpToMatchingBracket => {
+                self.jump(|text_box| {
+                    text_box.jump_to_matching_bracket();
+                });
+                CommandOutcome::handled()
+            }
+            EditorCommand::JumpForward => {
+                if let Some(pos) = self.jump_list.forward() {
+                    self.text_box.move_cursor_to_buffer_pos(pos);
+                }
+                CommandOutcome::handled()
+            }
+            EditorCommand::CenterCaret => {
+                self.text_box.center_caret_vertically();
+                CommandOutcome::handled()
+            }
+            EditorCommand::JumpToNextWordOccurrence => {
+                self.jump_to_word_occurrence(SearchDirection::Forward)
+            }
+            EditorCommand::JumpToPreviousWordOccurrence => {
+                self.jump_to_word_occurrence(SearchDirection::Backward)
+            }
+            EditorCommand::SetMark => {
+                command_bar.set_prompt(CommandBarPrompt::SetMark);
+                CommandOutcome::handled()
+            }
+            EditorCommand::JumpToMark => {
+                command_bar.set_prompt(CommandBarPrompt::JumpToMark);
+                CommandOutcome::handled()
+            }
+            EditorCommand::QuitAll
+            | EditorCommand::Dismiss
+            | EditorCommand::Copy
+            | EditorCommand::Cut
+            | EditorCommand::Paste
+            | EditorCommand::SwapSelectionWithClipboard
+            | EditorCommand::Yank
+            | EditorCommand::YankPop
+            | EditorCommand::ShowHelp
+            | EditorCommand::SplitVertical
+            | EditorCommand::SplitHorizontal
+            | EditorCommand::FocusNextPane
+            | EditorCommand::ClosePane
+            | EditorCommand::RepeatLastChange
+            | EditorCommand::StartRecordingMacro
+            | EditorCommand::StopRecordingMacro
+            | EditorCommand::PlayMacro(_) => CommandOutcome::not_handled(),
+        }
+    }
+
+    /// Returns the line with the brace that closes the first opening
+    /// brace on `line_idx`, if the block spans more than one line.
+    fn find_closing_brace_line(&self, line_idx: usize) -> Option<usize> {
+        let line = self.text_box.get_raw_line(line_idx)?;
+        let open_idx = line.find('{')?;
+
+        let mut depth = 0_usize;
+        let mut closing_line_idx = line_idx;
+        let mut rest = line[open_idx..].to_string();
+        loop {
+            for ch in rest.chars() {
+                match ch {
+                    '{' => depth = depth.saturating_add(1),
+                    '}' => {
+                        depth = depth.saturating_sub(1);
+                        if depth == 0 {
+                            return (closing_line_idx > line_idx).then_some(closing_line_idx);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            closing_line_idx = closing_line_idx.saturating_add(1);
+            rest = self.text_box.get_raw_line(closing_line_idx)?;
+        }
+    }
+
+    fn insert_text(&mut self, text: &str) -> CommandOutcome {
+        if self.text_box.insert_str_at_cursor(text).is_ok() {
+            CommandOutcome::buffer_changed()
+        } else {
+            CommandOutcome::not_handled()
+        }
+    }
+
+    /// Comment out the caret's line (or the selected lines), unless they
+    /// are all commented out already, in which case they are uncommented.
+    /// Blank lines are left alone.
+    fn toggle_comment(&mut self) -> CommandOutcome {
+        let Some(prefix) = self.file_type.line_comment_prefix() else {
+            return CommandOutcome::handled();
+        };
+
+        let caret = self.text_box.caret_buffer_pos();
+        let selection = self.text_box.selection_buffer_range();
+        let lines: Vec<(usize, String)> = self
+            .text_box
+            .selected_lines()
+            .filter_map(|line_idx| Some((line_idx, self.text_box.get_raw_line(line_idx)?)))
+            .filter(|(_, line)| !line.trim().is_empty())
+            .collect();
+        if lines.is_empty() {
+            return CommandOutcome::handled();
+        }
+
+        // where the comment prefix is inserted (or removed) on each line,
+        // and how many bytes are inserted (or removed)
+        let uncomment = lines
+            .iter()
+            .all(|(_, line)| line.trim_start().starts_with(prefix));
+        let changes: Vec<(TextBufferPos, usize)> = lines
+            .iter()
+            .map(|(line_idx, line)| {
+                if uncomment {
+                    let comment = line.trim_start();
+                    let len = if comment.starts_with(&format!("{prefix} ")) {
+                        prefix.len().saturating_add(1)
+                    } else {
+                        prefix.len()
+                    };
+                    let byte = line.len().saturating_sub(comment.len());
+                    (
+                        TextBufferPos {
+                            line: *line_idx,
+                            byte,
+                        },
+                        len,
+                    )
+                } else {
+                    let pos = TextBufferPos {
+                        line: *line_idx,
+                        byte: 0,
+                    };
+                    (pos, prefix.len().saturating_add(1))
+                }
+            })
+            .collect();
+        let edits = changes
+            .iter()
+            .map(|&(pos, len)| {
+                if uncomment {
+                    Edit::Remove {
+                        start: pos,
+                        end: TextBufferPos {
+                            line: pos.line,
+                            byte: pos.byte.saturating_add(len),
+                        },
+                    }
+                } else {
+                    Edit::Insert {
+                        pos,
+                        text: format!("{prefix} "),
+                    }
+                }
+            })
+            .collect();
+        if self.text_box.apply_edits(edits).is_err() {
+            return CommandOutcome::not_handled();
+        }
+
+        // the caret (and the selection) stay on the same text of their lines
+        let shift = |pos: TextBufferPos| match changes.iter().find(|(at, _)| at.line == pos.line) {
+            Some(&(at, len)) if pos.byte >= at.byte => TextBufferPos {
+                line: pos.line,
+                byte: if uncomment {
+                    pos.byte.saturating_sub(len).max(at.byte)
+                } else {
+                    pos.byte.saturating_add(len)
+                },
+            },
+            _ => pos,
+        };
+        match selection {
+            Some(range) => {
+                let anchor = if range.start == caret {
+                    range.end
+                } else {
+                    range.start
+                };
+                self.text_box
+                    .set_selection_buffer_range(shift(anchor), shift(caret));
             }
-            EditorCommand::QuitAll | EditorCommand::Dismiss => false,
+            None => self.text_box.move_cursor_to_buffer_pos(shift(caret)),
+        }
+
+        CommandOutcome::buffer_changed()
+    }
+
+    fn toggle_fold(&mut self) -> CommandOutcome {
+        let line_idx = self.text_box.caret_pos().y.to_usize();
+        if self.text_box.unfold_at(line_idx) {
+            return CommandOutcome::handled();
+        }
+
+        match self.find_closing_brace_line(line_idx) {
+            Some(closing_line_idx) => {
+                self.text_box
+                    .fold_lines(line_idx.saturating_add(1)..closing_line_idx.saturating_add(1));
+                CommandOutcome::handled()
+            }
+            None => CommandOutcome {
+                message: Some("Nothing to fold".to_string()),
+                ..CommandOutcome::handled()
+            },
+        }
+    }
+
+    /// Returns where the caret, scroll and folds are, to be restored
+    /// with `Self::restore_session` when the file is opened again.
+    pub(crate) fn get_session(&self) -> FileSession {
+        FileSession {
+            caret: self.text_box.caret_buffer_pos(),
+            scroll_offset: self.text_box.scroll_offset(),
+            folds: self.text_box.folded_lines(),
+        }
+    }
+
+    /// Restore the caret, scroll and folds from `session`. As the file could
+    /// have changed since, folds that no longer hide a block are dropped.
+    pub(crate) fn restore_session(&mut self, session: &FileSession) {
+        for fold in &session.folds {
+            let header_line_idx = fold.start.checked_sub(1);
+            let closing_line_idx = fold.end.checked_sub(1);
+            if header_line_idx.is_some()
+                && closing_line_idx.is_some()
+                && header_line_idx.and_then(|line_idx| self.find_closing_brace_line(line_idx))
+                    == closing_line_idx
+            {
+                self.text_box.fold_lines(fold.clone());
+            }
+        }
+
+        self.text_box.move_cursor_to_buffer_pos(session.caret);
+        self.text_box.scroll_to(session.scroll_offset);
+    }
+
+    pub(crate) fn get_entire_contents_as_string(&self) -> String {
+        self.text_box.get_entire_contents_as_string()
+    }
+
+    pub(crate) fn set_contents(&mut self, contents: &str) {
+        self.text_box.set_contents(contents);
+        self.indent_style = self
+            .text_box
+            .detect_indentation()
+            .unwrap_or(self.indent_style);
+        if self.highlight_info.whitespace_errors().is_some() {
+            self.highlight_info
+                .set_whitespace_errors(&self.text_box, Some(self.indent_style));
+        }
+        self.edits_since_save = self.edits_since_save.saturating_add(1);
+        // the lines jumped from are gone, as are the annotated lines
+        self.jump_list = JumpList::default();
+        self.set_annotations(vec![]);
+    }
+
+    pub(crate) fn caret_buffer_pos(&self) -> TextBufferPos {
+        self.text_box.caret_buffer_pos()
+    }
+
+    pub(crate) fn move_cursor_to_buffer_pos(&mut self, pos: TextBufferPos) {
+        self.text_box.move_cursor_to_buffer_pos(pos);
+    }
+
+    pub(crate) fn selection_buffer_range(&self) -> Option<Range<TextBufferPos>> {
+        self.text_box.selection_buffer_range()
+    }
+
+    pub(crate) fn set_selection_buffer_range(
+        &mut self,
+        anchor: TextBufferPos,
+        caret: TextBufferPos,
+    ) {
+        self.text_box.set_selection_buffer_range(anchor, caret);
+    }
+
+    /// Remove the selection, returning what was selected
+    /// (or `None` if nothing is selected).
+    pub(crate) fn cut_selection(&mut self) -> Option<String> {
+        let cut = self.text_box.cut_selection()?;
+        self.on_edited();
+        Some(cut)
+    }
+
+    /// Insert `text` at the caret, replacing the selection if there
+    /// is one. Returns whether it was inserted.
+    pub(crate) fn paste(&mut self, text: &str) -> bool {
+        if self.text_box.paste_at_cursor(text).is_err() {
+            return false;
         }
+        self.on_edited();
+        true
+    }
+
+    /// Insert `text` at the caret, returning where it was inserted.
+    pub(crate) fn insert_at_caret(&mut self, text: &str) -> Option<Range<TextBufferPos>> {
+        let range = self.text_box.insert_str_at_cursor(text).ok()?;
+        self.on_edited();
+        Some(range)
+    }
+
+    /// Replace the text in `range` with `text`, moving the caret to
+    /// the end of it. Returns where the text was inserted.
+    pub(crate) fn replace_range(
+        &mut self,
+        range: Range<TextBufferPos>,
+        text: &str,
+    ) -> Option<Range<TextBufferPos>> {
+        self.text_box
+            .set_selection_buffer_range(range.start, range.end);
+        self.text_box.replace_selection(text)?;
+        // the inserted text is selected, with the caret at its end
+        let end = self.text_box.caret_buffer_pos();
+        self.text_box.move_cursor_to_buffer_pos(end);
+        self.on_edited();
+        Some(range.start..end)
+    }
+
+    /// Apply `edits` as a single transaction, see `TextBox::apply_edits`.
+    pub(crate) fn apply_edits(&mut self, edits: Vec<Edit>) -> Result<Vec<Edit>, EditError> {
+        let revert_edits = self.text_box.apply_edits(edits)?;
+        self.on_edited();
+        Ok(revert_edits)
+    }
+
+    /// Regenerate the highlights after the contents of the buffer
+    /// have been changed.
+    pub(crate) fn on_buffer_changed(&mut self) {
+        // for the changes that are not made by a command, e.g. a yank
+        self.text_box.end_undo_group();
+        // the word being completed could have changed
+        self.completion = None;
+        self.track_line_shifts();
+        let changed_lines = self.text_box.take_changed_lines().unwrap_or_default();
+        self.highlight_info
+            .regenerate_on_lines_change(&self.text_box, changed_lines);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{annotations::Severity, session::SessionStore};
+
+    #[test]
+    fn test_highlight_word_under_caret() {
+        let mut view = CodeView::new();
+        view.text_box
+            .set_contents("let foo = 1;\nfoo + foo_bar + foo");
+        view.set_bounds(Bounds2f {
+            pos: Vec2f::ZERO,
+            size: Vec2f { x: 40.0, y: 5.0 },
+        });
+
+        let highlighted_ranges = |view: &CodeView, line_idx| {
+            view.highlight_info
+                .text_highlight()
+                .lines
+                .get(&line_idx)
+                .map(|line| {
+                    line.blocks
+                        .iter()
+                        .filter(|block| block.color.background.is_some())
+                        .map(|block| block.range.clone())
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        };
+
+        // "let" has no other occurrences
+        let now = Instant::now();
+        assert!(!view.tick(now));
+        assert!(view.tick(now + WORD_UNDER_CARET_DELAY));
+        assert!(highlighted_ranges(&view, 0).is_empty());
+
+        // move onto "foo", which is only highlighted after the delay
+        (0..4).for_each(|_| {
+            view.execute_command(EditorCommand::MoveCursorRight, &mut CommandBar::new());
+        });
+        assert!(!view.tick(now));
+        assert!(highlighted_ranges(&view, 1).is_empty());
+        assert!(view.tick(now + WORD_UNDER_CARET_DELAY));
+        assert!(highlighted_ranges(&view, 0).is_empty());
+        assert_eq!(highlighted_ranges(&view, 1), vec![0..3, 16..19]);
+        assert!(!view.tick(now + WORD_UNDER_CARET_DELAY));
+
+        // caret at the end of an occurrence also counts
+        view.execute_command(EditorCommand::MoveCursorToBottom, &mut CommandBar::new());
+        assert!(!view.tick(now));
+        assert!(view.tick(now + WORD_UNDER_CARET_DELAY));
+        assert_eq!(highlighted_ranges(&view, 0), vec![4..7]);
+        assert_eq!(highlighted_ranges(&view, 1), vec![0..3]);
+    }
+
+    #[test]
+    fn test_jump_to_matching_bracket() {
+        let mut view = CodeView::new();
+        view.text_box.set_contents("fn main() {\n    f(a);\n}\n(");
+        let mut command_bar = CommandBar::new();
+
+        view.move_cursor_to_buffer_pos(TextBufferPos { line: 0, byte: 10 });
+        assert!(view.tick(Instant::now()));
+        assert_eq!(
+            view.highlight_info.matching_brackets(),
+            Some((
+                TextBufferPos { line: 0, byte: 10 },
+                TextBufferPos { line: 2, byte: 0 }
+            ))
+        );
+
+        view.execute_command(EditorCommand::JumpToMatchingBracket, &mut command_bar);
+        assert_eq!(view.caret_buffer_pos(), TextBufferPos { line: 2, byte: 0 });
+        view.execute_command(EditorCommand::JumpBack, &mut command_bar);
+        assert_eq!(view.caret_buffer_pos(), TextBufferPos { line: 0, byte: 10 });
+
+        // an unmatched bracket has nothing to jump to or highlight
+        view.move_cursor_to_buffer_pos(TextBufferPos { line: 3, byte: 0 });
+        view.execute_command(EditorCommand::JumpToMatchingBracket, &mut command_bar);
+        assert_eq!(view.caret_buffer_pos(), TextBufferPos { line: 3, byte: 0 });
+        assert!(view.tick(Instant::now()));
+        assert_eq!(view.highlight_info.matching_brackets(), None);
+    }
+
+    #[test]
+    fn test_move_cursor_to_start_of_line() {
+        let mut view = CodeView::new();
+        view.text_box.set_contents("    let x;\n  \n");
+        let mut command_bar = CommandBar::new();
+        let mut move_to_start_of_line = |view: &mut CodeView| {
+            view.execute_command(EditorCommand::MoveCursorToStartOfLine, &mut command_bar);
+            view.caret_buffer_pos().byte
+        };
+
+        // toggles between the first non-whitespace character and the start
+        view.move_cursor_to_buffer_pos(TextBufferPos { line: 0, byte: 8 });
+        assert_eq!(move_to_start_of_line(&mut view), 4);
+        assert_eq!(move_to_start_of_line(&mut view), 0);
+        assert_eq!(move_to_start_of_line(&mut view), 4);
+
+        // from within the indentation
+        view.move_cursor_to_buffer_pos(TextBufferPos { line: 0, byte: 2 });
+        assert_eq!(move_to_start_of_line(&mut view), 4);
+
+        // a line of only whitespace toggles between its end and start
+        view.move_cursor_to_buffer_pos(TextBufferPos { line: 1, byte: 1 });
+        assert_eq!(move_to_start_of_line(&mut view), 2);
+        assert_eq!(move_to_start_of_line(&mut view), 0);
+
+        // an empty line stays at its start
+        view.move_cursor_to_buffer_pos(TextBufferPos { line: 2, byte: 0 });
+        assert_eq!(move_to_start_of_line(&mut view), 0);
+    }
+
+    #[test]
+    fn test_jump_to_word_occurrence() {
+        let mut view = CodeView::new();
+        view.text_box
+            .set_contents("let cat = 1;\nlet category = cat + 1;");
+        let mut command_bar = CommandBar::new();
+        let mut jump = |view: &mut CodeView, command: EditorCommand| {
+            let outcome = view.execute_command(command, &mut command_bar);
+            (view.caret_buffer_pos(), outcome.message)
+        };
+
+        // the occurrences are whole words, not `category`
+        view.move_cursor_to_buffer_pos(TextBufferPos { line: 0, byte: 5 });
+        assert_eq!(
+            jump(&mut view, EditorCommand::JumpToNextWordOccurrence),
+            (
+                TextBufferPos { line: 1, byte: 15 },
+                Some("Match 2 of 2".to_string())
+            )
+        );
+        assert_eq!(
+            view.selection_buffer_range(),
+            Some(TextBufferPos { line: 1, byte: 15 }..TextBufferPos { line: 1, byte: 18 })
+        );
+        assert_eq!(
+            jump(&mut view, EditorCommand::JumpToNextWordOccurrence),
+            (
+                TextBufferPos { line: 0, byte: 4 },
+                Some("Match 1 of 2 (search wrapped)".to_string())
+            )
+        );
+        assert_eq!(
+            jump(&mut view, EditorCommand::JumpToPreviousWordOccurrence),
+            (
+                TextBufferPos { line: 1, byte: 15 },
+                Some("Match 2 of 2 (search wrapped)".to_string())
+            )
+        );
+
+        // and can be jumped back from
+        assert_eq!(
+            jump(&mut view, EditorCommand::JumpBack).0,
+            TextBufferPos { line: 0, byte: 4 }
+        );
+
+        view.move_cursor_to_buffer_pos(TextBufferPos { line: 0, byte: 8 });
+        assert_eq!(
+            jump(&mut view, EditorCommand::JumpToNextWordOccurrence),
+            (
+                TextBufferPos { line: 0, byte: 8 },
+                Some("No word under caret".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn test_new_from_reader() {
+        let view = CodeView::new_from_reader("hello\nworld".as_bytes()).expect("valid utf-8");
+        assert_eq!(
+            view.text_box.get_entire_contents_as_string(),
+            "hello\nworld"
+        );
+        assert_eq!(view.get_status().filename, None);
+        assert!(view.get_status().is_dirty);
+
+        assert!(CodeView::new_from_reader([0xff, 0xfe].as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_command_outcome() {
+        let mut view = CodeView::new();
+        view.text_box.set_contents("ab\ncd");
+        view.set_bounds(Bounds2f {
+            pos: Vec2f::ZERO,
+            size: Vec2f { x: 40.0, y: 5.0 },
+        });
+        let mut command_bar = CommandBar::new();
+        let mut execute =
+            |view: &mut CodeView, command| view.execute_command(command, &mut command_bar);
+
+        assert_eq!(
+            execute(&mut view, EditorCommand::Noop),
+            CommandOutcome::handled()
+        );
+        assert_eq!(
+            execute(&mut view, EditorCommand::MoveCursorLeft),
+            CommandOutcome::handled()
+        );
+        assert_eq!(
+            execute(&mut view, EditorCommand::MoveCursorRight),
+            CommandOutcome::handled()
+        );
+        assert_eq!(
+            execute(&mut view, EditorCommand::MoveCursorDown),
+            CommandOutcome::handled()
+        );
+        assert_eq!(
+            execute(&mut view, EditorCommand::InsertCharacter('x')),
+            CommandOutcome::buffer_changed()
+        );
+        assert_eq!(
+            execute(&mut view, EditorCommand::EraseCharacterBeforeCursor),
+            CommandOutcome::buffer_changed()
+        );
+        assert_eq!(
+            execute(&mut view, EditorCommand::EraseCharacterAfterCursor),
+            CommandOutcome::buffer_changed()
+        );
+        assert_eq!(
+            execute(&mut view, EditorCommand::InsertNewline),
+            CommandOutcome::buffer_changed()
+        );
+        assert_eq!(
+            execute(&mut view, EditorCommand::ToggleOverwriteMode),
+            CommandOutcome::handled()
+        );
+        assert_eq!(
+            execute(&mut view, EditorCommand::MoveCursorToTop),
+            CommandOutcome::handled()
+        );
+        // nothing before the start of the buffer to erase
+        assert_eq!(
+            execute(&mut view, EditorCommand::EraseCharacterBeforeCursor),
+            CommandOutcome::not_handled()
+        );
+        assert_eq!(
+            execute(&mut view, EditorCommand::Copy),
+            CommandOutcome::not_handled()
+        );
+        assert_eq!(view.text_box.get_entire_contents_as_string(), "ab\nc\n");
+
+        // untitled buffers prompt for a filename instead of showing a message
+        assert_eq!(
+            execute(&mut view, EditorCommand::WriteBufferToDisk),
+            CommandOutcome::handled()
+        );
+    }
+
+    #[test]
+    fn test_toggle_fold() {
+        let contents = "fn main() {\n    let a = 1;\n    if a > 0 {\n        a;\n    }\n}\nlast";
+        let mut view = CodeView::new();
+        view.text_box.set_contents(contents);
+        view.text_box.set_is_dirty(false);
+        view.set_bounds(Bounds2f {
+            pos: Vec2f::ZERO,
+            size: Vec2f { x: 20.0, y: 4.0 },
+        });
+        let mut command_bar = CommandBar::new();
+        let mut execute =
+            |view: &mut CodeView, command| view.execute_command(command, &mut command_bar);
+
+        // the whole function is folded into its first line
+        execute(&mut view, EditorCommand::MoveCursorToEndOfLine);
+        execute(&mut view, EditorCommand::ToggleFold);
+        let mut drawer = Drawer::new();
+        view.render(&mut drawer);
+        let screen = drawer.snapshot(20, 4);
+        assert_eq!(screen[0].trim_end(), "fn main() { … }");
+        assert_eq!(screen[1].trim_end(), "last");
+
+        // moving down skips the hidden lines, and moving up comes back
+        execute(&mut view, EditorCommand::MoveCursorDown);
+        assert_eq!(view.text_box.caret_pos(), Vec2u { x: 4, y: 6 });
+        execute(&mut view, EditorCommand::MoveCursorLeft);
+        execute(&mut view, EditorCommand::MoveCursorUp);
+        assert_eq!(view.text_box.caret_pos(), Vec2u { x: 3, y: 0 });
+        execute(&mut view, EditorCommand::MoveCursorToEndOfLine);
+        execute(&mut view, EditorCommand::MoveCursorRight);
+        assert_eq!(view.text_box.caret_pos(), Vec2u { x: 0, y: 6 });
+
+        // the hidden lines are still part of the contents
+        assert_eq!(view.get_entire_contents_as_string(), contents);
+
+        // toggling again unfolds the function
+        execute(&mut view, EditorCommand::MoveCursorUp);
+        execute(&mut view, EditorCommand::ToggleFold);
+        let mut drawer = Drawer::new();
+        view.render(&mut drawer);
+        let screen = drawer.snapshot(20, 4);
+        assert_eq!(screen[0].trim_end(), "fn main() {");
+        assert_eq!(screen[1].trim_end(), "    let a = 1;");
+
+        // lines without a block spanning multiple lines cannot be folded
+        execute(&mut view, EditorCommand::MoveCursorDown);
+        assert_eq!(
+            execute(&mut view, EditorCommand::ToggleFold)
+                .message
+                .as_deref(),
+            Some("Nothing to fold")
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_format_on_save() {
+        let path = std::env::temp_dir().join("knap_test_format_on_save.txt");
+        let filename = path.to_str().expect("valid path");
+        std::fs::write(&path, "hello\nworld\n").expect("able to write test file");
+        let mut view = CodeView::new_from_file(filename).expect("able to open test file");
+        let mut command_bar = CommandBar::new();
+        view.execute_command(EditorCommand::MoveCursorDown, &mut command_bar);
+
+        // a stand-in for a formatter, which prints what it reads in uppercase
+        view.set_format_commands(HashMap::from([(
+            FileType::PlainText,
+            "tr a-z A-Z".to_string(),
+        )]));
+        let outcome = view.execute_command(EditorCommand::WriteBufferToDisk, &mut command_bar);
+        assert_eq!(outcome.message.as_deref(), Some("File saved successfully"));
+        assert_eq!(view.get_entire_contents_as_string(), "HELLO\nWORLD");
+        assert_eq!(view.text_box.caret_pos(), Vec2u { x: 0, y: 1 });
+        assert_eq!(
+            std::fs::read_to_string(&path).expect("able to read test file"),
+            "HELLO\nWORLD\n"
+        );
+        assert!(!view.get_status().is_dirty);
+
+        // the formatting is undone like any other change
+        view.execute_command(EditorCommand::Undo, &mut command_bar);
+        assert_eq!(view.get_entire_contents_as_string(), "hello\nworld");
+        assert!(view.get_status().is_dirty);
+        view.execute_command(EditorCommand::Redo, &mut command_bar);
+        assert_eq!(view.get_entire_contents_as_string(), "HELLO\nWORLD");
+
+        // a failing formatter leaves the buffer as it is
+        view.text_box.set_contents("unformatted");
+        view.set_format_commands(HashMap::from([(FileType::PlainText, "false".to_string())]));
+        let outcome = view.execute_command(EditorCommand::WriteBufferToDisk, &mut command_bar);
+        assert!(
+            outcome
+                .message
+                .is_some_and(|message| message.starts_with("File saved without formatting"))
+        );
+        assert_eq!(
+            std::fs::read_to_string(&path).expect("able to read test file"),
+            "unformatted\n"
+        );
+
+        std::fs::remove_file(&path).expect("able to remove test file");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_save_read_only() {
+        let path = std::env::temp_dir().join("knap_test_save_read_only.txt");
+        let filename = path.to_str().expect("valid path");
+        std::fs::write(&path, "hello  \nworld\n").expect("able to write test file");
+        let mut view = CodeView::new_from_file(filename).expect("able to open test file");
+        let mut command_bar = CommandBar::new();
+        view.set_format_commands(HashMap::from([(
+            FileType::PlainText,
+            "tr a-z A-Z".to_string(),
+        )]));
+        view.set_trim_trailing_whitespace_on_save(true);
+        view.set_read_only(true);
+
+        // neither formatted nor trimmed
+        let outcome = view.execute_command(EditorCommand::WriteBufferToDisk, &mut command_bar);
+        assert_eq!(outcome.message.as_deref(), Some("File saved successfully"));
+        assert_eq!(view.get_entire_contents_as_string(), "hello  \nworld");
+        assert_eq!(
+            std::fs::read_to_string(&path).expect("able to read test file"),
+            "hello  \nworld\n"
+        );
+
+        std::fs::remove_file(&path).expect("able to remove test file");
+    }
+
+    #[test]
+    fn test_reload_git_branch() {
+        let repo_dir = std::env::temp_dir().join("knap_test_reload_git_branch");
+        let git_dir = repo_dir.join(".git");
+        std::fs::create_dir_all(&git_dir).expect("able to create test directory");
+        std::fs::write(git_dir.join("HEAD"), "ref: refs/heads/main\n")
+            .expect("able to write test file");
+        let path = repo_dir.join("test.txt");
+        std::fs::write(&path, "hello").expect("able to write test file");
+        let mut view = CodeView::new_from_file(path.to_str().expect("valid path"))
+            .expect("able to open test file");
+        assert_eq!(view.get_status().git_branch.as_deref(), Some("main"));
+
+        // the branch is looked up again even if the contents are the same
+        std::fs::write(git_dir.join("HEAD"), "ref: refs/heads/feature\n")
+            .expect("able to write test file");
+        assert!(!view.reload_from_disk().expect("able to reload test file"));
+        assert_eq!(view.get_status().git_branch.as_deref(), Some("feature"));
+
+        std::fs::remove_dir_all(&repo_dir).expect("able to remove test directory");
+    }
+
+    #[test]
+    fn test_new_file() {
+        let path = std::env::temp_dir().join("knap_test_new_file.txt");
+        let filename = path.to_str().expect("valid path");
+        std::fs::remove_file(&path).ok();
+        let mut view = CodeView::new_from_file(filename).expect("able to open new file");
+        let mut command_bar = CommandBar::new();
+        assert!(view.is_new_file());
+        assert_eq!(view.filename(), Some(filename));
+        assert_eq!(view.get_entire_contents_as_string(), "");
+        assert!(!view.get_status().is_dirty);
+
+        // the file is created when it is first saved
+        view.execute_command(EditorCommand::InsertCharacter('a'), &mut command_bar);
+        view.execute_command(EditorCommand::WriteBufferToDisk, &mut command_bar);
+        assert!(!view.is_new_file());
+        assert_eq!(
+            std::fs::read_to_string(&path).expect("able to read test file"),
+            "a\n"
+        );
+
+        // but other errors are still reported
+        assert!(
+            CodeView::new_from_file(std::env::temp_dir().to_str().expect("valid path")).is_err()
+        );
+
+        std::fs::remove_file(&path).expect("able to remove test file");
+    }
+
+    #[test]
+    fn test_trim_trailing_whitespace_on_save() {
+        let path = std::env::temp_dir().join("knap_test_trim_trailing_whitespace_on_save.txt");
+        let filename = path.to_str().expect("valid path");
+        std::fs::write(&path, "hello  \n\tworld\t\n \nfoo  bar").expect("able to write test file");
+        let mut view = CodeView::new_from_file(filename).expect("able to open test file");
+        let mut command_bar = CommandBar::new();
+
+        // left as it is by default
+        view.execute_command(EditorCommand::WriteBufferToDisk, &mut command_bar);
+        assert_eq!(
+            std::fs::read_to_string(&path).expect("able to read test file"),
+            "hello  \n\tworld\t\n \nfoo  bar"
+        );
+
+        // the caret is moved out of the whitespace that is removed
+        view.set_trim_trailing_whitespace_on_save(true);
+        view.execute_command(EditorCommand::MoveCursorToEndOfLine, &mut command_bar);
+        assert_eq!(view.text_box.caret_pos(), Vec2u { x: 7, y: 0 });
+        let outcome = view.execute_command(EditorCommand::WriteBufferToDisk, &mut command_bar);
+        assert_eq!(outcome.message.as_deref(), Some("File saved successfully"));
+        assert_eq!(
+            std::fs::read_to_string(&path).expect("able to read test file"),
+            "hello\n\tworld\n\nfoo  bar"
+        );
+        assert_eq!(
+            view.get_entire_contents_as_string(),
+            "hello\n\tworld\n\nfoo  bar"
+        );
+        assert_eq!(view.text_box.caret_pos(), Vec2u { x: 5, y: 0 });
+        assert!(!view.get_status().is_dirty);
+
+        // as one change
+        view.execute_command(EditorCommand::Undo, &mut command_bar);
+        assert_eq!(
+            view.get_entire_contents_as_string(),
+            "hello  \n\tworld\t\n \nfoo  bar"
+        );
+
+        std::fs::remove_file(&path).expect("able to remove test file");
+    }
+
+    #[test]
+    fn test_save_trailing_newline() {
+        let path = std::env::temp_dir().join("knap_test_save_trailing_newline.txt");
+        let filename = path.to_str().expect("valid path");
+        let mut command_bar = CommandBar::new();
+
+        for file_contents in ["no newline\nat the end", "newline\n", "blank line\n\n", ""] {
+            std::fs::write(&path, file_contents).expect("able to write test file");
+            let mut view = CodeView::new_from_file(filename).expect("able to open test file");
+            assert_eq!(
+                view.get_entire_contents_as_string(),
+                file_contents.strip_suffix('\n').unwrap_or(file_contents)
+            );
+            view.execute_command(EditorCommand::WriteBufferToDisk, &mut command_bar);
+            assert_eq!(
+                std::fs::read_to_string(&path).expect("able to read test file"),
+                file_contents
+            );
+        }
+
+        // the empty file has no lines to end with a newline, until there is text
+        let mut view = CodeView::new_from_file(filename).expect("able to open test file");
+        assert_eq!(view.get_status().total_lines, 1);
+        view.execute_command(EditorCommand::InsertCharacter('a'), &mut command_bar);
+        view.execute_command(EditorCommand::WriteBufferToDisk, &mut command_bar);
+        assert_eq!(
+            std::fs::read_to_string(&path).expect("able to read test file"),
+            "a\n"
+        );
+
+        std::fs::remove_file(&path).expect("able to remove test file");
+    }
+
+    #[test]
+    fn test_insert_file_name() {
+        let path = std::env::temp_dir().join("knap_test_insert_file_name.txt");
+        let filename = path.to_str().expect("valid path");
+        std::fs::write(&path, "file: ").expect("able to write test file");
+        let mut view = CodeView::new_from_file(filename).expect("able to open test file");
+        let mut command_bar = CommandBar::new();
+        view.execute_command(EditorCommand::MoveCursorToEndOfLine, &mut command_bar);
+
+        let outcome = view.execute_command(EditorCommand::InsertFileName, &mut command_bar);
+        assert!(outcome.buffer_changed);
+        assert_eq!(
+            view.get_entire_contents_as_string(),
+            format!("file: {filename}")
+        );
+        assert_eq!(
+            view.caret_buffer_pos(),
+            TextBufferPos {
+                line: 0,
+                byte: "file: ".len() + filename.len()
+            }
+        );
+
+        // an untitled buffer has no filename to insert
+        let mut view = CodeView::new();
+        view.execute_command(EditorCommand::InsertFileName, &mut command_bar);
+        assert_eq!(view.get_entire_contents_as_string(), NO_NAME);
+
+        std::fs::remove_file(&path).expect("able to remove test file");
+    }
+
+    #[test]
+    fn test_insert_date_time() {
+        let mut view = CodeView::new();
+        let mut command_bar = CommandBar::new();
+
+        view.set_date_time_format("[%Y]");
+        let outcome = view.execute_command(EditorCommand::InsertDateTime, &mut command_bar);
+        assert!(outcome.buffer_changed);
+        let contents = view.get_entire_contents_as_string();
+        assert_eq!(contents.len(), "[YYYY]".len());
+        assert!(contents.starts_with("[2") && contents.ends_with(']'));
+
+        view.set_date_time_format("%Y-%Q");
+        let outcome = view.execute_command(EditorCommand::InsertDateTime, &mut command_bar);
+        assert!(!outcome.buffer_changed);
+        assert_eq!(
+            outcome.message.as_deref(),
+            Some("Invalid date format: unknown specifier %Q")
+        );
+        assert_eq!(view.get_entire_contents_as_string(), contents);
+    }
+
+    #[test]
+    fn test_detected_indentation() {
+        let mut command_bar = CommandBar::new();
+        let mut open_and_indent = |name: &str, contents: &str| {
+            let path = std::env::temp_dir().join(name);
+            std::fs::write(&path, contents).expect("able to write test file");
+            let mut view = CodeView::new_from_file(path.to_str().expect("valid path"))
+                .expect("able to open test file");
+            std::fs::remove_file(&path).expect("able to remove test file");
+
+            view.execute_command(EditorCommand::MoveCursorToBottom, &mut command_bar);
+            view.execute_command(EditorCommand::InsertIndent, &mut command_bar);
+            view.execute_command(EditorCommand::InsertCharacter('x'), &mut command_bar);
+            view
+        };
+
+        let view = open_and_indent(
+            "knap_test_detected_indentation_tabs.txt",
+            "fn main() {\n\tlet a = 1;\n\tif a {\n\t\tb();\n\t}\n\n",
+        );
+        assert_eq!(view.get_status().indent_style, IndentStyle::Tabs);
+        assert!(view.get_entire_contents_as_string().ends_with("\t}\n\tx"));
+
+        let view = open_and_indent(
+            "knap_test_detected_indentation_spaces.txt",
+            "a:\n  b:\n    c: 1\n  d: 2\n\n",
+        );
+        assert_eq!(view.get_status().indent_style, IndentStyle::Spaces(2));
+        assert!(
+            view.get_entire_contents_as_string()
+                .ends_with("  d: 2\n  x")
+        );
+
+        // without indentation to go by, tabs are used
+        let mut view = CodeView::new();
+        view.execute_command(EditorCommand::InsertIndent, &mut command_bar);
+        assert_eq!(view.get_entire_contents_as_string(), "\t");
+    }
+
+    #[test]
+    fn test_indent_selected_lines() {
+        let mut view = CodeView::new();
+        view.set_contents("a:\nb: 1\nc: 2\n");
+        view.indent_style = IndentStyle::Spaces(2);
+        let mut command_bar = CommandBar::new();
+
+        // with a selection, Tab indents the selected lines
+        view.set_selection_buffer_range(
+            TextBufferPos { line: 1, byte: 1 },
+            TextBufferPos { line: 2, byte: 2 },
+        );
+        view.execute_command(EditorCommand::InsertIndent, &mut command_bar);
+        assert_eq!(view.get_entire_contents_as_string(), "a:\n  b: 1\n  c: 2\n");
+        view.execute_command(EditorCommand::InsertIndent, &mut command_bar);
+        assert_eq!(
+            view.get_entire_contents_as_string(),
+            "a:\n    b: 1\n    c: 2\n"
+        );
+
+        view.execute_command(EditorCommand::DedentLines, &mut command_bar);
+        assert_eq!(view.get_entire_contents_as_string(), "a:\n  b: 1\n  c: 2\n");
+
+        // each indentation is undone at once
+        view.execute_command(EditorCommand::Undo, &mut command_bar);
+        view.execute_command(EditorCommand::Undo, &mut command_bar);
+        assert_eq!(view.get_entire_contents_as_string(), "a:\n  b: 1\n  c: 2\n");
+
+        // without a selection, Shift-Tab dedents the caret's line
+        view.text_box
+            .move_cursor_to_buffer_pos(TextBufferPos { line: 2, byte: 4 });
+        view.execute_command(EditorCommand::DedentLines, &mut command_bar);
+        assert_eq!(view.get_entire_contents_as_string(), "a:\n  b: 1\nc: 2\n");
+        assert_eq!(view.caret_buffer_pos(), TextBufferPos { line: 2, byte: 2 });
+
+        // Tab aligns to the next tab stop, and Backspace removes up to the
+        // previous one within the leading whitespace
+        view.text_box
+            .move_cursor_to_buffer_pos(TextBufferPos { line: 2, byte: 0 });
+        view.execute_command(EditorCommand::InsertCharacter(' '), &mut command_bar);
+        view.execute_command(EditorCommand::InsertIndent, &mut command_bar);
+        assert_eq!(view.get_entire_contents_as_string(), "a:\n  b: 1\n  c: 2\n");
+        view.execute_command(EditorCommand::InsertIndent, &mut command_bar);
+        view.execute_command(EditorCommand::EraseCharacterBeforeCursor, &mut command_bar);
+        assert_eq!(view.get_entire_contents_as_string(), "a:\n  b: 1\n  c: 2\n");
+        view.execute_command(EditorCommand::EraseCharacterBeforeCursor, &mut command_bar);
+        assert_eq!(view.get_entire_contents_as_string(), "a:\n  b: 1\nc: 2\n");
+    }
+
+    #[test]
+    fn test_restore_session() {
+        let tail = "x\n".repeat(20);
+        let bounds = Bounds2f {
+            pos: Vec2f::ZERO,
+            size: Vec2f { x: 20.0, y: 4.0 },
+        };
+
+        let mut view = CodeView::new();
+        view.text_box.set_contents(format!(
+            "fn a() {{\n    1;\n}}\nfn b() {{\n    2;\n}}\n{tail}"
+        ));
+        view.set_bounds(bounds);
+        view.text_box.fold_lines(1..3);
+        view.text_box.fold_lines(4..6);
+        view.text_box
+            .move_cursor_to_buffer_pos(TextBufferPos { line: 20, byte: 1 });
+        let session = view.get_session();
+        assert_eq!(session.folds, vec![1..3, 4..6]);
+        assert_eq!(session.caret, TextBufferPos { line: 20, byte: 1 });
+        assert_ne!(session.scroll_offset, Vec2u::ZERO);
+
+        let path = std::env::temp_dir().join("knap_test_restore_session.txt");
+        let mut session_store = SessionStore::load(&path).expect("able to load session file");
+        session_store.set("/a.rs", session.clone());
+        session_store.save().expect("able to save session file");
+        let session_store = SessionStore::load(&path).expect("able to load session file");
+        std::fs::remove_file(&path).expect("able to remove session file");
+        assert_eq!(session_store.get("/a.rs"), Some(&session));
+
+        // the second block has grown since, so its fold is dropped
+        let mut view = CodeView::new();
+        view.text_box.set_contents(format!(
+            "fn a() {{\n    1;\n}}\nfn b() {{\n    2;\n    3;\n}}\n{tail}"
+        ));
+        view.set_bounds(bounds);
+        view.restore_session(&session);
+        assert_eq!(view.text_box.folded_lines(), vec![1..3]);
+        assert_eq!(view.caret_buffer_pos(), session.caret);
+        assert_eq!(view.text_box.scroll_offset(), session.scroll_offset);
+    }
+
+    #[test]
+    fn test_completion() {
+        let mut view = CodeView::new();
+        view.text_box.set_contents("let print_line = println;\npri");
+        view.text_box.set_is_dirty(false);
+        view.set_bounds(Bounds2f {
+            pos: Vec2f::ZERO,
+            size: Vec2f { x: 30.0, y: 5.0 },
+        });
+        let mut command_bar = CommandBar::new();
+        let mut execute =
+            |view: &mut CodeView, command| view.execute_command(command, &mut command_bar);
+
+        execute(&mut view, EditorCommand::MoveCursorToBottom);
+        execute(&mut view, EditorCommand::TriggerCompletion);
+        let mut drawer = Drawer::new();
+        view.render(&mut drawer);
+        let screen = drawer.snapshot(30, 5);
+        assert_eq!(screen[2].trim_end(), " print_line");
+        assert_eq!(screen[3].trim_end(), " println");
+
+        // pressing it again selects the next one
+        execute(&mut view, EditorCommand::TriggerCompletion);
+        let outcome = execute(&mut view, EditorCommand::InsertNewline);
+        assert!(outcome.buffer_changed);
+        assert_eq!(
+            view.get_entire_contents_as_string(),
+            "let print_line = println;\nprintln"
+        );
+
+        // nothing to complete after a space
+        execute(&mut view, EditorCommand::InsertCharacter(' '));
+        let outcome = execute(&mut view, EditorCommand::TriggerCompletion);
+        assert_eq!(outcome.message.as_deref(), Some("Nothing to complete"));
+    }
+
+    #[test]
+    fn test_annotations() {
+        let mut view = CodeView::new();
+        view.text_box
+            .set_contents("fn main() {\n    let a = 1;\n    let long_name = 2;\n}");
+        view.text_box.set_is_dirty(false);
+        view.set_bounds(Bounds2f {
+            pos: Vec2f::ZERO,
+            size: Vec2f { x: 28.0, y: 5.0 },
+        });
+        view.set_annotations(vec![
+            (
+                1,
+                Annotation {
+                    message: "unused".to_string(),
+                    severity: Severity::Warning,
+                },
+            ),
+            (
+                2,
+                Annotation {
+                    message: "no room for this".to_string(),
+                    severity: Severity::Error,
+                },
+            ),
+        ]);
+        let render = |view: &CodeView| {
+            let mut drawer = Drawer::new();
+            view.render(&mut drawer);
+            drawer
+                .snapshot(28, 5)
+                .into_iter()
+                .map(|row| row.trim_end().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            render(&view),
+            vec![
+                "  fn main() {",
+                "●     let a = 1;  unused",
+                "●     let long_name = 2;  no",
+                "  }",
+                "  ~"
+            ]
+        );
+
+        // the annotations move down with their lines, and the
+        // lines changed since the last save are marked too
+        let mut command_bar = CommandBar::new();
+        view.execute_command(EditorCommand::InsertNewline, &mut command_bar);
+        assert_eq!(
+            render(&view),
+            vec![
+                " ▎",
+                " ▎fn main() {",
+                "●     let a = 1;  unused",
+                "●     let long_name = 2;  no",
+                "  }",
+            ]
+        );
+
+        // without either, there is no gutter
+        view.set_annotations(vec![]);
+        assert_eq!(render(&view)[1], " ▎fn main() {");
+        // as if saved
+        view.text_box.set_is_dirty(false);
+        view.update_gutter();
+        assert_eq!(render(&view)[1], "fn main() {");
+    }
+
+    #[test]
+    fn test_shift_lines_away_from_caret() {
+        let mut view = CodeView::new();
+        view.text_box.set_contents("a\nb\nc\nd\ne");
+        let mut command_bar = CommandBar::new();
+        view.jump_list.push(TextBufferPos { line: 3, byte: 0 });
+        view.move_cursor_to_buffer_pos(TextBufferPos { line: 4, byte: 0 });
+        view.set_annotations(vec![(
+            2,
+            Annotation {
+                message: "unused".to_string(),
+                severity: Severity::Warning,
+            },
+        )]);
+        let annotated_lines = |view: &CodeView| {
+            view.annotations
+                .get_lines(0..usize::MAX)
+                .into_iter()
+                .map(|(line_idx, _)| line_idx)
+                .collect::<Vec<_>>()
+        };
+
+        // lines added before the jumps and annotations, with the caret after them
+        view.apply_edits(vec![Edit::Insert {
+            pos: TextBufferPos { line: 0, byte: 0 },
+            text: "x\ny\n".to_string(),
+        }])
+        .expect("edits are valid");
+        assert_eq!(annotated_lines(&view), vec![4]);
+        view.execute_command(EditorCommand::JumpBack, &mut command_bar);
+        assert_eq!(view.caret_buffer_pos(), TextBufferPos { line: 5, byte: 0 });
+        view.execute_command(EditorCommand::JumpForward, &mut command_bar);
+
+        // undoing removes them again
+        view.execute_command(EditorCommand::Undo, &mut command_bar);
+        assert_eq!(annotated_lines(&view), vec![2]);
+        view.execute_command(EditorCommand::JumpBack, &mut command_bar);
+        assert_eq!(view.caret_buffer_pos(), TextBufferPos { line: 3, byte: 0 });
+    }
+
+    #[test]
+    fn test_toggle_comment() {
+        let mut view = CodeView::new();
+        view.set_contents("fn main() {\n    let a = 1;\n\n    // b();\n}");
+        view.file_type = FileType::Rust;
+        let mut command_bar = CommandBar::new();
+
+        view.execute_command(EditorCommand::MoveCursorDown, &mut command_bar);
+        view.execute_command(EditorCommand::MoveCursorToEndOfLine, &mut command_bar);
+        view.execute_command(EditorCommand::ToggleComment, &mut command_bar);
+        assert_eq!(
+            view.get_entire_contents_as_string(),
+            "fn main() {\n//     let a = 1;\n\n    // b();\n}"
+        );
+        assert_eq!(view.caret_buffer_pos(), TextBufferPos { line: 1, byte: 17 });
+
+        view.execute_command(EditorCommand::ToggleComment, &mut command_bar);
+        assert_eq!(
+            view.get_entire_contents_as_string(),
+            "fn main() {\n    let a = 1;\n\n    // b();\n}"
+        );
+        assert_eq!(view.caret_buffer_pos(), TextBufferPos { line: 1, byte: 14 });
+
+        // the selected lines are commented out, as one of them is not
+        view.set_selection_buffer_range(
+            TextBufferPos { line: 1, byte: 4 },
+            TextBufferPos { line: 4, byte: 0 },
+        );
+        view.execute_command(EditorCommand::ToggleComment, &mut command_bar);
+        assert_eq!(
+            view.get_entire_contents_as_string(),
+            "fn main() {\n//     let a = 1;\n\n//     // b();\n}"
+        );
+        assert_eq!(
+            view.selection_buffer_range(),
+            Some(TextBufferPos { line: 1, byte: 7 }..TextBufferPos { line: 4, byte: 0 })
+        );
+
+        view.execute_command(EditorCommand::Undo, &mut command_bar);
+        assert_eq!(
+            view.get_entire_contents_as_string(),
+            "fn main() {\n    let a = 1;\n\n    // b();\n}"
+        );
+
+        // nothing is commented out in plain text
+        view.file_type = FileType::PlainText;
+        view.execute_command(EditorCommand::ToggleComment, &mut command_bar);
+        assert_eq!(
+            view.get_entire_contents_as_string(),
+            "fn main() {\n    let a = 1;\n\n    // b();\n}"
+        );
     }
 }
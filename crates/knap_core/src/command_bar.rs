@@ -1,9 +1,10 @@
 use knap_base::math::{Bounds2f, Lossy, Vec2f};
 use knap_ui::{
     text_box::{TextBox, TextHighlights},
-    text_buffer::{SearchDirection, VecTextBuffer},
+    text_buffer::{FindResult, SearchDirection, VecTextBuffer},
 };
 use knap_window::drawer::Drawer;
+use regex::Regex;
 
 use crate::{code_view::CodeView, commands::EditorCommand, message_bar::MessageBar};
 
@@ -12,6 +13,18 @@ pub(crate) enum CommandBarPrompt {
     None,
     SaveAs,
     Search,
+    /// the regular expression to search for
+    RegexSearch,
+    /// the shell command to filter the selection through
+    FilterCommand,
+    /// whether to reload a modified buffer whose file was changed on disk
+    ReloadFile,
+    /// the (1-based) line number to move the caret to
+    GotoLine,
+    /// the character to record the caret position as
+    SetMark,
+    /// the character that the position to move the caret to was recorded as
+    JumpToMark,
 }
 
 impl CommandBarPrompt {
@@ -20,14 +33,84 @@ impl CommandBarPrompt {
             CommandBarPrompt::None => String::new(),
             CommandBarPrompt::SaveAs => "Save As: ".to_string(),
             CommandBarPrompt::Search => "Search (Esc to cancel, Arrows to navigate): ".to_string(),
+            CommandBarPrompt::RegexSearch => {
+                "Regex search (Esc to cancel, Arrows to navigate): ".to_string()
+            }
+            CommandBarPrompt::FilterCommand => "Filter through: ".to_string(),
+            CommandBarPrompt::ReloadFile => {
+                "File changed on disk, reload and lose changes? (y/n): ".to_string()
+            }
+            CommandBarPrompt::GotoLine => "Go to line: ".to_string(),
+            CommandBarPrompt::SetMark => "Set mark: ".to_string(),
+            CommandBarPrompt::JumpToMark => "Jump to mark: ".to_string(),
         }
     }
+
+    fn is_search(self) -> bool {
+        matches!(
+            self,
+            CommandBarPrompt::Search | CommandBarPrompt::RegexSearch
+        )
+    }
+}
+
+/// The reason that a regular expression is invalid, in a single line.
+fn describe_regex_error(err: &regex::Error) -> String {
+    match err {
+        // the syntax errors point at the problem in the pattern over
+        // multiple lines, with the reason on the last one
+        regex::Error::Syntax(message) => message
+            .lines()
+            .last()
+            .unwrap_or_default()
+            .trim_start_matches("error: ")
+            .to_string(),
+        _ => err.to_string(),
+    }
+}
+
+/// Describes which of the matches the caret is at, e.g. "Match 3 of 17", given
+/// the (1-based) ordinal of the match and the number of matches, after finding
+/// the next match (if it was `found`).
+pub(crate) fn get_search_matches_message(
+    found: Option<FindResult>,
+    (ordinal, count): (Option<usize>, usize),
+) -> String {
+    let message = match (found, ordinal, count) {
+        (_, _, 0) => "No matches".to_string(),
+        (None, _, _) => "No more matches".to_string(),
+        (Some(_), Some(ordinal), count) => format!("Match {ordinal} of {count}"),
+        // the caret is at a match that overlaps the counted ones
+        (Some(_), None, count) => format!("{count} matches"),
+    };
+    if found.is_some_and(|found| found.wrapped) {
+        format!("{message} (search wrapped)")
+    } else {
+        message
+    }
+}
+
+/// Show which of the matches of `search` the caret is at, see
+/// `get_search_matches_message`.
+fn show_search_matches(
+    view: &CodeView,
+    message_bar: &mut MessageBar,
+    search: &str,
+    found: Option<FindResult>,
+) {
+    message_bar.set_message(get_search_matches_message(
+        found,
+        view.get_search_match_ordinal(search),
+    ));
 }
 
 pub(crate) struct CommandBar {
     bounds: Bounds2f,
     prompt: CommandBarPrompt,
     text_box: TextBox<VecTextBuffer>,
+    /// the compiled regular expression of a regex search,
+    /// or `None` if what was entered is not a valid one
+    regex: Option<Regex>,
 }
 
 #[derive(Clone, PartialEq, Eq)]
@@ -42,6 +125,7 @@ impl CommandBar {
             bounds: Bounds2f::ZERO,
             prompt: CommandBarPrompt::None,
             text_box: TextBox::new_single_line_text_box(VecTextBuffer::new()),
+            regex: None,
         }
     }
 
@@ -53,6 +137,7 @@ impl CommandBar {
     pub(crate) fn clear_prompt(&mut self) {
         self.prompt = CommandBarPrompt::None;
         self.text_box = TextBox::new_single_line_text_box(VecTextBuffer::new());
+        self.regex = None;
     }
 
     pub(crate) fn set_prompt(&mut self, prompt: CommandBarPrompt) {
@@ -92,30 +177,64 @@ impl CommandBar {
         }
     }
 
-    fn on_input_updated(&self, view: &mut CodeView) {
-        if matches!(self.prompt, CommandBarPrompt::Search) {
-            view.find(
-                self.text_box.get_entire_contents_as_string(),
-                true,
-                SearchDirection::Forward,
-            );
+    /// Search again as the search is edited, always from where the caret was
+    /// before searching, so that the caret is at the match nearest to it.
+    fn on_input_updated(&mut self, view: &mut CodeView, message_bar: &mut MessageBar) {
+        let search = self.text_box.get_entire_contents_as_string();
+        if self.prompt.is_search() && search.is_empty() {
+            self.regex = None;
+            view.clear_search();
+            return;
         }
-    }
 
-    fn on_find_next(&self, view: &mut CodeView) {
-        view.find(
-            self.text_box.get_entire_contents_as_string(),
-            false,
-            SearchDirection::Forward,
-        );
+        match self.prompt {
+            CommandBarPrompt::Search => {
+                let found = view.find(&search, true, SearchDirection::Forward);
+                show_search_matches(view, message_bar, &search, found);
+            }
+            CommandBarPrompt::RegexSearch => match Regex::new(&search) {
+                Ok(regex) => {
+                    let found = view.find_regex(&regex, true, SearchDirection::Forward);
+                    if found.is_some_and(|found| found.wrapped) {
+                        message_bar.set_message("Search wrapped");
+                    }
+                    self.regex = Some(regex);
+                }
+                Err(err) => {
+                    self.regex = None;
+                    message_bar
+                        .set_message(format!("Invalid regex: {}", describe_regex_error(&err)));
+                }
+            },
+            _ => {}
+        }
     }
 
-    fn on_find_previous(&self, view: &mut CodeView) {
-        view.find(
-            self.text_box.get_entire_contents_as_string(),
-            false,
-            SearchDirection::Backward,
-        );
+    fn on_find(
+        &self,
+        view: &mut CodeView,
+        message_bar: &mut MessageBar,
+        search_direction: SearchDirection,
+    ) {
+        let found = match self.prompt {
+            CommandBarPrompt::Search => {
+                let search = self.text_box.get_entire_contents_as_string();
+                let found = view.find(&search, false, search_direction);
+                show_search_matches(view, message_bar, &search, found);
+                return;
+            }
+            CommandBarPrompt::RegexSearch => match &self.regex {
+                Some(regex) => view.find_regex(regex, false, search_direction),
+                // the reason was shown when the regex was entered
+                None => return,
+            },
+            _ => return,
+        };
+        match found {
+            Some(found) if found.wrapped => message_bar.set_message("Search wrapped"),
+            Some(_) => {}
+            None => message_bar.set_message("No more matches"),
+        }
     }
 
     // splitting the function up doesn't change the readability much
@@ -129,14 +248,61 @@ impl CommandBar {
         match command {
             EditorCommand::QuitAll
             | EditorCommand::WriteBufferToDisk
-            | EditorCommand::StartSearch => CommandBarExecuteResult {
+            | EditorCommand::StartSearch
+            | EditorCommand::StartRegexSearch
+            | EditorCommand::StartGotoLine
+            | EditorCommand::FilterThroughCommand
+            | EditorCommand::InsertDateTime
+            | EditorCommand::InsertFileName
+            | EditorCommand::TriggerCompletion
+            | EditorCommand::ToggleOverwriteMode
+            | EditorCommand::ToggleBlockSelection
+            | EditorCommand::RevealWhitespaceErrors
+            | EditorCommand::SelectAll
+            | EditorCommand::SelectLine
+            | EditorCommand::SelectWord
+            | EditorCommand::ToggleFold
+            | EditorCommand::JumpBack
+            | EditorCommand::JumpForward
+            | EditorCommand::JumpToMatchingBracket
+            | EditorCommand::CenterCaret
+            | EditorCommand::JumpToNextWordOccurrence
+            | EditorCommand::JumpToPreviousWordOccurrence
+            | EditorCommand::SetMark
+            | EditorCommand::JumpToMark
+            | EditorCommand::ExtendSelectionUp
+            | EditorCommand::ExtendSelectionDown
+            | EditorCommand::ExtendSelectionLeft
+            | EditorCommand::ExtendSelectionRight
+            | EditorCommand::Copy
+            | EditorCommand::Cut
+            | EditorCommand::Paste
+            | EditorCommand::DuplicateSelection
+            | EditorCommand::InsertIndent
+            | EditorCommand::DedentLines
+            | EditorCommand::MoveLineUp
+            | EditorCommand::MoveLineDown
+            | EditorCommand::JoinLines
+            | EditorCommand::ToggleComment
+            | EditorCommand::Undo
+            | EditorCommand::Redo
+            | EditorCommand::SwapSelectionWithClipboard
+            | EditorCommand::Yank
+            | EditorCommand::YankPop
+            | EditorCommand::ShowHelp
+            | EditorCommand::SplitVertical
+            | EditorCommand::SplitHorizontal
+            | EditorCommand::FocusNextPane
+            | EditorCommand::ClosePane
+            | EditorCommand::RepeatLastChange
+            | EditorCommand::StartRecordingMacro
+            | EditorCommand::StopRecordingMacro
+            | EditorCommand::PlayMacro(_) => CommandBarExecuteResult {
                 is_command_handled: false,
                 submitted_data: None,
             },
             EditorCommand::MoveCursorUp => {
-                if matches!(self.prompt, CommandBarPrompt::Search) {
-                    self.on_find_previous(view);
-                }
+                self.on_find(view, message_bar, SearchDirection::Backward);
 
                 CommandBarExecuteResult {
                     is_command_handled: true,
@@ -144,43 +310,55 @@ impl CommandBar {
                 }
             }
             EditorCommand::MoveCursorDown => {
-                if matches!(self.prompt, CommandBarPrompt::Search) {
-                    self.on_find_next(view);
-                }
+                self.on_find(view, message_bar, SearchDirection::Forward);
 
                 CommandBarExecuteResult {
                     is_command_handled: true,
                     submitted_data: None,
                 }
             }
-            EditorCommand::MoveCursorUpOnePage | EditorCommand::MoveCursorDownOnePage => {
+            EditorCommand::Noop
+            | EditorCommand::MoveCursorUpOnePage
+            | EditorCommand::MoveCursorDownOnePage => CommandBarExecuteResult {
+                is_command_handled: true,
+                submitted_data: None,
+            },
+            EditorCommand::MoveCursorLeft => {
+                self.text_box.move_cursor_left();
                 CommandBarExecuteResult {
                     is_command_handled: true,
                     submitted_data: None,
                 }
             }
-            EditorCommand::MoveCursorLeft => {
-                self.text_box.move_cursor_left();
+            EditorCommand::MoveCursorRight => {
+                self.text_box.move_cursor_right();
                 CommandBarExecuteResult {
                     is_command_handled: true,
                     submitted_data: None,
                 }
             }
-            EditorCommand::MoveCursorRight => {
-                self.text_box.move_cursor_right();
+            EditorCommand::MoveCursorWordLeft => {
+                self.text_box.move_cursor_word_left();
                 CommandBarExecuteResult {
                     is_command_handled: true,
                     submitted_data: None,
                 }
             }
-            EditorCommand::MoveCursorToStartOfLine => {
+            EditorCommand::MoveCursorWordRight => {
+                self.text_box.move_cursor_word_right();
+                CommandBarExecuteResult {
+                    is_command_handled: true,
+                    submitted_data: None,
+                }
+            }
+            EditorCommand::MoveCursorToStartOfLine | EditorCommand::MoveCursorToTop => {
                 self.text_box.move_cursor_to_start_of_line();
                 CommandBarExecuteResult {
                     is_command_handled: true,
                     submitted_data: None,
                 }
             }
-            EditorCommand::MoveCursorToEndOfLine => {
+            EditorCommand::MoveCursorToEndOfLine | EditorCommand::MoveCursorToBottom => {
                 self.text_box.move_cursor_to_end_of_line();
                 CommandBarExecuteResult {
                     is_command_handled: true,
@@ -189,7 +367,7 @@ impl CommandBar {
             }
             EditorCommand::InsertCharacter(ch) => {
                 if self.text_box.insert_character_at_cursor(ch).is_ok() {
-                    self.on_input_updated(view);
+                    self.on_input_updated(view, message_bar);
                     CommandBarExecuteResult {
                         is_command_handled: true,
                         submitted_data: None,
@@ -202,7 +380,7 @@ impl CommandBar {
                 }
             }
             EditorCommand::InsertNewline => {
-                if matches!(self.prompt, CommandBarPrompt::Search) {
+                if self.prompt.is_search() {
                     view.complete_search();
                 }
 
@@ -217,7 +395,7 @@ impl CommandBar {
             }
             EditorCommand::EraseCharacterBeforeCursor => {
                 if self.text_box.erase_character_before_cursor().is_ok() {
-                    self.on_input_updated(view);
+                    self.on_input_updated(view, message_bar);
                     CommandBarExecuteResult {
                         is_command_handled: true,
                         submitted_data: None,
@@ -231,7 +409,7 @@ impl CommandBar {
             }
             EditorCommand::EraseCharacterAfterCursor => {
                 if self.text_box.erase_character_after_cursor().is_ok() {
-                    self.on_input_updated(view);
+                    self.on_input_updated(view, message_bar);
                     CommandBarExecuteResult {
                         is_command_handled: true,
                         submitted_data: None,
@@ -243,16 +421,48 @@ impl CommandBar {
                     }
                 }
             }
+            EditorCommand::EraseWordBeforeCursor
+            | EditorCommand::EraseWordAfterCursor
+            | EditorCommand::DeleteToEndOfLine
+            | EditorCommand::DeleteLine
+            | EditorCommand::DeleteToStartOfLine => {
+                let removed = match command {
+                    EditorCommand::EraseWordBeforeCursor => {
+                        self.text_box.erase_word_before_cursor()
+                    }
+                    EditorCommand::EraseWordAfterCursor => self.text_box.erase_word_after_cursor(),
+                    EditorCommand::DeleteToEndOfLine => self.text_box.delete_to_end_of_line(),
+                    EditorCommand::DeleteLine => self.text_box.delete_current_line(),
+                    _ => self.text_box.delete_to_start_of_line(),
+                };
+                if removed.is_some() {
+                    self.on_input_updated(view, message_bar);
+                }
+
+                CommandBarExecuteResult {
+                    is_command_handled: true,
+                    submitted_data: None,
+                }
+            }
             EditorCommand::Dismiss => {
                 match self.prompt {
                     CommandBarPrompt::SaveAs => {
                         message_bar.set_message("Save aborted");
                     }
-                    CommandBarPrompt::Search => {
+                    CommandBarPrompt::Search | CommandBarPrompt::RegexSearch => {
                         view.abort_search();
                         message_bar.set_message("Search aborted");
                     }
-                    CommandBarPrompt::None => {}
+                    CommandBarPrompt::FilterCommand => {
+                        message_bar.set_message("Filter aborted");
+                    }
+                    CommandBarPrompt::ReloadFile => {
+                        message_bar.set_message("Reload aborted");
+                    }
+                    CommandBarPrompt::GotoLine
+                    | CommandBarPrompt::SetMark
+                    | CommandBarPrompt::JumpToMark
+                    | CommandBarPrompt::None => {}
                 }
 
                 self.clear_prompt();
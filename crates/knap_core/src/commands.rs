@@ -1,19 +1,294 @@
-#[derive(Clone, Copy, PartialEq, Eq)]
-pub(crate) enum EditorCommand {
+/// What the editor can be told to do, e.g. by pressing a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditorCommand {
+    /// does nothing, but is still considered handled, so binding a key
+    /// to it (see `Editor::bind_key`) disables the key
+    Noop,
     QuitAll,
     MoveCursorUp,
     MoveCursorDown,
     MoveCursorLeft,
     MoveCursorRight,
+    MoveCursorWordLeft,
+    MoveCursorWordRight,
     MoveCursorUpOnePage,
     MoveCursorDownOnePage,
     MoveCursorToStartOfLine,
     MoveCursorToEndOfLine,
+    MoveCursorToTop,
+    MoveCursorToBottom,
+    /// move the caret up, extending the selection to it
+    ExtendSelectionUp,
+    ExtendSelectionDown,
+    ExtendSelectionLeft,
+    ExtendSelectionRight,
     InsertCharacter(char),
+    /// insert one level of indentation at the caret,
+    /// or indent the selected lines
+    InsertIndent,
+    /// remove one level of indentation from the selected lines
+    /// (or the caret's line)
+    DedentLines,
     InsertNewline,
+    /// insert the current date and time, in the configured format
+    InsertDateTime,
+    InsertFileName,
+    /// complete the word before the caret with words from the buffer,
+    /// or select the next completion if they are already shown
+    TriggerCompletion,
+    ToggleOverwriteMode,
+    /// switch between selecting a stream of text and selecting
+    /// a block (the same columns of each line)
+    ToggleBlockSelection,
+    /// toggle flagging indentation that does not match the detected
+    /// indentation style, and trailing whitespace
+    RevealWhitespaceErrors,
     EraseCharacterBeforeCursor,
     EraseCharacterAfterCursor,
+    /// erase from the caret to the start of the word before it, or the
+    /// line break if the caret is at the start of the line
+    EraseWordBeforeCursor,
+    /// erase from the caret to the end of the word after it, or the
+    /// line break if the caret is at the end of the line
+    EraseWordAfterCursor,
+    /// delete from the caret to the end of the line, or the
+    /// line break if the caret is at the end of the line
+    DeleteToEndOfLine,
+    /// remove the caret's whole line
+    DeleteLine,
+    DeleteToStartOfLine,
+    /// revert the last change to the buffer
+    Undo,
+    /// re-apply the last change that was undone
+    Redo,
+    /// insert the newest text from the kill ring
+    Yank,
+    /// replace the text that was just yanked with the kill before it
+    YankPop,
     WriteBufferToDisk,
     Dismiss,
     StartSearch,
+    /// search for matches of a regular expression
+    StartRegexSearch,
+    /// prompt for a line number to move the caret to
+    StartGotoLine,
+    /// pipe the selection (or buffer) through a shell command, run by `sh`
+    FilterThroughCommand,
+    SelectAll,
+    SelectLine,
+    SelectWord,
+    /// fold the block starting on the caret's line, or unfold it
+    ToggleFold,
+    /// go back to where the caret was before the last large jump
+    JumpBack,
+    JumpForward,
+    /// move the caret to the bracket matching the one it is on
+    JumpToMatchingBracket,
+    /// scroll so that the caret's line is in the middle of the view
+    CenterCaret,
+    /// select the next occurrence of the word under the caret (as a whole
+    /// word), like `*` in Vim
+    JumpToNextWordOccurrence,
+    JumpToPreviousWordOccurrence,
+    /// prompt for a character to record the caret position as, like `m` in Vim
+    SetMark,
+    /// prompt for a character to move the caret back to the position
+    /// recorded as, like `` ` `` in Vim
+    JumpToMark,
+    Copy,
+    Cut,
+    /// replace the selection with the clipboard, or insert it at the caret
+    Paste,
+    /// insert a copy of the selection (or the caret's line) after it
+    DuplicateSelection,
+    /// swap the caret's line with the line before it
+    MoveLineUp,
+    /// swap the caret's line with the line after it
+    MoveLineDown,
+    /// join the line after the caret's line onto it, like `J` in Vim
+    JoinLines,
+    /// comment out the caret's line (or the selected lines),
+    /// or uncomment them if they are all commented out
+    ToggleComment,
+    SwapSelectionWithClipboard,
+    ShowHelp,
+    SplitVertical,
+    SplitHorizontal,
+    FocusNextPane,
+    ClosePane,
+    /// re-apply the last command that changed the buffer
+    RepeatLastChange,
+    StartRecordingMacro,
+    StopRecordingMacro,
+    /// replay the recorded macro the given number of times
+    PlayMacro(usize),
+}
+
+impl EditorCommand {
+    /// A short description of the command, shown in the help.
+    pub(crate) fn description(self) -> &'static str {
+        match self {
+            Self::Noop => "nothing",
+            Self::QuitAll => "quit",
+            Self::MoveCursorUp => "up",
+            Self::MoveCursorDown => "down",
+            Self::MoveCursorLeft => "left",
+            Self::MoveCursorRight => "right",
+            Self::MoveCursorWordLeft => "word left",
+            Self::MoveCursorWordRight => "word right",
+            Self::MoveCursorUpOnePage => "page up",
+            Self::MoveCursorDownOnePage => "page down",
+            Self::MoveCursorToStartOfLine => "line start",
+            Self::MoveCursorToEndOfLine => "line end",
+            Self::MoveCursorToTop => "top",
+            Self::MoveCursorToBottom => "bottom",
+            Self::ExtendSelectionUp => "select up",
+            Self::ExtendSelectionDown => "select down",
+            Self::ExtendSelectionLeft => "select left",
+            Self::ExtendSelectionRight => "select right",
+            Self::InsertCharacter(_) => "insert character",
+            Self::InsertIndent => "indent",
+            Self::DedentLines => "dedent",
+            Self::InsertNewline => "newline",
+            Self::InsertDateTime => "insert date",
+            Self::InsertFileName => "insert filename",
+            Self::TriggerCompletion => "complete word",
+            Self::ToggleOverwriteMode => "toggle overwrite",
+            Self::ToggleBlockSelection => "block selection",
+            Self::RevealWhitespaceErrors => "whitespace errors",
+            Self::EraseCharacterBeforeCursor => "erase before",
+            Self::EraseCharacterAfterCursor => "erase after",
+            Self::EraseWordBeforeCursor => "erase word before",
+            Self::EraseWordAfterCursor => "erase word after",
+            Self::DeleteToEndOfLine => "delete to end",
+            Self::DeleteLine => "delete line",
+            Self::DeleteToStartOfLine => "delete to start",
+            Self::Undo => "undo",
+            Self::Redo => "redo",
+            Self::Yank => "yank",
+            Self::YankPop => "yank older",
+            Self::WriteBufferToDisk => "save",
+            Self::Dismiss => "dismiss",
+            Self::StartSearch => "find",
+            Self::StartRegexSearch => "find regex",
+            Self::StartGotoLine => "go to line",
+            Self::FilterThroughCommand => "filter",
+            Self::SelectAll => "select all",
+            Self::SelectLine => "select line",
+            Self::SelectWord => "select word",
+            Self::ToggleFold => "toggle fold",
+            Self::JumpBack => "jump back",
+            Self::JumpForward => "jump forward",
+            Self::JumpToMatchingBracket => "matching bracket",
+            Self::CenterCaret => "center caret",
+            Self::JumpToNextWordOccurrence => "next occurrence",
+            Self::JumpToPreviousWordOccurrence => "previous occurrence",
+            Self::SetMark => "set mark",
+            Self::JumpToMark => "jump to mark",
+            Self::Copy => "copy",
+            Self::Cut => "cut",
+            Self::Paste => "paste",
+            Self::DuplicateSelection => "duplicate",
+            Self::MoveLineUp => "move line up",
+            Self::MoveLineDown => "move line down",
+            Self::JoinLines => "join lines",
+            Self::ToggleComment => "toggle comment",
+            Self::SwapSelectionWithClipboard => "swap with clipboard",
+            Self::ShowHelp => "help",
+            Self::SplitVertical => "split vertically",
+            Self::SplitHorizontal => "split horizontally",
+            Self::FocusNextPane => "next pane",
+            Self::ClosePane => "close pane",
+            Self::RepeatLastChange => "repeat change",
+            Self::StartRecordingMacro | Self::StopRecordingMacro => "record macro",
+            Self::PlayMacro(_) => "play macro",
+        }
+    }
+
+    /// Whether this command changes the buffer (or may, depending on
+    /// e.g. the selection), which means it is rejected in a read-only view.
+    pub(crate) fn is_edit_command(self) -> bool {
+        matches!(
+            self,
+            Self::InsertCharacter(_)
+                | Self::InsertIndent
+                | Self::DedentLines
+                | Self::InsertNewline
+                | Self::InsertDateTime
+                | Self::InsertFileName
+                | Self::TriggerCompletion
+                | Self::EraseCharacterBeforeCursor
+                | Self::EraseCharacterAfterCursor
+                | Self::EraseWordBeforeCursor
+                | Self::EraseWordAfterCursor
+                | Self::DeleteToEndOfLine
+                | Self::DeleteLine
+                | Self::DeleteToStartOfLine
+                | Self::Undo
+                | Self::Redo
+                | Self::Yank
+                | Self::YankPop
+                | Self::FilterThroughCommand
+                | Self::Cut
+                | Self::Paste
+                | Self::DuplicateSelection
+                | Self::MoveLineUp
+                | Self::MoveLineDown
+                | Self::JoinLines
+                | Self::ToggleComment
+                | Self::SwapSelectionWithClipboard
+                | Self::RepeatLastChange
+        )
+    }
+
+    /// Whether this command controls macros, which means it is never
+    /// recorded as part of one.
+    pub(crate) fn is_macro_command(self) -> bool {
+        matches!(
+            self,
+            Self::StartRecordingMacro | Self::StopRecordingMacro | Self::PlayMacro(_)
+        )
+    }
+}
+
+/// What happened as a result of executing a command.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct CommandOutcome {
+    pub handled: bool,
+    pub buffer_changed: bool,
+    /// to be shown in the message bar
+    pub message: Option<String>,
+    /// the text removed by a kill command (e.g. `DeleteToEndOfLine`),
+    /// to be added to the kill ring
+    pub killed: Option<String>,
+}
+
+impl CommandOutcome {
+    pub(crate) fn not_handled() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn handled() -> Self {
+        Self {
+            handled: true,
+            ..Self::default()
+        }
+    }
+
+    /// The command was rejected, as the view is read-only.
+    pub(crate) fn read_only() -> Self {
+        Self {
+            handled: true,
+            message: Some("Buffer is read-only".to_string()),
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn buffer_changed() -> Self {
+        Self {
+            handled: true,
+            buffer_changed: true,
+            ..Self::default()
+        }
+    }
 }
@@ -0,0 +1,145 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::code_view::is_identifier;
+
+/// How many completions are shown at once in the popup.
+pub(crate) const MAX_VISIBLE_COMPLETIONS: usize = 8;
+
+/// Returns the identifiers in `lines` that start with (but are not the same
+/// as) `prefix`, without duplicates and in the order they first appear.
+pub(crate) fn find_completions<I, S>(lines: I, prefix: &str) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    if prefix.is_empty() {
+        return vec![];
+    }
+
+    let mut completions: Vec<String> = vec![];
+    for line in lines {
+        for word in line.as_ref().split_word_bounds() {
+            if word.len() > prefix.len()
+                && word.starts_with(prefix)
+                && is_identifier(word)
+                && !completions.iter().any(|completion| completion == word)
+            {
+                completions.push(word.to_string());
+            }
+        }
+    }
+    completions
+}
+
+/// The completions offered for the word before the caret, one of which
+/// is selected to be inserted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Completion {
+    /// the part of the word that is already typed
+    prefix: String,
+    candidates: Vec<String>,
+    selected: usize,
+}
+
+impl Completion {
+    /// Returns `None` if there is nothing to complete `prefix` with.
+    pub(crate) fn new(prefix: String, candidates: Vec<String>) -> Option<Self> {
+        (!candidates.is_empty()).then_some(Self {
+            prefix,
+            candidates,
+            selected: 0,
+        })
+    }
+
+    pub(crate) fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Select the next candidate, going back to the first after the last.
+    pub(crate) fn select_next(&mut self) {
+        self.selected = self
+            .selected
+            .saturating_add(1)
+            .checked_rem(self.candidates.len())
+            .unwrap_or(0);
+    }
+
+    /// Select the previous candidate, going to the last before the first.
+    pub(crate) fn select_previous(&mut self) {
+        self.selected = self
+            .selected
+            .checked_sub(1)
+            .unwrap_or_else(|| self.candidates.len().saturating_sub(1));
+    }
+
+    /// The rest of the selected candidate, to be inserted after the prefix.
+    pub(crate) fn remaining_text(&self) -> &str {
+        self.candidates
+            .get(self.selected)
+            .and_then(|candidate| candidate.get(self.prefix.len()..))
+            .unwrap_or_default()
+    }
+
+    /// Returns the candidates that fit in the popup (keeping the selected
+    /// one in view), and the index of the selected one among them.
+    pub(crate) fn visible_candidates(&self) -> (&[String], usize) {
+        let start = self
+            .selected
+            .saturating_add(1)
+            .saturating_sub(MAX_VISIBLE_COMPLETIONS);
+        let end = start
+            .saturating_add(MAX_VISIBLE_COMPLETIONS)
+            .min(self.candidates.len());
+        (
+            self.candidates.get(start..end).unwrap_or_default(),
+            self.selected.saturating_sub(start),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_completions() {
+        let lines = [
+            "fn print_line(line: &str) {",
+            "    println!(\"{line}\");",
+            "    print_line(\"print\");",
+            "}",
+        ];
+
+        assert_eq!(
+            find_completions(lines, "pri"),
+            vec!["print_line", "println", "print"]
+        );
+        // the prefix itself is not a completion
+        assert_eq!(
+            find_completions(lines, "print"),
+            vec!["print_line", "println"]
+        );
+        assert_eq!(find_completions(lines, "li"), vec!["line"]);
+        assert!(find_completions(lines, "none").is_empty());
+        assert!(find_completions(lines, "").is_empty());
+    }
+
+    #[test]
+    fn test_completion_selection() {
+        let candidates = (0..10).map(|idx| format!("word{idx}")).collect();
+        let mut completion = Completion::new("wo".to_string(), candidates).expect("has candidates");
+        assert_eq!(completion.remaining_text(), "rd0");
+
+        completion.select_previous();
+        assert_eq!(completion.remaining_text(), "rd9");
+        let (visible, selected) = completion.visible_candidates();
+        assert_eq!(visible.first().map(String::as_str), Some("word2"));
+        assert_eq!(selected, MAX_VISIBLE_COMPLETIONS.saturating_sub(1));
+
+        completion.select_next();
+        assert_eq!(completion.remaining_text(), "rd0");
+        assert_eq!(completion.visible_candidates().1, 0);
+
+        assert_eq!(Completion::new("wo".to_string(), vec![]), None);
+    }
+}
@@ -0,0 +1,146 @@
+use std::{
+    fmt::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Result, bail};
+
+pub(crate) const DEFAULT_DATE_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// A date and time in UTC.
+struct DateTime {
+    year: u64,
+    month: u64,
+    day: u64,
+    hour: u64,
+    minute: u64,
+    second: u64,
+}
+
+impl DateTime {
+    /// Converts the number of days since 1970-01-01 into a
+    /// (year, month, day) date, see <https://howardhinnant.github.io/date_algorithms.html>.
+    fn get_date_from_days(days: u64) -> (u64, u64, u64) {
+        let days = days.saturating_add(719_468);
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era = day_of_era
+            .saturating_sub(day_of_era.div_euclid(1_460))
+            .saturating_add(day_of_era.div_euclid(36_524))
+            .saturating_sub(day_of_era.div_euclid(146_096))
+            .div_euclid(365);
+        let day_of_year = day_of_era.saturating_sub(
+            year_of_era
+                .saturating_mul(365)
+                .saturating_add(year_of_era.div_euclid(4))
+                .saturating_sub(year_of_era.div_euclid(100)),
+        );
+        // months counted from March, so that the leap day is at the end
+        let shifted_month = day_of_year
+            .saturating_mul(5)
+            .saturating_add(2)
+            .div_euclid(153);
+        let day = day_of_year
+            .saturating_sub(
+                shifted_month
+                    .saturating_mul(153)
+                    .saturating_add(2)
+                    .div_euclid(5),
+            )
+            .saturating_add(1);
+        let month = if shifted_month < 10 {
+            shifted_month.saturating_add(3)
+        } else {
+            shifted_month.saturating_sub(9)
+        };
+        let year = year_of_era
+            .saturating_add(era.saturating_mul(400))
+            .saturating_add(u64::from(month <= 2));
+
+        (year, month, day)
+    }
+
+    fn new(time: SystemTime) -> Self {
+        let seconds = time
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        let (year, month, day) = Self::get_date_from_days(seconds.div_euclid(SECONDS_PER_DAY));
+        let seconds_of_day = seconds.rem_euclid(SECONDS_PER_DAY);
+
+        Self {
+            year,
+            month,
+            day,
+            hour: seconds_of_day.div_euclid(3_600),
+            minute: seconds_of_day.rem_euclid(3_600).div_euclid(60),
+            second: seconds_of_day.rem_euclid(60),
+        }
+    }
+}
+
+/// Formats `time` (in UTC) with `format`, in which `%Y`, `%m`, `%d`, `%H`,
+/// `%M` and `%S` are replaced with the year, month, day, hour, minute and
+/// second, and `%%` with a `%`. Any other use of `%` is an error.
+pub(crate) fn format_date_time<T: AsRef<str>>(format: T, time: SystemTime) -> Result<String> {
+    let date_time = DateTime::new(time);
+
+    let mut formatted = String::new();
+    let mut chars = format.as_ref().chars();
+    while let Some(ch) = chars.next() {
+        if ch != '%' {
+            formatted.push(ch);
+            continue;
+        }
+
+        let (value, width) = match chars.next() {
+            Some('Y') => (date_time.year, 4),
+            Some('m') => (date_time.month, 2),
+            Some('d') => (date_time.day, 2),
+            Some('H') => (date_time.hour, 2),
+            Some('M') => (date_time.minute, 2),
+            Some('S') => (date_time.second, 2),
+            Some('%') => {
+                formatted.push('%');
+                continue;
+            }
+            Some(specifier) => bail!("unknown specifier %{specifier}"),
+            None => bail!("% at the end of the format"),
+        };
+        write!(formatted, "{value:0width$}")?;
+    }
+    Ok(formatted)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_format_date_time() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert_eq!(
+            format_date_time(DEFAULT_DATE_TIME_FORMAT, time).expect("valid format"),
+            "2023-11-14 22:13:20"
+        );
+        assert_eq!(
+            format_date_time(
+                "%d/%m/%Y %H:%M:%S 100%%",
+                UNIX_EPOCH + Duration::from_secs(951_868_799)
+            )
+            .expect("valid format"),
+            "29/02/2000 23:59:59 100%"
+        );
+        assert_eq!(
+            format_date_time("%Y", UNIX_EPOCH).expect("valid format"),
+            "1970"
+        );
+
+        assert!(format_date_time("%Y-%q", time).is_err());
+        assert!(format_date_time("%Y%", time).is_err());
+    }
+}
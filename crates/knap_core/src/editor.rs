@@ -1,16 +1,64 @@
+use std::{
+    collections::HashMap,
+    io::IsTerminal,
+    ops::Range,
+    path::Path,
+    sync::mpsc::{self, Receiver, Sender},
+    time::{Duration, Instant},
+};
+
 use anyhow::Result;
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
-use knap_base::math::{Bounds2f, Vec2f};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent,
+    MouseEventKind,
+};
+use knap_base::math::{Bounds2f, Lossy, Vec2f, Vec2u};
+use knap_ui::{
+    text_box::{Edit, EditError, TextRenderOptions},
+    text_buffer::{SearchOptions, TextBufferPos},
+};
 use knap_window::{drawer::Drawer, window::Window};
 
+pub use crate::annotations::{Annotation, Severity};
+pub use crate::code_view::FileType;
+pub use crate::commands::EditorCommand;
+pub use crate::keymap::Keymap;
+pub use crate::theme::Theme;
+
 use crate::{
     code_view::CodeView,
     command_bar::{CommandBar, CommandBarPrompt},
-    commands::EditorCommand,
+    commands::CommandOutcome,
+    date_time::DEFAULT_DATE_TIME_FORMAT,
+    highlighter::HighlightOptions,
+    kill_ring::KillRing,
     message_bar::MessageBar,
+    mouse::{self, ClickTracker},
+    panes::{Panes, SplitDirection},
+    session::SessionStore,
     status_bar::StatusBar,
 };
 
+/// How long to wait for an event before doing deferred work.
+const TICK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// What another thread (e.g. a file watcher) can ask the editor
+/// to do, through `Editor::external_command_sender`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExternalCommand {
+    /// executed as if its key was pressed
+    Command(EditorCommand),
+    /// applied to the focused view, see `Editor::apply_edits`
+    Edits(Vec<Edit>),
+    /// the file with this name was changed by another program, so its
+    /// views are reloaded (after asking, if they have unsaved changes)
+    FileChanged(String),
+    /// shown in the status bar while a long operation (e.g. a language
+    /// server indexing) runs, see `Editor::set_progress`, or `None`
+    /// once it is done
+    Progress(Option<String>),
+}
+
 pub struct Editor {
     should_quit: bool,
     window: Window,
@@ -20,23 +68,93 @@ pub struct Editor {
     /// quit the editor without saving a modified file
     block_quit_remaining_tries: usize,
 
-    view: CodeView,
+    /// internal clipboard, not shared with the system clipboard
+    clipboard: Option<String>,
+
+    kill_ring: KillRing,
+    /// whether the last command killed text, so that
+    /// the next kill is added to the same kill
+    is_after_kill: bool,
+    /// where the last command yanked text to, so
+    /// that it can be replaced with an older kill
+    last_yank: Option<Range<TextBufferPos>>,
+
+    /// which page of the help to show next
+    help_page: usize,
+
+    /// the last command that changed the buffer, for repeating it
+    last_change: Option<EditorCommand>,
+
+    /// the commands recorded so far, if a macro is being recorded
+    macro_recording: Option<Vec<EditorCommand>>,
+    /// the most recently recorded macro
+    recorded_macro: Vec<EditorCommand>,
+
+    keymap: Keymap,
+    highlight_options: HighlightOptions,
+    theme: Theme,
+    render_options: TextRenderOptions,
+    /// the command that buffers are formatted with before saving,
+    /// for each file type
+    format_commands: HashMap<FileType, String>,
+    date_time_format: String,
+    search_options: SearchOptions,
+    /// where the caret, scroll and folds of files are kept between runs
+    session_store: Option<SessionStore>,
+    /// the file that was changed on disk, while its views have
+    /// unsaved changes, waiting for the user to decide to reload it
+    file_to_reload: Option<String>,
+
+    click_tracker: ClickTracker,
+    /// whether the left mouse button is held down after clicking on the view
+    is_dragging: bool,
+
+    panes: Panes,
     status_bar: StatusBar,
     message_bar: MessageBar,
     command_bar: CommandBar,
+
+    /// kept so that the channel stays open, even if no
+    /// other thread has a sender
+    external_command_sender: Sender<ExternalCommand>,
+    /// the commands from other threads, which are handled
+    /// on the main thread in between events
+    external_command_receiver: Receiver<ExternalCommand>,
 }
 
 impl Editor {
     pub fn new() -> Self {
+        let (external_command_sender, external_command_receiver) = mpsc::channel();
         Self {
             should_quit: false,
             window: Window::new(),
             drawer: Drawer::new(),
             block_quit_remaining_tries: 0,
-            view: CodeView::new(),
+            clipboard: None,
+            kill_ring: KillRing::default(),
+            is_after_kill: false,
+            last_yank: None,
+            help_page: 0,
+            last_change: None,
+            macro_recording: None,
+            recorded_macro: vec![],
+            keymap: Keymap::default(),
+            highlight_options: HighlightOptions::default(),
+            theme: Theme::default(),
+            render_options: TextRenderOptions::default(),
+            format_commands: HashMap::new(),
+            date_time_format: DEFAULT_DATE_TIME_FORMAT.to_string(),
+            search_options: SearchOptions::default(),
+            session_store: None,
+            file_to_reload: None,
+            click_tracker: ClickTracker::new(),
+            is_dragging: false,
+            panes: Panes::new(CodeView::new()),
             status_bar: StatusBar::new(),
             message_bar: MessageBar::new(),
             command_bar: CommandBar::new(),
+            external_command_sender,
+            external_command_receiver,
         }
     }
 
@@ -48,86 +166,905 @@ impl Editor {
 
         let terminal_size = self.window.size();
         self.handle_new_window_size(terminal_size);
-        self.message_bar
-            .set_message("HELP: Ctrl-F = find | Ctrl-S = save | Ctrl-Q = quit");
+        self.execute_command(EditorCommand::ShowHelp);
 
         self.open_arg_file();
 
         let repl_result = self.repl();
+        let save_session_result = self.save_session();
 
         self.window.deinit();
         repl_result.expect("repl has no fatal error");
+        if let Err(err) = save_session_result {
+            eprintln!("Cannot save session: {err}");
+        }
     }
 
-    fn open_arg_file(&mut self) {
-        if let Some(filename) = std::env::args().nth(1) {
-            let view_bounds = self.view.bounds();
+    /// Keep the caret, scroll and folds of files in the session file at
+    /// `path`, restoring them when the files are opened again.
+    pub fn set_session_file<T: AsRef<Path>>(&mut self, path: T) {
+        match SessionStore::load(path) {
+            Ok(session_store) => self.session_store = Some(session_store),
+            Err(err) => self
+                .message_bar
+                .set_message(format!("Cannot load session: {err}")),
+        }
+    }
+
+    fn save_session(&mut self) -> Result<()> {
+        let Some(session_store) = &mut self.session_store else {
+            return Ok(());
+        };
+
+        for view in self.panes.views() {
+            if let Some(filename) = view.filename() {
+                session_store.set(filename, view.get_session());
+            }
+        }
+        session_store.save()
+    }
+
+    /// Set the maximum time allowed between clicks for them to
+    /// be treated as a double click or triple click.
+    pub fn set_multi_click_timeout(&mut self, timeout: Duration) {
+        self.click_tracker.set_multi_click_timeout(timeout);
+    }
+
+    /// Replace the commands that keys are bound to, and so the help too.
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
+    /// Bind a single key to `command`, replacing what it was bound to.
+    pub fn bind_key(&mut self, modifiers: KeyModifiers, code: KeyCode, command: EditorCommand) {
+        self.keymap.bind(modifiers, code, command);
+    }
+
+    /// Set the tags (e.g. `TODO`) that are highlighted when they
+    /// appear inside a comment.
+    pub fn set_comment_tags(&mut self, comment_tags: Vec<String>) {
+        self.highlight_options.comment_tags = comment_tags;
+        for view in self.panes.views_mut() {
+            view.set_highlight_options(self.highlight_options.clone());
+        }
+    }
+
+    /// Set whether spaces and tabs at the end of each line
+    /// should be highlighted.
+    pub fn set_highlight_trailing_whitespace(&mut self, highlight_trailing_whitespace: bool) {
+        self.highlight_options.highlight_trailing_whitespace = highlight_trailing_whitespace;
+        for view in self.panes.views_mut() {
+            view.set_highlight_options(self.highlight_options.clone());
+        }
+    }
+
+    /// Set whether brackets without a matching bracket (outside of
+    /// comments and literals) should be highlighted.
+    pub fn set_highlight_unmatched_brackets(&mut self, highlight_unmatched_brackets: bool) {
+        self.highlight_options.highlight_unmatched_brackets = highlight_unmatched_brackets;
+        for view in self.panes.views_mut() {
+            view.set_highlight_options(self.highlight_options.clone());
+        }
+    }
+
+    /// Set the colors that every view is drawn with, e.g. `Theme::light()`
+    /// for terminals with a light background.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        for view in self.panes.views_mut() {
+            view.set_theme(self.theme.clone());
+        }
+    }
+
+    /// Set whether control characters are rendered as their
+    /// code point (e.g. `<U+0007>`) instead of a single `▯`.
+    pub fn set_show_control_code_points(&mut self, show_control_code_points: bool) {
+        self.render_options.show_control_code_points = show_control_code_points;
+        for view in self.panes.views_mut() {
+            view.set_render_options(self.render_options);
+        }
+    }
+
+    /// Set whether tabs are rendered as a `→` padded up to the next
+    /// tab stop (every `tab_width` columns).
+    pub fn set_show_tabs(&mut self, show_tabs: bool, tab_width: u64) {
+        self.render_options.show_tabs = show_tabs;
+        self.render_options.tab_width = tab_width;
+        for view in self.panes.views_mut() {
+            view.set_render_options(self.render_options);
+        }
+    }
+
+    /// Set whether a scrollbar is shown in the rightmost column of each view.
+    pub fn set_show_scrollbar(&mut self, show_scrollbar: bool) {
+        self.render_options.show_scrollbar = show_scrollbar;
+        for view in self.panes.views_mut() {
+            view.set_render_options(self.render_options);
+        }
+    }
+
+    /// Set whether indent guides are drawn at each tab stop
+    /// within the leading whitespace of a line.
+    pub fn set_show_indent_guides(&mut self, show_indent_guides: bool) {
+        self.render_options.show_indent_guides = show_indent_guides;
+        for view in self.panes.views_mut() {
+            view.set_render_options(self.render_options);
+        }
+    }
+
+    /// Set whether the horizontal scroll stays put when moving up and down
+    /// across lines that are shorter than the caret's column, which is
+    /// useful for wide tabular data.
+    pub fn set_sticky_horizontal_scroll(&mut self, sticky_horizontal_scroll: bool) {
+        self.render_options.sticky_horizontal_scroll = sticky_horizontal_scroll;
+        for view in self.panes.views_mut() {
+            view.set_render_options(self.render_options);
+        }
+    }
+
+    /// Set whether finding the next match after the last one (or the previous
+    /// match before the first one) wraps around to the other end of the buffer.
+    /// If not, "No more matches" is shown instead. This is on by default.
+    pub fn set_search_wrap(&mut self, search_wrap: bool) {
+        self.search_options.wrap = search_wrap;
+        for view in self.panes.views_mut() {
+            view.set_search_options(self.search_options);
+        }
+    }
+
+    /// Set whether a search only matches whole words, so that searching for
+    /// `is` skips `this` and `history`. This is off by default.
+    pub fn set_search_whole_word(&mut self, search_whole_word: bool) {
+        self.search_options.whole_word = search_whole_word;
+        for view in self.panes.views_mut() {
+            view.set_search_options(self.search_options);
+        }
+    }
+
+    /// Set whether the buffers can only be viewed (e.g. when embedding the
+    /// editor as a pager). The caret can still be moved around and the buffer
+    /// searched, but the commands that would change it show a message instead.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        for view in self.panes.views_mut() {
+            view.set_read_only(read_only);
+        }
+    }
+
+    /// Attach `annotations` (e.g. diagnostics from a linter) to the lines of
+    /// `filename`, replacing its previous annotations. They are marked in the
+    /// gutter, with the messages shown after the lines if there is room, and
+    /// move along with their lines when lines are added or removed above them.
+    pub fn set_annotations<T: AsRef<str>>(
+        &mut self,
+        filename: T,
+        annotations: &[(usize, Annotation)],
+    ) {
+        for view in self.panes.views_mut() {
+            if view.filename() == Some(filename.as_ref()) {
+                view.set_annotations(annotations.to_vec());
+            }
+        }
+    }
+
+    /// Set the command (e.g. `rustfmt`) that buffers of `file_type` are piped
+    /// through before they are saved, replacing them with its output. If the
+    /// command fails, the buffer is saved as it is. `None` turns it off.
+    pub fn set_format_command<T: AsRef<str>>(&mut self, file_type: FileType, command: Option<T>) {
+        match command {
+            Some(command) => {
+                self.format_commands
+                    .insert(file_type, command.as_ref().to_string());
+            }
+            None => {
+                self.format_commands.remove(&file_type);
+            }
+        }
+        for view in self.panes.views_mut() {
+            view.set_format_commands(self.format_commands.clone());
+        }
+    }
+
+    /// Set whether the trailing spaces and tabs of every line are removed
+    /// before a buffer is saved (after it is formatted), in the buffer as well
+    /// as in the file. The removal can be undone. This is off by default.
+    pub fn set_trim_trailing_whitespace_on_save(&mut self, trim_trailing_whitespace_on_save: bool) {
+        for view in self.panes.views_mut() {
+            view.set_trim_trailing_whitespace_on_save(trim_trailing_whitespace_on_save);
+        }
+    }
+
+    /// Set the format of the date inserted by `Alt-D`, e.g. `%d/%m/%Y`.
+    ///
+    /// The supported fields are `%Y`, `%m`, `%d`, `%H`, `%M` and `%S` (in UTC),
+    /// with `%%` being a `%`. An invalid format is reported when inserting.
+    pub fn set_date_time_format<T: AsRef<str>>(&mut self, date_time_format: T) {
+        self.date_time_format = date_time_format.as_ref().to_string();
+        for view in self.panes.views_mut() {
+            view.set_date_time_format(&self.date_time_format);
+        }
+    }
+
+    /// Set what the left and right side of the status bar show, using
+    /// format strings such as `"{filetype} | Ln {line}, Col {col}"`.
+    ///
+    /// The supported fields are `filename`, `modified`, `lines`, `edits`,
+    /// `branch` (the git branch, if any), `filetype`, `indent` (the
+    /// detected indentation), `mode`, `chars`, `graphemes`, `words`,
+    /// `line`, `col` and `percent`. When the characters before the caret are
+    /// wider than a column (e.g. full-width characters), `col` is followed by
+    /// the column that the caret is drawn at, e.g. `2-3`.
+    pub fn set_status_bar_format<T: AsRef<str>, U: AsRef<str>>(&mut self, left: T, right: U) {
+        self.status_bar.set_format(left, right);
+    }
+
+    /// Show `message` (e.g. `Highlighting 40%`) in the status bar with
+    /// a spinner, to indicate that a long operation is still running.
+    pub fn set_progress<T: AsRef<str>>(&mut self, message: T) {
+        self.status_bar.set_progress(message, Instant::now());
+    }
+
+    /// Stop showing the progress set by [`Editor::set_progress`].
+    pub fn clear_progress(&mut self) {
+        self.status_bar.clear_progress();
+    }
+
+    /// The entire contents of the focused view.
+    pub fn contents(&self) -> String {
+        self.panes.focused().get_entire_contents_as_string()
+    }
+
+    /// Replace the entire contents of the focused view.
+    pub fn set_contents<T: AsRef<str>>(&mut self, contents: T) {
+        self.panes.focused_mut().set_contents(contents.as_ref());
+        self.on_focused_buffer_changed();
+    }
+
+    /// The position of the caret in the focused view.
+    pub fn caret(&self) -> TextBufferPos {
+        self.panes.focused().caret_buffer_pos()
+    }
+
+    /// Move the caret of the focused view to `pos`, which is
+    /// clamped to the end of the line (or contents).
+    pub fn set_caret(&mut self, pos: TextBufferPos) {
+        self.panes.focused_mut().move_cursor_to_buffer_pos(pos);
+    }
+
+    /// The selected range in the focused view, if anything is selected.
+    pub fn selection(&self) -> Option<Range<TextBufferPos>> {
+        self.panes.focused().selection_buffer_range()
+    }
+
+    /// Select from `anchor` to `caret` in the focused view,
+    /// with the caret moving to `caret`.
+    pub fn set_selection(&mut self, anchor: TextBufferPos, caret: TextBufferPos) {
+        self.panes
+            .focused_mut()
+            .set_selection_buffer_range(anchor, caret);
+    }
+
+    /// Apply `edits` to the focused view as a single transaction, where
+    /// each edit applies to the contents as left by the edits before it.
+    /// If any of the edits is invalid, none of them are applied.
+    ///
+    /// Returns the edits that revert the whole transaction.
+    pub fn apply_edits(&mut self, edits: Vec<Edit>) -> Result<Vec<Edit>, EditError> {
+        let revert_edits = self.panes.focused_mut().apply_edits(edits)?;
+        self.on_focused_buffer_changed();
+        Ok(revert_edits)
+    }
+
+    fn on_focused_buffer_changed(&mut self) {
+        self.panes.focused_mut().on_buffer_changed();
+        self.block_quit_remaining_tries = 3;
+    }
 
-            match CodeView::new_from_file(&filename) {
-                Ok(view) => {
-                    self.view = view;
-                    self.view.set_bounds(view_bounds);
-                    self.window.set_title(&filename).expect("able to set title");
+    fn open_arg_file(&mut self) {
+        let (view, title) = match std::env::args().nth(1) {
+            Some(arg) if arg == "-" => {
+                if std::io::stdin().is_terminal() {
+                    self.message_bar
+                        .set_message("Cannot read from stdin: stdin is a terminal");
+                    return;
+                }
+                (CodeView::new_from_reader(std::io::stdin().lock()), None)
+            }
+            Some(filename) => (CodeView::new_from_file(&filename), Some(filename)),
+            None => {
+                if std::io::stdin().is_terminal() {
+                    return;
                 }
-                Err(err) => {
+                (CodeView::new_from_reader(std::io::stdin().lock()), None)
+            }
+        };
+
+        match view {
+            Ok(view) => {
+                let read_only = self.panes.focused().is_read_only();
+                let trim_trailing_whitespace_on_save =
+                    self.panes.focused().is_trim_trailing_whitespace_on_save();
+                self.panes.replace_focused(view);
+                let view = self.panes.focused_mut();
+                view.set_highlight_options(self.highlight_options.clone());
+                view.set_theme(self.theme.clone());
+                view.set_render_options(self.render_options);
+                view.set_format_commands(self.format_commands.clone());
+                view.set_date_time_format(&self.date_time_format);
+                view.set_search_options(self.search_options);
+                view.set_read_only(read_only);
+                view.set_trim_trailing_whitespace_on_save(trim_trailing_whitespace_on_save);
+                #[cfg(feature = "notify")]
+                if let Err(err) = view.watch_file(self.external_command_sender.clone()) {
                     self.message_bar
-                        .set_message(format!("Cannot load {filename}: {err}"));
+                        .set_message(format!("Cannot watch file for changes: {err}"));
                 }
-            };
+                let view = self.panes.focused_mut();
+                if let Some(session) = title
+                    .as_ref()
+                    .zip(self.session_store.as_ref())
+                    .and_then(|(filename, session_store)| session_store.get(filename))
+                {
+                    view.restore_session(session);
+                }
+                if view.is_new_file() {
+                    self.message_bar.set_message("New file");
+                }
+                if let Some(title) = title {
+                    self.window.set_title(&title).expect("able to set title");
+                }
+            }
+            Err(err) => {
+                self.message_bar.set_message(format!(
+                    "Cannot load {}: {err}",
+                    title.as_deref().unwrap_or("stdin")
+                ));
+            }
         }
     }
 
     fn repl(&mut self) -> Result<()> {
         while !self.should_quit {
-            let event = event::read()?;
-            self.handle_event(&event);
-            self.draw()?;
+            let mut needs_redraw = false;
+            if event::poll(TICK_INTERVAL)? {
+                let event = event::read()?;
+                self.handle_event(&event);
+                needs_redraw = true;
+            }
+            needs_redraw |= self.handle_external_commands();
+
+            let now = Instant::now();
+            for view in self.panes.views_mut() {
+                needs_redraw |= view.tick(now);
+            }
+            needs_redraw |= self.status_bar.tick(now);
+            if needs_redraw {
+                self.draw()?;
+            }
         }
         Ok(())
     }
 
+    /// Returns a sender for other threads to send commands to the editor,
+    /// which are handled by the main loop in between events, in the order
+    /// they are sent.
+    pub fn external_command_sender(&self) -> Sender<ExternalCommand> {
+        self.external_command_sender.clone()
+    }
+
+    /// Handle the commands sent by other threads so far, returns whether
+    /// there were any.
+    fn handle_external_commands(&mut self) -> bool {
+        let mut is_any_handled = false;
+        while let Ok(command) = self.external_command_receiver.try_recv() {
+            match command {
+                ExternalCommand::Command(command) => {
+                    self.execute_command(command);
+                }
+                ExternalCommand::Edits(edits) => {
+                    if let Err(err) = self.apply_edits(edits) {
+                        self.message_bar
+                            .set_message(format!("Cannot apply edits: {err}"));
+                    }
+                }
+                ExternalCommand::FileChanged(filename) => self.on_file_changed(filename),
+                ExternalCommand::Progress(Some(message)) => self.set_progress(message),
+                ExternalCommand::Progress(None) => self.clear_progress(),
+            }
+            is_any_handled = true;
+        }
+        is_any_handled
+    }
+
+    fn on_file_changed(&mut self, filename: String) {
+        let views: Vec<_> = self
+            .panes
+            .views()
+            .into_iter()
+            .filter(|view| view.filename() == Some(filename.as_str()) && view.is_changed_on_disk())
+            .collect();
+        if views.is_empty() {
+            // e.g. the change was the views saving the file themselves
+            return;
+        }
+        if views.iter().any(|view| view.get_status().is_dirty) {
+            if self.command_bar.has_active_prompt() {
+                self.message_bar
+                    .set_message(format!("{filename} changed on disk"));
+            } else {
+                self.command_bar.set_prompt(CommandBarPrompt::ReloadFile);
+                self.file_to_reload = Some(filename);
+            }
+        } else {
+            self.reload_file(&filename);
+        }
+    }
+
+    /// Reload the views of `filename` from the disk, discarding their changes.
+    fn reload_file(&mut self, filename: &str) {
+        let mut message = None;
+        for view in self
+            .panes
+            .views_mut()
+            .into_iter()
+            .filter(|view| view.filename() == Some(filename))
+        {
+            match view.reload_from_disk() {
+                Ok(true) => message = Some(format!("Reloaded {filename}")),
+                Ok(false) => {}
+                Err(err) => message = Some(format!("Cannot reload {filename}: {err}")),
+            }
+        }
+        if let Some(message) = message {
+            self.message_bar.set_message(message);
+        }
+        self.update_block_quit_remaining_tries();
+    }
+
+    fn update_block_quit_remaining_tries(&mut self) {
+        self.block_quit_remaining_tries = if self
+            .panes
+            .views()
+            .iter()
+            .any(|view| view.get_status().is_dirty)
+        {
+            3
+        } else {
+            0
+        };
+    }
+
     fn execute_command(&mut self, command: EditorCommand) -> bool {
-        if matches!(command, EditorCommand::QuitAll) {
+        let outcome = self.dispatch_command(command);
+
+        if outcome.handled
+            && !command.is_macro_command()
+            && let Some(recording) = &mut self.macro_recording
+        {
+            recording.push(command);
+        }
+
+        if outcome.buffer_changed {
+            if !matches!(
+                command,
+                EditorCommand::RepeatLastChange | EditorCommand::Undo | EditorCommand::Redo
+            ) && !command.is_macro_command()
+            {
+                self.last_change = Some(command);
+            }
+            self.panes.focused_mut().on_buffer_changed();
+        }
+        if let Some(message) = outcome.message {
+            self.message_bar.set_message(message);
+        }
+
+        outcome.handled
+    }
+
+    /// Act on the `value` submitted to `prompt`, returns `None`
+    /// if there is nothing to do for the prompt.
+    fn submit_prompt(&mut self, prompt: CommandBarPrompt, value: &str) -> Option<CommandOutcome> {
+        match prompt {
+            CommandBarPrompt::SaveAs => {
+                self.panes.focused_mut().change_filename(value);
+                self.window.set_title(value).expect("able to set title");
+                // dispatched directly so that it does not get recorded
+                // into a macro separately from the submission
+                let outcome = self.dispatch_command(EditorCommand::WriteBufferToDisk);
+                #[cfg(feature = "notify")]
+                if let Err(err) = self
+                    .panes
+                    .focused_mut()
+                    .watch_file(self.external_command_sender.clone())
+                {
+                    return Some(CommandOutcome {
+                        message: Some(format!("Cannot watch file for changes: {err}")),
+                        ..outcome
+                    });
+                }
+                Some(outcome)
+            }
+            CommandBarPrompt::FilterCommand => {
+                let outcome = self.panes.focused_mut().filter_through_command(value);
+                // the change is not reported as the outcome, or the
+                // submission would be repeated as the last change
+                if outcome.buffer_changed {
+                    self.on_focused_buffer_changed();
+                }
+                Some(CommandOutcome {
+                    buffer_changed: false,
+                    ..outcome
+                })
+            }
+            CommandBarPrompt::GotoLine => {
+                let Ok(line_number) = value.trim().parse() else {
+                    return Some(CommandOutcome {
+                        message: Some("Not a valid line number".to_string()),
+                        ..CommandOutcome::handled()
+                    });
+                };
+                self.panes.focused_mut().goto_line(line_number);
+                Some(CommandOutcome::handled())
+            }
+            CommandBarPrompt::SetMark | CommandBarPrompt::JumpToMark => {
+                let mut chars = value.chars();
+                let (Some(id), None) = (chars.next(), chars.next()) else {
+                    return Some(CommandOutcome {
+                        message: Some("Not a valid mark".to_string()),
+                        ..CommandOutcome::handled()
+                    });
+                };
+                let view = self.panes.focused_mut();
+                Some(if prompt == CommandBarPrompt::SetMark {
+                    view.set_mark(id)
+                } else {
+                    view.jump_to_mark(id)
+                })
+            }
+            CommandBarPrompt::ReloadFile => {
+                match self.file_to_reload.take() {
+                    Some(filename) if value.eq_ignore_ascii_case("y") => {
+                        self.reload_file(&filename);
+                    }
+                    _ => self.message_bar.set_message("Reload aborted"),
+                }
+                Some(CommandOutcome::handled())
+            }
+            CommandBarPrompt::Search | CommandBarPrompt::RegexSearch | CommandBarPrompt::None => {
+                None
+            }
+        }
+    }
+
+    fn dispatch_command(&mut self, command: EditorCommand) -> CommandOutcome {
+        // both only apply to the command right after
+        let is_after_kill = std::mem::take(&mut self.is_after_kill);
+        let last_yank = self.last_yank.take();
+
+        if command.is_macro_command() {
+            self.dispatch_macro_command(command)
+        } else if matches!(command, EditorCommand::QuitAll) {
             if self.block_quit_remaining_tries == 0 {
                 self.should_quit = true;
+                CommandOutcome::handled()
             } else {
-                self.message_bar.set_message(format!(
+                let message = format!(
                     "WARNING! File has unsaved changes. Press Ctrl-Q {} more times to quit.",
                     self.block_quit_remaining_tries
-                ));
+                );
                 self.block_quit_remaining_tries = self.block_quit_remaining_tries.saturating_sub(1);
+                CommandOutcome {
+                    message: Some(message),
+                    ..CommandOutcome::handled()
+                }
             }
-            true
         } else if self.command_bar.has_active_prompt() {
-            let result =
-                self.command_bar
-                    .execute_command(command, &mut self.message_bar, &mut self.view);
+            let result = self.command_bar.execute_command(
+                command,
+                &mut self.message_bar,
+                self.panes.focused_mut(),
+            );
 
             if let Some((prompt, value)) = result.submitted_data {
                 self.command_bar.clear_prompt();
-                if matches!(prompt, CommandBarPrompt::SaveAs) {
-                    self.view.change_filename(&value);
-                    self.window.set_title(&value).expect("able to set title");
-                    self.execute_command(EditorCommand::WriteBufferToDisk);
+                if let Some(outcome) = self.submit_prompt(prompt, &value) {
+                    return outcome;
                 }
             }
 
-            result.is_command_handled
+            CommandOutcome {
+                handled: result.is_command_handled,
+                ..CommandOutcome::default()
+            }
+        } else if matches!(command, EditorCommand::ShowHelp) {
+            CommandOutcome {
+                message: Some(self.get_next_help_page()),
+                ..CommandOutcome::handled()
+            }
+        } else if matches!(command, EditorCommand::RepeatLastChange) {
+            match self.last_change {
+                Some(last_change) => self.dispatch_command(last_change),
+                None => CommandOutcome::not_handled(),
+            }
+        } else if let Some(outcome) = self.dispatch_pane_command(command) {
+            outcome
+        } else if matches!(
+            command,
+            EditorCommand::Cut
+                | EditorCommand::Paste
+                | EditorCommand::SwapSelectionWithClipboard
+                | EditorCommand::Yank
+                | EditorCommand::YankPop
+        ) && self.panes.focused().is_read_only()
+        {
+            // the view only rejects the commands that it executes itself
+            CommandOutcome::read_only()
+        } else if matches!(command, EditorCommand::Copy) {
+            if let Some(text) = self.panes.focused().copy_selection() {
+                self.clipboard = Some(text);
+            }
+            CommandOutcome::handled()
+        } else if matches!(command, EditorCommand::Cut) {
+            match self.panes.focused_mut().cut_selection() {
+                Some(text) => {
+                    self.clipboard = Some(text);
+                    self.block_quit_remaining_tries = 3;
+                    CommandOutcome::buffer_changed()
+                }
+                None => CommandOutcome::handled(),
+            }
+        } else if matches!(command, EditorCommand::Paste) {
+            self.paste()
+        } else if matches!(command, EditorCommand::SwapSelectionWithClipboard) {
+            self.swap_selection_with_clipboard()
+        } else if matches!(command, EditorCommand::Yank | EditorCommand::YankPop) {
+            self.yank(command, last_yank)
         } else {
-            let result =
-                self.view
-                    .execute_command(command, &mut self.message_bar, &mut self.command_bar);
-            self.block_quit_remaining_tries = if self.view.get_status().is_dirty {
-                3
-            } else {
-                0
+            let outcome = self
+                .panes
+                .focused_mut()
+                .execute_command(command, &mut self.command_bar);
+            if let Some(killed) = &outcome.killed {
+                self.add_kill(command, killed, is_after_kill);
+            }
+
+            self.update_block_quit_remaining_tries();
+            outcome
+        }
+    }
+
+    fn paste(&mut self) -> CommandOutcome {
+        let Some(clipboard) = &self.clipboard else {
+            return CommandOutcome {
+                message: Some("Clipboard is empty".to_string()),
+                ..CommandOutcome::handled()
             };
-            result
+        };
+
+        if self.panes.focused_mut().paste(clipboard) {
+            self.block_quit_remaining_tries = 3;
+            CommandOutcome::buffer_changed()
+        } else {
+            CommandOutcome::handled()
         }
     }
 
+    fn swap_selection_with_clipboard(&mut self) -> CommandOutcome {
+        let Some(clipboard) = &self.clipboard else {
+            return CommandOutcome {
+                message: Some("Clipboard is empty".to_string()),
+                ..CommandOutcome::handled()
+            };
+        };
+
+        match self.panes.focused_mut().swap_selection(clipboard) {
+            Some(swapped) => {
+                self.clipboard = Some(swapped);
+                self.block_quit_remaining_tries = 3;
+                CommandOutcome::buffer_changed()
+            }
+            None => CommandOutcome::handled(),
+        }
+    }
+
+    /// Adds the text killed by `command` to the kill ring, joining it to the
+    /// newest kill if the previous command was a kill too.
+    fn add_kill(&mut self, command: EditorCommand, killed: &str, is_after_kill: bool) {
+        if !is_after_kill {
+            self.kill_ring.push(killed.to_string());
+        } else if matches!(
+            command,
+            EditorCommand::DeleteToStartOfLine | EditorCommand::EraseWordBeforeCursor
+        ) {
+            self.kill_ring.prepend(killed);
+        } else {
+            self.kill_ring.append(killed);
+        }
+        self.is_after_kill = true;
+    }
+
+    /// Inserts the newest kill at the caret, or for `EditorCommand::YankPop`,
+    /// replaces the text that was just yanked (at `last_yank`) with an older kill.
+    fn yank(
+        &mut self,
+        command: EditorCommand,
+        last_yank: Option<Range<TextBufferPos>>,
+    ) -> CommandOutcome {
+        let message = |message: &str| CommandOutcome {
+            message: Some(message.to_string()),
+            ..CommandOutcome::handled()
+        };
+
+        let (text, last_yank) = if matches!(command, EditorCommand::YankPop) {
+            let Some(last_yank) = last_yank else {
+                return message("Previous command was not a yank");
+            };
+            match self.kill_ring.yank_pop() {
+                Some(text) => (text.to_string(), Some(last_yank)),
+                None => return message("Kill ring is empty"),
+            }
+        } else {
+            match self.kill_ring.yank() {
+                Some(text) => (text.to_string(), None),
+                None => return message("Kill ring is empty"),
+            }
+        };
+
+        let view = self.panes.focused_mut();
+        let yanked = match last_yank {
+            Some(last_yank) => view.replace_range(last_yank, &text),
+            None => view.insert_at_caret(&text),
+        };
+        match yanked {
+            Some(yanked) => {
+                self.last_yank = Some(yanked);
+                self.block_quit_remaining_tries = 3;
+                CommandOutcome::buffer_changed()
+            }
+            None => CommandOutcome::handled(),
+        }
+    }
+
+    /// Returns the next page of the help that fits within the message
+    /// bar, cycling back to the first page after the last one.
+    fn get_next_help_page(&mut self) -> String {
+        let width = <f64 as Lossy<usize>>::lossy(&self.message_bar.bounds().size.x);
+        let mut pages = self.keymap.get_help_pages(width);
+
+        if self.help_page >= pages.len() {
+            self.help_page = 0;
+        }
+        let page = pages.swap_remove(self.help_page);
+        self.help_page = self.help_page.saturating_add(1);
+        page
+    }
+
+    /// Executes commands that manage the panes, returning
+    /// `None` if `command` is not one of them.
+    fn dispatch_pane_command(&mut self, command: EditorCommand) -> Option<CommandOutcome> {
+        match command {
+            EditorCommand::SplitVertical | EditorCommand::SplitHorizontal => {
+                let direction = if matches!(command, EditorCommand::SplitVertical) {
+                    SplitDirection::Vertical
+                } else {
+                    SplitDirection::Horizontal
+                };
+
+                // the new pane shows the file of the pane that is split (as
+                // it was last saved), or starts with an empty buffer
+                let focused = self.panes.focused();
+                let mut view = match focused.filename() {
+                    Some(filename) => match CodeView::new_from_file(filename) {
+                        Ok(view) => view,
+                        Err(err) => {
+                            return Some(CommandOutcome {
+                                message: Some(format!("Cannot load {filename}: {err}")),
+                                ..CommandOutcome::handled()
+                            });
+                        }
+                    },
+                    None => CodeView::new(),
+                };
+                view.move_cursor_to_buffer_pos(focused.caret_buffer_pos());
+                view.set_highlight_options(self.highlight_options.clone());
+                view.set_theme(self.theme.clone());
+                view.set_render_options(self.render_options);
+                view.set_format_commands(self.format_commands.clone());
+                view.set_date_time_format(&self.date_time_format);
+                view.set_search_options(self.search_options);
+                // the options that are kept by the views alone are
+                // taken from the pane that is split
+                view.set_read_only(focused.is_read_only());
+                view.set_trim_trailing_whitespace_on_save(
+                    focused.is_trim_trailing_whitespace_on_save(),
+                );
+                #[cfg(feature = "notify")]
+                if let Err(err) = view.watch_file(self.external_command_sender.clone()) {
+                    self.message_bar
+                        .set_message(format!("Cannot watch file for changes: {err}"));
+                }
+                self.panes.split(direction, view);
+                Some(CommandOutcome::handled())
+            }
+            EditorCommand::FocusNextPane => {
+                self.panes.focus_next();
+                Some(CommandOutcome::handled())
+            }
+            EditorCommand::ClosePane => {
+                let message = if self.panes.focused().get_status().is_dirty {
+                    "Cannot close a pane with unsaved changes"
+                } else if self.panes.close_focused() {
+                    return Some(CommandOutcome::handled());
+                } else {
+                    "Cannot close the last pane"
+                };
+
+                Some(CommandOutcome {
+                    message: Some(message.to_string()),
+                    ..CommandOutcome::handled()
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn dispatch_macro_command(&mut self, command: EditorCommand) -> CommandOutcome {
+        let message = match command {
+            EditorCommand::StartRecordingMacro => {
+                if self.macro_recording.is_some() {
+                    "Already recording a macro"
+                } else {
+                    self.macro_recording = Some(vec![]);
+                    "Recording macro..."
+                }
+            }
+            EditorCommand::StopRecordingMacro => match self.macro_recording.take() {
+                Some(recording) => {
+                    self.recorded_macro = recording;
+                    "Macro recorded"
+                }
+                None => "Not recording a macro",
+            },
+            EditorCommand::PlayMacro(times) => {
+                // playing while recording would make the recorded macro
+                // depend on the previous one, so disallow it
+                if self.macro_recording.is_some() {
+                    "Cannot play a macro while recording one"
+                } else {
+                    return self.play_macro(times);
+                }
+            }
+            _ => return CommandOutcome::not_handled(),
+        };
+
+        CommandOutcome {
+            message: Some(message.to_string()),
+            ..CommandOutcome::handled()
+        }
+    }
+
+    /// Replays the recorded macro. The outcomes of the replayed
+    /// commands are combined, so that work such as regenerating
+    /// highlights is done once after the whole replay.
+    fn play_macro(&mut self, times: usize) -> CommandOutcome {
+        let commands = self.recorded_macro.clone();
+        let mut outcome = CommandOutcome::handled();
+
+        for _ in 0..times {
+            for command in &commands {
+                // macros never contain macro commands, so this cannot recurse
+                let command_outcome = self.dispatch_command(*command);
+                outcome.buffer_changed |= command_outcome.buffer_changed;
+                if command_outcome.message.is_some() {
+                    outcome.message = command_outcome.message;
+                }
+            }
+        }
+
+        outcome
+    }
+
     fn handle_new_window_size(&mut self, size: Vec2f) {
-        self.view.set_bounds(Bounds2f {
+        self.panes.set_bounds(Bounds2f {
             pos: Vec2f::ZERO,
             size: Vec2f {
                 x: size.x,
@@ -168,49 +1105,11 @@ impl Editor {
                 kind: KeyEventKind::Press,
                 ..
             }) => {
-                let command = match (modifiers, code) {
-                    (&KeyModifiers::CONTROL, &KeyCode::Char('q')) => Some(EditorCommand::QuitAll),
-                    (&KeyModifiers::NONE, &KeyCode::Up) => Some(EditorCommand::MoveCursorUp),
-                    (&KeyModifiers::NONE, &KeyCode::Down) => Some(EditorCommand::MoveCursorDown),
-                    (&KeyModifiers::NONE, &KeyCode::Left) => Some(EditorCommand::MoveCursorLeft),
-                    (&KeyModifiers::NONE, &KeyCode::Right) => Some(EditorCommand::MoveCursorRight),
-                    (&KeyModifiers::NONE, &KeyCode::Home) => {
-                        Some(EditorCommand::MoveCursorToStartOfLine)
-                    }
-                    (&KeyModifiers::NONE, &KeyCode::End) => {
-                        Some(EditorCommand::MoveCursorToEndOfLine)
-                    }
-                    (&KeyModifiers::NONE, &KeyCode::PageUp) => {
-                        Some(EditorCommand::MoveCursorUpOnePage)
-                    }
-                    (&KeyModifiers::NONE, &KeyCode::PageDown) => {
-                        Some(EditorCommand::MoveCursorDownOnePage)
-                    }
-                    (&KeyModifiers::NONE | &KeyModifiers::SHIFT, &KeyCode::Char(ch)) => {
-                        // NOTE: for SHIFT case, crossterm automatically
-                        // converts ch to uppercase for us already. This
-                        // also means we do not need to manually handle
-                        // capslock scenario
-                        Some(EditorCommand::InsertCharacter(ch))
+                let command = match self.keymap.get_command(*modifiers, *code) {
+                    Some(EditorCommand::StartRecordingMacro) if self.macro_recording.is_some() => {
+                        Some(EditorCommand::StopRecordingMacro)
                     }
-                    (&KeyModifiers::NONE, &KeyCode::Backspace) => {
-                        Some(EditorCommand::EraseCharacterBeforeCursor)
-                    }
-                    (&KeyModifiers::NONE, &KeyCode::Delete) => {
-                        Some(EditorCommand::EraseCharacterAfterCursor)
-                    }
-                    (&KeyModifiers::NONE, &KeyCode::Tab) => {
-                        Some(EditorCommand::InsertCharacter('\t'))
-                    }
-                    (&KeyModifiers::NONE, &KeyCode::Enter) => Some(EditorCommand::InsertNewline),
-                    (&KeyModifiers::CONTROL, &KeyCode::Char('s')) => {
-                        Some(EditorCommand::WriteBufferToDisk)
-                    }
-                    (&KeyModifiers::NONE, &KeyCode::Esc) => Some(EditorCommand::Dismiss),
-                    (&KeyModifiers::CONTROL, &KeyCode::Char('f')) => {
-                        Some(EditorCommand::StartSearch)
-                    }
-                    _ => None,
+                    command => command,
                 };
 
                 if let Some(command) = command {
@@ -219,6 +1118,7 @@ impl Editor {
                     false
                 }
             }
+            Event::Mouse(mouse_event) => self.handle_mouse_event(*mouse_event),
             Event::Resize(width, height) => {
                 let size = Vec2f {
                     x: f64::from(*width),
@@ -232,12 +1132,64 @@ impl Editor {
         }
     }
 
+    fn handle_mouse_event(&mut self, mouse_event: MouseEvent) -> bool {
+        let screen_pos = Vec2f {
+            x: f64::from(mouse_event.column),
+            y: f64::from(mouse_event.row),
+        };
+
+        match mouse_event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                if self.command_bar.has_active_prompt() || !self.panes.focus_at(screen_pos) {
+                    return false;
+                }
+
+                let click_count = self.click_tracker.register_click(
+                    Vec2u {
+                        x: u64::from(mouse_event.column),
+                        y: u64::from(mouse_event.row),
+                    },
+                    Instant::now(),
+                );
+                self.panes.focused_mut().click(screen_pos, click_count);
+                self.is_dragging = true;
+                true
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                if !self.is_dragging {
+                    return false;
+                }
+
+                self.panes
+                    .focused_mut()
+                    .drag(screen_pos, self.click_tracker.click_count());
+                true
+            }
+            MouseEventKind::Up(MouseButton::Left) => {
+                if !self.is_dragging {
+                    return false;
+                }
+
+                self.is_dragging = false;
+                self.panes.focused_mut().end_drag();
+                true
+            }
+            kind => match mouse::get_scroll_delta(kind) {
+                Some(delta) => {
+                    self.panes.focused_mut().scroll_by(delta);
+                    true
+                }
+                None => false,
+            },
+        }
+    }
+
     fn draw(&mut self) -> Result<()> {
         self.drawer.clear();
 
-        self.view.render(&mut self.drawer);
+        self.panes.render(&mut self.drawer);
         self.status_bar
-            .render(&mut self.drawer, self.view.get_status());
+            .render(&mut self.drawer, &self.panes.focused().get_status());
 
         if self.command_bar.has_active_prompt() {
             self.command_bar.render(&mut self.drawer);
@@ -249,3 +1201,733 @@ impl Editor {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_play_macro() {
+        let mut editor = Editor::new();
+
+        editor.execute_command(EditorCommand::StartRecordingMacro);
+        editor.execute_command(EditorCommand::InsertCharacter('a'));
+        editor.execute_command(EditorCommand::InsertCharacter('b'));
+        editor.execute_command(EditorCommand::MoveCursorLeft);
+        // macro commands are not recorded, and do not restart the recording
+        editor.execute_command(EditorCommand::StartRecordingMacro);
+        editor.execute_command(EditorCommand::PlayMacro(1));
+        editor.execute_command(EditorCommand::StopRecordingMacro);
+        assert_eq!(
+            editor.recorded_macro,
+            vec![
+                EditorCommand::InsertCharacter('a'),
+                EditorCommand::InsertCharacter('b'),
+                EditorCommand::MoveCursorLeft,
+            ]
+        );
+        assert_eq!(editor.panes.focused().get_entire_contents_as_string(), "ab");
+
+        assert!(editor.execute_command(EditorCommand::PlayMacro(3)));
+        assert_eq!(
+            editor.panes.focused().get_entire_contents_as_string(),
+            "aaaabbbb"
+        );
+        assert_eq!(
+            editor.panes.focused().get_status().caret_position,
+            Vec2u { x: 4, y: 0 }
+        );
+
+        // commands played back are not recorded into a new macro
+        editor.execute_command(EditorCommand::StartRecordingMacro);
+        editor.execute_command(EditorCommand::StopRecordingMacro);
+        assert!(editor.recorded_macro.is_empty());
+    }
+
+    #[test]
+    fn test_repeat_last_change() {
+        let mut editor = Editor::new();
+
+        // nothing to repeat yet
+        assert!(!editor.execute_command(EditorCommand::RepeatLastChange));
+
+        editor.execute_command(EditorCommand::InsertCharacter('a'));
+        editor.execute_command(EditorCommand::InsertCharacter('b'));
+        editor.execute_command(EditorCommand::MoveCursorToStartOfLine);
+        assert!(editor.execute_command(EditorCommand::RepeatLastChange));
+        assert_eq!(
+            editor.panes.focused().get_entire_contents_as_string(),
+            "bab"
+        );
+
+        // movement does not replace the last change
+        editor.execute_command(EditorCommand::MoveCursorToEndOfLine);
+        editor.execute_command(EditorCommand::EraseCharacterBeforeCursor);
+        editor.execute_command(EditorCommand::MoveCursorToStartOfLine);
+        editor.execute_command(EditorCommand::MoveCursorRight);
+        editor.execute_command(EditorCommand::RepeatLastChange);
+        assert_eq!(editor.panes.focused().get_entire_contents_as_string(), "a");
+    }
+
+    #[test]
+    fn test_show_help() {
+        let mut editor = Editor::new();
+
+        assert!(editor.execute_command(EditorCommand::ShowHelp));
+        let message = editor.message_bar.message().expect("help to be shown");
+        assert!(message.contains("Ctrl-F = find"));
+        assert!(message.contains("Ctrl-S = save"));
+        assert!(message.contains("Ctrl-Q = quit"));
+    }
+
+    #[test]
+    fn test_jump_back_and_forward() {
+        let mut editor = Editor::new();
+        let caret_position = |editor: &Editor| editor.panes.focused().get_status().caret_position;
+        let type_text = |editor: &mut Editor, text: &str| {
+            text.chars().for_each(|ch| {
+                editor.execute_command(match ch {
+                    '\n' => EditorCommand::InsertNewline,
+                    ch => EditorCommand::InsertCharacter(ch),
+                });
+            });
+        };
+
+        type_text(&mut editor, "abc\nxyz\nabc xyz\nlast");
+        editor.execute_command(EditorCommand::MoveCursorToTop);
+        editor.execute_command(EditorCommand::MoveCursorRight);
+
+        // search for "xyz", then go to the last line
+        editor.execute_command(EditorCommand::StartSearch);
+        type_text(&mut editor, "xyz\n");
+        assert_eq!(caret_position(&editor), Vec2u { x: 0, y: 1 });
+        editor.execute_command(EditorCommand::MoveCursorToBottom);
+        assert_eq!(caret_position(&editor), Vec2u { x: 4, y: 3 });
+
+        editor.execute_command(EditorCommand::JumpBack);
+        assert_eq!(caret_position(&editor), Vec2u { x: 0, y: 1 });
+        editor.execute_command(EditorCommand::JumpBack);
+        assert_eq!(caret_position(&editor), Vec2u { x: 1, y: 0 });
+
+        // lines added before the positions move them along
+        editor.execute_command(EditorCommand::MoveCursorToStartOfLine);
+        editor.execute_command(EditorCommand::InsertNewline);
+        editor.execute_command(EditorCommand::JumpForward);
+        assert_eq!(caret_position(&editor), Vec2u { x: 0, y: 2 });
+        editor.execute_command(EditorCommand::JumpForward);
+        assert_eq!(caret_position(&editor), Vec2u { x: 4, y: 4 });
+        editor.execute_command(EditorCommand::JumpForward);
+        assert_eq!(caret_position(&editor), Vec2u { x: 4, y: 4 });
+    }
+
+    #[test]
+    fn test_incremental_search() {
+        let mut editor = Editor::new();
+        let caret_position = |editor: &Editor| editor.panes.focused().get_status().caret_position;
+        "xx abd abc".chars().for_each(|ch| {
+            editor.execute_command(EditorCommand::InsertCharacter(ch));
+        });
+        editor.execute_command(EditorCommand::MoveCursorToStartOfLine);
+        for _ in 0..5 {
+            editor.execute_command(EditorCommand::MoveCursorRight);
+        }
+
+        // each edit searches again from where the search started
+        editor.execute_command(EditorCommand::StartSearch);
+        let mut edit_search = |command: EditorCommand| {
+            editor.execute_command(command);
+            caret_position(&editor)
+        };
+        assert_eq!(
+            edit_search(EditorCommand::InsertCharacter('a')),
+            Vec2u { x: 7, y: 0 }
+        );
+        assert_eq!(
+            edit_search(EditorCommand::InsertCharacter('b')),
+            Vec2u { x: 7, y: 0 }
+        );
+        assert_eq!(
+            edit_search(EditorCommand::InsertCharacter('d')),
+            Vec2u { x: 3, y: 0 }
+        );
+        assert_eq!(
+            edit_search(EditorCommand::EraseCharacterBeforeCursor),
+            Vec2u { x: 7, y: 0 }
+        );
+
+        // even after moving between the matches
+        assert_eq!(
+            edit_search(EditorCommand::MoveCursorDown),
+            Vec2u { x: 3, y: 0 }
+        );
+        assert_eq!(
+            edit_search(EditorCommand::InsertCharacter('c')),
+            Vec2u { x: 7, y: 0 }
+        );
+
+        // nothing to search for goes back to where the search started
+        assert_eq!(
+            edit_search(EditorCommand::DeleteToStartOfLine),
+            Vec2u { x: 5, y: 0 }
+        );
+        assert_eq!(
+            edit_search(EditorCommand::InsertCharacter('x')),
+            Vec2u { x: 0, y: 0 }
+        );
+        assert_eq!(
+            edit_search(EditorCommand::EraseCharacterBeforeCursor),
+            Vec2u { x: 5, y: 0 }
+        );
+    }
+
+    #[test]
+    fn test_read_only() {
+        let mut editor = Editor::new();
+        let caret_position = |editor: &Editor| editor.panes.focused().get_status().caret_position;
+        "hello hello".chars().for_each(|ch| {
+            editor.execute_command(EditorCommand::InsertCharacter(ch));
+        });
+        editor.clipboard = Some("pasted".to_string());
+        editor.set_read_only(true);
+
+        // the commands that change the buffer are rejected
+        for command in [
+            EditorCommand::InsertCharacter('a'),
+            EditorCommand::EraseCharacterBeforeCursor,
+            EditorCommand::InsertNewline,
+            EditorCommand::Paste,
+            EditorCommand::Undo,
+        ] {
+            assert!(editor.execute_command(command));
+            assert_eq!(editor.message_bar.message(), Some("Buffer is read-only"));
+        }
+        assert_eq!(
+            editor.apply_edits(vec![Edit::Insert {
+                pos: TextBufferPos::default(),
+                text: "edited".to_string(),
+            }]),
+            Err(EditError::ReadOnly)
+        );
+        assert_eq!(
+            editor.panes.focused().get_entire_contents_as_string(),
+            "hello hello"
+        );
+        assert_eq!(
+            editor.last_change,
+            Some(EditorCommand::InsertCharacter('o'))
+        );
+
+        // but the caret can still be moved around, and the buffer searched
+        editor.execute_command(EditorCommand::MoveCursorToStartOfLine);
+        editor.execute_command(EditorCommand::MoveCursorRight);
+        assert_eq!(caret_position(&editor), Vec2u { x: 1, y: 0 });
+        editor.execute_command(EditorCommand::StartSearch);
+        "hello".chars().for_each(|ch| {
+            editor.execute_command(EditorCommand::InsertCharacter(ch));
+        });
+        assert_eq!(caret_position(&editor), Vec2u { x: 6, y: 0 });
+        assert_eq!(editor.message_bar.message(), Some("Match 2 of 2"));
+    }
+
+    #[test]
+    fn test_search_match_count() {
+        let mut editor = Editor::new();
+        let type_text = |editor: &mut Editor, text: &str| {
+            text.chars().for_each(|ch| {
+                editor.execute_command(EditorCommand::InsertCharacter(ch));
+            });
+        };
+
+        type_text(&mut editor, "one two one");
+        editor.execute_command(EditorCommand::InsertNewline);
+        type_text(&mut editor, "one");
+        editor.execute_command(EditorCommand::MoveCursorToTop);
+
+        editor.execute_command(EditorCommand::StartSearch);
+        type_text(&mut editor, "one");
+        assert_eq!(editor.message_bar.message(), Some("Match 1 of 3"));
+        editor.execute_command(EditorCommand::MoveCursorDown);
+        assert_eq!(editor.message_bar.message(), Some("Match 2 of 3"));
+        editor.execute_command(EditorCommand::MoveCursorDown);
+        assert_eq!(editor.message_bar.message(), Some("Match 3 of 3"));
+        editor.execute_command(EditorCommand::MoveCursorDown);
+        assert_eq!(
+            editor.message_bar.message(),
+            Some("Match 1 of 3 (search wrapped)")
+        );
+        editor.execute_command(EditorCommand::MoveCursorUp);
+        assert_eq!(
+            editor.message_bar.message(),
+            Some("Match 3 of 3 (search wrapped)")
+        );
+
+        type_text(&mut editor, "x");
+        assert_eq!(editor.message_bar.message(), Some("No matches"));
+        editor.execute_command(EditorCommand::Dismiss);
+
+        // without wrapping around, the end of the matches is reported
+        editor.set_search_wrap(false);
+        editor.execute_command(EditorCommand::MoveCursorToBottom);
+        editor.execute_command(EditorCommand::StartSearch);
+        type_text(&mut editor, "two");
+        editor.execute_command(EditorCommand::MoveCursorDown);
+        assert_eq!(editor.message_bar.message(), Some("No more matches"));
+    }
+
+    #[test]
+    fn test_regex_search() {
+        let mut editor = Editor::new();
+        let caret_position = |editor: &Editor| editor.panes.focused().get_status().caret_position;
+        let type_text = |editor: &mut Editor, text: &str| {
+            text.chars().for_each(|ch| {
+                editor.execute_command(match ch {
+                    '\n' => EditorCommand::InsertNewline,
+                    ch => EditorCommand::InsertCharacter(ch),
+                });
+            });
+        };
+
+        type_text(&mut editor, "let a1 = 1;\nlet b22 = 2;\nfn c3() {}");
+        editor.execute_command(EditorCommand::MoveCursorToTop);
+
+        editor.execute_command(EditorCommand::StartRegexSearch);
+        type_text(&mut editor, "[a-z][0-9]+");
+        assert_eq!(caret_position(&editor), Vec2u { x: 4, y: 0 });
+        editor.execute_command(EditorCommand::MoveCursorDown);
+        assert_eq!(caret_position(&editor), Vec2u { x: 4, y: 1 });
+        editor.execute_command(EditorCommand::MoveCursorDown);
+        assert_eq!(caret_position(&editor), Vec2u { x: 3, y: 2 });
+        editor.execute_command(EditorCommand::MoveCursorUp);
+        assert_eq!(caret_position(&editor), Vec2u { x: 4, y: 1 });
+        editor.execute_command(EditorCommand::InsertNewline);
+        assert_eq!(caret_position(&editor), Vec2u { x: 4, y: 1 });
+
+        // an invalid regex is reported, and does not move the caret
+        editor.execute_command(EditorCommand::StartRegexSearch);
+        type_text(&mut editor, "^fn (");
+        assert_eq!(
+            editor.message_bar.message(),
+            Some("Invalid regex: unclosed group")
+        );
+        assert_eq!(caret_position(&editor), Vec2u { x: 0, y: 2 });
+        editor.execute_command(EditorCommand::MoveCursorDown);
+        assert_eq!(caret_position(&editor), Vec2u { x: 0, y: 2 });
+        editor.execute_command(EditorCommand::Dismiss);
+        assert_eq!(caret_position(&editor), Vec2u { x: 4, y: 1 });
+    }
+
+    #[test]
+    fn test_swap_selection_with_clipboard() {
+        let mut editor = Editor::new();
+        "one two".chars().for_each(|ch| {
+            editor.execute_command(EditorCommand::InsertCharacter(ch));
+        });
+
+        editor.execute_command(EditorCommand::SwapSelectionWithClipboard);
+        assert_eq!(editor.message_bar.message(), Some("Clipboard is empty"));
+
+        editor.execute_command(EditorCommand::MoveCursorToStartOfLine);
+        editor.execute_command(EditorCommand::SelectWord);
+        editor.execute_command(EditorCommand::Copy);
+        editor.execute_command(EditorCommand::MoveCursorToEndOfLine);
+        editor.execute_command(EditorCommand::MoveCursorLeft);
+        editor.execute_command(EditorCommand::SelectWord);
+
+        assert!(editor.execute_command(EditorCommand::SwapSelectionWithClipboard));
+        assert_eq!(
+            editor.panes.focused().get_entire_contents_as_string(),
+            "one one"
+        );
+        assert_eq!(editor.clipboard.as_deref(), Some("two"));
+
+        // the swapped in text stays selected, so swapping again undoes it
+        assert!(editor.execute_command(EditorCommand::SwapSelectionWithClipboard));
+        assert_eq!(
+            editor.panes.focused().get_entire_contents_as_string(),
+            "one two"
+        );
+        assert_eq!(editor.clipboard.as_deref(), Some("one"));
+    }
+
+    #[test]
+    fn test_external_commands() {
+        let mut editor = Editor::new();
+        editor.set_contents("b");
+        assert!(!editor.handle_external_commands());
+
+        let sender = editor.external_command_sender();
+        std::thread::spawn(move || {
+            sender
+                .send(ExternalCommand::Command(EditorCommand::InsertCharacter(
+                    'a',
+                )))
+                .expect("editor is still receiving");
+            sender
+                .send(ExternalCommand::Edits(vec![Edit::Insert {
+                    pos: TextBufferPos { line: 0, byte: 2 },
+                    text: "c".to_string(),
+                }]))
+                .expect("editor is still receiving");
+        })
+        .join()
+        .expect("sending thread does not panic");
+        // nothing is applied until the next tick
+        assert_eq!(editor.contents(), "b");
+
+        assert!(editor.handle_external_commands());
+        assert_eq!(editor.contents(), "abc");
+        assert!(!editor.handle_external_commands());
+
+        // the progress of the other thread is shown until it is done
+        let sender = editor.external_command_sender();
+        sender
+            .send(ExternalCommand::Progress(Some("Indexing 40%".to_string())))
+            .expect("editor is still receiving");
+        assert!(editor.handle_external_commands());
+        assert_eq!(
+            editor.status_bar.get_progress_text().as_deref(),
+            Some("⠋ Indexing 40%")
+        );
+        sender
+            .send(ExternalCommand::Progress(None))
+            .expect("editor is still receiving");
+        assert!(editor.handle_external_commands());
+        assert_eq!(editor.status_bar.get_progress_text(), None);
+    }
+
+    #[test]
+    fn test_goto_line() {
+        let mut editor = Editor::new();
+        editor.set_contents("one\ntwo\nthree");
+        let goto_line = |editor: &mut Editor, input: &str| {
+            editor.execute_command(EditorCommand::StartGotoLine);
+            for ch in input.chars() {
+                editor.execute_command(EditorCommand::InsertCharacter(ch));
+            }
+            editor.execute_command(EditorCommand::InsertNewline);
+            editor.caret()
+        };
+        let pos = |line| TextBufferPos { line, byte: 0 };
+
+        assert_eq!(goto_line(&mut editor, "2"), pos(1));
+        // beyond the last line
+        assert_eq!(goto_line(&mut editor, "10"), pos(2));
+        assert_eq!(goto_line(&mut editor, "x"), pos(2));
+        assert_eq!(
+            editor.message_bar.message(),
+            Some("Not a valid line number")
+        );
+        // the caret can jump back to where it was before
+        editor.execute_command(EditorCommand::JumpBack);
+        assert_eq!(editor.caret(), pos(1));
+    }
+
+    #[test]
+    fn test_marks() {
+        let mut editor = Editor::new();
+        editor.set_contents("one\ntwo\nthree");
+        let submit = |editor: &mut Editor, command: EditorCommand, input: &str| {
+            editor.execute_command(command);
+            for ch in input.chars() {
+                editor.execute_command(EditorCommand::InsertCharacter(ch));
+            }
+            editor.execute_command(EditorCommand::InsertNewline);
+            editor.caret()
+        };
+
+        editor.execute_command(EditorCommand::MoveCursorDown);
+        editor.execute_command(EditorCommand::MoveCursorRight);
+        submit(&mut editor, EditorCommand::SetMark, "a");
+        assert_eq!(editor.message_bar.message(), Some("Mark a set"));
+
+        editor.execute_command(EditorCommand::MoveCursorToBottom);
+        assert_eq!(
+            submit(&mut editor, EditorCommand::JumpToMark, "a"),
+            TextBufferPos { line: 1, byte: 1 }
+        );
+        assert_eq!(
+            submit(&mut editor, EditorCommand::JumpToMark, "b"),
+            TextBufferPos { line: 1, byte: 1 }
+        );
+        assert_eq!(editor.message_bar.message(), Some("Mark b not set"));
+        submit(&mut editor, EditorCommand::SetMark, "ab");
+        assert_eq!(editor.message_bar.message(), Some("Not a valid mark"));
+
+        // the caret can jump back to where it was before
+        editor.execute_command(EditorCommand::JumpBack);
+        assert_eq!(editor.caret(), TextBufferPos { line: 2, byte: 5 });
+    }
+
+    #[test]
+    fn test_cut_and_paste() {
+        let mut editor = Editor::new();
+        editor.set_contents("one two");
+        assert!(editor.execute_command(EditorCommand::Paste));
+        assert_eq!(editor.contents(), "one two");
+
+        editor.execute_command(EditorCommand::ExtendSelectionRight);
+        editor.execute_command(EditorCommand::ExtendSelectionRight);
+        editor.execute_command(EditorCommand::ExtendSelectionRight);
+        editor.execute_command(EditorCommand::Cut);
+        assert_eq!(editor.contents(), " two");
+
+        editor.execute_command(EditorCommand::MoveCursorToEndOfLine);
+        editor.execute_command(EditorCommand::Paste);
+        editor.execute_command(EditorCommand::Paste);
+        assert_eq!(editor.contents(), " twooneone");
+    }
+
+    #[test]
+    fn test_file_changed() {
+        let path = std::env::temp_dir().join("knap_test_file_changed.txt");
+        let filename = path.to_str().expect("valid path").to_string();
+        std::fs::write(&path, "one").expect("able to write test file");
+        let mut editor = Editor::new();
+        editor
+            .panes
+            .replace_focused(CodeView::new_from_file(&filename).expect("able to open test file"));
+        let sender = editor.external_command_sender();
+
+        // without changes, the buffer is reloaded right away
+        std::fs::write(&path, "two").expect("able to write test file");
+        sender
+            .send(ExternalCommand::FileChanged(filename.clone()))
+            .expect("editor is still receiving");
+        assert!(editor.handle_external_commands());
+        assert_eq!(editor.contents(), "two");
+        assert!(!editor.panes.focused().get_status().is_dirty);
+
+        // with changes, the user is asked first
+        editor.execute_command(EditorCommand::InsertCharacter('a'));
+        std::fs::write(&path, "three").expect("able to write test file");
+        sender
+            .send(ExternalCommand::FileChanged(filename.clone()))
+            .expect("editor is still receiving");
+        assert!(editor.handle_external_commands());
+        assert_eq!(editor.contents(), "atwo");
+        editor.execute_command(EditorCommand::InsertCharacter('n'));
+        editor.execute_command(EditorCommand::InsertNewline);
+        assert_eq!(editor.contents(), "atwo");
+
+        sender
+            .send(ExternalCommand::FileChanged(filename.clone()))
+            .expect("editor is still receiving");
+        assert!(editor.handle_external_commands());
+        editor.execute_command(EditorCommand::InsertCharacter('y'));
+        editor.execute_command(EditorCommand::InsertNewline);
+        assert_eq!(editor.contents(), "three");
+        assert!(!editor.panes.focused().get_status().is_dirty);
+
+        // the view saving the file is not a change by another program
+        editor.execute_command(EditorCommand::InsertCharacter('a'));
+        editor.execute_command(EditorCommand::WriteBufferToDisk);
+        editor.execute_command(EditorCommand::InsertCharacter('b'));
+        sender
+            .send(ExternalCommand::FileChanged(filename))
+            .expect("editor is still receiving");
+        assert!(editor.handle_external_commands());
+        assert!(!editor.command_bar.has_active_prompt());
+        assert_eq!(editor.contents(), "tabhree");
+
+        std::fs::remove_file(&path).expect("able to remove test file");
+    }
+
+    #[test]
+    #[cfg(feature = "notify")]
+    fn test_watch_file_saved_as() {
+        let path = std::env::temp_dir().join("knap_test_watch_file_saved_as.txt");
+        let filename = path.to_str().expect("valid path");
+        std::fs::remove_file(&path).ok();
+        let mut editor = Editor::new();
+        editor.execute_command(EditorCommand::InsertCharacter('a'));
+        editor.execute_command(EditorCommand::WriteBufferToDisk);
+        for ch in filename.chars() {
+            editor.execute_command(EditorCommand::InsertCharacter(ch));
+        }
+        editor.execute_command(EditorCommand::InsertNewline);
+        assert_eq!(
+            std::fs::read_to_string(&path).expect("able to read test file"),
+            "a\n"
+        );
+
+        // the file that the untitled buffer was saved as is watched
+        std::fs::write(&path, "b").expect("able to write test file");
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while editor.contents() != "b" && Instant::now() < deadline {
+            editor.handle_external_commands();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(editor.contents(), "b");
+
+        std::fs::remove_file(&path).expect("able to remove test file");
+    }
+
+    #[test]
+    fn test_split_shows_focused_file() {
+        let path = std::env::temp_dir().join("knap_test_split_shows_focused_file.txt");
+        let filename = path.to_str().expect("valid path").to_string();
+        std::fs::write(&path, "one\ntwo").expect("able to write test file");
+        let mut editor = Editor::new();
+
+        // an untitled buffer is split into an empty one
+        editor.set_contents("scratch");
+        editor.execute_command(EditorCommand::SplitVertical);
+        assert_eq!(editor.contents(), "");
+        editor.execute_command(EditorCommand::ClosePane);
+
+        // a file is shown in both panes, at the same position
+        editor
+            .panes
+            .replace_focused(CodeView::new_from_file(&filename).expect("able to open test file"));
+        editor.set_selection(
+            TextBufferPos { line: 1, byte: 2 },
+            TextBufferPos { line: 1, byte: 2 },
+        );
+        editor.execute_command(EditorCommand::SplitHorizontal);
+        assert_eq!(editor.panes.focused().filename(), Some(filename.as_str()));
+        assert_eq!(editor.contents(), "one\ntwo");
+        assert_eq!(editor.caret(), TextBufferPos { line: 1, byte: 2 });
+
+        std::fs::remove_file(&path).expect("able to remove test file");
+    }
+
+    #[test]
+    fn test_apply_edits() {
+        let pos = |line, byte| TextBufferPos { line, byte };
+        let mut editor = Editor::new();
+        editor.set_contents("fn a() {}\nfn b() {}");
+        editor.set_selection(pos(1, 3), pos(1, 4));
+        assert_eq!(editor.selection(), Some(pos(1, 3)..pos(1, 4)));
+
+        let revert_edits = editor
+            .apply_edits(vec![
+                Edit::Insert {
+                    pos: pos(0, 0),
+                    text: "// a\n".to_string(),
+                },
+                Edit::Insert {
+                    pos: pos(2, 0),
+                    text: "// b\n".to_string(),
+                },
+            ])
+            .expect("edits are valid");
+        assert_eq!(editor.contents(), "// a\nfn a() {}\n// b\nfn b() {}");
+        assert!(editor.panes.focused().get_status().is_dirty);
+
+        // a single transaction reverts all of the edits
+        editor
+            .apply_edits(revert_edits)
+            .expect("revert edits are valid");
+        assert_eq!(editor.contents(), "fn a() {}\nfn b() {}");
+
+        editor.set_caret(pos(1, 40));
+        assert_eq!(editor.caret(), pos(1, 9));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_filter_through_command() {
+        let mut editor = Editor::new();
+        let type_text = |editor: &mut Editor, text: &str| {
+            text.chars().for_each(|ch| {
+                editor.execute_command(EditorCommand::InsertCharacter(ch));
+            });
+        };
+        type_text(&mut editor, "one two");
+        editor.execute_command(EditorCommand::MoveCursorLeft);
+        editor.execute_command(EditorCommand::SelectWord);
+
+        // a stand-in for a filter, which prints what it reads in uppercase
+        editor.execute_command(EditorCommand::FilterThroughCommand);
+        type_text(&mut editor, "tr a-z A-Z");
+        editor.execute_command(EditorCommand::InsertNewline);
+        assert_eq!(editor.contents(), "one TWO");
+
+        // without a selection, the whole buffer is filtered
+        editor.execute_command(EditorCommand::FilterThroughCommand);
+        type_text(&mut editor, "tr ' ' '\\n' | LC_ALL=C sort");
+        editor.execute_command(EditorCommand::InsertNewline);
+        assert_eq!(editor.contents(), "TWO\none");
+
+        // a failing command leaves the buffer as it is
+        editor.execute_command(EditorCommand::FilterThroughCommand);
+        type_text(&mut editor, "echo oops >&2; exit 1");
+        editor.execute_command(EditorCommand::InsertNewline);
+        assert_eq!(editor.contents(), "TWO\none");
+        assert!(editor.message_bar.message().is_some_and(|message| {
+            message.starts_with("Filter failed") && message.ends_with("oops")
+        }));
+
+        // a command can exit before reading all of its input
+        editor.set_contents("line\n".repeat(20_000));
+        editor.execute_command(EditorCommand::FilterThroughCommand);
+        type_text(&mut editor, "head -n 1");
+        editor.execute_command(EditorCommand::InsertNewline);
+        assert_eq!(editor.contents(), "line");
+    }
+
+    #[test]
+    fn test_kill_and_yank() {
+        let mut editor = Editor::new();
+        editor.set_contents("one\ntwo\nthree");
+
+        // consecutive kills are yanked back together
+        for _ in 0..4 {
+            editor.execute_command(EditorCommand::DeleteToEndOfLine);
+        }
+        assert_eq!(
+            editor.panes.focused().get_entire_contents_as_string(),
+            "three"
+        );
+        assert!(editor.execute_command(EditorCommand::Yank));
+        assert_eq!(
+            editor.panes.focused().get_entire_contents_as_string(),
+            "one\ntwo\nthree"
+        );
+        assert_eq!(editor.caret(), TextBufferPos { line: 2, byte: 0 });
+
+        editor.execute_command(EditorCommand::YankPop);
+        assert_eq!(
+            editor.panes.focused().get_entire_contents_as_string(),
+            "one\ntwo\nthree"
+        );
+        editor.execute_command(EditorCommand::MoveCursorRight);
+        editor.execute_command(EditorCommand::YankPop);
+        assert_eq!(
+            editor.message_bar.message(),
+            Some("Previous command was not a yank")
+        );
+
+        // a kill that does not follow a kill starts a new entry
+        editor.execute_command(EditorCommand::DeleteToEndOfLine);
+        editor.execute_command(EditorCommand::DeleteToStartOfLine);
+        editor.execute_command(EditorCommand::MoveCursorUp);
+        editor.execute_command(EditorCommand::DeleteToEndOfLine);
+        assert_eq!(
+            editor.panes.focused().get_entire_contents_as_string(),
+            "one\n\n"
+        );
+
+        // yanking then cycles through the kills, newest first
+        editor.execute_command(EditorCommand::Yank);
+        assert_eq!(
+            editor.panes.focused().get_entire_contents_as_string(),
+            "one\ntwo\n"
+        );
+        editor.execute_command(EditorCommand::YankPop);
+        assert_eq!(
+            editor.panes.focused().get_entire_contents_as_string(),
+            "one\nthree\n"
+        );
+        editor.execute_command(EditorCommand::YankPop);
+        assert_eq!(
+            editor.panes.focused().get_entire_contents_as_string(),
+            "one\none\ntwo\n\n"
+        );
+        editor.execute_command(EditorCommand::YankPop);
+        assert_eq!(
+            editor.panes.focused().get_entire_contents_as_string(),
+            "one\ntwo\n"
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, RecvTimeoutError, Sender},
+    thread,
+    time::Duration,
+};
+
+use anyhow::Result;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::editor::ExternalCommand;
+
+/// How long a file has to stay unchanged before its change is reported,
+/// so that a burst of writes (e.g. by a formatter) is reported once.
+const DEBOUNCE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Watches a file for changes made outside of the editor, and sends
+/// `ExternalCommand::FileChanged` for them. The file is no longer
+/// watched once this is dropped.
+pub(crate) struct FileWatcher {
+    /// kept so that the watching goes on until this is dropped
+    _watcher: RecommendedWatcher,
+}
+
+fn is_change_of(event: &Event, path: &Path) -> bool {
+    matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+        && event.paths.iter().any(|event_path| event_path == path)
+}
+
+impl FileWatcher {
+    pub(crate) fn new(filename: &str, sender: Sender<ExternalCommand>) -> Result<Self> {
+        let path = std::path::absolute(filename)?;
+        // the directory is watched instead of the file, so that the file is
+        // still watched after it is replaced (e.g. by renaming another file)
+        let dir = path.parent().map_or_else(PathBuf::new, Path::to_path_buf);
+
+        let (event_sender, event_receiver) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            if event.is_ok_and(|event| is_change_of(&event, &path)) {
+                // only fails once the debouncing thread is gone
+                event_sender.send(()).ok();
+            }
+        })?;
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+
+        let filename = filename.to_string();
+        // ends once the watcher, which has the event sender, is dropped
+        thread::spawn(move || {
+            while event_receiver.recv().is_ok() {
+                loop {
+                    match event_receiver.recv_timeout(DEBOUNCE_INTERVAL) {
+                        Ok(()) => {}
+                        Err(RecvTimeoutError::Timeout) => break,
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+                if sender
+                    .send(ExternalCommand::FileChanged(filename.clone()))
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        });
+
+        Ok(Self { _watcher: watcher })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_change_is_reported_once() {
+        let path = std::env::temp_dir().join("knap_test_file_watcher.txt");
+        std::fs::write(&path, "before").expect("able to write test file");
+        let filename = path.to_str().expect("valid path");
+
+        let (sender, receiver) = mpsc::channel();
+        let watcher = FileWatcher::new(filename, sender).expect("able to watch test file");
+        for contents in ["one", "two", "three"] {
+            std::fs::write(&path, contents).expect("able to write test file");
+        }
+
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_secs(5)),
+            Ok(ExternalCommand::FileChanged(filename.to_string()))
+        );
+        // the writes are close together, so they are reported as one change
+        assert!(
+            receiver
+                .recv_timeout(DEBOUNCE_INTERVAL.saturating_mul(3))
+                .is_err()
+        );
+
+        drop(watcher);
+        std::fs::remove_file(&path).expect("able to remove test file");
+    }
+}
@@ -0,0 +1,64 @@
+use std::path::Path;
+
+/// How many characters of the commit hash to show for a detached `HEAD`.
+const SHORT_HASH_LEN: usize = 7;
+
+/// Parses the contents of `.git/HEAD`, returning the branch name
+/// (or the short commit hash if `HEAD` is detached).
+fn parse_head(head: &str) -> Option<String> {
+    let head = head.trim();
+
+    if let Some(head_ref) = head.strip_prefix("ref:") {
+        let head_ref = head_ref.trim();
+        Some(
+            head_ref
+                .strip_prefix("refs/heads/")
+                .unwrap_or(head_ref)
+                .to_string(),
+        )
+    } else if !head.is_empty() && head.chars().all(|ch| ch.is_ascii_hexdigit()) {
+        Some(head.chars().take(SHORT_HASH_LEN).collect())
+    } else {
+        None
+    }
+}
+
+/// Returns the current git branch of the repository that `path`
+/// is in, by looking for `.git/HEAD` in each of its ancestors.
+pub(crate) fn find_git_branch<T: AsRef<Path>>(path: T) -> Option<String> {
+    let path = std::path::absolute(path.as_ref()).ok()?;
+
+    path.ancestors().find_map(|dir| {
+        let head = std::fs::read_to_string(dir.join(".git").join("HEAD")).ok()?;
+        parse_head(&head)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_git_branch() {
+        let repo_dir = std::env::temp_dir().join("knap_test_find_git_branch");
+        let git_dir = repo_dir.join(".git");
+        std::fs::create_dir_all(&git_dir).expect("able to create test directory");
+        let file_path = repo_dir.join("src").join("main.rs");
+
+        std::fs::write(git_dir.join("HEAD"), "ref: refs/heads/feature/status-bar\n")
+            .expect("able to write test file");
+        assert_eq!(
+            find_git_branch(&file_path).as_deref(),
+            Some("feature/status-bar")
+        );
+
+        std::fs::write(
+            git_dir.join("HEAD"),
+            "0123456789abcdef0123456789abcdef01234567\n",
+        )
+        .expect("able to write test file");
+        assert_eq!(find_git_branch(&file_path).as_deref(), Some("0123456"));
+
+        std::fs::remove_dir_all(&repo_dir).expect("able to remove test directory");
+    }
+}
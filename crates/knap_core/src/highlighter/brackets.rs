@@ -0,0 +1,73 @@
+use std::ops::Range;
+
+/// The brackets that are matched, as (opening, closing) pairs.
+const BRACKET_PAIRS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+/// Returns the positions (line index and byte index) of the brackets in
+/// `lines` that have no matching bracket. Each line comes with the byte
+/// ranges (e.g. strings and comments) whose brackets are not counted.
+///
+/// A closing bracket matches the nearest opening bracket of its kind that
+/// is still open, and the opening brackets after that one are unmatched,
+/// e.g. the `(` in `{ ( }`.
+pub(crate) fn find_unmatched_brackets<I, S>(lines: I) -> Vec<(usize, usize)>
+where
+    I: IntoIterator<Item = (S, Vec<Range<usize>>)>,
+    S: AsRef<str>,
+{
+    let mut unmatched = vec![];
+    // the opening brackets that are still open, innermost last
+    let mut open: Vec<(char, (usize, usize))> = vec![];
+
+    for (line_idx, (line, skipped_ranges)) in lines.into_iter().enumerate() {
+        for (byte_idx, ch) in line.as_ref().char_indices() {
+            if skipped_ranges.iter().any(|range| range.contains(&byte_idx)) {
+                continue;
+            }
+
+            if BRACKET_PAIRS.iter().any(|(opening, _)| *opening == ch) {
+                open.push((ch, (line_idx, byte_idx)));
+            } else if let Some((opening, _)) =
+                BRACKET_PAIRS.iter().find(|(_, closing)| *closing == ch)
+            {
+                match open.iter().rposition(|(open_ch, _)| open_ch == opening) {
+                    Some(open_idx) => {
+                        unmatched.extend(open.drain(open_idx..).skip(1).map(|(_, pos)| pos));
+                    }
+                    None => unmatched.push((line_idx, byte_idx)),
+                }
+            }
+        }
+    }
+
+    unmatched.extend(open.into_iter().map(|(_, pos)| pos));
+    unmatched.sort_unstable();
+    unmatched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(lines: &[&str]) -> Vec<(usize, usize)> {
+        find_unmatched_brackets(lines.iter().map(|line| (*line, vec![])))
+    }
+
+    #[test]
+    fn test_find_unmatched_brackets() {
+        assert!(find(&["fn main() {", "    let a = [1, (2)];", "}"]).is_empty());
+
+        // an extra `)`
+        assert_eq!(find(&["f(a));", "g()"]), vec![(0, 4)]);
+
+        // an unclosed `{`
+        assert_eq!(find(&["fn main() {", "    f();", ""]), vec![(0, 10)]);
+
+        // closing the outer bracket leaves the inner one unmatched
+        assert_eq!(find(&["{ ( }"]), vec![(0, 2)]);
+        assert_eq!(find(&["( ] )"]), vec![(0, 2)]);
+
+        // brackets in the skipped ranges are not counted
+        assert!(find_unmatched_brackets([("f(\")\") // )", vec![2..5, 7..11])]).is_empty());
+    }
+}
@@ -0,0 +1,1240 @@
+mod brackets;
+mod rust_highlighter;
+#[cfg(feature = "tree-sitter")]
+mod tree_sitter_highlighter;
+
+use std::{cmp::Reverse, collections::HashMap, ops::Range};
+
+use knap_base::math::{ToUsize, Vec2u};
+use knap_ui::{
+    text_box::{IndentStyle, TextBox, TextHighlightBlock, TextHighlightLine, TextHighlights},
+    text_buffer::{TextBuffer, TextBufferPos, is_whole_word},
+};
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{code_view::FileType, theme::Theme};
+
+use brackets::find_unmatched_brackets;
+pub(crate) use rust_highlighter::RustHighlighter;
+#[cfg(feature = "tree-sitter")]
+pub(crate) use tree_sitter_highlighter::TreeSitterHighlighter;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum HighlightType {
+    Number,
+    SearchMatch,
+    SearchCursor,
+    Keyword,
+    BasicType,
+    EnumLiteral,
+    /// sort of... the tutorial doesn't specify how to verify that
+    /// it is actually a legal Rust character
+    Character,
+    LifetimeSpecifier,
+    Comment,
+    String,
+    /// tags like `TODO` that appear inside a comment
+    CommentTag,
+    TrailingWhitespace,
+    /// indentation that does not match the indentation style of the file,
+    /// or trailing whitespace, when revealing whitespace errors
+    WhitespaceError,
+    /// other occurrences of the identifier under the caret
+    WordUnderCaret,
+    /// a bracket without a matching bracket
+    UnmatchedBracket,
+    /// the bracket under the caret, and the bracket matching it
+    MatchingBracket,
+}
+
+impl HighlightType {
+    pub(crate) const ALL: [HighlightType; 16] = [
+        HighlightType::Number,
+        HighlightType::SearchMatch,
+        HighlightType::SearchCursor,
+        HighlightType::Keyword,
+        HighlightType::BasicType,
+        HighlightType::EnumLiteral,
+        HighlightType::Character,
+        HighlightType::LifetimeSpecifier,
+        HighlightType::Comment,
+        HighlightType::String,
+        HighlightType::CommentTag,
+        HighlightType::TrailingWhitespace,
+        HighlightType::WhitespaceError,
+        HighlightType::WordUnderCaret,
+        HighlightType::UnmatchedBracket,
+        HighlightType::MatchingBracket,
+    ];
+
+    /// When highlights overlap, the one with the higher priority wins.
+    fn priority(self) -> u8 {
+        match self {
+            HighlightType::SearchCursor => 10,
+            HighlightType::SearchMatch => 9,
+            HighlightType::WordUnderCaret | HighlightType::MatchingBracket => 8,
+            HighlightType::UnmatchedBracket => 7,
+            HighlightType::TrailingWhitespace | HighlightType::WhitespaceError => 6,
+            HighlightType::CommentTag => 5,
+            HighlightType::Comment | HighlightType::String => 4,
+            HighlightType::Character => 3,
+            HighlightType::LifetimeSpecifier => 2,
+            HighlightType::Keyword
+            | HighlightType::BasicType
+            | HighlightType::EnumLiteral
+            | HighlightType::Number => 1,
+        }
+    }
+}
+
+pub(crate) struct Highlight {
+    highlight_type: HighlightType,
+    range: Range<usize>,
+}
+
+pub(crate) struct Highlights {
+    highlights: Vec<Highlight>,
+}
+
+pub(crate) const DEFAULT_COMMENT_TAGS: [&str; 5] = ["TODO", "FIXME", "HACK", "SAFETY", "NOTE"];
+
+/// Highlighting behaviour that applies regardless of the language.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct HighlightOptions {
+    /// tags to highlight when they appear inside a comment
+    pub comment_tags: Vec<String>,
+    pub highlight_trailing_whitespace: bool,
+    /// off by default, as brackets are unmatched for a while when typing
+    pub highlight_unmatched_brackets: bool,
+}
+
+impl Default for HighlightOptions {
+    fn default() -> Self {
+        Self {
+            comment_tags: DEFAULT_COMMENT_TAGS
+                .iter()
+                .map(|tag| (*tag).to_string())
+                .collect(),
+            highlight_trailing_whitespace: false,
+            highlight_unmatched_brackets: false,
+        }
+    }
+}
+
+/// An identifier whose occurrences on the visible lines are highlighted,
+/// apart from the occurrence under the caret itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct WordUnderCaret {
+    pub word: String,
+    /// line of the occurrence under the caret
+    pub line_idx: usize,
+    /// byte index (within its line) of the occurrence under the caret
+    pub byte_idx: usize,
+    pub visible_lines: Range<usize>,
+}
+
+pub(crate) struct HighlightInfo<B: TextBuffer> {
+    text_highlight: TextHighlights,
+    highlighter: Box<dyn Highlighter>,
+    options: HighlightOptions,
+    theme: Theme,
+    /// what is searched for and the position of the search cursor
+    search: Option<(SearchPattern, Vec2u)>,
+    word_under_caret: Option<WordUnderCaret>,
+    /// the indentation style that whitespace errors are found
+    /// against, if they are being revealed
+    whitespace_errors: Option<IndentStyle>,
+    /// for each line, the ranges of the brackets without a match, which
+    /// are only found when the buffer changes as the whole buffer is scanned
+    unmatched_brackets: HashMap<usize, Vec<Range<usize>>>,
+    /// the bracket under the caret and the bracket matching it
+    matching_brackets: Option<(TextBufferPos, TextBufferPos)>,
+    /// the context that each line starts with (and so the previous line
+    /// ends with), kept until the buffer changes
+    line_contexts: Vec<LineContext>,
+    _phantom: std::marker::PhantomData<B>,
+}
+
+/// State carried over from the previous line, for constructs
+/// that can span multiple lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct LineContext {
+    pub line_idx: usize,
+    /// how many block comments are still open at the start of the
+    /// line, as they can be nested (e.g. `/* /* */ */` in Rust)
+    pub block_comment_depth: usize,
+}
+
+/// Produces the language specific highlights of a file, one line
+/// at a time.
+pub(crate) trait Highlighter {
+    /// Returns the highlights of `line`, where each range is in bytes.
+    fn highlight_line(&self, line: &str, ctx: LineContext) -> Vec<(Range<usize>, HighlightType)>;
+
+    /// Returns the context that the line after `line` should be
+    /// highlighted with.
+    fn next_line_context(&self, _line: &str, ctx: LineContext) -> LineContext {
+        ctx
+    }
+
+    /// Called with the entire contents of the buffer whenever it
+    /// changes, for highlighters that need more than a single line.
+    /// Only `changed_lines` (numbered as they are now, see
+    /// `TextBox::take_changed_lines`) have changed since the last call,
+    /// or any line could have if it is `None`.
+    fn on_buffer_change(&mut self, _contents: &str, _changed_lines: Option<Range<usize>>) {}
+
+    /// Whether the highlights of a line can depend on more than the line
+    /// and its context, in which case every line is highlighted again
+    /// whenever the buffer changes (after `Self::on_buffer_change`).
+    fn needs_whole_buffer(&self) -> bool {
+        false
+    }
+}
+
+/// Does not highlight anything, used for plain text.
+pub(crate) struct NullHighlighter;
+
+impl Highlighter for NullHighlighter {
+    fn highlight_line(&self, _line: &str, _ctx: LineContext) -> Vec<(Range<usize>, HighlightType)> {
+        vec![]
+    }
+}
+
+fn get_highlighter_for_file_type(file_type: FileType) -> Box<dyn Highlighter> {
+    match file_type {
+        #[cfg(feature = "tree-sitter")]
+        FileType::Rust => match TreeSitterHighlighter::new_rust() {
+            Some(highlighter) => Box::new(highlighter),
+            None => Box::new(RustHighlighter),
+        },
+        #[cfg(not(feature = "tree-sitter"))]
+        FileType::Rust => Box::new(RustHighlighter),
+        FileType::PlainText => Box::new(NullHighlighter),
+    }
+}
+
+fn get_comment_tag_highlights(
+    line: &str,
+    comment_range: Range<usize>,
+    comment_tags: &[String],
+) -> Vec<Highlight> {
+    line[comment_range.clone()]
+        .split_word_bound_indices()
+        .filter(|(_, word)| comment_tags.iter().any(|tag| tag == word))
+        .map(|(byte_idx, word)| {
+            let start = comment_range.start.saturating_add(byte_idx);
+            Highlight {
+                highlight_type: HighlightType::CommentTag,
+                range: start..(start.saturating_add(word.len())),
+            }
+        })
+        .collect()
+}
+
+fn get_trailing_whitespace_highlight(line: &str) -> Option<Highlight> {
+    let trimmed_len = line.trim_end_matches([' ', '\t']).len();
+    (trimmed_len < line.len()).then_some(Highlight {
+        highlight_type: HighlightType::TrailingWhitespace,
+        range: trimmed_len..line.len(),
+    })
+}
+
+/// Returns the whitespace in the indentation of `line` that is of the
+/// wrong kind for `indent_style` (e.g. tabs in a file indented with
+/// spaces), and the trailing whitespace.
+fn get_whitespace_error_highlights(line: &str, indent_style: IndentStyle) -> Vec<Highlight> {
+    let wrong_ch = match indent_style {
+        IndentStyle::Tabs => ' ',
+        IndentStyle::Spaces(_) => '\t',
+    };
+    // a line with only whitespace has no indentation, as it is all trailing
+    let indent_len = line
+        .len()
+        .saturating_sub(line.trim_start_matches([' ', '\t']).len());
+    let indent = if indent_len < line.len() {
+        &line[..indent_len]
+    } else {
+        ""
+    };
+
+    indent
+        .match_indices(wrong_ch)
+        .map(|(byte_idx, _)| Highlight {
+            highlight_type: HighlightType::WhitespaceError,
+            range: byte_idx..byte_idx.saturating_add(1),
+        })
+        .chain(
+            get_trailing_whitespace_highlight(line).map(|highlight| Highlight {
+                highlight_type: HighlightType::WhitespaceError,
+                ..highlight
+            }),
+        )
+        .collect()
+}
+
+/// What is searched for.
+pub(crate) enum SearchPattern {
+    /// only matched as a whole word if `whole_word`, like `SearchOptions::whole_word`
+    Text { text: String, whole_word: bool },
+    /// matched line by line, like `TextBuffer::find_regex`
+    Regex(Regex),
+}
+
+fn get_search_highlights(
+    line: &str,
+    search: &SearchPattern,
+    search_cursor_x_pos: Option<u64>,
+) -> Vec<Highlight> {
+    let ranges: Vec<Range<usize>> = match search {
+        SearchPattern::Text { text, whole_word } => line
+            .match_indices(text.as_str())
+            .filter(|(byte_idx, _)| !whole_word || is_whole_word(line, *byte_idx, text.len()))
+            .map(|(byte_idx, _)| byte_idx..byte_idx.saturating_add(text.len()))
+            .collect(),
+        SearchPattern::Regex(pattern) => pattern
+            .find_iter(line)
+            .filter(|found| !found.is_empty())
+            .map(|found| found.range())
+            .collect(),
+    };
+
+    ranges
+        .into_iter()
+        .map(|range| Highlight {
+            highlight_type: if search_cursor_x_pos.is_some()
+                && range.start == search_cursor_x_pos.unwrap_or_default().to_usize()
+            {
+                HighlightType::SearchCursor
+            } else {
+                HighlightType::SearchMatch
+            },
+            range,
+        })
+        .collect()
+}
+
+fn get_word_under_caret_highlights(
+    line: &str,
+    line_idx: usize,
+    word_under_caret: &WordUnderCaret,
+) -> Vec<Highlight> {
+    line.split_word_bound_indices()
+        .filter(|(byte_idx, word)| {
+            *word == word_under_caret.word
+                && (line_idx, *byte_idx) != (word_under_caret.line_idx, word_under_caret.byte_idx)
+        })
+        .map(|(byte_idx, word)| Highlight {
+            highlight_type: HighlightType::WordUnderCaret,
+            range: byte_idx..(byte_idx.saturating_add(word.len())),
+        })
+        .collect()
+}
+
+/// `overlays` are highlights that are not based on the language (such as search
+/// matches). The returned highlights are sorted from the highest priority to the
+/// lowest, so that the first highlight containing a byte is the one that is shown.
+fn get_highlights_for_line<T: AsRef<str>>(
+    line: T,
+    highlighter: &dyn Highlighter,
+    ctx: LineContext,
+    options: &HighlightOptions,
+    overlays: Vec<Highlight>,
+) -> Highlights {
+    let mut highlights = overlays;
+
+    if options.highlight_trailing_whitespace {
+        highlights.extend(get_trailing_whitespace_highlight(line.as_ref()));
+    }
+
+    let language_highlights = highlighter.highlight_line(line.as_ref(), ctx);
+
+    for (range, highlight_type) in &language_highlights {
+        if matches!(highlight_type, HighlightType::Comment) {
+            highlights.extend(get_comment_tag_highlights(
+                line.as_ref(),
+                range.clone(),
+                &options.comment_tags,
+            ));
+        }
+    }
+
+    highlights.extend(
+        language_highlights
+            .into_iter()
+            .map(|(range, highlight_type)| Highlight {
+                highlight_type,
+                range,
+            }),
+    );
+
+    highlights.sort_by_key(|highlight| Reverse(highlight.highlight_type.priority()));
+
+    Highlights { highlights }
+}
+
+fn map_highlights_to_text_highlight_line(
+    highlights: Highlights,
+    theme: &Theme,
+) -> TextHighlightLine {
+    TextHighlightLine {
+        blocks: highlights
+            .highlights
+            .into_iter()
+            .map(|highlight| TextHighlightBlock {
+                color: theme.highlight_color(highlight.highlight_type),
+                range: highlight.range,
+            })
+            .collect(),
+    }
+}
+
+impl<B: TextBuffer> HighlightInfo<B> {
+    pub(crate) fn new() -> Self {
+        Self {
+            text_highlight: TextHighlights::new(),
+            highlighter: get_highlighter_for_file_type(FileType::PlainText),
+            options: HighlightOptions::default(),
+            theme: Theme::default(),
+            search: None,
+            word_under_caret: None,
+            whitespace_errors: None,
+            unmatched_brackets: HashMap::new(),
+            matching_brackets: None,
+            line_contexts: vec![],
+            _phantom: std::marker::PhantomData,
+        }
+    }
+
+    pub(crate) fn update_file_type(&mut self, text_box: &TextBox<B>, file_type: FileType) {
+        self.set_highlighter(text_box, get_highlighter_for_file_type(file_type));
+    }
+
+    pub(crate) fn set_highlighter(
+        &mut self,
+        text_box: &TextBox<B>,
+        highlighter: Box<dyn Highlighter>,
+    ) {
+        self.highlighter = highlighter;
+        self.regenerate_on_buffer_change(text_box);
+    }
+
+    pub(crate) fn set_options(&mut self, text_box: &TextBox<B>, options: HighlightOptions) {
+        self.options = options;
+        self.regenerate_on_buffer_change(text_box);
+    }
+
+    pub(crate) fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    pub(crate) fn set_theme(&mut self, text_box: &TextBox<B>, theme: Theme) {
+        self.theme = theme;
+        self.regenerate(text_box);
+    }
+
+    pub(crate) fn word_under_caret(&self) -> Option<&WordUnderCaret> {
+        self.word_under_caret.as_ref()
+    }
+
+    pub(crate) fn whitespace_errors(&self) -> Option<IndentStyle> {
+        self.whitespace_errors
+    }
+
+    /// Reveal the whitespace that does not fit `indent_style`, or stop
+    /// revealing it if `None`.
+    pub(crate) fn set_whitespace_errors(
+        &mut self,
+        text_box: &TextBox<B>,
+        indent_style: Option<IndentStyle>,
+    ) {
+        self.whitespace_errors = indent_style;
+        self.regenerate(text_box);
+    }
+
+    pub(crate) fn matching_brackets(&self) -> Option<(TextBufferPos, TextBufferPos)> {
+        self.matching_brackets
+    }
+
+    pub(crate) fn set_matching_brackets(
+        &mut self,
+        text_box: &TextBox<B>,
+        matching_brackets: Option<(TextBufferPos, TextBufferPos)>,
+    ) {
+        self.matching_brackets = matching_brackets;
+        self.regenerate(text_box);
+    }
+
+    pub(crate) fn set_word_under_caret(
+        &mut self,
+        text_box: &TextBox<B>,
+        word_under_caret: Option<WordUnderCaret>,
+    ) {
+        self.word_under_caret = word_under_caret;
+        self.regenerate(text_box);
+    }
+
+    /// Returns the highlights of `line`, which is the line at `line_idx`.
+    fn get_line_highlight(&self, line_idx: usize, line: &str) -> TextHighlightLine {
+        let mut overlays = vec![];
+        if let Some((search, search_cursor_pos)) = &self.search {
+            let search_cursor_x_pos =
+                (line_idx == search_cursor_pos.y.to_usize()).then_some(search_cursor_pos.x);
+            overlays.extend(get_search_highlights(line, search, search_cursor_x_pos));
+        }
+        if let Some(word_under_caret) = &self.word_under_caret
+            && word_under_caret.visible_lines.contains(&line_idx)
+        {
+            overlays.extend(get_word_under_caret_highlights(
+                line,
+                line_idx,
+                word_under_caret,
+            ));
+        }
+        if let Some(indent_style) = self.whitespace_errors {
+            overlays.extend(get_whitespace_error_highlights(line, indent_style));
+        }
+        if let Some((bracket, matching_bracket)) = self.matching_brackets {
+            overlays.extend(
+                [bracket, matching_bracket]
+                    .into_iter()
+                    .filter(|pos| pos.line == line_idx)
+                    .map(|pos| Highlight {
+                        highlight_type: HighlightType::MatchingBracket,
+                        range: pos.byte..pos.byte.saturating_add(1),
+                    }),
+            );
+        }
+        if let Some(ranges) = self.unmatched_brackets.get(&line_idx) {
+            overlays.extend(ranges.iter().map(|range| Highlight {
+                highlight_type: HighlightType::UnmatchedBracket,
+                range: range.clone(),
+            }));
+        }
+
+        let highlights = get_highlights_for_line(
+            line,
+            self.highlighter.as_ref(),
+            self.line_context(line_idx),
+            &self.options,
+            overlays,
+        );
+
+        map_highlights_to_text_highlight_line(highlights, &self.theme)
+    }
+
+    // TODO: When we use a backend text object (like ropey), this shouldn't take in text_box
+    fn regenerate(&mut self, text_box: &TextBox<B>) {
+        self.text_highlight = TextHighlights {
+            lines: (0..text_box.get_total_lines())
+                .filter_map(|line_idx| text_box.get_raw_line(line_idx))
+                .enumerate()
+                .map(|(line_idx, line)| (line_idx, self.get_line_highlight(line_idx, &line)))
+                .collect(),
+        }
+    }
+
+    pub(crate) fn regenerate_on_search_change(
+        &mut self,
+        text_box: &TextBox<B>,
+        search: SearchPattern,
+        search_cursor_pos: Vec2u,
+    ) {
+        self.search = Some((search, search_cursor_pos));
+        self.regenerate(text_box);
+    }
+
+    pub(crate) fn regenerate_on_buffer_change(&mut self, text_box: &TextBox<B>) {
+        self.regenerate_whole_buffer(text_box, None);
+    }
+
+    /// Regenerate the highlights of every line, where only `changed_lines`
+    /// have changed since the highlighter was last given the buffer (or
+    /// any line could have if it is `None`).
+    fn regenerate_whole_buffer(
+        &mut self,
+        text_box: &TextBox<B>,
+        changed_lines: Option<Range<usize>>,
+    ) {
+        // buffer change should not happen during search for our current
+        // implementation, so safe to clear it
+        self.search = None;
+        self.highlighter
+            .on_buffer_change(&text_box.get_entire_contents_as_string(), changed_lines);
+        self.line_contexts = self.find_line_contexts(text_box);
+        self.unmatched_brackets = if self.options.highlight_unmatched_brackets {
+            self.find_unmatched_brackets(text_box)
+        } else {
+            HashMap::new()
+        };
+        self.regenerate(text_box);
+    }
+
+    /// Regenerate the highlights after `changed_lines` (numbered as they
+    /// are now, see `TextBox::take_changed_lines`) have changed, keeping
+    /// the highlights of the other lines where possible.
+    ///
+    /// The lines after the changed lines are highlighted again until one
+    /// starts with the same context as before (e.g. a block comment that
+    /// was opened is closed again), as the lines after that one are then
+    /// highlighted the same as before.
+    pub(crate) fn regenerate_on_lines_change(
+        &mut self,
+        text_box: &TextBox<B>,
+        changed_lines: Range<usize>,
+    ) {
+        let old_total_lines = self.line_contexts.len();
+        let total_lines = text_box.get_total_lines();
+        if self.search.is_some() || self.highlighter.needs_whole_buffer() || old_total_lines == 0 {
+            self.regenerate_whole_buffer(text_box, Some(changed_lines));
+            return;
+        }
+
+        // the lines after the changed lines are the same lines as before,
+        // which are shifted by the number of lines added
+        let start = changed_lines.start.min(total_lines);
+        let to_old_line_idx = |line_idx: usize| {
+            line_idx
+                .saturating_add(old_total_lines)
+                .checked_sub(total_lines)
+        };
+
+        let mut line_contexts: Vec<LineContext> =
+            self.line_contexts.iter().take(start).copied().collect();
+        let mut ctx = start
+            .checked_sub(1)
+            .and_then(|line_idx| {
+                Some(self.highlighter.next_line_context(
+                    &text_box.get_raw_line(line_idx)?,
+                    self.line_context(line_idx),
+                ))
+            })
+            .unwrap_or_default();
+        let mut unchanged_start = total_lines;
+        for line_idx in start..total_lines {
+            ctx.line_idx = line_idx;
+            let old_ctx = to_old_line_idx(line_idx).and_then(|old_line_idx| {
+                self.line_contexts
+                    .get(old_line_idx)
+                    .map(|old_ctx| LineContext {
+                        line_idx,
+                        ..*old_ctx
+                    })
+            });
+            if line_idx >= changed_lines.end && old_ctx == Some(ctx) {
+                unchanged_start = line_idx;
+                break;
+            }
+
+            line_contexts.push(ctx);
+            let Some(line) = text_box.get_raw_line(line_idx) else {
+                break;
+            };
+            ctx = self.highlighter.next_line_context(&line, ctx);
+        }
+        let to_old_line_idx = |line_idx: usize| {
+            if line_idx < start {
+                Some(line_idx)
+            } else if line_idx >= unchanged_start {
+                to_old_line_idx(line_idx)
+            } else {
+                None
+            }
+        };
+        line_contexts.extend((unchanged_start..total_lines).filter_map(|line_idx| {
+            let old_ctx = self.line_contexts.get(to_old_line_idx(line_idx)?)?;
+            Some(LineContext {
+                line_idx,
+                ..*old_ctx
+            })
+        }));
+        self.line_contexts = line_contexts;
+
+        // a change can make a bracket on any other line (un)matched
+        let old_unmatched_brackets = std::mem::take(&mut self.unmatched_brackets);
+        if self.options.highlight_unmatched_brackets {
+            self.unmatched_brackets = self.find_unmatched_brackets(text_box);
+        }
+
+        let mut old_lines = std::mem::take(&mut self.text_highlight.lines);
+        self.text_highlight.lines = (0..total_lines)
+            .filter_map(|line_idx| {
+                let old_line_idx = to_old_line_idx(line_idx);
+                let old_line_highlight = old_line_idx
+                    .filter(|old_line_idx| {
+                        self.unmatched_brackets.get(&line_idx)
+                            == old_unmatched_brackets.get(old_line_idx)
+                    })
+                    .and_then(|old_line_idx| old_lines.remove(&old_line_idx));
+                let line_highlight = match old_line_highlight {
+                    Some(line_highlight) => line_highlight,
+                    None => self.get_line_highlight(line_idx, &text_box.get_raw_line(line_idx)?),
+                };
+                Some((line_idx, line_highlight))
+            })
+            .collect();
+    }
+
+    /// Returns the context that each line starts with, which depends
+    /// on the lines before it (e.g. whether a comment is still open).
+    fn find_line_contexts(&self, text_box: &TextBox<B>) -> Vec<LineContext> {
+        let mut ctx = LineContext::default();
+        (0..text_box.get_total_lines())
+            .filter_map(|line_idx| text_box.get_raw_line(line_idx))
+            .enumerate()
+            .map(|(line_idx, line)| {
+                ctx.line_idx = line_idx;
+                let line_ctx = ctx;
+                ctx = self.highlighter.next_line_context(&line, ctx);
+                line_ctx
+            })
+            .collect()
+    }
+
+    fn line_context(&self, line_idx: usize) -> LineContext {
+        self.line_contexts
+            .get(line_idx)
+            .copied()
+            .unwrap_or(LineContext {
+                line_idx,
+                ..LineContext::default()
+            })
+    }
+
+    /// Returns the ranges of the unmatched brackets on each line, where
+    /// the brackets in comments and literals are not counted.
+    fn find_unmatched_brackets(&self, text_box: &TextBox<B>) -> HashMap<usize, Vec<Range<usize>>> {
+        let lines = (0..text_box.get_total_lines())
+            .filter_map(|line_idx| text_box.get_raw_line(line_idx))
+            .enumerate()
+            .map(|(line_idx, line)| {
+                let skipped_ranges = self
+                    .highlighter
+                    .highlight_line(&line, self.line_context(line_idx))
+                    .into_iter()
+                    .filter(|(_, highlight_type)| {
+                        matches!(
+                            highlight_type,
+                            HighlightType::Comment
+                                | HighlightType::String
+                                | HighlightType::Character
+                        )
+                    })
+                    .map(|(range, _)| range)
+                    .collect();
+                (line, skipped_ranges)
+            });
+
+        let mut unmatched_brackets: HashMap<usize, Vec<Range<usize>>> = HashMap::new();
+        for (line_idx, byte_idx) in find_unmatched_brackets(lines) {
+            // all of the brackets are a single byte
+            unmatched_brackets
+                .entry(line_idx)
+                .or_default()
+                .push(byte_idx..byte_idx.saturating_add(1));
+        }
+        unmatched_brackets
+    }
+
+    pub(crate) fn clear_search_highlights(&mut self, text_box: &TextBox<B>) {
+        self.search = None;
+        self.regenerate(text_box);
+    }
+
+    pub(crate) fn text_highlight(&self) -> &TextHighlights {
+        &self.text_highlight
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use knap_ui::text_buffer::VecTextBuffer;
+
+    use super::*;
+
+    struct DigitHighlighter;
+
+    impl Highlighter for DigitHighlighter {
+        fn highlight_line(
+            &self,
+            line: &str,
+            _ctx: LineContext,
+        ) -> Vec<(Range<usize>, HighlightType)> {
+            line.char_indices()
+                .filter(|(_, ch)| ch.is_ascii_digit())
+                .map(|(idx, _)| (idx..idx.saturating_add(1), HighlightType::Number))
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_custom_highlighter() {
+        let mut text_box = TextBox::new(VecTextBuffer::new());
+        text_box.set_contents("a1b\nfoo\n22");
+
+        let mut highlight_info = HighlightInfo::new();
+        highlight_info.set_highlighter(&text_box, Box::new(DigitHighlighter));
+
+        let ranges = |line_idx| {
+            highlight_info
+                .text_highlight()
+                .lines
+                .get(&line_idx)
+                .expect("line to be highlighted")
+                .blocks
+                .iter()
+                .map(|block| block.range.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ranges(0), vec![1..2]);
+        assert!(ranges(1).is_empty());
+        assert_eq!(ranges(2), vec![0..1, 1..2]);
+    }
+
+    #[test]
+    fn test_comment_tags() {
+        let options = HighlightOptions::default();
+        let get_highlights = |line| {
+            get_highlights_for_line(
+                line,
+                &RustHighlighter,
+                LineContext::default(),
+                &options,
+                vec![],
+            )
+            .highlights
+            .into_iter()
+            .map(|highlight| (highlight.range, highlight.highlight_type))
+            .collect::<Vec<_>>()
+        };
+
+        let highlights = get_highlights("// TODO: x");
+        assert!(highlights.contains(&(0..10, HighlightType::Comment)));
+        assert!(highlights.contains(&(3..7, HighlightType::CommentTag)));
+
+        // only inside comments, and only whole words
+        let highlights = get_highlights("let TODO = 1; // TODOS NOTE");
+        assert!(highlights.contains(&(14..27, HighlightType::Comment)));
+        assert!(highlights.contains(&(23..27, HighlightType::CommentTag)));
+        assert_eq!(
+            highlights
+                .iter()
+                .filter(|(_, highlight_type)| *highlight_type == HighlightType::CommentTag)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_whitespace_errors() {
+        let mut text_box = TextBox::new(VecTextBuffer::new());
+        text_box.set_contents("fn a() {\n    b();\n  \tc(); \n\t\n}");
+
+        let mut highlight_info = HighlightInfo::new();
+        let error_ranges = |highlight_info: &HighlightInfo<_>, line_idx| {
+            highlight_info
+                .text_highlight()
+                .lines
+                .get(&line_idx)
+                .expect("line to be highlighted")
+                .blocks
+                .iter()
+                .map(|block| block.range.clone())
+                .collect::<Vec<_>>()
+        };
+
+        highlight_info.set_whitespace_errors(&text_box, Some(IndentStyle::Spaces(4)));
+        assert!(error_ranges(&highlight_info, 0).is_empty());
+        assert!(error_ranges(&highlight_info, 1).is_empty());
+        // the tab in the indentation, and the trailing space
+        assert_eq!(error_ranges(&highlight_info, 2), vec![2..3, 7..8]);
+        assert_eq!(error_ranges(&highlight_info, 3), vec![0..1]);
+
+        // in a file indented with tabs, it is the spaces that are flagged
+        highlight_info.set_whitespace_errors(&text_box, Some(IndentStyle::Tabs));
+        assert_eq!(
+            error_ranges(&highlight_info, 1),
+            vec![0..1, 1..2, 2..3, 3..4]
+        );
+        assert_eq!(error_ranges(&highlight_info, 2), vec![0..1, 1..2, 7..8]);
+
+        highlight_info.set_whitespace_errors(&text_box, None);
+        assert!(error_ranges(&highlight_info, 2).is_empty());
+    }
+
+    #[test]
+    fn test_trailing_whitespace() {
+        let get_trailing_whitespace_ranges = |line, highlight_trailing_whitespace| {
+            get_highlights_for_line(
+                line,
+                &NullHighlighter,
+                LineContext::default(),
+                &HighlightOptions {
+                    highlight_trailing_whitespace,
+                    ..HighlightOptions::default()
+                },
+                vec![],
+            )
+            .highlights
+            .into_iter()
+            .filter(|highlight| highlight.highlight_type == HighlightType::TrailingWhitespace)
+            .map(|highlight| highlight.range)
+            .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            get_trailing_whitespace_ranges("a b  c \t ", true),
+            vec![6..9]
+        );
+        assert!(get_trailing_whitespace_ranges("a b  c", true).is_empty());
+        assert_eq!(get_trailing_whitespace_ranges("  \t", true), vec![0..3]);
+        assert!(get_trailing_whitespace_ranges("", true).is_empty());
+        assert!(get_trailing_whitespace_ranges("a b  c \t ", false).is_empty());
+    }
+
+    #[test]
+    fn test_search_highlights() {
+        let get_search_ranges = |line, search| {
+            get_search_highlights(line, &search, Some(4))
+                .into_iter()
+                .map(|highlight| (highlight.range, highlight.highlight_type))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            get_search_ranges(
+                "let a1 = b22;",
+                SearchPattern::Text {
+                    text: "2".to_string(),
+                    whole_word: false
+                }
+            ),
+            vec![
+                (10..11, HighlightType::SearchMatch),
+                (11..12, HighlightType::SearchMatch)
+            ]
+        );
+        // the matches within other words are skipped, like when navigating
+        assert_eq!(
+            get_search_ranges(
+                "is this history is",
+                SearchPattern::Text {
+                    text: "is".to_string(),
+                    whole_word: true
+                }
+            ),
+            vec![
+                (0..2, HighlightType::SearchMatch),
+                (16..18, HighlightType::SearchMatch)
+            ]
+        );
+        assert_eq!(
+            get_search_ranges(
+                "let a1 = b22;",
+                SearchPattern::Regex(Regex::new("[a-z][0-9]+").expect("valid regex"))
+            ),
+            vec![
+                (4..6, HighlightType::SearchCursor),
+                (9..12, HighlightType::SearchMatch)
+            ]
+        );
+        // the empty matches are not highlighted
+        assert_eq!(
+            get_search_ranges(
+                "let a1 = b22;",
+                SearchPattern::Regex(Regex::new(";$|^").expect("valid regex"))
+            ),
+            vec![(12..13, HighlightType::SearchMatch)]
+        );
+    }
+
+    #[test]
+    fn test_highlight_priority() {
+        struct UnorderedHighlighter;
+
+        impl Highlighter for UnorderedHighlighter {
+            fn highlight_line(
+                &self,
+                _line: &str,
+                _ctx: LineContext,
+            ) -> Vec<(Range<usize>, HighlightType)> {
+                vec![
+                    (1..3, HighlightType::Keyword),
+                    (10..12, HighlightType::Keyword),
+                    (0..6, HighlightType::String),
+                    (7..16, HighlightType::Comment),
+                ]
+            }
+        }
+
+        let get_highlight_at = |highlights: &Highlights, byte_idx| {
+            highlights
+                .highlights
+                .iter()
+                .find(|highlight| highlight.range.contains(&byte_idx))
+                .map(|highlight| highlight.highlight_type)
+        };
+
+        let highlights = get_highlights_for_line(
+            "\"if\"; // if TODO",
+            &UnorderedHighlighter,
+            LineContext::default(),
+            &HighlightOptions::default(),
+            vec![Highlight {
+                highlight_type: HighlightType::SearchMatch,
+                range: 2..4,
+            }],
+        );
+        assert_eq!(
+            get_highlight_at(&highlights, 1),
+            Some(HighlightType::String)
+        );
+        assert_eq!(
+            get_highlight_at(&highlights, 2),
+            Some(HighlightType::SearchMatch)
+        );
+        assert_eq!(
+            get_highlight_at(&highlights, 10),
+            Some(HighlightType::Comment)
+        );
+        assert_eq!(
+            get_highlight_at(&highlights, 13),
+            Some(HighlightType::CommentTag)
+        );
+
+        let highlights = get_highlights_for_line(
+            "// let x = 1;",
+            &RustHighlighter,
+            LineContext::default(),
+            &HighlightOptions::default(),
+            vec![],
+        );
+        assert_eq!(
+            get_highlight_at(&highlights, 4),
+            Some(HighlightType::Comment)
+        );
+        assert_eq!(
+            get_highlight_at(&highlights, 11),
+            Some(HighlightType::Comment)
+        );
+    }
+
+    #[test]
+    fn test_set_theme() {
+        let mut text_box = TextBox::new(VecTextBuffer::new());
+        text_box.set_contents("fn main() {}");
+
+        let mut highlight_info = HighlightInfo::new();
+        highlight_info.set_highlighter(&text_box, Box::new(RustHighlighter));
+        let keyword_color = |highlight_info: &HighlightInfo<VecTextBuffer>| {
+            highlight_info
+                .text_highlight()
+                .lines
+                .get(&0)
+                .expect("line to be highlighted")
+                .blocks
+                .iter()
+                .find(|block| block.range.contains(&0))
+                .map(|block| block.color)
+        };
+        assert_eq!(
+            keyword_color(&highlight_info),
+            Some(Theme::dark().highlight_color(HighlightType::Keyword))
+        );
+
+        highlight_info.set_theme(&text_box, Theme::light());
+        assert_eq!(
+            keyword_color(&highlight_info),
+            Some(Theme::light().highlight_color(HighlightType::Keyword))
+        );
+    }
+
+    #[test]
+    fn test_unmatched_brackets() {
+        let mut text_box = TextBox::new(VecTextBuffer::new());
+        text_box.set_contents("fn main() {\n    f(a));\n    g(\"(\"); // (\n");
+
+        let mut highlight_info = HighlightInfo::new();
+        highlight_info.set_highlighter(&text_box, Box::new(RustHighlighter));
+        let unmatched_ranges = |highlight_info: &HighlightInfo<VecTextBuffer>, line_idx| {
+            highlight_info
+                .text_highlight()
+                .lines
+                .get(&line_idx)
+                .expect("line to be highlighted")
+                .blocks
+                .iter()
+                .filter(|block| {
+                    block.color == Theme::dark().highlight_color(HighlightType::UnmatchedBracket)
+                })
+                .map(|block| block.range.clone())
+                .collect::<Vec<_>>()
+        };
+        assert!(unmatched_ranges(&highlight_info, 1).is_empty());
+
+        highlight_info.set_options(
+            &text_box,
+            HighlightOptions {
+                highlight_unmatched_brackets: true,
+                ..HighlightOptions::default()
+            },
+        );
+        // the extra `)`, and the unclosed `{`
+        assert_eq!(unmatched_ranges(&highlight_info, 0), vec![10..11]);
+        assert_eq!(unmatched_ranges(&highlight_info, 1), vec![8..9]);
+        // but not the brackets in the string and comment
+        assert!(unmatched_ranges(&highlight_info, 2).is_empty());
+
+        text_box.set_contents("fn main() {\n    f(a);\n}");
+        highlight_info.regenerate_on_buffer_change(&text_box);
+        assert!(unmatched_ranges(&highlight_info, 0).is_empty());
+    }
+
+    #[test]
+    fn test_matching_brackets() {
+        let mut text_box = TextBox::new(VecTextBuffer::new());
+        text_box.set_contents("fn main() {\n    f(a);\n}");
+        text_box.move_cursor_to_buffer_pos(TextBufferPos { line: 2, byte: 0 });
+
+        let mut highlight_info = HighlightInfo::new();
+        let matching_ranges = |highlight_info: &HighlightInfo<VecTextBuffer>, line_idx| {
+            highlight_info
+                .text_highlight()
+                .lines
+                .get(&line_idx)
+                .expect("line to be highlighted")
+                .blocks
+                .iter()
+                .filter(|block| {
+                    block.color == Theme::dark().highlight_color(HighlightType::MatchingBracket)
+                })
+                .map(|block| block.range.clone())
+                .collect::<Vec<_>>()
+        };
+
+        highlight_info.set_matching_brackets(&text_box, text_box.matching_bracket());
+        assert_eq!(matching_ranges(&highlight_info, 0), vec![10..11]);
+        assert!(matching_ranges(&highlight_info, 1).is_empty());
+        assert_eq!(matching_ranges(&highlight_info, 2), vec![0..1]);
+
+        text_box.move_cursor_to_buffer_pos(TextBufferPos { line: 1, byte: 5 });
+        highlight_info.set_matching_brackets(&text_box, text_box.matching_bracket());
+        assert!(matching_ranges(&highlight_info, 0).is_empty());
+        assert_eq!(matching_ranges(&highlight_info, 1), vec![5..6, 7..8]);
+
+        highlight_info.set_matching_brackets(&text_box, None);
+        assert!(matching_ranges(&highlight_info, 1).is_empty());
+    }
+
+    #[test]
+    fn test_block_comments() {
+        let mut text_box = TextBox::new(VecTextBuffer::new());
+        text_box.set_contents("let a = 1; /* start /*\n  let b = 2; */\n*/ let c = 3;");
+
+        let mut highlight_info = HighlightInfo::new();
+        highlight_info.set_highlighter(&text_box, Box::new(RustHighlighter));
+        let comment_ranges = |highlight_info: &HighlightInfo<VecTextBuffer>, line_idx| {
+            highlight_info
+                .text_highlight()
+                .lines
+                .get(&line_idx)
+                .expect("line to be highlighted")
+                .blocks
+                .iter()
+                .filter(|block| {
+                    block.color == Theme::dark().highlight_color(HighlightType::Comment)
+                })
+                .map(|block| block.range.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(comment_ranges(&highlight_info, 0), vec![11..22]);
+        // the nested comment is still open after the middle line
+        assert_eq!(comment_ranges(&highlight_info, 1), vec![0..15]);
+        assert_eq!(comment_ranges(&highlight_info, 2), vec![0..2]);
+
+        // closing the comment earlier changes the lines after it
+        text_box.set_contents("let a = 1; /* start */\n  let b = 2; */\n*/ let c = 3;");
+        highlight_info.regenerate_on_buffer_change(&text_box);
+        assert_eq!(comment_ranges(&highlight_info, 0), vec![11..22]);
+        assert!(comment_ranges(&highlight_info, 1).is_empty());
+        assert!(comment_ranges(&highlight_info, 2).is_empty());
+    }
+
+    #[test]
+    fn test_regenerate_on_lines_change() {
+        use std::{cell::RefCell, rc::Rc};
+
+        use knap_ui::text_buffer::TextBufferPos;
+
+        /// Records the lines that are highlighted.
+        struct RecordingHighlighter(Rc<RefCell<Vec<usize>>>);
+
+        impl Highlighter for RecordingHighlighter {
+            fn highlight_line(
+                &self,
+                line: &str,
+                ctx: LineContext,
+            ) -> Vec<(Range<usize>, HighlightType)> {
+                self.0.borrow_mut().push(ctx.line_idx);
+                RustHighlighter.highlight_line(line, ctx)
+            }
+
+            fn next_line_context(&self, line: &str, ctx: LineContext) -> LineContext {
+                RustHighlighter.next_line_context(line, ctx)
+            }
+        }
+
+        let mut text_box = TextBox::new(VecTextBuffer::new());
+        text_box.set_contents(
+            "let a = 1;
+let b = 2;
+let c = 3;
+let d = 4; */
+let e = 5;",
+        );
+        let highlighted_lines = Rc::new(RefCell::new(vec![]));
+        let mut highlight_info = HighlightInfo::new();
+        highlight_info.set_highlighter(
+            &text_box,
+            Box::new(RecordingHighlighter(Rc::clone(&highlighted_lines))),
+        );
+        let mut regenerate = |text_box: &mut TextBox<VecTextBuffer>| {
+            highlighted_lines.borrow_mut().clear();
+            let changed_lines = text_box.take_changed_lines().unwrap_or_default();
+            highlight_info.regenerate_on_lines_change(text_box, changed_lines);
+            let mut expected = HighlightInfo::new();
+            expected.set_highlighter(text_box, Box::new(RustHighlighter));
+            let blocks = |highlight_info: &HighlightInfo<VecTextBuffer>| {
+                (0..text_box.get_total_lines())
+                    .map(|line_idx| {
+                        highlight_info
+                            .text_highlight()
+                            .lines
+                            .get(&line_idx)
+                            .map(|line_highlight| line_highlight.blocks.clone())
+                    })
+                    .collect::<Vec<_>>()
+            };
+            // the same as highlighting every line again
+            assert_eq!(blocks(&highlight_info), blocks(&expected));
+            highlighted_lines.take()
+        };
+        text_box.take_changed_lines();
+
+        // only the edited line
+        text_box.move_cursor_to_buffer_pos(TextBufferPos { line: 1, byte: 9 });
+        assert!(text_box.insert_character_at_cursor('0').is_ok());
+        assert_eq!(regenerate(&mut text_box), vec![1]);
+
+        // the lines after a new line are moved down, but not highlighted again
+        text_box.insert_newline_at_cursor();
+        assert_eq!(regenerate(&mut text_box), vec![1, 2]);
+
+        // an opened comment changes the lines after it, up to where it is closed
+        text_box.move_cursor_to_buffer_pos(TextBufferPos { line: 0, byte: 0 });
+        assert!(text_box.insert_str_at_cursor("/* ").is_ok());
+        assert_eq!(regenerate(&mut text_box), vec![0, 1, 2, 3, 4]);
+    }
+}
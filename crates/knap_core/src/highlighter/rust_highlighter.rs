@@ -0,0 +1,431 @@
+use std::{cell::RefCell, collections::HashSet, ops::Range};
+
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{HighlightType, Highlighter, LineContext};
+
+thread_local! {
+    static NUMBER_REGEX: RefCell<Regex> =
+        RefCell::new(Regex::new(r"^\d+(_\d+)*(\.\d+)?(e\d+)?$").expect("valid regex expression"));
+
+    static BINARY_REGEX: RefCell<Regex> =
+        RefCell::new(Regex::new(r"^0[bB][01]+$").expect("valid regex expression"));
+
+    static OCTAL_REGEX: RefCell<Regex> =
+        RefCell::new(Regex::new(r"^0[oO][01234567]+$").expect("valid regex expression"));
+
+    static HEXADECIMAL_REGEX: RefCell<Regex> =
+        RefCell::new(Regex::new(r"^0[xX][\dabcdefABCDEF]+$").expect("valid regex expression"));
+
+    static KEYWORD_TYPES: RefCell<HashSet<String>> = RefCell::new([
+        "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn",
+        "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+        "return", "self", "Self", "static", "struct", "super", "trait", "type", "unsafe", "use",
+        "where", "while", "async", "await", "dyn", "abstract", "become", "box", "do", "final",
+        "macro", "override", "priv", "typeof", "unsized", "virtual", "yield", "try"
+    ].iter().map(|s| (*s).to_string()).collect());
+
+    static BASIC_TYPES: RefCell<HashSet<String>> = RefCell::new([
+        "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize",
+        "f32", "f64",
+        "bool", "char",
+        "Option", "Result",
+        "String", "str",
+        "Vec", "HashMap", "HashSet"
+    ].iter().map(|s| (*s).to_string()).collect());
+
+    static ENUM_LITERALS: RefCell<HashSet<String>> = RefCell::new([
+        "Some", "None", "Ok", "Err", "true", "false",
+    ].iter().map(|s| (*s).to_string()).collect());
+}
+
+/// Returns the length (in bytes) of a character literal, excluding its
+/// opening quote, if `rest` (what comes after the quote) starts with one.
+fn get_character_literal_len(rest: &str) -> Option<usize> {
+    let mut chars = rest.char_indices();
+
+    let closing_quote = match chars.next()? {
+        (_, '\'') => None,
+        (_, '\\') => match chars.next()? {
+            // e.g. '\u{1F600}' or '\x7f'
+            (_, 'u' | 'x') => {
+                chars.find(|(_, ch)| !(ch.is_ascii_hexdigit() || *ch == '{' || *ch == '}'))
+            }
+            _ => chars.next(),
+        },
+        _ => chars.next(),
+    };
+
+    closing_quote
+        .filter(|(_, ch)| *ch == '\'')
+        .map(|(idx, _)| idx.saturating_add(1))
+}
+
+/// Returns the length (in bytes) of a lifetime, excluding its leading
+/// quote, if `rest` (what comes after the quote) starts with one.
+fn get_lifetime_len(rest: &str) -> Option<usize> {
+    let len = rest
+        .find(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+        .unwrap_or(rest.len());
+
+    (len > 0 && !rest.starts_with(|ch: char| ch.is_ascii_digit())).then_some(len)
+}
+
+/// Highlights character literals and lifetimes, which both start with a `'`.
+fn get_quote_highlights(line: &str) -> Vec<(Range<usize>, HighlightType)> {
+    let mut highlights = vec![];
+    let mut byte_idx = 0;
+
+    while let Some(quote_offset) = line[byte_idx..].find('\'') {
+        let quote_idx = byte_idx.saturating_add(quote_offset);
+        let rest_idx = quote_idx.saturating_add(1);
+        let rest = &line[rest_idx..];
+
+        byte_idx = if let Some(len) = get_character_literal_len(rest) {
+            highlights.push((
+                quote_idx..rest_idx.saturating_add(len),
+                HighlightType::Character,
+            ));
+            rest_idx.saturating_add(len)
+        } else if let Some(len) = get_lifetime_len(rest) {
+            highlights.push((
+                quote_idx..rest_idx.saturating_add(len),
+                HighlightType::LifetimeSpecifier,
+            ));
+            rest_idx.saturating_add(len)
+        } else {
+            // a stray quote
+            rest_idx
+        };
+    }
+
+    highlights
+}
+
+/// Highlights string literals, which are assumed to end on the line
+/// they start on (or else run to its end). The quotes within the
+/// `skipped_ranges` (e.g. character literals like `'"'`) are ignored.
+fn get_string_highlights(
+    line: &str,
+    skipped_ranges: &[Range<usize>],
+) -> Vec<(Range<usize>, HighlightType)> {
+    let mut highlights = vec![];
+    let mut chars = line.char_indices();
+
+    while let Some((start, ch)) = chars.next() {
+        if ch != '"' || skipped_ranges.iter().any(|range| range.contains(&start)) {
+            continue;
+        }
+
+        let mut end = line.len();
+        while let Some((byte_idx, ch)) = chars.next() {
+            match ch {
+                '\\' => {
+                    chars.next();
+                }
+                '"' => {
+                    end = byte_idx.saturating_add(1);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        highlights.push((start..end, HighlightType::String));
+    }
+
+    highlights
+}
+
+/// Returns the ranges of the character literals and strings in `line`.
+fn get_literal_ranges(line: &str) -> Vec<Range<usize>> {
+    let character_ranges = get_quote_highlights(line)
+        .into_iter()
+        .filter(|(_, highlight_type)| *highlight_type == HighlightType::Character)
+        .map(|(range, _)| range)
+        .collect::<Vec<_>>();
+    let string_ranges = get_string_highlights(line, &character_ranges)
+        .into_iter()
+        .map(|(range, _)| range)
+        .collect::<Vec<_>>();
+
+    [character_ranges, string_ranges].concat()
+}
+
+/// Returns the ranges of the block comments in `line`, which starts with
+/// `depth` block comments still open, and how many are still open at its
+/// end. Block comments can be nested, so each `/*` within one has to be
+/// closed by its own `*/`.
+///
+/// The `/*` within the `literal_ranges` do not start a comment, and
+/// neither do those after a line comment.
+fn get_block_comment_ranges(
+    line: &str,
+    mut depth: usize,
+    literal_ranges: &[Range<usize>],
+) -> (Vec<Range<usize>>, usize) {
+    let mut ranges = vec![];
+    let mut start = 0;
+    let mut byte_idx = 0;
+
+    while let Some(rest) = line.get(byte_idx..)
+        && !rest.is_empty()
+    {
+        if depth == 0 {
+            if literal_ranges.iter().any(|range| range.contains(&byte_idx)) {
+                byte_idx = byte_idx.saturating_add(1);
+                continue;
+            }
+            if rest.starts_with("//") {
+                break;
+            }
+        }
+
+        if rest.starts_with("/*") {
+            if depth == 0 {
+                start = byte_idx;
+            }
+            depth = depth.saturating_add(1);
+            byte_idx = byte_idx.saturating_add(2);
+        } else if depth > 0 && rest.starts_with("*/") {
+            depth = depth.saturating_sub(1);
+            byte_idx = byte_idx.saturating_add(2);
+            if depth == 0 {
+                ranges.push(start..byte_idx);
+            }
+        } else {
+            byte_idx = byte_idx.saturating_add(1);
+        }
+    }
+
+    if depth > 0 {
+        ranges.push(start..line.len());
+    }
+    (ranges, depth)
+}
+
+pub(crate) struct RustHighlighter;
+
+impl Highlighter for RustHighlighter {
+    fn highlight_line(&self, line: &str, ctx: LineContext) -> Vec<(Range<usize>, HighlightType)> {
+        let mut highlights = vec![];
+
+        let quote_highlights = get_quote_highlights(line);
+        let character_ranges = quote_highlights
+            .iter()
+            .filter(|(_, highlight_type)| *highlight_type == HighlightType::Character)
+            .map(|(range, _)| range.clone())
+            .collect::<Vec<_>>();
+        let string_highlights = get_string_highlights(line, &character_ranges);
+        let literal_ranges = character_ranges
+            .into_iter()
+            .chain(string_highlights.iter().map(|(range, _)| range.clone()))
+            .collect::<Vec<_>>();
+        let (block_comment_ranges, _) =
+            get_block_comment_ranges(line, ctx.block_comment_depth, &literal_ranges);
+        let is_in_block_comment = |byte_idx: usize| {
+            block_comment_ranges
+                .iter()
+                .any(|range| range.contains(&byte_idx))
+        };
+
+        // highlight single line comments
+        if let Some((single_line_comment_start, _)) =
+            line.match_indices("//").find(|(byte_idx, _)| {
+                !literal_ranges.iter().any(|range| range.contains(byte_idx))
+                    && !is_in_block_comment(*byte_idx)
+            })
+        {
+            highlights.push((
+                single_line_comment_start..(line.len()),
+                HighlightType::Comment,
+            ));
+        }
+
+        // the quotes in a block comment do not start a literal
+        let string_highlights = string_highlights
+            .into_iter()
+            .filter(|(range, _)| !is_in_block_comment(range.start))
+            .collect::<Vec<_>>();
+        // e.g. the `'s` in `"it's"` is not a lifetime
+        highlights.extend(quote_highlights.into_iter().filter(|(range, _)| {
+            !is_in_block_comment(range.start)
+                && !string_highlights
+                    .iter()
+                    .any(|(string_range, _)| string_range.contains(&range.start))
+        }));
+        highlights.extend(string_highlights);
+        highlights.extend(
+            block_comment_ranges
+                .into_iter()
+                .map(|range| (range, HighlightType::Comment)),
+        );
+
+        line.split_word_bound_indices()
+            .for_each(|(byte_idx, word)| {
+                let range = byte_idx..(byte_idx.saturating_add(word.len()));
+                let highlight_type = if KEYWORD_TYPES.with_borrow(|set| set.contains(word)) {
+                    Some(HighlightType::Keyword)
+                } else if BASIC_TYPES.with_borrow(|set| set.contains(word)) {
+                    Some(HighlightType::BasicType)
+                } else if ENUM_LITERALS.with_borrow(|set| set.contains(word)) {
+                    Some(HighlightType::EnumLiteral)
+                } else if NUMBER_REGEX.with_borrow(|regex| regex.is_match(word))
+                    || BINARY_REGEX.with_borrow(|regex| regex.is_match(word))
+                    || OCTAL_REGEX.with_borrow(|regex| regex.is_match(word))
+                    || HEXADECIMAL_REGEX.with_borrow(|regex| regex.is_match(word))
+                {
+                    Some(HighlightType::Number)
+                } else {
+                    None
+                };
+
+                if let Some(highlight_type) = highlight_type {
+                    highlights.push((range, highlight_type));
+                }
+            });
+
+        highlights
+    }
+
+    fn next_line_context(&self, line: &str, ctx: LineContext) -> LineContext {
+        let (_, block_comment_depth) =
+            get_block_comment_ranges(line, ctx.block_comment_depth, &get_literal_ranges(line));
+
+        LineContext {
+            line_idx: ctx.line_idx.saturating_add(1),
+            block_comment_depth,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_highlights() {
+        assert_eq!(
+            get_quote_highlights("let x = 'a';"),
+            vec![(8..11, HighlightType::Character)]
+        );
+        assert_eq!(
+            get_quote_highlights("fn f<'a>() -> &'a str"),
+            vec![
+                (5..7, HighlightType::LifetimeSpecifier),
+                (15..17, HighlightType::LifetimeSpecifier),
+            ]
+        );
+        assert_eq!(
+            get_quote_highlights("'\\''"),
+            vec![(0..4, HighlightType::Character)]
+        );
+        assert_eq!(
+            get_quote_highlights("['\\n', '\\\\', '\\u{1F600}', '\u{e9}']"),
+            vec![
+                (1..5, HighlightType::Character),
+                (7..11, HighlightType::Character),
+                (13..24, HighlightType::Character),
+                (26..30, HighlightType::Character),
+            ]
+        );
+        assert_eq!(
+            get_quote_highlights("struct S<'de>(&'de str, char); let c = 'x';"),
+            vec![
+                (9..12, HighlightType::LifetimeSpecifier),
+                (15..18, HighlightType::LifetimeSpecifier),
+                (39..42, HighlightType::Character),
+            ]
+        );
+
+        // a stray quote does not swallow the rest of the line
+        assert!(get_quote_highlights("' x = 1; '' 3").is_empty());
+        assert!(
+            RustHighlighter
+                .highlight_line("' let x = 1;", LineContext::default())
+                .contains(&(2..5, HighlightType::Keyword))
+        );
+    }
+
+    #[test]
+    fn test_string_highlights() {
+        let highlights = RustHighlighter
+            .highlight_line(r#"let s = "it's \"b\" // c"; // d"#, LineContext::default());
+        assert!(highlights.contains(&(8..25, HighlightType::String)));
+        assert!(highlights.contains(&(27..31, HighlightType::Comment)));
+        assert!(
+            !highlights
+                .iter()
+                .any(|(_, highlight_type)| *highlight_type == HighlightType::LifetimeSpecifier)
+        );
+
+        // quotes in character literals do not start a string
+        assert_eq!(
+            get_string_highlights(r#"('"', "x", '"')"#, &[1..4, 11..14]),
+            vec![(6..9, HighlightType::String)]
+        );
+
+        // an unclosed string runs to the end of the line
+        assert_eq!(
+            get_string_highlights(r#"f("abc"#, &[]),
+            vec![(2..6, HighlightType::String)]
+        );
+    }
+
+    #[test]
+    fn test_block_comment_highlights() {
+        let has_type = |highlights: &[(Range<usize>, HighlightType)], expected| {
+            highlights
+                .iter()
+                .any(|(_, highlight_type)| *highlight_type == expected)
+        };
+
+        let lines = [
+            "let a = 1; /* start",
+            r#"   middle "x" it's fn"#,
+            r#"end */ let b = "/*";"#,
+        ];
+        let ctx = LineContext::default();
+        let highlights = RustHighlighter.highlight_line(lines[0], ctx);
+        assert!(highlights.contains(&(11..19, HighlightType::Comment)));
+
+        // the middle line is entirely in the comment
+        let ctx = RustHighlighter.next_line_context(lines[0], ctx);
+        assert_eq!(
+            ctx,
+            LineContext {
+                line_idx: 1,
+                block_comment_depth: 1
+            }
+        );
+        let highlights = RustHighlighter.highlight_line(lines[1], ctx);
+        assert!(highlights.contains(&(0..21, HighlightType::Comment)));
+        assert!(!has_type(&highlights, HighlightType::String));
+        assert!(!has_type(&highlights, HighlightType::LifetimeSpecifier));
+
+        // the `/*` in the string does not open another comment
+        let ctx = RustHighlighter.next_line_context(lines[1], ctx);
+        let highlights = RustHighlighter.highlight_line(lines[2], ctx);
+        assert!(highlights.contains(&(0..6, HighlightType::Comment)));
+        assert!(highlights.contains(&(15..19, HighlightType::String)));
+        assert_eq!(
+            RustHighlighter
+                .next_line_context(lines[2], ctx)
+                .block_comment_depth,
+            0
+        );
+
+        // nested comments have to be closed as many times as they are opened
+        let (ranges, depth) = get_block_comment_ranges("/* a /* b */ c", 0, &[]);
+        assert_eq!(ranges.first(), Some(&(0..14)));
+        assert_eq!((ranges.len(), depth), (1, 1));
+        assert_eq!(
+            get_block_comment_ranges("d */ fn f() {} /* e */ // /*", 1, &[]),
+            (vec![0..4, 15..22], 0)
+        );
+        assert_eq!(
+            get_block_comment_ranges("*/ */ f(); /*", 2, &[]),
+            (vec![0..5, 11..13], 1)
+        );
+    }
+}
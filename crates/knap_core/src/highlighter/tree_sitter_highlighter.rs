@@ -0,0 +1,265 @@
+use std::{collections::HashMap, ops::Range};
+
+use knap_base::math::ToUsize;
+use tree_sitter::{
+    InputEdit, Language, Node, Parser, Point, Query, QueryCursor, StreamingIterator, Tree,
+};
+
+use super::{HighlightType, Highlighter, LineContext};
+
+fn get_highlight_type_from_capture(capture_name: &str, node: Node) -> Option<HighlightType> {
+    match capture_name {
+        "keyword" => Some(HighlightType::Keyword),
+        "string" => Some(HighlightType::String),
+        "comment" | "comment.documentation" => Some(HighlightType::Comment),
+        "type.builtin" => Some(HighlightType::BasicType),
+        "label" => Some(HighlightType::LifetimeSpecifier),
+        "constant.builtin" => {
+            if node.kind() == "boolean_literal" {
+                Some(HighlightType::EnumLiteral)
+            } else {
+                Some(HighlightType::Number)
+            }
+        }
+        _ => None,
+    }
+}
+
+fn get_point_at(text: &str, byte_idx: usize) -> Point {
+    let preceding = &text.as_bytes()[..byte_idx];
+    let row = preceding
+        .split(|byte| *byte == b'\n')
+        .count()
+        .saturating_sub(1);
+    let column = match preceding.iter().rposition(|byte| *byte == b'\n') {
+        Some(newline_idx) => byte_idx.saturating_sub(newline_idx).saturating_sub(1),
+        None => byte_idx,
+    };
+    Point { row, column }
+}
+
+/// Returns where line `line_idx` of `text` starts, or where
+/// `text` ends if it does not have that many lines.
+fn get_line_start(text: &str, line_idx: usize) -> (usize, Point) {
+    let Some(newline_idx) = line_idx.checked_sub(1) else {
+        return (0, Point { row: 0, column: 0 });
+    };
+    match text.match_indices('\n').nth(newline_idx) {
+        Some((byte_idx, _)) => (
+            byte_idx.saturating_add(1),
+            Point {
+                row: line_idx,
+                column: 0,
+            },
+        ),
+        None => (text.len(), get_point_at(text, text.len())),
+    }
+}
+
+/// Returns the edit that turns `old_text` into `new_text`, where only
+/// `changed_lines` (numbered as they are in `new_text`) have changed,
+/// so the lines after them are the same lines as before, shifted by
+/// the number of lines added.
+fn get_input_edit(old_text: &str, new_text: &str, changed_lines: Range<usize>) -> InputEdit {
+    let old_line_breaks = old_text.matches('\n').count();
+    let new_line_breaks = new_text.matches('\n').count();
+    let old_changed_end = changed_lines
+        .end
+        .saturating_add(old_line_breaks)
+        .saturating_sub(new_line_breaks);
+
+    let (start_byte, start_position) = get_line_start(new_text, changed_lines.start);
+    let (old_end_byte, old_end_position) = get_line_start(old_text, old_changed_end);
+    let (new_end_byte, new_end_position) = get_line_start(new_text, changed_lines.end);
+    InputEdit {
+        start_byte,
+        old_end_byte,
+        new_end_byte,
+        start_position,
+        old_end_position,
+        new_end_position,
+    }
+}
+
+/// Highlights a file using a tree-sitter grammar and its highlights
+/// query, reparsing incrementally whenever the buffer changes.
+pub(crate) struct TreeSitterHighlighter {
+    parser: Parser,
+    query: Query,
+    tree: Option<Tree>,
+    text: String,
+    line_highlights: HashMap<usize, Vec<(Range<usize>, HighlightType)>>,
+}
+
+impl TreeSitterHighlighter {
+    pub(crate) fn new(language: &Language, highlights_query: &str) -> Option<Self> {
+        let mut parser = Parser::new();
+        parser.set_language(language).ok()?;
+        let query = Query::new(language, highlights_query).ok()?;
+
+        Some(Self {
+            parser,
+            query,
+            tree: None,
+            text: String::new(),
+            line_highlights: HashMap::new(),
+        })
+    }
+
+    pub(crate) fn new_rust() -> Option<Self> {
+        Self::new(
+            &tree_sitter_rust::LANGUAGE.into(),
+            tree_sitter_rust::HIGHLIGHTS_QUERY,
+        )
+    }
+
+    fn regenerate_line_highlights(&mut self) {
+        self.line_highlights.clear();
+
+        let Some(tree) = &self.tree else {
+            return;
+        };
+
+        let line_lens = self.text.split('\n').map(str::len).collect::<Vec<_>>();
+        let capture_names = self.query.capture_names();
+
+        let mut cursor = QueryCursor::new();
+        let mut captures = cursor.captures(&self.query, tree.root_node(), self.text.as_bytes());
+        while let Some((query_match, capture_idx)) = captures.next() {
+            let capture = query_match.captures[*capture_idx];
+            let Some(highlight_type) = get_highlight_type_from_capture(
+                capture_names[u64::from(capture.index).to_usize()],
+                capture.node,
+            ) else {
+                continue;
+            };
+
+            // captures may span multiple lines (e.g. multi-line strings), so
+            // split them up into a range for each line
+            let (start, end) = (capture.node.start_position(), capture.node.end_position());
+            for row in start.row..=end.row {
+                let start_column = if row == start.row { start.column } else { 0 };
+                let end_column = if row == end.row {
+                    end.column
+                } else {
+                    line_lens.get(row).copied().unwrap_or_default()
+                };
+
+                if start_column < end_column {
+                    self.line_highlights
+                        .entry(row)
+                        .or_default()
+                        .push((start_column..end_column, highlight_type));
+                }
+            }
+        }
+    }
+}
+
+impl Highlighter for TreeSitterHighlighter {
+    fn highlight_line(&self, _line: &str, ctx: LineContext) -> Vec<(Range<usize>, HighlightType)> {
+        self.line_highlights
+            .get(&ctx.line_idx)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn on_buffer_change(&mut self, contents: &str, changed_lines: Option<Range<usize>>) {
+        match (&mut self.tree, changed_lines) {
+            (Some(tree), Some(changed_lines)) => {
+                tree.edit(&get_input_edit(&self.text, contents, changed_lines));
+            }
+            (Some(_), None) => {
+                if self.text == contents {
+                    return;
+                }
+                // without knowing what changed, it is parsed from scratch
+                self.tree = None;
+            }
+            (None, _) => {}
+        }
+
+        self.tree = self.parser.parse(contents, self.tree.as_ref());
+        contents.clone_into(&mut self.text);
+        self.regenerate_line_highlights();
+    }
+
+    fn needs_whole_buffer(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight_rust() {
+        let mut highlighter = TreeSitterHighlighter::new_rust().expect("rust grammar to load");
+        let highlights = |highlighter: &TreeSitterHighlighter, line_idx| {
+            highlighter.highlight_line(
+                "",
+                LineContext {
+                    line_idx,
+                    ..LineContext::default()
+                },
+            )
+        };
+
+        highlighter.on_buffer_change("fn main() {\n    let s = \"hi\";\n}", None);
+        assert!(highlights(&highlighter, 0).contains(&(0..2, HighlightType::Keyword)));
+        assert!(highlights(&highlighter, 1).contains(&(4..7, HighlightType::Keyword)));
+        assert!(highlights(&highlighter, 1).contains(&(12..16, HighlightType::String)));
+
+        // incremental reparse after an edit
+        highlighter.on_buffer_change("fn main() {\n    let mut s = \"hi\";\n}", Some(1..2));
+        assert!(highlights(&highlighter, 1).contains(&(4..7, HighlightType::Keyword)));
+        assert!(highlights(&highlighter, 1).contains(&(8..11, HighlightType::Keyword)));
+        assert!(highlights(&highlighter, 1).contains(&(16..20, HighlightType::String)));
+        assert!(!highlights(&highlighter, 1).contains(&(12..16, HighlightType::String)));
+
+        // and a line added, with the lines after it shifted down
+        highlighter.on_buffer_change(
+            "fn main() {\n    let mut s = \"hi\";\n    s = \"a\";\n}",
+            Some(1..3),
+        );
+        assert!(highlights(&highlighter, 1).contains(&(8..11, HighlightType::Keyword)));
+        assert!(highlights(&highlighter, 2).contains(&(8..11, HighlightType::String)));
+
+        // without the changed lines, it is parsed from scratch
+        highlighter.on_buffer_change("fn f() {}", None);
+        assert!(highlights(&highlighter, 0).contains(&(0..2, HighlightType::Keyword)));
+        assert!(highlights(&highlighter, 1).is_empty());
+    }
+
+    #[test]
+    fn test_get_input_edit() {
+        let point = |row, column| Point { row, column };
+
+        // a line replaced by two, between unchanged lines
+        let edit = get_input_edit("a\nb\nc", "a\nxy\nz\nc", 1..3);
+        assert_eq!(
+            (edit.start_byte, edit.old_end_byte, edit.new_end_byte),
+            (2, 4, 7)
+        );
+        assert_eq!(
+            (
+                edit.start_position,
+                edit.old_end_position,
+                edit.new_end_position
+            ),
+            (point(1, 0), point(2, 0), point(3, 0))
+        );
+
+        // up to the end, which is not at the start of a line
+        let edit = get_input_edit("a\nb", "a\nbc", 1..2);
+        assert_eq!(
+            (edit.start_byte, edit.old_end_byte, edit.new_end_byte),
+            (2, 3, 4)
+        );
+        assert_eq!(
+            (edit.old_end_position, edit.new_end_position),
+            (point(1, 1), point(1, 2))
+        );
+    }
+}
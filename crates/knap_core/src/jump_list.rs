@@ -0,0 +1,104 @@
+use knap_ui::text_buffer::TextBufferPos;
+
+/// How many positions are remembered, the oldest ones are forgotten first.
+const MAX_JUMPS: usize = 100;
+
+/// The positions that the caret was at before each large jump (e.g.
+/// a search), so that the user can go back (and forth) through them.
+#[derive(Debug, Default)]
+pub(crate) struct JumpList {
+    positions: Vec<TextBufferPos>,
+    /// the position that going forward returns to, or the length
+    /// of `positions` if the caret is not at one of them
+    current: usize,
+}
+
+impl JumpList {
+    /// Remember `pos` as the position before a jump, which
+    /// forgets the positions that can be gone forward to.
+    pub(crate) fn push(&mut self, pos: TextBufferPos) {
+        self.positions.truncate(self.current);
+        if self.positions.last() != Some(&pos) {
+            self.positions.push(pos);
+        }
+        if self.positions.len() > MAX_JUMPS {
+            self.positions.remove(0);
+        }
+        self.current = self.positions.len();
+    }
+
+    /// Returns the position to go back to, with the caret being at
+    /// `caret_pos`, which can be gone forward to again afterwards.
+    pub(crate) fn back(&mut self, caret_pos: TextBufferPos) -> Option<TextBufferPos> {
+        if self.current == 0 {
+            return None;
+        }
+
+        if self.current == self.positions.len() {
+            self.positions.push(caret_pos);
+        }
+        self.current = self.current.saturating_sub(1);
+        self.positions.get(self.current).copied()
+    }
+
+    /// Returns the position to go forward to, if any.
+    pub(crate) fn forward(&mut self) -> Option<TextBufferPos> {
+        let next = self.current.saturating_add(1);
+        let pos = self.positions.get(next).copied()?;
+        self.current = next;
+        Some(pos)
+    }
+
+    /// `new_lines` lines were inserted into `line_idx`, shifting
+    /// the positions after it down by as many.
+    pub(crate) fn split_lines(&mut self, line_idx: usize, new_lines: usize) {
+        for pos in self.positions.iter_mut().filter(|pos| pos.line > line_idx) {
+            pos.line = pos.line.saturating_add(new_lines);
+        }
+    }
+
+    /// The `removed_lines` lines after `line_idx` were joined onto it,
+    /// shifting the positions after those up by as many. The positions
+    /// on the removed lines move to `line_idx`.
+    pub(crate) fn join_lines(&mut self, line_idx: usize, removed_lines: usize) {
+        for pos in self.positions.iter_mut().filter(|pos| pos.line > line_idx) {
+            pos.line = pos.line.saturating_sub(removed_lines).max(line_idx);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(line: usize) -> TextBufferPos {
+        TextBufferPos { line, byte: 0 }
+    }
+
+    #[test]
+    fn test_jump_list() {
+        let mut jump_list = JumpList::default();
+        assert_eq!(jump_list.back(pos(0)), None);
+
+        jump_list.push(pos(1));
+        jump_list.push(pos(5));
+        assert_eq!(jump_list.back(pos(9)), Some(pos(5)));
+        assert_eq!(jump_list.back(pos(5)), Some(pos(1)));
+        assert_eq!(jump_list.back(pos(1)), None);
+        assert_eq!(jump_list.forward(), Some(pos(5)));
+        assert_eq!(jump_list.forward(), Some(pos(9)));
+        assert_eq!(jump_list.forward(), None);
+
+        // jumping after going back forgets the positions ahead
+        jump_list.back(pos(9));
+        jump_list.push(pos(5));
+        assert_eq!(jump_list.forward(), None);
+        assert_eq!(jump_list.back(pos(2)), Some(pos(5)));
+
+        // lines added or removed move the positions after them
+        jump_list.split_lines(3, 2);
+        assert_eq!(jump_list.positions, vec![pos(1), pos(7), pos(2)]);
+        jump_list.join_lines(0, 3);
+        assert_eq!(jump_list.positions, vec![pos(0), pos(4), pos(0)]);
+    }
+}
@@ -0,0 +1,580 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+
+use crate::commands::EditorCommand;
+
+/// The keys that are bound to a command by default. This table is also
+/// used to generate the help, so the most important bindings come first.
+///
+/// Typing a character is not listed here, as it inserts whichever
+/// character was typed.
+const KEY_BINDINGS: &[(KeyModifiers, KeyCode, EditorCommand)] = &[
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::Char('f'),
+        EditorCommand::StartSearch,
+    ),
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::Char('s'),
+        EditorCommand::WriteBufferToDisk,
+    ),
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::Char('q'),
+        EditorCommand::QuitAll,
+    ),
+    (KeyModifiers::NONE, KeyCode::F(1), EditorCommand::ShowHelp),
+    (KeyModifiers::NONE, KeyCode::Up, EditorCommand::MoveCursorUp),
+    (
+        KeyModifiers::NONE,
+        KeyCode::Down,
+        EditorCommand::MoveCursorDown,
+    ),
+    (
+        KeyModifiers::NONE,
+        KeyCode::Left,
+        EditorCommand::MoveCursorLeft,
+    ),
+    (
+        KeyModifiers::NONE,
+        KeyCode::Right,
+        EditorCommand::MoveCursorRight,
+    ),
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::Left,
+        EditorCommand::MoveCursorWordLeft,
+    ),
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::Right,
+        EditorCommand::MoveCursorWordRight,
+    ),
+    (
+        KeyModifiers::SHIFT,
+        KeyCode::Up,
+        EditorCommand::ExtendSelectionUp,
+    ),
+    (
+        KeyModifiers::SHIFT,
+        KeyCode::Down,
+        EditorCommand::ExtendSelectionDown,
+    ),
+    (
+        KeyModifiers::SHIFT,
+        KeyCode::Left,
+        EditorCommand::ExtendSelectionLeft,
+    ),
+    (
+        KeyModifiers::SHIFT,
+        KeyCode::Right,
+        EditorCommand::ExtendSelectionRight,
+    ),
+    (
+        KeyModifiers::NONE,
+        KeyCode::Home,
+        EditorCommand::MoveCursorToStartOfLine,
+    ),
+    (
+        KeyModifiers::NONE,
+        KeyCode::End,
+        EditorCommand::MoveCursorToEndOfLine,
+    ),
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::Home,
+        EditorCommand::MoveCursorToTop,
+    ),
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::End,
+        EditorCommand::MoveCursorToBottom,
+    ),
+    (
+        KeyModifiers::NONE,
+        KeyCode::PageUp,
+        EditorCommand::MoveCursorUpOnePage,
+    ),
+    (
+        KeyModifiers::NONE,
+        KeyCode::PageDown,
+        EditorCommand::MoveCursorDownOnePage,
+    ),
+    (
+        KeyModifiers::NONE,
+        KeyCode::Backspace,
+        EditorCommand::EraseCharacterBeforeCursor,
+    ),
+    (
+        KeyModifiers::NONE,
+        KeyCode::Delete,
+        EditorCommand::EraseCharacterAfterCursor,
+    ),
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::Backspace,
+        EditorCommand::EraseWordBeforeCursor,
+    ),
+    // NOTE: terminals send Ctrl-Backspace as either Backspace or Ctrl-H,
+    // so it is also bound to Alt-Backspace
+    (
+        KeyModifiers::ALT,
+        KeyCode::Backspace,
+        EditorCommand::EraseWordBeforeCursor,
+    ),
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::Delete,
+        EditorCommand::EraseWordAfterCursor,
+    ),
+    (
+        KeyModifiers::NONE,
+        KeyCode::Tab,
+        EditorCommand::InsertIndent,
+    ),
+    (
+        KeyModifiers::SHIFT,
+        KeyCode::BackTab,
+        EditorCommand::DedentLines,
+    ),
+    (
+        KeyModifiers::NONE,
+        KeyCode::Enter,
+        EditorCommand::InsertNewline,
+    ),
+    (
+        KeyModifiers::NONE,
+        KeyCode::Insert,
+        EditorCommand::ToggleOverwriteMode,
+    ),
+    (
+        KeyModifiers::ALT,
+        KeyCode::Char('b'),
+        EditorCommand::ToggleBlockSelection,
+    ),
+    (KeyModifiers::NONE, KeyCode::Esc, EditorCommand::Dismiss),
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::Char('a'),
+        EditorCommand::SelectAll,
+    ),
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::Char('c'),
+        EditorCommand::Copy,
+    ),
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::Char('g'),
+        EditorCommand::StartGotoLine,
+    ),
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::Char('x'),
+        EditorCommand::Cut,
+    ),
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::Char('v'),
+        EditorCommand::Paste,
+    ),
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::Char('d'),
+        EditorCommand::DuplicateSelection,
+    ),
+    (KeyModifiers::ALT, KeyCode::Up, EditorCommand::MoveLineUp),
+    (
+        KeyModifiers::ALT,
+        KeyCode::Down,
+        EditorCommand::MoveLineDown,
+    ),
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::Char('j'),
+        EditorCommand::JoinLines,
+    ),
+    // NOTE: terminals send the same control code for Ctrl-7 and Ctrl-/,
+    // so both of them are reported as Ctrl-7
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::Char('7'),
+        EditorCommand::ToggleComment,
+    ),
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::Char('k'),
+        EditorCommand::DeleteToEndOfLine,
+    ),
+    // NOTE: not Ctrl-Shift-K, as terminals send the same control code
+    // for it as for Ctrl-K
+    (
+        KeyModifiers::ALT.union(KeyModifiers::SHIFT),
+        KeyCode::Char('K'),
+        EditorCommand::DeleteLine,
+    ),
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::Char('u'),
+        EditorCommand::DeleteToStartOfLine,
+    ),
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::Char('z'),
+        EditorCommand::Undo,
+    ),
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::Char('y'),
+        EditorCommand::Redo,
+    ),
+    (KeyModifiers::ALT, KeyCode::Char('y'), EditorCommand::Yank),
+    (
+        KeyModifiers::ALT.union(KeyModifiers::SHIFT),
+        KeyCode::Char('Y'),
+        EditorCommand::YankPop,
+    ),
+    (
+        KeyModifiers::ALT,
+        KeyCode::Char('x'),
+        EditorCommand::SwapSelectionWithClipboard,
+    ),
+    (
+        KeyModifiers::ALT,
+        KeyCode::Char('p'),
+        EditorCommand::FilterThroughCommand,
+    ),
+    (
+        KeyModifiers::ALT,
+        KeyCode::Char('d'),
+        EditorCommand::InsertDateTime,
+    ),
+    (
+        KeyModifiers::ALT,
+        KeyCode::Char('n'),
+        EditorCommand::InsertFileName,
+    ),
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::Char(' '),
+        EditorCommand::TriggerCompletion,
+    ),
+    (
+        KeyModifiers::ALT,
+        KeyCode::Char('e'),
+        EditorCommand::RevealWhitespaceErrors,
+    ),
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::Char('l'),
+        EditorCommand::SelectLine,
+    ),
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::Char('w'),
+        EditorCommand::SelectWord,
+    ),
+    (
+        KeyModifiers::ALT,
+        KeyCode::Char('f'),
+        EditorCommand::ToggleFold,
+    ),
+    (
+        KeyModifiers::ALT,
+        KeyCode::Char('r'),
+        EditorCommand::StartRegexSearch,
+    ),
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::Char('o'),
+        EditorCommand::JumpBack,
+    ),
+    // NOTE: not Ctrl-I like vim, as terminals send Tab for it
+    (
+        KeyModifiers::ALT,
+        KeyCode::Char('o'),
+        EditorCommand::JumpForward,
+    ),
+    // NOTE: terminals send the same control code for Ctrl-5 and Ctrl-],
+    // so both of them are reported as Ctrl-5
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::Char('5'),
+        EditorCommand::JumpToMatchingBracket,
+    ),
+    (
+        KeyModifiers::ALT,
+        KeyCode::Char('l'),
+        EditorCommand::CenterCaret,
+    ),
+    (
+        KeyModifiers::ALT,
+        KeyCode::Char('j'),
+        EditorCommand::JumpToNextWordOccurrence,
+    ),
+    (
+        KeyModifiers::ALT,
+        KeyCode::Char('k'),
+        EditorCommand::JumpToPreviousWordOccurrence,
+    ),
+    (
+        KeyModifiers::ALT,
+        KeyCode::Char('m'),
+        EditorCommand::SetMark,
+    ),
+    (
+        KeyModifiers::ALT,
+        KeyCode::Char('`'),
+        EditorCommand::JumpToMark,
+    ),
+    (
+        KeyModifiers::ALT,
+        KeyCode::Char('v'),
+        EditorCommand::SplitVertical,
+    ),
+    (
+        KeyModifiers::ALT,
+        KeyCode::Char('s'),
+        EditorCommand::SplitHorizontal,
+    ),
+    (
+        KeyModifiers::ALT,
+        KeyCode::Char('w'),
+        EditorCommand::FocusNextPane,
+    ),
+    (
+        KeyModifiers::ALT,
+        KeyCode::Char('q'),
+        EditorCommand::ClosePane,
+    ),
+    (
+        KeyModifiers::ALT,
+        KeyCode::Char('.'),
+        EditorCommand::RepeatLastChange,
+    ),
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::Char('r'),
+        EditorCommand::StartRecordingMacro,
+    ),
+    (
+        KeyModifiers::CONTROL,
+        KeyCode::Char('p'),
+        EditorCommand::PlayMacro(1),
+    ),
+];
+
+/// The commands that keys are bound to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    /// in the order that they are listed in the help
+    bindings: Vec<(KeyModifiers, KeyCode, EditorCommand)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            bindings: KEY_BINDINGS.to_vec(),
+        }
+    }
+}
+
+impl Keymap {
+    /// A keymap without any bindings, apart from typing characters.
+    pub fn empty() -> Self {
+        Self { bindings: vec![] }
+    }
+
+    /// Bind the key to `command`, replacing the command that
+    /// it was bound to (if any).
+    pub fn bind(&mut self, modifiers: KeyModifiers, code: KeyCode, command: EditorCommand) {
+        match self
+            .bindings
+            .iter_mut()
+            .find(|(binding_modifiers, binding_code, _)| {
+                *binding_modifiers == modifiers && *binding_code == code
+            }) {
+            Some((_, _, bound_command)) => *bound_command = command,
+            None => self.bindings.push((modifiers, code, command)),
+        }
+    }
+
+    /// Remove the binding of the key, returning the command
+    /// that it was bound to (if any).
+    pub fn unbind(&mut self, modifiers: KeyModifiers, code: KeyCode) -> Option<EditorCommand> {
+        let idx = self
+            .bindings
+            .iter()
+            .position(|(binding_modifiers, binding_code, _)| {
+                *binding_modifiers == modifiers && *binding_code == code
+            })?;
+        Some(self.bindings.remove(idx).2)
+    }
+
+    /// Returns the command bound to the given key, if any.
+    pub fn get_command(&self, modifiers: KeyModifiers, code: KeyCode) -> Option<EditorCommand> {
+        if matches!(modifiers, KeyModifiers::NONE | KeyModifiers::SHIFT)
+            && let KeyCode::Char(ch) = code
+        {
+            // NOTE: for SHIFT case, crossterm automatically
+            // converts ch to uppercase for us already. This
+            // also means we do not need to manually handle
+            // capslock scenario
+            return Some(EditorCommand::InsertCharacter(ch));
+        }
+
+        self.bindings
+            .iter()
+            .find(|(binding_modifiers, binding_code, _)| {
+                *binding_modifiers == modifiers && *binding_code == code
+            })
+            .map(|(_, _, command)| *command)
+    }
+
+    /// Splits the list of key bindings into pages of help messages,
+    /// each fitting within `max_width` (or on a single page if `max_width`
+    /// is 0).
+    pub(crate) fn get_help_pages(&self, max_width: usize) -> Vec<String> {
+        const PREFIX: &str = "HELP: ";
+        const MORE_SUFFIX: &str = " | F1 = more";
+        const SEPARATOR: &str = " | ";
+
+        let max_width = if max_width == 0 {
+            usize::MAX
+        } else {
+            max_width.saturating_sub(MORE_SUFFIX.len())
+        };
+
+        let mut pages = vec![];
+        let mut page = String::from(PREFIX);
+        for (modifiers, code, command) in &self.bindings {
+            let entry = format!(
+                "{} = {}",
+                format_key(*modifiers, *code),
+                command.description()
+            );
+
+            if page.len() > PREFIX.len() {
+                if page
+                    .len()
+                    .saturating_add(SEPARATOR.len())
+                    .saturating_add(entry.len())
+                    > max_width
+                {
+                    pages.push(std::mem::replace(&mut page, String::from(PREFIX)));
+                } else {
+                    page.push_str(SEPARATOR);
+                }
+            }
+            page.push_str(&entry);
+        }
+        pages.push(page);
+
+        if pages.len() > 1 {
+            for page in &mut pages {
+                page.push_str(MORE_SUFFIX);
+            }
+        }
+
+        pages
+    }
+}
+
+/// Returns how a key is shown to the user, e.g. `Ctrl-S`.
+fn format_key(modifiers: KeyModifiers, code: KeyCode) -> String {
+    let modifier_prefix = if modifiers.contains(KeyModifiers::CONTROL) {
+        "Ctrl-"
+    } else if modifiers.contains(KeyModifiers::ALT) {
+        "Alt-"
+    } else {
+        ""
+    };
+    let shift_prefix = if modifiers.contains(KeyModifiers::SHIFT) {
+        "Shift-"
+    } else {
+        ""
+    };
+
+    let key = match code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        // Shift-Tab comes as a key of its own
+        KeyCode::BackTab => "Tab".to_string(),
+        KeyCode::Char(ch) => ch.to_uppercase().to_string(),
+        code => code.to_string(),
+    };
+
+    format!("{modifier_prefix}{shift_prefix}{key}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_help_pages() {
+        let keymap = Keymap::default();
+        let pages = keymap.get_help_pages(0);
+        assert_eq!(pages.len(), 1);
+        assert!(pages[0].starts_with("HELP: Ctrl-F = find | Ctrl-S = save | Ctrl-Q = quit"));
+        assert!(pages[0].contains(" | Ctrl-K = delete to end | Alt-Shift-K = delete line | "));
+        assert!(pages[0].contains(" | Tab = indent | Shift-Tab = dedent | "));
+        assert!(pages[0].contains(" | Ctrl-7 = toggle comment | "));
+        assert!(pages[0].contains(
+            " | Ctrl-Backspace = erase word before | Alt-Backspace = erase word before | "
+        ));
+
+        let pages = keymap.get_help_pages(60);
+        assert!(pages.len() > 1);
+        assert!(pages.iter().all(|page| page.len() <= 60));
+        assert!(pages.iter().all(|page| page.ends_with(" | F1 = more")));
+    }
+
+    #[test]
+    fn test_rebind_key() {
+        let mut keymap = Keymap::default();
+        assert_eq!(
+            keymap.get_command(KeyModifiers::CONTROL, KeyCode::Char('s')),
+            Some(EditorCommand::WriteBufferToDisk)
+        );
+        assert_eq!(
+            keymap.get_command(KeyModifiers::NONE, KeyCode::Char('s')),
+            Some(EditorCommand::InsertCharacter('s'))
+        );
+
+        keymap.bind(
+            KeyModifiers::CONTROL,
+            KeyCode::Char('s'),
+            EditorCommand::StartSearch,
+        );
+        assert_eq!(
+            keymap.get_command(KeyModifiers::CONTROL, KeyCode::Char('s')),
+            Some(EditorCommand::StartSearch)
+        );
+        // the binding keeps its place in the help
+        assert!(
+            keymap.get_help_pages(0)[0]
+                .starts_with("HELP: Ctrl-F = find | Ctrl-S = find | Ctrl-Q = quit")
+        );
+
+        assert_eq!(
+            keymap.unbind(KeyModifiers::CONTROL, KeyCode::Char('s')),
+            Some(EditorCommand::StartSearch)
+        );
+        assert_eq!(
+            keymap.get_command(KeyModifiers::CONTROL, KeyCode::Char('s')),
+            None
+        );
+
+        let mut keymap = Keymap::empty();
+        keymap.bind(
+            KeyModifiers::ALT,
+            KeyCode::Char('q'),
+            EditorCommand::QuitAll,
+        );
+        assert_eq!(
+            keymap.get_command(KeyModifiers::ALT, KeyCode::Char('q')),
+            Some(EditorCommand::QuitAll)
+        );
+        assert_eq!(keymap.get_help_pages(0), vec!["HELP: Alt-Q = quit"]);
+    }
+}
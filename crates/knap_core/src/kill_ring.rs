@@ -0,0 +1,90 @@
+const MAX_KILLS: usize = 32;
+
+/// The text removed by kill commands (e.g. `DeleteToEndOfLine`), for
+/// yanking back later. This is separate from the clipboard.
+#[derive(Debug, Default)]
+pub(crate) struct KillRing {
+    /// oldest first
+    kills: Vec<String>,
+    /// how many kills back from the newest one the last yank was
+    yank_offset: usize,
+}
+
+impl KillRing {
+    /// Add `text` as the newest kill, dropping the oldest kill if full.
+    pub(crate) fn push(&mut self, text: String) {
+        if self.kills.len() >= MAX_KILLS {
+            self.kills.remove(0);
+        }
+        self.kills.push(text);
+    }
+
+    /// Add `text` to the end of the newest kill, for consecutive kills
+    /// going forward.
+    pub(crate) fn append(&mut self, text: &str) {
+        match self.kills.last_mut() {
+            Some(kill) => kill.push_str(text),
+            None => self.push(text.to_string()),
+        }
+    }
+
+    /// Add `text` to the start of the newest kill, for consecutive kills
+    /// going backward.
+    pub(crate) fn prepend(&mut self, text: &str) {
+        match self.kills.last_mut() {
+            Some(kill) => kill.insert_str(0, text),
+            None => self.push(text.to_string()),
+        }
+    }
+
+    /// Returns the newest kill, or `None` if nothing was killed yet.
+    pub(crate) fn yank(&mut self) -> Option<&str> {
+        self.yank_offset = 0;
+        self.kills.last().map(String::as_str)
+    }
+
+    /// Returns the kill before the one that was last yanked,
+    /// cycling back to the newest one after the oldest one.
+    pub(crate) fn yank_pop(&mut self) -> Option<&str> {
+        self.yank_offset = self
+            .yank_offset
+            .saturating_add(1)
+            .checked_rem(self.kills.len())?;
+        self.kills
+            .len()
+            .checked_sub(self.yank_offset.saturating_add(1))
+            .and_then(|idx| self.kills.get(idx))
+            .map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kill_ring() {
+        let mut kill_ring = KillRing::default();
+        assert_eq!(kill_ring.yank(), None);
+        assert_eq!(kill_ring.yank_pop(), None);
+
+        kill_ring.push("one".to_string());
+        kill_ring.append("\n");
+        kill_ring.push("two".to_string());
+        kill_ring.prepend("to ");
+        kill_ring.push("three".to_string());
+
+        assert_eq!(kill_ring.yank(), Some("three"));
+        assert_eq!(kill_ring.yank_pop(), Some("to two"));
+        assert_eq!(kill_ring.yank_pop(), Some("one\n"));
+        assert_eq!(kill_ring.yank_pop(), Some("three"));
+        assert_eq!(kill_ring.yank(), Some("three"));
+
+        // the oldest kills are dropped when full
+        for kill in 0..MAX_KILLS {
+            kill_ring.push(kill.to_string());
+        }
+        assert_eq!(kill_ring.kills.len(), MAX_KILLS);
+        assert_eq!(kill_ring.kills.first().map(String::as_str), Some("0"));
+    }
+}
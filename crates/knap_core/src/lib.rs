@@ -1,7 +1,21 @@
+mod annotations;
 mod code_view;
 mod command_bar;
 mod commands;
+mod completion;
+mod date_time;
 pub mod editor;
+#[cfg(feature = "notify")]
+mod file_watcher;
+mod git;
 mod highlighter;
+mod jump_list;
+mod keymap;
+mod kill_ring;
 mod message_bar;
+mod mouse;
+mod panes;
+mod process;
+mod session;
 mod status_bar;
+mod theme;
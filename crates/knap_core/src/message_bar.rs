@@ -14,6 +14,10 @@ impl MessageBar {
         }
     }
 
+    pub(crate) fn bounds(&self) -> Bounds2f {
+        self.bounds
+    }
+
     pub(crate) fn set_bounds(&mut self, bounds: Bounds2f) {
         self.bounds = bounds;
     }
@@ -22,11 +26,16 @@ impl MessageBar {
         self.message = Some(message.as_ref().to_string());
     }
 
+    #[cfg(test)]
+    pub(crate) fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
     pub(crate) fn render(&self, drawer: &mut Drawer) {
-        if self.bounds.size.x * self.bounds.size.y > 0.0 {
-            if let Some(message) = &self.message {
-                drawer.draw_text(self.bounds.pos, message);
-            }
+        if self.bounds.size.x * self.bounds.size.y > 0.0
+            && let Some(message) = &self.message
+        {
+            drawer.draw_text(self.bounds.pos, message);
         }
     }
 }
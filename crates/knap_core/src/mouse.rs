@@ -0,0 +1,136 @@
+use std::time::{Duration, Instant};
+
+use crossterm::event::MouseEventKind;
+use knap_base::math::{Vec2i, Vec2u};
+
+const DEFAULT_MULTI_CLICK_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// How many lines a notch of the mouse wheel scrolls by.
+const SCROLL_LINES_PER_NOTCH: i64 = 3;
+/// How many columns a notch of a horizontal mouse wheel scrolls by.
+const SCROLL_COLUMNS_PER_NOTCH: i64 = 6;
+
+/// Returns how far a notch of the mouse wheel scrolls, or `None`
+/// if `kind` is not from the mouse wheel.
+pub(crate) fn get_scroll_delta(kind: MouseEventKind) -> Option<Vec2i> {
+    match kind {
+        MouseEventKind::ScrollUp => Some(Vec2i {
+            x: 0,
+            y: -SCROLL_LINES_PER_NOTCH,
+        }),
+        MouseEventKind::ScrollDown => Some(Vec2i {
+            x: 0,
+            y: SCROLL_LINES_PER_NOTCH,
+        }),
+        MouseEventKind::ScrollLeft => Some(Vec2i {
+            x: -SCROLL_COLUMNS_PER_NOTCH,
+            y: 0,
+        }),
+        MouseEventKind::ScrollRight => Some(Vec2i {
+            x: SCROLL_COLUMNS_PER_NOTCH,
+            y: 0,
+        }),
+        _ => None,
+    }
+}
+
+/// Keeps track of consecutive clicks, so that double clicks
+/// and triple clicks can be detected.
+pub(crate) struct ClickTracker {
+    /// The maximum time allowed between two clicks for them
+    /// to be counted as part of the same multi-click.
+    multi_click_timeout: Duration,
+
+    last_click: Option<(Vec2u, Instant)>,
+    click_count: usize,
+}
+
+impl ClickTracker {
+    pub(crate) fn new() -> Self {
+        Self {
+            multi_click_timeout: DEFAULT_MULTI_CLICK_TIMEOUT,
+            last_click: None,
+            click_count: 0,
+        }
+    }
+
+    pub(crate) fn set_multi_click_timeout(&mut self, timeout: Duration) {
+        self.multi_click_timeout = timeout;
+    }
+
+    /// Register a click, and return the number of consecutive clicks
+    /// (1 for a single click, 2 for a double click, 3 for a triple click).
+    ///
+    /// A click after a triple click starts over as a single click.
+    pub(crate) fn register_click(&mut self, pos: Vec2u, time: Instant) -> usize {
+        let is_consecutive = self.last_click.is_some_and(|(last_pos, last_time)| {
+            last_pos == pos
+                && time
+                    .checked_duration_since(last_time)
+                    .is_some_and(|elapsed| elapsed <= self.multi_click_timeout)
+        });
+
+        self.click_count = if is_consecutive && self.click_count < 3 {
+            self.click_count.saturating_add(1)
+        } else {
+            1
+        };
+        self.last_click = Some((pos, time));
+
+        self.click_count
+    }
+
+    pub(crate) fn click_count(&self) -> usize {
+        self.click_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_click() {
+        let mut tracker = ClickTracker::new();
+        tracker.set_multi_click_timeout(Duration::from_millis(100));
+
+        let pos = Vec2u { x: 3, y: 4 };
+        let start = Instant::now();
+        let after = |millis| start + Duration::from_millis(millis);
+
+        // single, double, triple, and then start over
+        assert_eq!(tracker.register_click(pos, start), 1);
+        assert_eq!(tracker.register_click(pos, after(50)), 2);
+        assert_eq!(tracker.register_click(pos, after(100)), 3);
+        assert_eq!(tracker.click_count(), 3);
+        assert_eq!(tracker.register_click(pos, after(150)), 1);
+
+        // too slow
+        assert_eq!(tracker.register_click(pos, after(400)), 1);
+        assert_eq!(tracker.register_click(pos, after(501)), 1);
+        assert_eq!(tracker.register_click(pos, after(550)), 2);
+
+        // different position
+        assert_eq!(tracker.register_click(Vec2u { x: 4, y: 4 }, after(560)), 1);
+        assert_eq!(tracker.register_click(Vec2u { x: 4, y: 4 }, after(570)), 2);
+    }
+
+    #[test]
+    fn test_get_scroll_delta() {
+        assert_eq!(
+            get_scroll_delta(MouseEventKind::ScrollDown),
+            Some(Vec2i {
+                x: 0,
+                y: SCROLL_LINES_PER_NOTCH
+            })
+        );
+        assert_eq!(
+            get_scroll_delta(MouseEventKind::ScrollLeft),
+            Some(Vec2i {
+                x: -SCROLL_COLUMNS_PER_NOTCH,
+                y: 0
+            })
+        );
+        assert_eq!(get_scroll_delta(MouseEventKind::Moved), None);
+    }
+}
@@ -0,0 +1,400 @@
+use knap_base::math::{Bounds2f, Vec2f};
+use knap_window::drawer::Drawer;
+
+use crate::code_view::CodeView;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SplitDirection {
+    /// side by side, separated by a vertical line
+    Vertical,
+    /// one above the other, separated by a horizontal line
+    Horizontal,
+}
+
+enum PaneNode {
+    Leaf(Box<CodeView>),
+    Split {
+        direction: SplitDirection,
+        first: Box<PaneNode>,
+        second: Box<PaneNode>,
+    },
+}
+
+impl PaneNode {
+    fn views(&self) -> Vec<&CodeView> {
+        match self {
+            Self::Leaf(view) => vec![view.as_ref()],
+            Self::Split { first, second, .. } => {
+                let mut views = first.views();
+                views.extend(second.views());
+                views
+            }
+        }
+    }
+
+    fn views_mut(&mut self) -> Vec<&mut CodeView> {
+        match self {
+            Self::Leaf(view) => vec![view.as_mut()],
+            Self::Split { first, second, .. } => {
+                let mut views = first.views_mut();
+                views.extend(second.views_mut());
+                views
+            }
+        }
+    }
+
+    fn total_panes(&self) -> usize {
+        match self {
+            Self::Leaf(_) => 1,
+            Self::Split { first, second, .. } => {
+                first.total_panes().saturating_add(second.total_panes())
+            }
+        }
+    }
+
+    /// Moves the node out, leaving an empty view in its place.
+    fn take(&mut self) -> Self {
+        std::mem::replace(self, Self::Leaf(Box::new(CodeView::new())))
+    }
+
+    /// Splits the `pane_idx`-th pane (counting from 0, in the order
+    /// the panes are laid out), putting `view` after it.
+    fn split_pane(&mut self, pane_idx: usize, direction: SplitDirection, view: CodeView) {
+        match self {
+            Self::Leaf(_) => {
+                *self = Self::Split {
+                    direction,
+                    first: Box::new(self.take()),
+                    second: Box::new(Self::Leaf(Box::new(view))),
+                };
+            }
+            Self::Split { first, second, .. } => {
+                let first_total_panes = first.total_panes();
+                if pane_idx < first_total_panes {
+                    first.split_pane(pane_idx, direction, view);
+                } else {
+                    second.split_pane(pane_idx.saturating_sub(first_total_panes), direction, view);
+                }
+            }
+        }
+    }
+
+    /// Removes the `pane_idx`-th pane, giving its space to its sibling.
+    /// Returns whether the pane was removed (the last pane cannot be).
+    fn remove_pane(&mut self, pane_idx: usize) -> bool {
+        let Self::Split { first, second, .. } = self else {
+            return false;
+        };
+
+        let first_total_panes = first.total_panes();
+        let sibling = if pane_idx == 0 && first_total_panes == 1 {
+            second
+        } else if pane_idx == first_total_panes && second.total_panes() == 1 {
+            first
+        } else if pane_idx < first_total_panes {
+            return first.remove_pane(pane_idx);
+        } else {
+            return second.remove_pane(pane_idx.saturating_sub(first_total_panes));
+        };
+
+        *self = sibling.take();
+        true
+    }
+
+    /// Returns the bounds of each pane in `bounds`, along with
+    /// the separators between them.
+    fn layout(&self, bounds: Bounds2f, layout: &mut Layout) {
+        match self {
+            Self::Leaf(_) => layout.panes.push(bounds),
+            Self::Split {
+                direction,
+                first,
+                second,
+            } => {
+                let (first_bounds, separator, second_bounds) = match direction {
+                    SplitDirection::Vertical => {
+                        let (left, separator_x, right) = bounds.split_vertically();
+                        (
+                            left,
+                            Separator::Vertical {
+                                x: separator_x,
+                                bounds,
+                            },
+                            right,
+                        )
+                    }
+                    SplitDirection::Horizontal => {
+                        let (top, separator_y, bottom) = bounds.split_horizontally();
+                        (
+                            top,
+                            Separator::Horizontal {
+                                y: separator_y,
+                                bounds,
+                            },
+                            bottom,
+                        )
+                    }
+                };
+
+                layout.separators.push(separator);
+                first.layout(first_bounds, layout);
+                second.layout(second_bounds, layout);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Separator {
+    /// a vertical line at `x`, spanning the height of `bounds`
+    Vertical { x: f64, bounds: Bounds2f },
+    /// a horizontal line at `y`, spanning the width of `bounds`
+    Horizontal { y: f64, bounds: Bounds2f },
+}
+
+/// Where each pane and the separators between them are placed.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Layout {
+    /// the bounds of each pane, in the same order as [`Panes::views`]
+    pub panes: Vec<Bounds2f>,
+    pub separators: Vec<Separator>,
+}
+
+/// The views shown in the window, which can be split into
+/// multiple panes. Only one of the panes is focused at a time.
+pub(crate) struct Panes {
+    root: PaneNode,
+    focused: usize,
+    bounds: Bounds2f,
+    layout: Layout,
+}
+
+impl Panes {
+    pub(crate) fn new(view: CodeView) -> Self {
+        Self {
+            root: PaneNode::Leaf(Box::new(view)),
+            focused: 0,
+            bounds: Bounds2f::ZERO,
+            layout: Layout::default(),
+        }
+    }
+
+    pub(crate) fn views(&self) -> Vec<&CodeView> {
+        self.root.views()
+    }
+
+    pub(crate) fn views_mut(&mut self) -> Vec<&mut CodeView> {
+        self.root.views_mut()
+    }
+
+    pub(crate) fn focused(&self) -> &CodeView {
+        self.root.views().swap_remove(self.focused)
+    }
+
+    pub(crate) fn focused_mut(&mut self) -> &mut CodeView {
+        self.root.views_mut().swap_remove(self.focused)
+    }
+
+    /// Replace the view in the focused pane.
+    pub(crate) fn replace_focused(&mut self, mut view: CodeView) {
+        view.set_bounds(self.focused().bounds());
+        *self.focused_mut() = view;
+    }
+
+    pub(crate) fn set_bounds(&mut self, bounds: Bounds2f) {
+        self.bounds = bounds;
+        self.relayout();
+    }
+
+    fn relayout(&mut self) {
+        self.layout = Layout::default();
+        self.root.layout(self.bounds, &mut self.layout);
+
+        for (view, bounds) in self.root.views_mut().into_iter().zip(&self.layout.panes) {
+            view.set_bounds(*bounds);
+        }
+    }
+
+    /// Split the focused pane in two, putting `view` in the new
+    /// pane (after the focused one), which becomes focused.
+    pub(crate) fn split(&mut self, direction: SplitDirection, view: CodeView) {
+        self.root.split_pane(self.focused, direction, view);
+
+        self.focused = self.focused.saturating_add(1);
+        self.relayout();
+    }
+
+    /// Close the focused pane, giving its space to its sibling. Returns
+    /// whether the pane was closed (the last pane cannot be closed).
+    pub(crate) fn close_focused(&mut self) -> bool {
+        if !self.root.remove_pane(self.focused) {
+            return false;
+        }
+
+        self.focused = self.focused.min(self.root.total_panes().saturating_sub(1));
+        self.relayout();
+        true
+    }
+
+    /// Move the focus to the next pane, wrapping around after the last one.
+    pub(crate) fn focus_next(&mut self) {
+        self.focused = self.focused.saturating_add(1);
+        if self.focused >= self.root.total_panes() {
+            self.focused = 0;
+        }
+    }
+
+    /// Move the focus to the pane at `screen_pos`, returning
+    /// whether there is a pane there.
+    pub(crate) fn focus_at(&mut self, screen_pos: Vec2f) -> bool {
+        match self
+            .layout
+            .panes
+            .iter()
+            .position(|bounds| bounds.contains(screen_pos))
+        {
+            Some(pane_idx) => {
+                self.focused = pane_idx;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn render(&self, drawer: &mut Drawer) {
+        for separator in &self.layout.separators {
+            match *separator {
+                Separator::Vertical { x, bounds } => {
+                    drawer.draw_vertical_line(x, bounds.pos.y..(bounds.pos.y + bounds.size.y));
+                }
+                Separator::Horizontal { y, bounds } => {
+                    drawer.draw_horizontal_line(y, bounds.pos.x..(bounds.pos.x + bounds.size.x));
+                }
+            }
+        }
+
+        // the focused view is rendered last, so that its cursor is the one shown
+        let views = self.views();
+        for (pane_idx, view) in views.iter().enumerate() {
+            if pane_idx != self.focused {
+                view.render(drawer);
+            }
+        }
+        views[self.focused].render(drawer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vertical_split_layout() {
+        let mut panes = Panes::new(CodeView::new());
+        let window = Bounds2f {
+            pos: Vec2f::ZERO,
+            size: Vec2f { x: 81.0, y: 24.0 },
+        };
+        panes.set_bounds(window);
+        assert_eq!(panes.layout.panes, vec![window]);
+
+        panes.split(SplitDirection::Vertical, CodeView::new());
+        let left = Bounds2f {
+            pos: Vec2f::ZERO,
+            size: Vec2f { x: 40.0, y: 24.0 },
+        };
+        let right = Bounds2f {
+            pos: Vec2f { x: 41.0, y: 0.0 },
+            size: Vec2f { x: 40.0, y: 24.0 },
+        };
+        assert_eq!(panes.layout.panes, vec![left, right]);
+        assert_eq!(
+            panes.layout.separators,
+            vec![Separator::Vertical {
+                x: 40.0,
+                bounds: window
+            }]
+        );
+        assert_eq!(
+            panes
+                .views()
+                .iter()
+                .map(|view| view.bounds())
+                .collect::<Vec<_>>(),
+            vec![left, right]
+        );
+        assert_eq!(panes.focused, 1);
+
+        // resizing lays the panes out again
+        panes.set_bounds(Bounds2f {
+            pos: Vec2f::ZERO,
+            size: Vec2f { x: 21.0, y: 10.0 },
+        });
+        assert_eq!(
+            panes.layout.panes[1],
+            Bounds2f {
+                pos: Vec2f { x: 11.0, y: 0.0 },
+                size: Vec2f { x: 10.0, y: 10.0 },
+            }
+        );
+    }
+
+    #[test]
+    fn test_split_and_close() {
+        let mut panes = Panes::new(CodeView::new());
+        let window = Bounds2f {
+            pos: Vec2f::ZERO,
+            size: Vec2f { x: 21.0, y: 21.0 },
+        };
+        panes.set_bounds(window);
+        assert!(!panes.close_focused());
+
+        panes.split(SplitDirection::Vertical, CodeView::new());
+        panes.split(SplitDirection::Horizontal, CodeView::new());
+        assert_eq!(
+            panes.layout.panes,
+            vec![
+                Bounds2f {
+                    pos: Vec2f::ZERO,
+                    size: Vec2f { x: 10.0, y: 21.0 },
+                },
+                Bounds2f {
+                    pos: Vec2f { x: 11.0, y: 0.0 },
+                    size: Vec2f { x: 10.0, y: 10.0 },
+                },
+                Bounds2f {
+                    pos: Vec2f { x: 11.0, y: 11.0 },
+                    size: Vec2f { x: 10.0, y: 10.0 },
+                },
+            ]
+        );
+        assert_eq!(panes.focused, 2);
+
+        panes.focus_next();
+        assert_eq!(panes.focused, 0);
+        assert!(panes.focus_at(Vec2f { x: 15.0, y: 15.0 }));
+        assert_eq!(panes.focused, 2);
+
+        // the space of the closed pane is given back to its sibling
+        assert!(panes.close_focused());
+        assert_eq!(panes.focused, 1);
+        assert_eq!(
+            panes.layout.panes,
+            vec![
+                Bounds2f {
+                    pos: Vec2f::ZERO,
+                    size: Vec2f { x: 10.0, y: 21.0 },
+                },
+                Bounds2f {
+                    pos: Vec2f { x: 11.0, y: 0.0 },
+                    size: Vec2f { x: 10.0, y: 21.0 },
+                },
+            ]
+        );
+
+        panes.focus_next();
+        assert!(panes.close_focused());
+        assert_eq!(panes.focused, 0);
+        assert_eq!(panes.layout.panes, vec![window]);
+    }
+}
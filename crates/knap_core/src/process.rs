@@ -0,0 +1,74 @@
+use std::{
+    io::{ErrorKind, Write},
+    process::{Command, Stdio},
+};
+
+use anyhow::{Result, anyhow};
+
+/// Runs `command` with `input` as its stdin, returning what it printed
+/// to stdout if it exits successfully. `name` is how the command is
+/// referred to if it fails.
+fn run_with_input(mut command: Command, name: &str, input: &str) -> Result<String> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // written from another thread, so that a command that starts
+    // printing before reading everything does not block forever
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("unable to write to {name}"))?;
+    let input = input.to_string();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+    let output = child.wait_with_output()?;
+    let written = writer
+        .join()
+        .map_err(|_| anyhow!("unable to write to {name}"))?;
+    // a command can exit without reading all of its input (e.g.
+    // `head`), which is only a failure if its exit status says so
+    if let Err(err) = written
+        && err.kind() != ErrorKind::BrokenPipe
+    {
+        return Err(err.into());
+    }
+
+    if output.status.success() {
+        Ok(String::from_utf8(output.stdout)?)
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(anyhow!(
+            "{name} failed ({}): {}",
+            output.status,
+            stderr.lines().next().unwrap_or_default()
+        ))
+    }
+}
+
+/// Pipes `contents` through `command` (e.g. `rustfmt --edition 2024`),
+/// returning what it printed to stdout if it exits successfully.
+///
+/// The command is split on whitespace, with the first word being the
+/// program to run and the rest being its arguments (no shell is involved).
+pub(crate) fn format<T: AsRef<str>>(command: T, contents: &str) -> Result<String> {
+    let mut words = command.as_ref().split_whitespace();
+    let program = words.next().ok_or_else(|| anyhow!("no format command"))?;
+
+    let mut format_command = Command::new(program);
+    format_command.args(words);
+    run_with_input(format_command, program, contents)
+}
+
+/// Pipes `input` through `shell_command` (e.g. `sort | uniq`), which is run
+/// by `sh`, returning what it printed to stdout if it exits successfully.
+///
+/// This needs a POSIX shell, so it fails on platforms without one
+/// (e.g. Windows, unless `sh` is installed).
+pub(crate) fn filter<T: AsRef<str>>(shell_command: T, input: &str) -> Result<String> {
+    let mut filter_command = Command::new("sh");
+    filter_command.arg("-c").arg(shell_command.as_ref());
+    run_with_input(filter_command, shell_command.as_ref(), input)
+}
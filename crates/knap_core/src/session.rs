@@ -0,0 +1,177 @@
+use std::{
+    collections::HashMap,
+    io::ErrorKind,
+    ops::Range,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::Result;
+use knap_base::math::Vec2u;
+use knap_ui::text_buffer::TextBufferPos;
+
+/// Where the caret, scroll and folds of a file were when it was
+/// last closed, to be restored when it is opened again.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct FileSession {
+    pub caret: TextBufferPos,
+    pub scroll_offset: Vec2u,
+    /// the lines hidden by each fold
+    pub folds: Vec<Range<usize>>,
+}
+
+/// The sessions of files, kept in a session file with a line for each
+/// file, e.g. `3 4 0 2 5-9,12-20\t/path/to/file.rs` for the caret (line
+/// and byte), the scroll offset (x and y) and the folds (or `-` if none).
+#[derive(Debug)]
+pub(crate) struct SessionStore {
+    path: PathBuf,
+    /// keyed by the absolute path of the file
+    files: HashMap<String, FileSession>,
+}
+
+/// Returns the key that `filename` is stored under, so that the same
+/// file opened through different relative paths has the same session.
+fn get_key(filename: &str) -> String {
+    std::fs::canonicalize(filename)
+        .ok()
+        .and_then(|path| path.to_str().map(str::to_string))
+        .unwrap_or_else(|| filename.to_string())
+}
+
+fn parse_pair<T: FromStr>(first: Option<&str>, second: Option<&str>) -> Option<(T, T)> {
+    Some((first?.parse().ok()?, second?.parse().ok()?))
+}
+
+fn parse_line(line: &str) -> Option<(String, FileSession)> {
+    let (fields, key) = line.split_once('\t')?;
+    let mut fields = fields.split(' ');
+
+    let (caret_line, caret_byte) = parse_pair(fields.next(), fields.next())?;
+    let (scroll_x, scroll_y) = parse_pair(fields.next(), fields.next())?;
+    let folds = match fields.next()? {
+        "-" => vec![],
+        folds => folds
+            .split(',')
+            .map(|fold| {
+                let mut lines = fold.split('-');
+                parse_pair(lines.next(), lines.next()).map(|(start, end)| start..end)
+            })
+            .collect::<Option<Vec<_>>>()?,
+    };
+
+    Some((
+        key.to_string(),
+        FileSession {
+            caret: TextBufferPos {
+                line: caret_line,
+                byte: caret_byte,
+            },
+            scroll_offset: Vec2u {
+                x: scroll_x,
+                y: scroll_y,
+            },
+            folds,
+        },
+    ))
+}
+
+fn format_line(key: &str, session: &FileSession) -> String {
+    let folds = if session.folds.is_empty() {
+        "-".to_string()
+    } else {
+        session
+            .folds
+            .iter()
+            .map(|fold| format!("{}-{}", fold.start, fold.end))
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+
+    format!(
+        "{} {} {} {} {folds}\t{key}",
+        session.caret.line, session.caret.byte, session.scroll_offset.x, session.scroll_offset.y
+    )
+}
+
+impl SessionStore {
+    /// Load the sessions from the session file at `path`, which
+    /// does not need to exist yet. Lines that cannot be read are skipped.
+    pub(crate) fn load<T: AsRef<Path>>(path: T) -> Result<Self> {
+        let contents = match std::fs::read_to_string(path.as_ref()) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Self {
+            path: path.as_ref().to_path_buf(),
+            files: contents.lines().filter_map(parse_line).collect(),
+        })
+    }
+
+    pub(crate) fn get(&self, filename: &str) -> Option<&FileSession> {
+        self.files.get(&get_key(filename))
+    }
+
+    pub(crate) fn set(&mut self, filename: &str, session: FileSession) {
+        self.files.insert(get_key(filename), session);
+    }
+
+    /// Write the sessions back to the session file.
+    pub(crate) fn save(&self) -> Result<()> {
+        let mut keys = self
+            .files
+            .keys()
+            // such paths cannot be told apart from the other fields
+            .filter(|key| !key.contains(['\t', '\n', '\r']))
+            .collect::<Vec<_>>();
+        keys.sort_unstable();
+
+        let contents = keys
+            .into_iter()
+            .filter_map(|key| Some(format_line(key, self.files.get(key)?) + "\n"))
+            .collect::<String>();
+        std::fs::write(&self.path, contents)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_store() {
+        let path = std::env::temp_dir().join("knap_test_session_store.txt");
+        let _ = std::fs::remove_file(&path);
+
+        let mut store = SessionStore::load(&path).expect("able to load missing session file");
+        assert_eq!(store.get("/some file.rs"), None);
+
+        let session = FileSession {
+            caret: TextBufferPos { line: 12, byte: 4 },
+            scroll_offset: Vec2u { x: 3, y: 7 },
+            folds: vec![2..5, 10..12],
+        };
+        store.set("/some file.rs", session.clone());
+        store.set("/other.rs", FileSession::default());
+        store.save().expect("able to save session file");
+        assert_eq!(
+            std::fs::read_to_string(&path).expect("able to read session file"),
+            "0 0 0 0 -\t/other.rs\n12 4 3 7 2-5,10-12\t/some file.rs\n"
+        );
+
+        // unreadable lines are skipped
+        let mut contents = std::fs::read_to_string(&path).expect("able to read session file");
+        contents.push_str("1 x 0 0 -\t/bad.rs\ngarbage\n");
+        std::fs::write(&path, contents).expect("able to write session file");
+
+        let store = SessionStore::load(&path).expect("able to load session file");
+        assert_eq!(store.get("/some file.rs"), Some(&session));
+        assert_eq!(store.get("/other.rs"), Some(&FileSession::default()));
+        assert_eq!(store.get("/bad.rs"), None);
+
+        std::fs::remove_file(&path).expect("able to remove session file");
+    }
+}
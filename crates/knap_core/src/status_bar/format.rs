@@ -0,0 +1,225 @@
+use knap_base::math::ToU64;
+use knap_ui::text_box::IndentStyle;
+
+use crate::code_view::FileType;
+
+use super::ViewStatus;
+
+pub(crate) const DEFAULT_LEFT_FORMAT: &str = "{filename} {modified} - {lines} lines {edits}";
+pub(crate) const DEFAULT_RIGHT_FORMAT: &str = "{filetype} | {indent} | {mode} | {line}:{col}";
+
+enum Segment<'a> {
+    Literal(&'a str),
+    /// the name between the braces, e.g. `line` for `{line}`
+    Field(&'a str),
+}
+
+fn tokenize(format: &str) -> Vec<Segment<'_>> {
+    let mut segments = vec![];
+    let mut rest = format;
+
+    while let Some(open_idx) = rest.find('{') {
+        let Some(close_offset) = rest[open_idx..].find('}') else {
+            break;
+        };
+        let close_idx = open_idx.saturating_add(close_offset);
+
+        if open_idx > 0 {
+            segments.push(Segment::Literal(&rest[..open_idx]));
+        }
+        segments.push(Segment::Field(&rest[open_idx.saturating_add(1)..close_idx]));
+        rest = &rest[close_idx.saturating_add(1)..];
+    }
+
+    if !rest.is_empty() {
+        segments.push(Segment::Literal(rest));
+    }
+    segments
+}
+
+/// Returns the value of a field, or `None` if the field is unknown.
+fn get_field_value(field: &str, view_status: &ViewStatus, filename: &str) -> Option<String> {
+    let line = view_status.caret_position.y.saturating_add(1);
+
+    Some(match field {
+        "filename" => filename.to_string(),
+        "modified" => {
+            if view_status.is_dirty {
+                "[+]".to_string()
+            } else {
+                String::new()
+            }
+        }
+        "lines" => view_status.total_lines.to_string(),
+        "edits" => match view_status.edits_since_save {
+            0 => String::new(),
+            edits => format!("({edits} edits)"),
+        },
+        "branch" => view_status.git_branch.clone().unwrap_or_default(),
+        "filetype" => match view_status.file_type {
+            FileType::Rust => "Rust",
+            FileType::PlainText => "Plain Text",
+        }
+        .to_string(),
+        "indent" => match view_status.indent_style {
+            IndentStyle::Tabs => "Tabs".to_string(),
+            IndentStyle::Spaces(width) => format!("Spaces: {width}"),
+        },
+        "mode" => {
+            if view_status.is_overwrite_mode {
+                "OVR"
+            } else {
+                "INS"
+            }
+        }
+        .to_string(),
+        "chars" => view_status.char_count.to_string(),
+        "graphemes" => view_status.grapheme_count.to_string(),
+        "words" => view_status.word_count.to_string(),
+        "line" => line.to_string(),
+        "col" => {
+            let col = view_status.caret_position.x.saturating_add(1);
+            let visual_col = view_status.caret_visual_column.saturating_add(1);
+            // like Vim, the column that the caret is drawn at follows when
+            // it is not the same, e.g. after a tab or a full-width character
+            if visual_col == col {
+                col.to_string()
+            } else {
+                format!("{col}-{visual_col}")
+            }
+        }
+        "percent" => {
+            let percent = line
+                .saturating_mul(100)
+                .checked_div(view_status.total_lines.to_u64())
+                .unwrap_or(100)
+                .min(100);
+            format!("{percent}%")
+        }
+        _ => return None,
+    })
+}
+
+/// Substitutes the fields (e.g. `{line}`) in `format` with their values
+/// from `view_status`, using `filename` for `{filename}`. Unknown fields
+/// are left as they are.
+///
+/// Fields can be empty (e.g. `{modified}` when the file is not modified),
+/// in which case the space before the field (or after it, if there is
+/// none before) is dropped too, so that no gap is left behind. The rest
+/// of the literal text is kept exactly as it is.
+pub(crate) fn expand_format(format: &str, view_status: &ViewStatus, filename: &str) -> String {
+    let mut expanded = String::with_capacity(format.len());
+    let mut drops_next_space = false;
+    for segment in tokenize(format) {
+        match segment {
+            Segment::Literal(literal) => {
+                let literal = if drops_next_space {
+                    literal.strip_prefix(' ').unwrap_or(literal)
+                } else {
+                    literal
+                };
+                expanded.push_str(literal);
+                drops_next_space = false;
+            }
+            Segment::Field(field) => match get_field_value(field, view_status, filename) {
+                Some(value) if value.is_empty() => {
+                    if expanded.ends_with(' ') {
+                        expanded.pop();
+                    } else {
+                        drops_next_space = true;
+                    }
+                }
+                value => {
+                    expanded.push_str(&value.unwrap_or_else(|| format!("{{{field}}}")));
+                    drops_next_space = false;
+                }
+            },
+        }
+    }
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use knap_base::math::Vec2u;
+
+    use super::*;
+
+    #[test]
+    fn test_expand_format() {
+        let view_status = ViewStatus {
+            filename: Some("main.rs".to_string()),
+            total_lines: 200,
+            is_dirty: true,
+            caret_position: Vec2u { x: 4, y: 49 },
+            caret_visual_column: 4,
+            file_type: FileType::Rust,
+            is_overwrite_mode: false,
+            edits_since_save: 3,
+            git_branch: Some("main".to_string()),
+            indent_style: IndentStyle::Spaces(4),
+            char_count: 5012,
+            grapheme_count: 4998,
+            word_count: 731,
+        };
+
+        assert_eq!(
+            expand_format(DEFAULT_LEFT_FORMAT, &view_status, "main.rs"),
+            "main.rs [+] - 200 lines (3 edits)"
+        );
+        assert_eq!(
+            expand_format(DEFAULT_RIGHT_FORMAT, &view_status, "main.rs"),
+            "Rust | Spaces: 4 | INS | 50:5"
+        );
+        assert_eq!(
+            expand_format(
+                "{filename} {modified} | {filetype} | Ln {line}, Col {col} | {percent}",
+                &view_status,
+                "main.rs"
+            ),
+            "main.rs [+] | Rust | Ln 50, Col 5 | 25%"
+        );
+
+        assert_eq!(
+            expand_format("{branch} {filetype}", &view_status, "main.rs"),
+            "main Rust"
+        );
+        assert_eq!(
+            expand_format(
+                "{words} words, {chars} chars ({graphemes} graphemes)",
+                &view_status,
+                "main.rs"
+            ),
+            "731 words, 5012 chars (4998 graphemes)"
+        );
+
+        // empty fields do not leave extra spaces behind
+        let saved_status = ViewStatus {
+            is_dirty: false,
+            edits_since_save: 0,
+            git_branch: None,
+            ..view_status
+        };
+        assert_eq!(
+            expand_format(DEFAULT_LEFT_FORMAT, &saved_status, "main.rs"),
+            "main.rs - 200 lines"
+        );
+        assert_eq!(
+            expand_format("{branch} {filetype}", &saved_status, "main.rs"),
+            "Rust"
+        );
+
+        // and the rest of the literal text is kept as it is
+        assert_eq!(
+            expand_format("  {line}  |  {modified} {col} ", &saved_status, "main.rs"),
+            "  50  |  5 "
+        );
+
+        // unknown fields and unclosed braces are left alone
+        assert_eq!(
+            expand_format("{unknown} {line} {line", &saved_status, "main.rs"),
+            "{unknown} 50 {line"
+        );
+    }
+}
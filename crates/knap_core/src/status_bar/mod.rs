@@ -0,0 +1,424 @@
+use std::{
+    ffi::OsStr,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use knap_base::math::{Bounds2f, Lossy, ToU64, Vec2u};
+use knap_ui::text_box::IndentStyle;
+use knap_window::drawer::Drawer;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::code_view::FileType;
+
+use format::{DEFAULT_LEFT_FORMAT, DEFAULT_RIGHT_FORMAT, expand_format};
+
+mod format;
+
+pub(crate) struct ViewStatus {
+    pub filename: Option<String>,
+    pub total_lines: usize,
+    pub is_dirty: bool,
+    pub caret_position: Vec2u,
+    /// the x of `Self::caret_position` in display width, see
+    /// `TextBox::caret_visual_column`
+    pub caret_visual_column: u64,
+    pub file_type: FileType,
+    pub is_overwrite_mode: bool,
+    pub edits_since_save: usize,
+    pub git_branch: Option<String>,
+    pub indent_style: IndentStyle,
+    pub char_count: usize,
+    pub grapheme_count: usize,
+    pub word_count: usize,
+}
+
+const ELLIPSIS: &str = "…";
+pub(crate) const NO_NAME: &str = "[No Name]";
+
+const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const SPINNER_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Shortens `filename` to fit within `max_width` columns by replacing
+/// its start with an ellipsis, so that the basename stays visible.
+fn truncate_filename(filename: &str, max_width: usize) -> String {
+    if filename.width() <= max_width {
+        return filename.to_string();
+    }
+
+    let mut width = ELLIPSIS.width();
+    let mut start_idx = filename.len();
+    for (idx, grapheme) in filename.grapheme_indices(true).rev() {
+        width = width.saturating_add(grapheme.width());
+        if width > max_width {
+            break;
+        }
+        start_idx = idx;
+    }
+
+    format!("{ELLIPSIS}{}", &filename[start_idx..])
+}
+
+/// Cuts off the end of `text` so that it is at most `width` columns
+/// wide, then pads it with spaces so that it is exactly `width` wide.
+fn fit_to_width(text: &str, width: usize) -> String {
+    let mut fitted = String::new();
+    let mut fitted_width = 0usize;
+    for grapheme in text.graphemes(true) {
+        let grapheme_width = grapheme.width();
+        if fitted_width.saturating_add(grapheme_width) > width {
+            break;
+        }
+        fitted.push_str(grapheme);
+        fitted_width = fitted_width.saturating_add(grapheme_width);
+    }
+
+    let padding = width.saturating_sub(fitted_width);
+    format!("{fitted}{:padding$}", "")
+}
+
+/// Returns the left side expanded from `format`, with the filename
+/// truncated so that everything fits within `max_width` if possible.
+fn get_left_content(view_status: &ViewStatus, format: &str, max_width: usize) -> String {
+    let Some(filename) = &view_status.filename else {
+        return expand_format(format, view_status, NO_NAME);
+    };
+
+    let left = expand_format(format, view_status, filename);
+    if left.width() <= max_width || !format.contains("{filename}") {
+        return left;
+    }
+
+    let filename_max_width =
+        max_width.saturating_sub(left.width().saturating_sub(filename.width()));
+    expand_format(
+        format,
+        view_status,
+        &truncate_filename(filename, filename_max_width),
+    )
+}
+
+/// Returns the narrowest that the left side can be while
+/// still keeping the basename of the file visible.
+fn get_min_left_width(view_status: &ViewStatus, format: &str) -> usize {
+    let left_width = get_left_content(view_status, format, usize::MAX).width();
+
+    match &view_status.filename {
+        Some(filename) if format.contains("{filename}") => {
+            let basename = Path::new(filename)
+                .file_name()
+                .and_then(OsStr::to_str)
+                .unwrap_or(filename);
+            // e.g. "…/main.rs"
+            let truncated_width = ELLIPSIS
+                .width()
+                .saturating_add(1)
+                .saturating_add(basename.width());
+
+            left_width
+                .saturating_sub(filename.width())
+                .saturating_add(filename.width().min(truncated_width))
+        }
+        _ => left_width,
+    }
+}
+
+fn get_right_content(view_status: &ViewStatus, format: &str, progress: Option<&str>) -> String {
+    let right = expand_format(
+        format,
+        view_status,
+        view_status.filename.as_deref().unwrap_or(NO_NAME),
+    );
+
+    match progress {
+        Some(progress) => format!("{progress} | {right}"),
+        None => right,
+    }
+}
+
+/// Lays out the whole status bar within `width` columns. If there is
+/// not enough space, the filename is truncated first, then the right
+/// side is dropped so that the basename stays visible.
+fn get_content(
+    view_status: &ViewStatus,
+    (left_format, right_format): (&str, &str),
+    progress: Option<&str>,
+    width: usize,
+) -> String {
+    let right = get_right_content(view_status, right_format, progress);
+    // leave at least a space between the left and right side
+    let left_max_width = width.saturating_sub(right.width()).saturating_sub(1);
+
+    let min_left_width = get_min_left_width(view_status, left_format);
+    if left_max_width >= min_left_width {
+        let left = get_left_content(view_status, left_format, left_max_width);
+        let right_space = width.saturating_sub(left.width());
+        format!("{left}{right:>right_space$}")
+    } else {
+        let left = get_left_content(view_status, left_format, width.max(min_left_width));
+        fit_to_width(&left, width)
+    }
+}
+
+/// An operation that is still running, shown with a spinner.
+struct Progress {
+    message: String,
+    started_at: Instant,
+    spinner_frame: usize,
+}
+
+pub(crate) struct StatusBar {
+    bounds: Bounds2f,
+    left_format: String,
+    right_format: String,
+    progress: Option<Progress>,
+}
+
+impl StatusBar {
+    pub(crate) fn new() -> Self {
+        Self {
+            bounds: Bounds2f::ZERO,
+            left_format: DEFAULT_LEFT_FORMAT.to_string(),
+            right_format: DEFAULT_RIGHT_FORMAT.to_string(),
+            progress: None,
+        }
+    }
+
+    pub(crate) fn set_bounds(&mut self, bounds: Bounds2f) {
+        self.bounds = bounds;
+    }
+
+    /// Set the format strings of the left and right side,
+    /// see [`expand_format`] for how they are expanded.
+    pub(crate) fn set_format<T: AsRef<str>, U: AsRef<str>>(&mut self, left: T, right: U) {
+        left.as_ref().clone_into(&mut self.left_format);
+        right.as_ref().clone_into(&mut self.right_format);
+    }
+
+    /// Show `message` (e.g. `Highlighting 40%`) with a spinner, until
+    /// the progress is cleared. Updating the message of an operation
+    /// that is already in progress does not restart the spinner.
+    pub(crate) fn set_progress<T: AsRef<str>>(&mut self, message: T, now: Instant) {
+        match &mut self.progress {
+            Some(progress) => message.as_ref().clone_into(&mut progress.message),
+            None => {
+                self.progress = Some(Progress {
+                    message: message.as_ref().to_string(),
+                    started_at: now,
+                    spinner_frame: 0,
+                });
+            }
+        }
+    }
+
+    pub(crate) fn clear_progress(&mut self) {
+        self.progress = None;
+    }
+
+    /// Advance the spinner, returning whether the status bar
+    /// needs to be redrawn.
+    pub(crate) fn tick(&mut self, now: Instant) -> bool {
+        let Some(progress) = &mut self.progress else {
+            return false;
+        };
+
+        let spinner_frame = now
+            .duration_since(progress.started_at)
+            .as_millis()
+            .checked_div(SPINNER_INTERVAL.as_millis())
+            .and_then(|frame| frame.checked_rem(SPINNER_FRAMES.len().to_u64().into()))
+            .and_then(|frame| usize::try_from(frame).ok())
+            .unwrap_or_default();
+        let needs_redraw = spinner_frame != progress.spinner_frame;
+        progress.spinner_frame = spinner_frame;
+        needs_redraw
+    }
+
+    pub(crate) fn get_progress_text(&self) -> Option<String> {
+        self.progress.as_ref().map(|progress| {
+            format!(
+                "{} {}",
+                SPINNER_FRAMES[progress.spinner_frame], progress.message
+            )
+        })
+    }
+
+    pub(crate) fn render(&self, drawer: &mut Drawer, view_status: &ViewStatus) {
+        if self.bounds.size.x * self.bounds.size.y > 0.0 {
+            let size_x = self.bounds.size.x.lossy();
+
+            let final_content = get_content(
+                view_status,
+                (&self.left_format, &self.right_format),
+                self.get_progress_text().as_deref(),
+                size_x,
+            );
+
+            drawer.draw_text(
+                self.bounds.pos,
+                format!(
+                    "{}{}{}",
+                    crossterm::style::Attribute::Reverse,
+                    final_content,
+                    crossterm::style::Attribute::Reset,
+                ),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{code_view::CodeView, command_bar::CommandBar, commands::EditorCommand};
+
+    use super::*;
+
+    const DEFAULT_FORMAT: (&str, &str) = (DEFAULT_LEFT_FORMAT, DEFAULT_RIGHT_FORMAT);
+
+    #[test]
+    fn test_dirty_indicator() {
+        let path = std::env::temp_dir().join("knap_test_dirty_indicator.txt");
+        std::fs::write(&path, "hello").expect("able to write test file");
+        let filename = path.to_str().expect("valid path");
+
+        let mut view = CodeView::new_from_file(filename).expect("able to open test file");
+        assert_eq!(
+            get_left_content(&view.get_status(), DEFAULT_LEFT_FORMAT, usize::MAX),
+            format!("{filename} - 1 lines")
+        );
+
+        view.execute_command(EditorCommand::InsertCharacter('a'), &mut CommandBar::new());
+        view.execute_command(EditorCommand::MoveCursorRight, &mut CommandBar::new());
+        view.execute_command(EditorCommand::InsertCharacter('b'), &mut CommandBar::new());
+        assert_eq!(
+            get_left_content(&view.get_status(), DEFAULT_LEFT_FORMAT, usize::MAX),
+            format!("{filename} [+] - 1 lines (2 edits)")
+        );
+
+        view.execute_command(EditorCommand::WriteBufferToDisk, &mut CommandBar::new());
+        assert_eq!(
+            get_left_content(&view.get_status(), DEFAULT_LEFT_FORMAT, usize::MAX),
+            format!("{filename} - 1 lines")
+        );
+
+        std::fs::remove_file(&path).expect("able to remove test file");
+    }
+
+    #[test]
+    fn test_visual_column() {
+        let mut view = CodeView::new();
+        let mut command_bar = CommandBar::new();
+        for command in [
+            EditorCommand::InsertCharacter('\u{4f60}'),
+            EditorCommand::InsertCharacter('\u{597d}'),
+            EditorCommand::MoveCursorLeft,
+        ] {
+            view.execute_command(command, &mut command_bar);
+        }
+
+        // the full-width character before the caret is two columns wide
+        let view_status = view.get_status();
+        assert_eq!(view_status.caret_position, Vec2u { x: 1, y: 0 });
+        assert_eq!(view_status.caret_visual_column, 2);
+        assert_eq!(
+            get_right_content(&view_status, "{line}:{col}", None),
+            "1:2-3"
+        );
+
+        view.execute_command(EditorCommand::MoveCursorToStartOfLine, &mut command_bar);
+        assert_eq!(
+            get_right_content(&view.get_status(), "{line}:{col}", None),
+            "1:1"
+        );
+    }
+
+    #[test]
+    fn test_truncate_long_filename() {
+        let view_status = ViewStatus {
+            filename: Some("/home/user/projects/some/very/deep/path/src/main.rs".to_string()),
+            total_lines: 10,
+            is_dirty: false,
+            caret_position: Vec2u { x: 0, y: 0 },
+            caret_visual_column: 0,
+            file_type: FileType::Rust,
+            is_overwrite_mode: false,
+            edits_since_save: 0,
+            git_branch: None,
+            indent_style: IndentStyle::Tabs,
+            char_count: 200,
+            grapheme_count: 200,
+            word_count: 40,
+        };
+
+        // enough space for everything
+        assert_eq!(
+            get_content(&view_status, DEFAULT_FORMAT, None, 90),
+            format!(
+                "/home/user/projects/some/very/deep/path/src/main.rs - 10 lines{:>28}",
+                "Rust | Tabs | INS | 1:1"
+            )
+        );
+
+        // the start of the path is cut off to make space for the right side
+        assert_eq!(
+            get_content(&view_status, DEFAULT_FORMAT, None, 50),
+            "…th/src/main.rs - 10 lines Rust | Tabs | INS | 1:1"
+        );
+
+        // the right side is dropped to keep the basename visible
+        assert_eq!(
+            get_content(&view_status, DEFAULT_FORMAT, None, 24),
+            "…/src/main.rs - 10 lines"
+        );
+        assert_eq!(
+            get_content(&view_status, DEFAULT_FORMAT, None, 12),
+            "…/main.rs - "
+        );
+
+        let untitled_status = ViewStatus {
+            filename: None,
+            ..view_status
+        };
+        assert_eq!(
+            get_content(&untitled_status, DEFAULT_FORMAT, None, 12),
+            "[No Name] - "
+        );
+    }
+
+    #[test]
+    fn test_progress() {
+        let view_status = CodeView::new().get_status();
+        let now = Instant::now();
+        let mut status_bar = StatusBar::new();
+        assert!(!status_bar.tick(now));
+        assert_eq!(status_bar.get_progress_text(), None);
+
+        status_bar.set_progress("Highlighting 40%", now);
+        assert_eq!(
+            get_content(
+                &view_status,
+                DEFAULT_FORMAT,
+                status_bar.get_progress_text().as_deref(),
+                80
+            ),
+            format!(
+                "[No Name] - 1 lines{:>61}",
+                "⠋ Highlighting 40% | Plain Text | Tabs | INS | 1:1"
+            )
+        );
+
+        // the spinner keeps animating while in progress
+        assert!(!status_bar.tick(now));
+        assert!(status_bar.tick(now + SPINNER_INTERVAL));
+        status_bar.set_progress("Highlighting 80%", now + SPINNER_INTERVAL);
+        assert_eq!(
+            status_bar.get_progress_text().as_deref(),
+            Some("⠙ Highlighting 80%")
+        );
+
+        status_bar.clear_progress();
+        assert!(!status_bar.tick(now + SPINNER_INTERVAL * 2));
+        assert_eq!(status_bar.get_progress_text(), None);
+    }
+}
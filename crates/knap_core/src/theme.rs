@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+
+use knap_base::color::Color;
+use knap_ui::text_box::{TextBoxColors, TextColor};
+use knap_window::drawer::PopupColors;
+
+use crate::{annotations::Severity, highlighter::HighlightType};
+
+/// The colors that the editor is drawn with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    highlights: HashMap<HighlightType, TextColor>,
+    /// drawn over the character under the caret, if any
+    caret: Option<TextColor>,
+    selection: TextColor,
+    /// the background of the caret's line, if any
+    current_line: Option<Color>,
+    /// the background of the gutter, if any
+    gutter: Option<Color>,
+    /// the marker in the gutter of the lines changed since the last save
+    dirty_line: Color,
+    /// the markers and messages of the annotations, by severity
+    error: Color,
+    warning: Color,
+    info: Color,
+    /// the popups drawn over the text, e.g. the completions
+    popup: PopupColors,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+fn foreground(color: Color) -> TextColor {
+    TextColor {
+        foreground: Some(color),
+        background: None,
+    }
+}
+
+fn background(color: Color) -> TextColor {
+    TextColor {
+        foreground: None,
+        background: Some(color),
+    }
+}
+
+fn colored(foreground: Color, background: Color) -> TextColor {
+    TextColor {
+        foreground: Some(foreground),
+        background: Some(background),
+    }
+}
+
+fn dark_highlight_color(highlight_type: HighlightType) -> TextColor {
+    match highlight_type {
+        HighlightType::SearchMatch => colored(Color::BLACK, Color::YELLOW),
+        HighlightType::SearchCursor => colored(Color::BLACK, Color::BLUE),
+        HighlightType::Number => foreground(Color::DARK_RED),
+        HighlightType::Keyword => foreground(Color::BLUE),
+        HighlightType::BasicType => foreground(Color::GREEN),
+        HighlightType::EnumLiteral => foreground(Color::CYAN),
+        HighlightType::Character | HighlightType::LifetimeSpecifier | HighlightType::String => {
+            foreground(Color::DARK_YELLOW)
+        }
+        HighlightType::Comment => foreground(Color::DARK_GREEN),
+        HighlightType::CommentTag => colored(Color::BLACK, Color::DARK_MAGENTA),
+        HighlightType::TrailingWhitespace | HighlightType::WhitespaceError => {
+            background(Color::RED)
+        }
+        HighlightType::WordUnderCaret => background(Color::GRAY),
+        HighlightType::UnmatchedBracket => colored(Color::WHITE, Color::RED),
+        HighlightType::MatchingBracket => colored(Color::BLACK, Color::CYAN),
+    }
+}
+
+/// A light gray, for backgrounds that should stand out only slightly
+/// from a light terminal background.
+const LIGHT_GRAY: Color = Color {
+    r: 224,
+    g: 224,
+    b: 224,
+};
+
+fn light_highlight_color(highlight_type: HighlightType) -> TextColor {
+    match highlight_type {
+        HighlightType::SearchMatch => colored(Color::BLACK, Color::YELLOW),
+        HighlightType::SearchCursor => colored(Color::WHITE, Color::DARK_BLUE),
+        HighlightType::Number => foreground(Color::DARK_MAGENTA),
+        HighlightType::Keyword => foreground(Color::DARK_BLUE),
+        HighlightType::BasicType => foreground(Color::DARK_GREEN),
+        HighlightType::EnumLiteral => foreground(Color::DARK_CYAN),
+        HighlightType::Character | HighlightType::LifetimeSpecifier | HighlightType::String => {
+            foreground(Color::DARK_RED)
+        }
+        HighlightType::Comment => foreground(Color::GRAY),
+        HighlightType::CommentTag => colored(Color::WHITE, Color::DARK_MAGENTA),
+        HighlightType::TrailingWhitespace | HighlightType::WhitespaceError => {
+            background(Color::RED)
+        }
+        HighlightType::WordUnderCaret => background(LIGHT_GRAY),
+        HighlightType::UnmatchedBracket => colored(Color::WHITE, Color::RED),
+        HighlightType::MatchingBracket => colored(Color::WHITE, Color::DARK_CYAN),
+    }
+}
+
+impl Theme {
+    /// For terminals with a dark background.
+    pub fn dark() -> Self {
+        Self {
+            highlights: HighlightType::ALL
+                .into_iter()
+                .map(|highlight_type| (highlight_type, dark_highlight_color(highlight_type)))
+                .collect(),
+            caret: None,
+            selection: colored(Color::WHITE, Color::BLUE),
+            current_line: None,
+            gutter: None,
+            dirty_line: Color::DARK_YELLOW,
+            error: Color::RED,
+            warning: Color::YELLOW,
+            info: Color::CYAN,
+            popup: PopupColors {
+                foreground: Color::BLACK,
+                background: Color::GRAY,
+                selected_foreground: Color::WHITE,
+                selected_background: Color::BLUE,
+            },
+        }
+    }
+
+    /// For terminals with a light background.
+    pub fn light() -> Self {
+        Self {
+            highlights: HighlightType::ALL
+                .into_iter()
+                .map(|highlight_type| (highlight_type, light_highlight_color(highlight_type)))
+                .collect(),
+            caret: Some(colored(Color::WHITE, Color::BLACK)),
+            selection: colored(Color::WHITE, Color::DARK_BLUE),
+            current_line: Some(LIGHT_GRAY),
+            gutter: Some(LIGHT_GRAY),
+            dirty_line: Color::DARK_BLUE,
+            error: Color::RED,
+            warning: Color::DARK_YELLOW,
+            info: Color::DARK_CYAN,
+            popup: PopupColors {
+                foreground: Color::BLACK,
+                background: LIGHT_GRAY,
+                selected_foreground: Color::WHITE,
+                selected_background: Color::DARK_BLUE,
+            },
+        }
+    }
+
+    pub(crate) fn highlight_color(&self, highlight_type: HighlightType) -> TextColor {
+        self.highlights
+            .get(&highlight_type)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn text_box_colors(&self) -> TextBoxColors {
+        TextBoxColors {
+            caret: self.caret,
+            selection: self.selection,
+            current_line: self.current_line,
+            trailing_whitespace: self.highlight_color(HighlightType::TrailingWhitespace),
+        }
+    }
+
+    pub(crate) fn gutter(&self) -> Option<Color> {
+        self.gutter
+    }
+
+    pub(crate) fn dirty_line(&self) -> Color {
+        self.dirty_line
+    }
+
+    pub(crate) fn severity(&self, severity: Severity) -> Color {
+        match severity {
+            Severity::Error => self.error,
+            Severity::Warning => self.warning,
+            Severity::Info => self.info,
+        }
+    }
+
+    pub(crate) fn popup(&self) -> PopupColors {
+        self.popup
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_themes() {
+        assert_ne!(
+            Theme::dark().highlight_color(HighlightType::Keyword),
+            Theme::light().highlight_color(HighlightType::Keyword)
+        );
+        assert_eq!(
+            Theme::dark().highlight_color(HighlightType::Keyword),
+            foreground(Color::BLUE)
+        );
+
+        // the annotations and popups are themed too
+        assert_ne!(
+            Theme::dark().severity(Severity::Warning),
+            Theme::light().severity(Severity::Warning)
+        );
+        assert_ne!(Theme::dark().popup(), Theme::light().popup());
+
+        // every highlight type has a color
+        for theme in [Theme::dark(), Theme::light()] {
+            for highlight_type in HighlightType::ALL {
+                assert_ne!(theme.highlight_color(highlight_type), TextColor::default());
+            }
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use std::ops::Range;
+
+/// The lines changed since they were last taken, numbered as they are
+/// after the changes. Lines are only ever added or removed within the
+/// range, so the lines after it are the same lines as before, shifted
+/// by the number of lines added or removed.
+#[derive(Debug, Default)]
+pub(crate) struct ChangedLines {
+    lines: Option<Range<usize>>,
+}
+
+impl ChangedLines {
+    pub(crate) fn take(&mut self) -> Option<Range<usize>> {
+        self.lines.take()
+    }
+
+    fn add(&mut self, lines: Range<usize>) {
+        self.lines = Some(match self.lines.take() {
+            Some(changed) => changed.start.min(lines.start)..changed.end.max(lines.end),
+            None => lines,
+        });
+    }
+
+    pub(crate) fn mark_all(&mut self, total_lines: usize) {
+        self.lines = Some(0..total_lines);
+    }
+
+    pub(crate) fn mark(&mut self, line_idx: usize) {
+        self.add(line_idx..line_idx.saturating_add(1));
+    }
+
+    /// `line_idx` was split in two, shifting the lines after it down.
+    pub(crate) fn split_line(&mut self, line_idx: usize) {
+        self.split_lines(line_idx, 1);
+    }
+
+    /// `new_lines` lines were inserted into `line_idx`, shifting
+    /// the lines after it down by as many.
+    pub(crate) fn split_lines(&mut self, line_idx: usize, new_lines: usize) {
+        if let Some(changed) = &mut self.lines {
+            if changed.start > line_idx {
+                changed.start = changed.start.saturating_add(new_lines);
+            }
+            if changed.end > line_idx {
+                changed.end = changed.end.saturating_add(new_lines);
+            }
+        }
+        self.add(line_idx..line_idx.saturating_add(new_lines).saturating_add(1));
+    }
+
+    /// The line after `line_idx` was joined onto it, shifting
+    /// the lines after that up.
+    pub(crate) fn join_line(&mut self, line_idx: usize) {
+        self.join_lines(line_idx, 1);
+    }
+
+    /// The `removed_lines` lines after `line_idx` were joined onto
+    /// it, shifting the lines after those up by as many.
+    pub(crate) fn join_lines(&mut self, line_idx: usize, removed_lines: usize) {
+        let last_removed_line_idx = line_idx.saturating_add(removed_lines);
+        let shift = |changed_line_idx: usize| {
+            if changed_line_idx > last_removed_line_idx {
+                changed_line_idx.saturating_sub(removed_lines)
+            } else {
+                changed_line_idx.min(line_idx.saturating_add(1))
+            }
+        };
+        if let Some(changed) = &mut self.lines {
+            changed.start = shift(changed.start);
+            changed.end = shift(changed.end);
+        }
+        self.mark(line_idx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_lines() {
+        let mut changed_lines = ChangedLines::default();
+        assert_eq!(changed_lines.take(), None);
+
+        changed_lines.mark(5);
+        changed_lines.mark(3);
+        assert_eq!(changed_lines.take(), Some(3..6));
+        assert_eq!(changed_lines.take(), None);
+
+        // the changed lines after the split move down with it
+        changed_lines.mark(5);
+        changed_lines.split_line(2);
+        assert_eq!(changed_lines.take(), Some(2..7));
+
+        changed_lines.mark(5);
+        changed_lines.split_lines(3, 3);
+        assert_eq!(changed_lines.take(), Some(3..9));
+
+        // and up with a join
+        changed_lines.mark(5);
+        changed_lines.join_line(1);
+        assert_eq!(changed_lines.take(), Some(1..5));
+
+        changed_lines.mark(2);
+        changed_lines.join_line(1);
+        assert_eq!(changed_lines.take(), Some(1..2));
+
+        changed_lines.mark(3);
+        changed_lines.mark(8);
+        changed_lines.join_lines(1, 4);
+        assert_eq!(changed_lines.take(), Some(1..5));
+
+        changed_lines.mark(1);
+        changed_lines.mark_all(4);
+        assert_eq!(changed_lines.take(), Some(0..4));
+    }
+}
@@ -0,0 +1,117 @@
+/// Whether each line was changed since the buffer was last saved,
+/// following the lines around as lines are added and removed.
+#[derive(Debug, Clone)]
+pub(crate) struct DirtyLines {
+    /// one for each line of the buffer
+    lines: Vec<bool>,
+    /// how many of `lines` are dirty, so that it is not counted again
+    dirty_count: usize,
+}
+
+impl DirtyLines {
+    pub(crate) fn new(total_lines: usize) -> Self {
+        Self {
+            lines: vec![false; total_lines],
+            dirty_count: 0,
+        }
+    }
+
+    pub(crate) fn lines(&self) -> &[bool] {
+        &self.lines
+    }
+
+    pub(crate) fn dirty_count(&self) -> usize {
+        self.dirty_count
+    }
+
+    /// Start over with `total_lines` lines, which are all dirty
+    /// if `is_dirty` is set, and all clean otherwise.
+    pub(crate) fn reset(&mut self, total_lines: usize, is_dirty: bool) {
+        self.lines = vec![is_dirty; total_lines];
+        self.dirty_count = if is_dirty { total_lines } else { 0 };
+    }
+
+    pub(crate) fn mark(&mut self, line_idx: usize) {
+        if let Some(is_dirty) = self.lines.get_mut(line_idx)
+            && !*is_dirty
+        {
+            *is_dirty = true;
+            self.dirty_count = self.dirty_count.saturating_add(1);
+        }
+    }
+
+    /// `line_idx` was split in two, so both halves are dirty.
+    pub(crate) fn split_line(&mut self, line_idx: usize) {
+        self.split_lines(line_idx, 1);
+    }
+
+    /// `new_lines` lines were inserted into `line_idx`,
+    /// so all of the lines it is now made of are dirty.
+    pub(crate) fn split_lines(&mut self, line_idx: usize, new_lines: usize) {
+        let new_line_idx = line_idx.saturating_add(1).min(self.lines.len());
+        self.lines.splice(
+            new_line_idx..new_line_idx,
+            std::iter::repeat_n(true, new_lines),
+        );
+        self.dirty_count = self.dirty_count.saturating_add(new_lines);
+        self.mark(line_idx);
+    }
+
+    /// The line after `line_idx` was joined onto it.
+    pub(crate) fn join_line(&mut self, line_idx: usize) {
+        self.join_lines(line_idx, 1);
+    }
+
+    /// The `removed_lines` lines after `line_idx` were joined onto it.
+    pub(crate) fn join_lines(&mut self, line_idx: usize, removed_lines: usize) {
+        let removed_start = line_idx.saturating_add(1).min(self.lines.len());
+        let removed_end = removed_start
+            .saturating_add(removed_lines)
+            .min(self.lines.len());
+        let removed_dirty_count = self
+            .lines
+            .drain(removed_start..removed_end)
+            .filter(|is_dirty| *is_dirty)
+            .count();
+        self.dirty_count = self.dirty_count.saturating_sub(removed_dirty_count);
+        self.mark(line_idx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dirty_lines() {
+        let mut dirty_lines = DirtyLines::new(4);
+        dirty_lines.mark(2);
+        dirty_lines.mark(2);
+        assert_eq!(dirty_lines.lines(), [false, false, true, false]);
+        assert_eq!(dirty_lines.dirty_count(), 1);
+
+        dirty_lines.split_line(0);
+        assert_eq!(dirty_lines.lines(), [true, true, false, true, false]);
+        assert_eq!(dirty_lines.dirty_count(), 3);
+
+        dirty_lines.reset(3, false);
+        dirty_lines.split_lines(1, 2);
+        assert_eq!(dirty_lines.lines(), [false, true, true, true, false]);
+
+        dirty_lines.reset(5, false);
+        dirty_lines.mark(4);
+        dirty_lines.join_line(2);
+        assert_eq!(dirty_lines.lines(), [false, false, true, true]);
+        assert_eq!(dirty_lines.dirty_count(), 2);
+
+        // the last line has nothing to join
+        dirty_lines.join_line(3);
+        assert_eq!(dirty_lines.lines(), [false, false, true, true]);
+
+        dirty_lines.reset(5, false);
+        dirty_lines.mark(4);
+        dirty_lines.join_lines(0, 3);
+        assert_eq!(dirty_lines.lines(), [true, true]);
+        assert_eq!(dirty_lines.dirty_count(), 2);
+    }
+}
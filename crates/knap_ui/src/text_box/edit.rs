@@ -0,0 +1,47 @@
+use std::{error::Error, fmt::Display};
+
+use crate::text_buffer::TextBufferPos;
+
+/// A change to the contents of a text box, see `TextBox::apply_edits`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Edit {
+    /// Insert `text` (which can span multiple lines) at `pos`.
+    Insert { pos: TextBufferPos, text: String },
+    /// Remove everything from `start` to `end` (which can be on a later line).
+    Remove {
+        start: TextBufferPos,
+        end: TextBufferPos,
+    },
+}
+
+/// How lines were added or removed by an edit, see `TextBox::take_line_shifts`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineShift {
+    /// `new_lines` lines were inserted into `line_idx`, shifting
+    /// the lines after it down by as many
+    Split { line_idx: usize, new_lines: usize },
+    /// the `removed_lines` lines after `line_idx` were joined onto
+    /// it, shifting the lines after those up by as many
+    Join {
+        line_idx: usize,
+        removed_lines: usize,
+    },
+}
+
+/// Why none of the edits were applied, see `TextBox::apply_edits`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EditError {
+    /// the edit at `edit_idx` refers to a position that is not in
+    /// the contents (or between the bytes of a character)
+    InvalidEdit { edit_idx: usize },
+    /// the text box is read-only
+    ReadOnly,
+}
+
+impl Display for EditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl Error for EditError {}
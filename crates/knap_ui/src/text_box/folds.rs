@@ -0,0 +1,207 @@
+use std::ops::Range;
+
+use knap_base::math::ToU64;
+
+/// The ranges of lines that are folded (hidden), sorted and
+/// never overlapping. The line just before each range is the
+/// header of the fold, which stays visible.
+///
+/// Lines that are not hidden are rendered one after another, so
+/// the "visual row" of a line is its index among the visible lines.
+///
+/// The folds follow their lines around as lines are added and removed,
+/// and are only dropped once all of their hidden lines are removed.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Folds {
+    ranges: Vec<Range<u64>>,
+}
+
+impl Folds {
+    /// Hide the lines in `hidden`, replacing any fold that overlaps it.
+    pub(crate) fn add(&mut self, hidden: Range<u64>) {
+        if hidden.is_empty() || hidden.start == 0 {
+            return;
+        }
+
+        self.ranges
+            .retain(|range| range.end <= hidden.start || range.start >= hidden.end);
+        let insert_idx = self
+            .ranges
+            .partition_point(|range| range.start < hidden.start);
+        self.ranges.insert(insert_idx, hidden);
+    }
+
+    /// Show the lines hidden by the fold whose header is
+    /// `header_line`, returning whether there is such a fold.
+    pub(crate) fn remove_at_header(&mut self, header_line: u64) -> bool {
+        let total_folds = self.ranges.len();
+        self.ranges
+            .retain(|range| range.start != header_line.saturating_add(1));
+        self.ranges.len() != total_folds
+    }
+
+    /// Show the lines hidden by the fold that contains `line`.
+    pub(crate) fn remove_containing(&mut self, line: u64) {
+        self.ranges.retain(|range| !range.contains(&line));
+    }
+
+    pub(crate) fn ranges(&self) -> &[Range<u64>] {
+        &self.ranges
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.ranges.clear();
+    }
+
+    /// `line_idx` was split in two, shifting the lines after it down.
+    pub(crate) fn split_line(&mut self, line_idx: usize) {
+        self.split_lines(line_idx, 1);
+    }
+
+    /// `new_lines` lines were inserted into `line_idx`, shifting the lines
+    /// after it down by as many. A fold that hides `line_idx` grows with it.
+    pub(crate) fn split_lines(&mut self, line_idx: usize, new_lines: usize) {
+        let line_idx = line_idx.to_u64();
+        let shift = |line: u64| {
+            if line > line_idx {
+                line.saturating_add(new_lines.to_u64())
+            } else {
+                line
+            }
+        };
+        for range in &mut self.ranges {
+            *range = shift(range.start)..shift(range.end);
+        }
+    }
+
+    /// The line after `line_idx` was joined onto it, shifting
+    /// the lines after that up.
+    pub(crate) fn join_line(&mut self, line_idx: usize) {
+        self.join_lines(line_idx, 1);
+    }
+
+    /// The `removed_lines` lines after `line_idx` were joined onto it,
+    /// shifting the lines after those up by as many. A fold shrinks by
+    /// the lines that it hid, and is dropped if it hid only those.
+    pub(crate) fn join_lines(&mut self, line_idx: usize, removed_lines: usize) {
+        let line_idx = line_idx.to_u64();
+        let removed_lines = removed_lines.to_u64();
+        let last_removed_line_idx = line_idx.saturating_add(removed_lines);
+        let shift = |line: u64| {
+            if line > last_removed_line_idx {
+                line.saturating_sub(removed_lines)
+            } else {
+                line.min(line_idx.saturating_add(1))
+            }
+        };
+        for range in &mut self.ranges {
+            *range = shift(range.start)..shift(range.end);
+        }
+        self.ranges.retain(|range| !range.is_empty());
+    }
+
+    pub(crate) fn get_containing(&self, line: u64) -> Option<Range<u64>> {
+        self.ranges
+            .iter()
+            .find(|range| range.contains(&line))
+            .cloned()
+    }
+
+    pub(crate) fn get_at_header(&self, header_line: u64) -> Option<Range<u64>> {
+        self.ranges
+            .iter()
+            .find(|range| range.start == header_line.saturating_add(1))
+            .cloned()
+    }
+
+    /// Returns the visual row of `line`. Hidden lines are
+    /// treated as if they were on the row of their header.
+    pub(crate) fn get_visual_row(&self, line: u64) -> u64 {
+        let hidden_lines = self
+            .ranges
+            .iter()
+            .take_while(|range| range.start <= line)
+            .map(|range| {
+                range
+                    .end
+                    .min(line.saturating_add(1))
+                    .saturating_sub(range.start)
+            })
+            .sum::<u64>();
+
+        line.saturating_sub(hidden_lines)
+    }
+
+    /// Returns the line that is shown on `visual_row`.
+    pub(crate) fn get_line(&self, visual_row: u64) -> u64 {
+        self.ranges.iter().fold(visual_row, |line, range| {
+            if range.start <= line {
+                line.saturating_add(range.end.saturating_sub(range.start))
+            } else {
+                line
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_folds() {
+        let mut folds = Folds::default();
+        folds.add(2..5);
+        folds.add(7..9);
+        assert!(folds.get_containing(1).is_none());
+        assert_eq!(folds.get_containing(4), Some(2..5));
+        assert_eq!(folds.get_at_header(6), Some(7..9));
+
+        // rows:  0 1 2 3 4 5 6
+        // lines: 0 1 5 6 9 10 11
+        assert_eq!(
+            (0..7).map(|row| folds.get_line(row)).collect::<Vec<_>>(),
+            vec![0, 1, 5, 6, 9, 10, 11]
+        );
+        assert_eq!(
+            (0..12)
+                .map(|line| folds.get_visual_row(line))
+                .collect::<Vec<_>>(),
+            vec![0, 1, 1, 1, 1, 2, 3, 3, 3, 4, 5, 6]
+        );
+
+        // overlapping folds are replaced
+        folds.add(4..8);
+        assert_eq!(folds.ranges, vec![4..8]);
+
+        assert!(!folds.remove_at_header(4));
+        assert!(folds.remove_at_header(3));
+        assert!(folds.ranges.is_empty());
+    }
+
+    #[test]
+    fn test_shift_folds() {
+        let mut folds = Folds::default();
+        folds.add(2..5);
+        folds.add(7..9);
+
+        // the folds after the split move down with it, and
+        // the fold with the split line grows instead
+        folds.split_line(5);
+        assert_eq!(folds.ranges, vec![2..5, 8..10]);
+        folds.split_lines(3, 2);
+        assert_eq!(folds.ranges, vec![2..7, 10..12]);
+        folds.split_lines(1, 1);
+        assert_eq!(folds.ranges, vec![3..8, 11..13]);
+
+        // and up with a join, shrinking by their removed lines
+        folds.join_line(8);
+        assert_eq!(folds.ranges, vec![3..8, 10..12]);
+        folds.join_lines(1, 3);
+        assert_eq!(folds.ranges, vec![2..5, 7..9]);
+
+        // until all of their lines are removed
+        folds.join_lines(6, 4);
+        assert_eq!(folds.ranges, vec![2..5]);
+    }
+}
@@ -0,0 +1,180 @@
+use knap_base::math::{ToU64, ToUsize};
+
+/// What is inserted for one level of indentation.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IndentStyle {
+    #[default]
+    Tabs,
+    /// the number of spaces per level
+    Spaces(u64),
+}
+
+impl IndentStyle {
+    /// The text of one level of indentation.
+    pub fn indent_unit(&self) -> String {
+        match self {
+            Self::Tabs => "\t".to_string(),
+            Self::Spaces(width) => " ".repeat(width.to_usize()),
+        }
+    }
+
+    /// The text that indents from `column` to the next indentation
+    /// stop: a tab, or the spaces up to the next multiple of the width.
+    pub(crate) fn indent_at_column(&self, column: u64) -> String {
+        match self {
+            Self::Tabs => "\t".to_string(),
+            Self::Spaces(width) => {
+                let remainder = column.checked_rem(*width).unwrap_or_default();
+                " ".repeat(width.saturating_sub(remainder).to_usize())
+            }
+        }
+    }
+
+    /// The number of spaces that dedent from `column` (within the leading
+    /// whitespace of a line) back to the previous indentation stop, or
+    /// `None` if indenting with tabs.
+    pub(crate) fn dedent_width_at_column(&self, column: u64) -> Option<u64> {
+        match self {
+            Self::Tabs => None,
+            Self::Spaces(width) => {
+                let remainder = column
+                    .saturating_sub(1)
+                    .checked_rem(*width)
+                    .unwrap_or_default();
+                Some(remainder.saturating_add(1).min(column))
+            }
+        }
+    }
+
+    /// The length in bytes of up to one level of indentation at the
+    /// start of `line`, which is what is removed when dedenting it.
+    /// A leading tab always counts as one level, and spaces count as
+    /// one level up to the indent width (or `tab_width` for tabs).
+    pub(crate) fn leading_indent_len(&self, line: &str, tab_width: u64) -> usize {
+        if line.starts_with('\t') {
+            return 1;
+        }
+
+        let width = match self {
+            Self::Tabs => tab_width,
+            Self::Spaces(width) => *width,
+        };
+        line.bytes()
+            .take(width.to_usize())
+            .take_while(|byte| *byte == b' ')
+            .count()
+    }
+}
+
+/// The space indentation widths that are recognised. A change of a
+/// single space is usually alignment (e.g. ` *` in block comments).
+const SPACE_WIDTHS: std::ops::RangeInclusive<usize> = 2..=8;
+
+/// Guess the indentation style used by `lines`, or `None` if the
+/// lines are not indented (or indented too irregularly to tell).
+///
+/// Lines starting with a tab are counted against lines starting
+/// with spaces. For the latter, the unit is the most common change
+/// in indentation between consecutive lines.
+pub(crate) fn detect_indentation<I, S>(lines: I) -> Option<IndentStyle>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut tab_lines = 0_usize;
+    let mut space_lines = 0_usize;
+    let mut width_counts = [0_usize; 9];
+    let mut previous_spaces = 0_usize;
+
+    for line in lines {
+        let line = line.as_ref();
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if line.starts_with('\t') {
+            tab_lines = tab_lines.saturating_add(1);
+            previous_spaces = 0;
+            continue;
+        }
+
+        let spaces = line
+            .len()
+            .saturating_sub(line.trim_start_matches(' ').len());
+        if spaces > 0 {
+            space_lines = space_lines.saturating_add(1);
+        }
+
+        let width = spaces.abs_diff(previous_spaces);
+        if SPACE_WIDTHS.contains(&width)
+            && let Some(count) = width_counts.get_mut(width)
+        {
+            *count = count.saturating_add(1);
+        }
+        previous_spaces = spaces;
+    }
+
+    if tab_lines > 0 && tab_lines >= space_lines {
+        return Some(IndentStyle::Tabs);
+    }
+
+    // on a tie, the narrower width wins, as the wider
+    // indentation is likely made up of multiple levels
+    SPACE_WIDTHS
+        .filter_map(|width| {
+            let count = *width_counts.get(width)?;
+            (count > 0).then_some((count, width))
+        })
+        .min_by_key(|(count, width)| (std::cmp::Reverse(*count), *width))
+        .map(|(_, width)| IndentStyle::Spaces(width.to_u64()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_indent_stops() {
+        assert_eq!(IndentStyle::Tabs.indent_at_column(3), "\t");
+        assert_eq!(IndentStyle::Spaces(4).indent_at_column(0), "    ");
+        assert_eq!(IndentStyle::Spaces(4).indent_at_column(6), "  ");
+        assert_eq!(IndentStyle::Spaces(4).indent_at_column(8), "    ");
+
+        assert_eq!(IndentStyle::Tabs.dedent_width_at_column(4), None);
+        assert_eq!(IndentStyle::Spaces(4).dedent_width_at_column(0), Some(0));
+        assert_eq!(IndentStyle::Spaces(4).dedent_width_at_column(6), Some(2));
+        assert_eq!(IndentStyle::Spaces(4).dedent_width_at_column(8), Some(4));
+    }
+
+    #[test]
+    fn test_detect_indentation() {
+        assert_eq!(
+            detect_indentation(["fn main() {", "\tif x {", "\t\ty();", "\t}", "}"]),
+            Some(IndentStyle::Tabs)
+        );
+        assert_eq!(
+            detect_indentation(["a:", "  b:", "    c: 1", "", "    d: 2", "  e: 3"]),
+            Some(IndentStyle::Spaces(2))
+        );
+        assert_eq!(
+            detect_indentation([
+                "fn f() {",
+                "    /**",
+                "     * doc",
+                "     */",
+                "    g();",
+                "}"
+            ]),
+            Some(IndentStyle::Spaces(4))
+        );
+
+        // mostly spaces, with a stray tab
+        assert_eq!(
+            detect_indentation(["{", "    a", "    b", "\tc", "}"]),
+            Some(IndentStyle::Spaces(4))
+        );
+
+        assert_eq!(detect_indentation(["no", "indentation", ""]), None);
+        assert_eq!(detect_indentation(Vec::<String>::new()), None);
+    }
+}
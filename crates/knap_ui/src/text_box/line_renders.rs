@@ -0,0 +1,134 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use super::text_line::TextLine;
+
+/// At most this many lines are kept, which is many screens' worth.
+const MAX_LINES: usize = 1024;
+
+/// The renders of the lines that were read recently, so that reading a line
+/// again does not segment it again until it is changed. The renders follow
+/// the lines around as lines are added and removed.
+#[derive(Default)]
+pub(crate) struct LineRenders {
+    lines: RefCell<HashMap<usize, Rc<TextLine>>>,
+}
+
+impl LineRenders {
+    /// Returns the render of `line_idx`, which is made with `render`
+    /// if there is none yet.
+    pub(crate) fn get_or_render(
+        &self,
+        line_idx: usize,
+        render: impl FnOnce() -> Option<TextLine>,
+    ) -> Option<Rc<TextLine>> {
+        if let Some(line) = self.lines.borrow().get(&line_idx) {
+            return Some(Rc::clone(line));
+        }
+
+        let line = Rc::new(render()?);
+        let mut lines = self.lines.borrow_mut();
+        if lines.len() >= MAX_LINES {
+            lines.clear();
+        }
+        lines.insert(line_idx, Rc::clone(&line));
+        Some(line)
+    }
+
+    pub(crate) fn contains(&self, line_idx: usize) -> bool {
+        self.lines.borrow().contains_key(&line_idx)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.lines.get_mut().clear();
+    }
+
+    pub(crate) fn mark(&mut self, line_idx: usize) {
+        self.lines.get_mut().remove(&line_idx);
+    }
+
+    /// `line_idx` was split in two, shifting the lines after it down.
+    pub(crate) fn split_line(&mut self, line_idx: usize) {
+        self.split_lines(line_idx, 1);
+    }
+
+    /// `new_lines` lines were inserted into `line_idx`, shifting
+    /// the lines after it down by as many.
+    pub(crate) fn split_lines(&mut self, line_idx: usize, new_lines: usize) {
+        self.shift_lines_after(line_idx, |idx| idx.checked_add(new_lines));
+    }
+
+    /// The line after `line_idx` was joined onto it, shifting
+    /// the lines after that up.
+    pub(crate) fn join_line(&mut self, line_idx: usize) {
+        self.join_lines(line_idx, 1);
+    }
+
+    /// The `removed_lines` lines after `line_idx` were joined onto
+    /// it, shifting the lines after those up by as many.
+    pub(crate) fn join_lines(&mut self, line_idx: usize, removed_lines: usize) {
+        let last_removed_line_idx = line_idx.saturating_add(removed_lines);
+        self.shift_lines_after(line_idx, |idx| {
+            (idx > last_removed_line_idx).then(|| idx.saturating_sub(removed_lines))
+        });
+    }
+
+    /// Forget `line_idx`, and move the lines after it with `shift`.
+    fn shift_lines_after(&mut self, line_idx: usize, shift: impl Fn(usize) -> Option<usize>) {
+        let lines = self.lines.get_mut();
+        *lines = std::mem::take(lines)
+            .into_iter()
+            .filter_map(|(idx, line)| match idx.cmp(&line_idx) {
+                std::cmp::Ordering::Less => Some((idx, line)),
+                std::cmp::Ordering::Equal => None,
+                std::cmp::Ordering::Greater => Some((shift(idx)?, line)),
+            })
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text_box::TextRenderOptions;
+
+    #[test]
+    fn test_line_renders() {
+        let mut line_renders = LineRenders::default();
+        let render = |line_renders: &LineRenders, line_idx: usize| {
+            line_renders
+                .get_or_render(line_idx, || {
+                    Some(TextLine::new(
+                        format!("line {line_idx}"),
+                        TextRenderOptions::default(),
+                    ))
+                })
+                .expect("line to be rendered")
+        };
+        let lines: Vec<_> = (0..4)
+            .map(|line_idx| render(&line_renders, line_idx))
+            .collect();
+        assert!(Rc::ptr_eq(&render(&line_renders, 1), &lines[1]));
+
+        // the lines after the split move down with it
+        line_renders.split_line(1);
+        assert!(Rc::ptr_eq(&render(&line_renders, 0), &lines[0]));
+        assert!(!Rc::ptr_eq(&render(&line_renders, 1), &lines[1]));
+        assert!(Rc::ptr_eq(&render(&line_renders, 3), &lines[2]));
+        assert!(Rc::ptr_eq(&render(&line_renders, 4), &lines[3]));
+
+        // and up with a join
+        line_renders.join_line(2);
+        assert!(Rc::ptr_eq(&render(&line_renders, 3), &lines[3]));
+
+        line_renders.split_lines(2, 3);
+        assert!(Rc::ptr_eq(&render(&line_renders, 6), &lines[3]));
+        line_renders.join_lines(1, 4);
+        assert!(Rc::ptr_eq(&render(&line_renders, 2), &lines[3]));
+
+        line_renders.mark(0);
+        assert!(!Rc::ptr_eq(&render(&line_renders, 0), &lines[0]));
+
+        line_renders.clear();
+        assert!(!Rc::ptr_eq(&render(&line_renders, 3), &lines[3]));
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use knap_base::math::{ToU64, Vec2u};
+
+/// The caret positions recorded under a name, which follow their
+/// lines around as lines are added and removed. The marks on removed
+/// lines are moved to the line that they were joined onto.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Marks {
+    marks: HashMap<char, Vec2u>,
+}
+
+impl Marks {
+    pub(crate) fn get(&self, id: char) -> Option<Vec2u> {
+        self.marks.get(&id).copied()
+    }
+
+    pub(crate) fn set(&mut self, id: char, pos: Vec2u) {
+        self.marks.insert(id, pos);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.marks.clear();
+    }
+
+    /// `line_idx` was split in two, shifting the lines after it down.
+    pub(crate) fn split_line(&mut self, line_idx: usize) {
+        self.split_lines(line_idx, 1);
+    }
+
+    /// `new_lines` lines were inserted into `line_idx`, shifting
+    /// the lines after it down by as many.
+    pub(crate) fn split_lines(&mut self, line_idx: usize, new_lines: usize) {
+        let line_idx = line_idx.to_u64();
+        for pos in self.marks.values_mut() {
+            if pos.y > line_idx {
+                pos.y = pos.y.saturating_add(new_lines.to_u64());
+            }
+        }
+    }
+
+    /// The line after `line_idx` was joined onto it, shifting
+    /// the lines after that up.
+    pub(crate) fn join_line(&mut self, line_idx: usize) {
+        self.join_lines(line_idx, 1);
+    }
+
+    /// The `removed_lines` lines after `line_idx` were joined onto
+    /// it, shifting the lines after those up by as many.
+    pub(crate) fn join_lines(&mut self, line_idx: usize, removed_lines: usize) {
+        let line_idx = line_idx.to_u64();
+        let last_removed_line_idx = line_idx.saturating_add(removed_lines.to_u64());
+        for pos in self.marks.values_mut() {
+            if pos.y > last_removed_line_idx {
+                pos.y = pos.y.saturating_sub(removed_lines.to_u64());
+            } else if pos.y > line_idx {
+                pos.y = line_idx;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_marks() {
+        let mut marks = Marks::default();
+        assert_eq!(marks.get('a'), None);
+
+        marks.set('a', Vec2u { x: 2, y: 5 });
+        marks.set('b', Vec2u { x: 1, y: 1 });
+
+        // the marks after the split move down with it
+        marks.split_line(1);
+        assert_eq!(marks.get('a'), Some(Vec2u { x: 2, y: 6 }));
+        assert_eq!(marks.get('b'), Some(Vec2u { x: 1, y: 1 }));
+        marks.split_lines(0, 3);
+        assert_eq!(marks.get('a'), Some(Vec2u { x: 2, y: 9 }));
+        assert_eq!(marks.get('b'), Some(Vec2u { x: 1, y: 4 }));
+
+        // and up with a join, the marks on the removed lines
+        // stay on the line that they were joined onto
+        marks.join_lines(2, 3);
+        assert_eq!(marks.get('a'), Some(Vec2u { x: 2, y: 6 }));
+        assert_eq!(marks.get('b'), Some(Vec2u { x: 1, y: 2 }));
+        marks.join_line(5);
+        assert_eq!(marks.get('a'), Some(Vec2u { x: 2, y: 5 }));
+
+        marks.clear();
+        assert_eq!(marks.get('a'), None);
+    }
+}
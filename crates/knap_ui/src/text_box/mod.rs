@@ -1,6 +1,18 @@
+mod changed_lines;
+mod dirty_lines;
+mod edit;
+mod folds;
+mod indent;
+mod line_renders;
+mod marks;
 mod text_box_impl;
+mod text_counts;
 mod text_highlights;
 mod text_line;
+mod undo;
 
+pub use edit::*;
+pub use indent::IndentStyle;
 pub use text_box_impl::*;
 pub use text_highlights::*;
+pub use text_line::TextRenderOptions;
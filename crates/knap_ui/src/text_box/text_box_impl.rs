@@ -1,14 +1,32 @@
-use std::ops::Range;
+use std::{cell::OnceCell, ops::Range, rc::Rc};
 
 use anyhow::Result;
-use knap_base::math::{Bounds2f, Lossy, ToU64, ToUsize, Vec2f, Vec2u};
+use knap_base::{
+    color::Color,
+    math::{Bounds2f, Lossy, ToU64, ToUsize, Vec2f, Vec2i, Vec2u},
+};
 use knap_window::drawer::Drawer;
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
 
 use crate::text_buffer::{
-    InsertCharError, RemoveCharError, SearchDirection, TextBuffer, TextBufferPos,
+    FindResult, InsertCharError, RemoveCharError, SearchDirection, SearchOptions, TextBuffer,
+    TextBufferPos, get_pos_after_str, is_whole_word, is_word_char,
 };
 
-use super::{TextHighlightLine, TextHighlights, text_line::TextLine};
+use super::{
+    TextColor, TextHighlightBlock, TextHighlightLine, TextHighlights,
+    changed_lines::ChangedLines,
+    dirty_lines::DirtyLines,
+    edit::{Edit, EditError, LineShift},
+    folds::Folds,
+    indent::{IndentStyle, detect_indentation},
+    line_renders::LineRenders,
+    marks::Marks,
+    text_counts::TextCounts,
+    text_line::{TextLine, TextRenderOptions},
+    undo::{EditOp, UndoHistory},
+};
 
 pub struct InsertCharResult {
     /// There could be scenarios where an insertion of
@@ -39,11 +57,159 @@ pub struct RemoveCharResult {
     pub line_len_decreased: bool,
 }
 
+/// The unit by which a selection grows when it is dragged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionGranularity {
+    Character,
+    Word,
+    Line,
+}
+
+/// How the text between the selection anchor and the caret is selected.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionMode {
+    /// everything from the anchor to the caret
+    #[default]
+    Normal,
+    /// the same columns of each line from the anchor's line to the
+    /// caret's line, i.e. the rectangle with the anchor and the caret
+    /// at its corners
+    Block,
+}
+
+/// Returns the fragments of `line_render` that are within `columns` of a
+/// block selection, or `None` if the line ends before the columns.
+fn get_block_fragment_range(line_render: &TextLine, columns: &Range<u64>) -> Option<Range<usize>> {
+    if line_render.get_line_text_width(line_render.get_line_len()) < columns.start {
+        return None;
+    }
+
+    Some(
+        line_render.get_fragment_idx_from_line_text_width(columns.start)
+            ..line_render.get_fragment_idx_from_line_text_width(columns.end),
+    )
+}
+
+/// Map a position on the screen into a position on the text grid.
+///
+/// When `scroll_at_edges` is true, positions that are on the top/left
+/// edge of `bounds` are mapped to the row/column just before the visible
+/// area, and positions beyond the bottom/right edge are mapped to the
+/// row/column just after the visible area. This is so that the text box
+/// scrolls when a drag reaches its edges. (the top/left edge is used
+/// instead of going beyond it, as it is usually not possible to go
+/// beyond it when the text box is at the top/left of the window)
+fn get_grid_pos_from_screen_pos(
+    bounds: Bounds2f,
+    scroll_offset: Vec2u,
+    screen_pos: Vec2f,
+    scroll_at_edges: bool,
+) -> Vec2u {
+    let relative_x = (screen_pos.x - bounds.pos.x).floor();
+    let relative_y = (screen_pos.y - bounds.pos.y).floor();
+
+    let map_axis = |relative: f64, size: f64, scroll: u64| {
+        if scroll_at_edges && relative <= 0.0 {
+            scroll.saturating_sub(1)
+        } else if scroll_at_edges && relative >= size {
+            scroll.saturating_add(size.lossy())
+        } else {
+            scroll.saturating_add(relative.max(0.0).lossy())
+        }
+    };
+
+    Vec2u {
+        x: map_axis(relative_x, bounds.size.x, scroll_offset.x),
+        y: map_axis(relative_y, bounds.size.y, scroll_offset.y),
+    }
+}
+
+const SCROLLBAR_THUMB: &str = "█";
+const SCROLLBAR_TRACK: &str = "░";
+const FOLD_MARKER: &str = "…";
+const INDENT_GUIDE: &str = "│";
+/// The brackets that can be jumped between, as (opening, closing) pairs.
+const BRACKET_PAIRS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+/// The closing counterpart of `ch`, if it opens a pair that is auto-paired.
+fn auto_pair_closer(ch: char) -> Option<char> {
+    match ch {
+        '"' | '\'' => Some(ch),
+        _ => BRACKET_PAIRS
+            .iter()
+            .find_map(|&(open, close)| (open == ch).then_some(close)),
+    }
+}
+
+/// Returns the rows of the scrollbar (of `height` rows) that are covered
+/// by its thumb, whose size is proportional to how much of the file is
+/// visible, and whose position follows `scroll_offset_y`.
+fn get_scrollbar_thumb(scroll_offset_y: u64, height: u64, total_lines: u64) -> Range<u64> {
+    if total_lines <= height {
+        return 0..height;
+    }
+
+    let thumb_size = height
+        .saturating_mul(height)
+        .checked_div(total_lines)
+        .unwrap_or_default()
+        .clamp(1, height);
+    let max_scroll_offset_y = total_lines.saturating_sub(height);
+    let thumb_start = scroll_offset_y
+        .min(max_scroll_offset_y)
+        .saturating_mul(height.saturating_sub(thumb_size))
+        .checked_div(max_scroll_offset_y)
+        .unwrap_or_default();
+
+    thumb_start..thumb_start.saturating_add(thumb_size)
+}
+
+/// The colors of what a text box draws on its own, as opposed
+/// to the highlights that it is given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextBoxColors {
+    /// drawn over the character under the caret, in addition to
+    /// the cursor of the terminal
+    pub caret: Option<TextColor>,
+    /// drawn over the highlights of the selected text
+    pub selection: TextColor,
+    /// the background of the caret's line, if it is highlighted
+    pub current_line: Option<Color>,
+    /// drawn over the spaces and tabs at the end of each line, if
+    /// `TextRenderOptions::highlight_trailing_whitespace` is set
+    pub trailing_whitespace: TextColor,
+}
+
+impl Default for TextBoxColors {
+    fn default() -> Self {
+        Self {
+            caret: None,
+            selection: TextColor {
+                foreground: Some(Color::WHITE),
+                background: Some(Color::BLUE),
+            },
+            current_line: None,
+            trailing_whitespace: TextColor {
+                foreground: None,
+                background: Some(Color::RED),
+            },
+        }
+    }
+}
+
+// the bools are independent options, not the states of a state machine
+#[allow(clippy::struct_excessive_bools)]
 pub struct TextBox<B: TextBuffer> {
     bounds: Bounds2f,
 
     contents: B,
     is_dirty: bool,
+    /// the lines changed since the contents were last saved
+    dirty_lines: DirtyLines,
+    /// the lines changed since they were last taken by the owner
+    changed_lines: ChangedLines,
+    /// the lines added or removed since they were last taken by the owner
+    line_shifts: Vec<LineShift>,
 
     /// Best effort single line mode.
     ///
@@ -69,6 +235,51 @@ pub struct TextBox<B: TextBuffer> {
     /// job of this variable.
     previous_line_caret_max_x: Option<u64>,
 
+    /// When this is true, inserting a character replaces the
+    /// grapheme under the caret instead (unless the caret is
+    /// at the end of the line, in which case it is appended).
+    overwrite_mode: bool,
+
+    /// When this is true, typing an opening bracket or quote also
+    /// inserts its closing counterpart after the caret.
+    auto_pair: bool,
+    /// The closing characters that were inserted by `Self::auto_pair`,
+    /// which are typed over instead of typed again, and erased along
+    /// with the opening character when the pair is empty. They are
+    /// forgotten once a line is split or joined.
+    auto_pair_closers: Vec<TextBufferPos>,
+
+    /// When this is true, the editing methods leave the contents as they
+    /// are (without marking the text box dirty), while the caret can still
+    /// be moved around and the contents searched. The contents can still be
+    /// replaced by the owner with `Self::set_contents`.
+    read_only: bool,
+
+    render_options: TextRenderOptions,
+    colors: TextBoxColors,
+
+    /// The fixed end of the selection, with the caret being
+    /// the moving end. When this is `None` (or equal to the
+    /// caret), nothing is selected.
+    selection_anchor: Option<Vec2u>,
+    selection_mode: SelectionMode,
+
+    /// The lines that are hidden by folds. The caret is never
+    /// placed on a hidden line, moving onto one unfolds it.
+    folds: Folds,
+
+    /// The caret positions recorded with `Self::set_mark`. They are
+    /// forgotten when the contents are replaced.
+    marks: Marks,
+
+    /// The changes to the contents, for undoing and redoing them.
+    undo_history: UndoHistory,
+
+    /// Counted when first needed, and forgotten whenever the contents change.
+    counts: OnceCell<TextCounts>,
+    /// Rendered when a line is first needed, and forgotten when it changes.
+    line_renders: LineRenders,
+
     before_search_caret_pos: Option<Vec2u>,
     before_search_scroll_offset: Option<Vec2u>,
 }
@@ -77,12 +288,28 @@ impl<B: TextBuffer> TextBox<B> {
     pub fn new(buffer: B) -> Self {
         Self {
             bounds: Bounds2f::ZERO,
+            dirty_lines: DirtyLines::new(buffer.total_lines()),
+            changed_lines: ChangedLines::default(),
+            line_shifts: Vec::new(),
             contents: buffer,
             is_dirty: false,
             single_line_mode: false,
             caret_pos: Vec2u::ZERO,
             scroll_offset: Vec2u::ZERO,
             previous_line_caret_max_x: None,
+            overwrite_mode: false,
+            auto_pair: false,
+            auto_pair_closers: Vec::new(),
+            read_only: false,
+            render_options: TextRenderOptions::default(),
+            colors: TextBoxColors::default(),
+            selection_anchor: None,
+            selection_mode: SelectionMode::Normal,
+            folds: Folds::default(),
+            marks: Marks::default(),
+            undo_history: UndoHistory::default(),
+            counts: OnceCell::new(),
+            line_renders: LineRenders::default(),
             before_search_caret_pos: None,
             before_search_scroll_offset: None,
         }
@@ -103,6 +330,22 @@ impl<B: TextBuffer> TextBox<B> {
         self.bounds
     }
 
+    /// The part of the bounds that text is rendered in, which
+    /// excludes the column reserved for the scrollbar (if shown).
+    fn text_bounds(&self) -> Bounds2f {
+        if self.render_options.show_scrollbar && self.bounds.size.x >= 1.0 {
+            Bounds2f {
+                pos: self.bounds.pos,
+                size: Vec2f {
+                    x: self.bounds.size.x - 1.0,
+                    y: self.bounds.size.y,
+                },
+            }
+        } else {
+            self.bounds
+        }
+    }
+
     pub fn set_bounds(&mut self, bounds: Bounds2f) {
         self.bounds = bounds;
         self.adjust_scroll_to_caret_grid_pos();
@@ -112,21 +355,108 @@ impl<B: TextBuffer> TextBox<B> {
         self.is_dirty
     }
 
+    /// Setting it to `false` (e.g. after saving) also marks every line as
+    /// unchanged, see `Self::dirty_lines`.
     pub fn set_is_dirty(&mut self, is_dirty: bool) {
         self.is_dirty = is_dirty;
+        if !is_dirty {
+            self.dirty_lines.reset(self.contents.total_lines(), false);
+        }
+    }
+
+    /// Whether each line was changed since the text box was last
+    /// marked as not dirty, one for each line.
+    pub fn dirty_lines(&self) -> &[bool] {
+        self.dirty_lines.lines()
+    }
+
+    /// Whether any line was changed since the text box was last marked
+    /// as not dirty, without going through `Self::dirty_lines`.
+    pub fn has_dirty_lines(&self) -> bool {
+        self.dirty_lines.dirty_count() > 0
+    }
+
+    /// Returns the lines changed since this was last called (numbered as
+    /// they are now), or `None` if nothing changed. Lines are only added
+    /// or removed within the returned lines, so the lines after them are
+    /// the same as before, only shifted by the number of lines added.
+    pub fn take_changed_lines(&mut self) -> Option<Range<usize>> {
+        self.changed_lines.take()
+    }
+
+    /// Returns how lines were added or removed since this was last
+    /// called, in the order that the edits were made, so that the owner
+    /// can move what it keeps on each line along with the lines.
+    /// Replacing the contents with `Self::set_contents` forgets them.
+    pub fn take_line_shifts(&mut self) -> Vec<LineShift> {
+        std::mem::take(&mut self.line_shifts)
+    }
+
+    pub fn is_overwrite_mode(&self) -> bool {
+        self.overwrite_mode
+    }
+
+    pub fn set_overwrite_mode(&mut self, overwrite_mode: bool) {
+        self.overwrite_mode = overwrite_mode;
+    }
+
+    pub fn is_auto_pair(&self) -> bool {
+        self.auto_pair
+    }
+
+    pub fn set_auto_pair(&mut self, auto_pair: bool) {
+        self.auto_pair = auto_pair;
+        self.auto_pair_closers.clear();
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    pub fn colors(&self) -> TextBoxColors {
+        self.colors
+    }
+
+    pub fn set_colors(&mut self, colors: TextBoxColors) {
+        self.colors = colors;
+    }
+
+    pub fn render_options(&self) -> TextRenderOptions {
+        self.render_options
+    }
+
+    pub fn set_render_options(&mut self, render_options: TextRenderOptions) {
+        self.render_options = render_options;
+        self.line_renders.clear();
+        self.adjust_scroll_to_caret_grid_pos();
     }
 
     pub fn set_contents<T: AsRef<str>>(&mut self, contents: T) {
         self.contents.set_contents(contents.as_ref());
         self.is_dirty = true;
+        self.dirty_lines.reset(self.contents.total_lines(), true);
+        self.changed_lines.mark_all(self.contents.total_lines());
+        self.line_shifts.clear();
+        self.counts.take();
+        self.line_renders.clear();
+        self.folds.clear();
+        self.marks.clear();
+        self.auto_pair_closers.clear();
+        // the recorded changes no longer apply to the contents
+        self.undo_history.clear();
 
         self.caret_pos.y = self.caret_pos.y.clamp(0, self.get_total_lines().to_u64());
         self.caret_pos.x = self
             .caret_pos
             .x
             .clamp(0, self.get_line_len(self.caret_pos.y.to_usize()).to_u64());
-        self.adjust_scroll_to_caret_grid_pos();
         self.previous_line_caret_max_x.take();
+        self.adjust_scroll_to_caret_grid_pos();
+        self.selection_anchor.take();
         self.before_search_caret_pos.take();
         self.before_search_scroll_offset.take();
     }
@@ -135,578 +465,4999 @@ impl<B: TextBuffer> TextBox<B> {
         self.caret_pos
     }
 
-    pub fn get_line_len(&self, line_idx: usize) -> usize {
-        self.contents
-            .line(line_idx)
-            // TODO: This is not efficient
-            .map_or(0, |line| TextLine::new(line).get_line_len())
+    /// The column that the caret is drawn at, counted in display width
+    /// (e.g. a full-width character or a tab takes up more than one column),
+    /// unlike the x of `Self::caret_pos`, which counts graphemes.
+    pub fn caret_visual_column(&self) -> u64 {
+        self.get_grid_pos_from_caret_pos(self.caret_pos).x
     }
 
-    fn get_grid_pos_from_caret_pos(&self, caret_pos: Vec2u) -> Vec2u {
-        Vec2u {
-            x: self
-                .contents
-                .line(caret_pos.y.to_usize())
-                // TODO: This is not efficient
-                .map_or(0, |line| {
-                    TextLine::new(line).get_line_text_width(caret_pos.x.to_usize())
-                }),
-            y: caret_pos.y,
+    /// Get the selected range, ordered from the start of the
+    /// document to the end of the document (regardless of which
+    /// end the caret is on).
+    ///
+    /// Returns `None` if nothing is selected.
+    pub fn selection_range(&self) -> Option<Range<Vec2u>> {
+        let anchor = self.selection_anchor?;
+
+        if anchor == self.caret_pos {
+            None
+        } else if (anchor.y, anchor.x) < (self.caret_pos.y, self.caret_pos.x) {
+            Some(anchor..self.caret_pos)
+        } else {
+            Some(self.caret_pos..anchor)
         }
     }
 
-    fn adjust_scroll_to_caret_grid_pos(&mut self) {
-        let grid_cursor_pos = self.get_grid_pos_from_caret_pos(self.caret_pos);
-
-        if grid_cursor_pos.x < self.scroll_offset.x {
-            self.scroll_offset.x = grid_cursor_pos.x;
-        }
+    pub fn clear_selection(&mut self) {
+        self.selection_anchor.take();
+    }
 
-        if grid_cursor_pos.y < self.scroll_offset.y {
-            self.scroll_offset.y = grid_cursor_pos.y;
-        }
+    pub fn select_all(&mut self) {
+        let last_line_idx = self.get_total_lines().saturating_sub(1);
+        let last_line_len = self.get_line_len(last_line_idx).to_u64();
 
-        if grid_cursor_pos.x
-            >= self
-                .scroll_offset
-                .x
-                .saturating_add(self.bounds.size.x.lossy())
-        {
-            self.scroll_offset.x = grid_cursor_pos
-                .x
-                .saturating_sub(self.bounds.size.x.lossy())
-                .saturating_add(1);
+        if last_line_idx == 0 && last_line_len == 0 {
+            // nothing to select
+            return;
         }
 
-        if grid_cursor_pos.y
-            >= self
-                .scroll_offset
-                .y
-                .saturating_add(self.bounds.size.y.lossy())
-        {
-            self.scroll_offset.y = grid_cursor_pos
-                .y
-                .saturating_sub(self.bounds.size.y.lossy())
-                .saturating_add(1);
-        }
+        self.change_caret_xy(Vec2u {
+            x: last_line_len,
+            y: last_line_idx.to_u64(),
+        });
+        self.selection_anchor = Some(Vec2u::ZERO);
     }
 
-    /// See `Self::previous_line_caret_max_x` for more details about the purpose
-    /// of this function.
-    fn adjust_caret_x_on_caret_y_movement(&mut self) {
-        let line_len = self.get_line_len(self.caret_pos.y.to_usize()).to_u64();
+    pub fn select_line(&mut self) {
+        let line_idx = self.caret_pos.y;
 
-        if self.caret_pos.x > line_len {
-            // x is not on a valid position, move it back
-            if self.previous_line_caret_max_x.is_none() {
-                self.previous_line_caret_max_x = Some(self.caret_pos.x);
+        if line_idx.to_usize() >= self.get_total_lines() {
+            return;
+        }
+
+        let end = if line_idx.to_usize() == self.get_total_lines().saturating_sub(1) {
+            // no trailing newline on the last line
+            Vec2u {
+                x: self.get_line_len(line_idx.to_usize()).to_u64(),
+                y: line_idx,
             }
-            self.caret_pos.x = line_len;
         } else {
-            // check to see if we have previous memory of x
-            if let Some(previous_x) = self.previous_line_caret_max_x {
-                if previous_x > line_len {
-                    // previous entry still too far out...
-                    self.caret_pos.x = line_len;
-                } else {
-                    self.caret_pos.x = previous_x;
-                    self.previous_line_caret_max_x = None;
-                }
+            Vec2u {
+                x: 0,
+                y: line_idx.saturating_add(1),
             }
-        }
+        };
+
+        self.change_caret_xy(end);
+        self.selection_anchor = Some(Vec2u { x: 0, y: line_idx });
     }
 
-    fn change_caret_x(&mut self, new_x: u64) {
-        self.caret_pos.x = new_x;
-        self.adjust_scroll_to_caret_grid_pos();
-        self.previous_line_caret_max_x.take();
+    /// Select the word under the caret. If the caret is on whitespace,
+    /// the whole whitespace run is selected instead.
+    ///
+    /// If a selection on the current line already ends at the caret,
+    /// the selection is extended to the end of the next word.
+    pub fn select_word(&mut self) {
+        let Some(line_render) = self.get_line_render(self.caret_pos.y.to_usize()) else {
+            return;
+        };
+        let words = line_render.get_words();
+        let caret_x = self.caret_pos.x.to_usize();
+
+        if let Some(selection) = self.selection_range()
+            && selection.start.y == self.caret_pos.y
+            && selection.end == self.caret_pos
+        {
+            if let Some(next_word) = words
+                .iter()
+                .find(|word| word.range.start >= caret_x && !word.is_whitespace)
+                && let Some(anchor) = self.selection_anchor
+            {
+                self.set_selection(
+                    anchor,
+                    Vec2u {
+                        x: next_word.range.end.to_u64(),
+                        y: self.caret_pos.y,
+                    },
+                );
+            }
+            return;
+        }
+
+        if let Some(word_range) = self.get_word_range_at(self.caret_pos) {
+            self.set_selection(
+                Vec2u {
+                    x: word_range.start,
+                    y: self.caret_pos.y,
+                },
+                Vec2u {
+                    x: word_range.end,
+                    y: self.caret_pos.y,
+                },
+            );
+        }
     }
 
-    fn change_caret_y(&mut self, new_y: u64) {
-        self.caret_pos.y = new_y;
-        self.adjust_caret_x_on_caret_y_movement();
-        self.adjust_scroll_to_caret_grid_pos();
+    /// Get the range (in fragment idx) of the word at `pos`. If `pos` is
+    /// at the end of the line, the last word of the line is returned.
+    fn get_word_range_at(&self, pos: Vec2u) -> Option<Range<u64>> {
+        let words = self.get_line_render(pos.y.to_usize())?.get_words();
+        let x = pos.x.to_usize();
+
+        words
+            .iter()
+            .find(|word| word.range.contains(&x))
+            .or_else(|| words.last().filter(|word| word.range.end == x))
+            .map(|word| word.range.start.to_u64()..word.range.end.to_u64())
     }
 
-    fn change_caret_xy(&mut self, new_pos: Vec2u) {
-        self.caret_pos = new_pos;
-        self.adjust_scroll_to_caret_grid_pos();
-        self.previous_line_caret_max_x.take();
+    /// Returns the byte range (within its line) of the word at `pos`.
+    pub fn get_word_byte_range_at(&self, pos: Vec2u) -> Option<Range<usize>> {
+        let fragment_range = self.get_word_range_at(pos)?;
+
+        let line = self.get_line_render(pos.y.to_usize())?;
+        Some(
+            line.get_byte_idx_from_fragment_idx(fragment_range.start.to_usize())?
+                ..line.get_byte_idx_from_fragment_idx(fragment_range.end.to_usize())?,
+        )
     }
 
-    pub fn move_cursor_up(&mut self) {
-        if self.single_line_mode {
-            self.change_caret_y(0);
-        } else {
-            self.change_caret_y(self.caret_pos.y.saturating_sub(1));
-        }
+    /// Returns the word at the caret (or right before it, if the caret is
+    /// at its end), if it is made of word characters, unlike e.g. `+=`.
+    pub fn word_under_caret(&self) -> Option<String> {
+        let line = self.contents.line(self.caret_pos.y.to_usize())?;
+        line.get(self.get_word_byte_range_under_caret()?)
+            .map(str::to_string)
     }
 
-    pub fn move_cursor_down(&mut self) {
-        if self.single_line_mode {
-            self.change_caret_y(0);
-        } else {
-            self.change_caret_y(
-                self.caret_pos
-                    .y
-                    .saturating_add(1)
-                    .clamp(0, self.get_total_lines().to_u64()),
-            );
-        }
+    /// See `Self::word_under_caret`.
+    fn get_word_byte_range_under_caret(&self) -> Option<Range<usize>> {
+        let line = self.contents.line(self.caret_pos.y.to_usize())?;
+        [
+            Some(self.caret_pos),
+            self.caret_pos.x.checked_sub(1).map(|x| Vec2u {
+                x,
+                ..self.caret_pos
+            }),
+        ]
+        .into_iter()
+        .flatten()
+        .filter_map(|pos| self.get_word_byte_range_at(pos))
+        .find(|range| {
+            line.get(range.clone())
+                .is_some_and(|word| !word.is_empty() && word.chars().all(is_word_char))
+        })
     }
 
-    pub fn move_cursor_left(&mut self) {
-        if self.caret_pos.x == 0 {
-            if self.caret_pos.y > 0 && !self.single_line_mode {
-                self.change_caret_xy(Vec2u {
-                    x: self
-                        .get_line_len(self.caret_pos.y.saturating_sub(1).to_usize())
-                        .to_u64(),
-                    y: self.caret_pos.y.saturating_sub(1),
-                });
-            } else {
-                self.previous_line_caret_max_x.take();
-            }
-        } else {
-            self.change_caret_x(self.caret_pos.x.saturating_sub(1));
-        }
+    /// Select from `anchor` to `caret`, moving the caret to `caret`.
+    pub fn set_selection(&mut self, anchor: Vec2u, caret: Vec2u) {
+        self.change_caret_xy(caret);
+        self.selection_anchor = Some(anchor);
     }
 
-    pub fn move_cursor_right(&mut self) {
-        let line_len = self.get_line_len(self.caret_pos.y.to_usize()).to_u64();
+    /// Extend a selection that is being dragged, from `origin` (what was
+    /// selected when the drag started) to the position rendered at `screen_pos`.
+    ///
+    /// Dragging on (or beyond) the edges of the text box scrolls it.
+    pub fn drag_selection_to_screen_pos(
+        &mut self,
+        origin: Range<Vec2u>,
+        screen_pos: Vec2f,
+        granularity: SelectionGranularity,
+    ) {
+        let target = self.get_caret_pos_from_grid_pos(get_grid_pos_from_screen_pos(
+            self.text_bounds(),
+            self.scroll_offset,
+            screen_pos,
+            true,
+        ));
+        let is_forward = (target.y, target.x) >= (origin.start.y, origin.start.x);
+        let anchor = if is_forward { origin.start } else { origin.end };
 
-        if self.caret_pos.x == line_len {
-            if self.caret_pos.y < self.get_total_lines().to_u64() && !self.single_line_mode {
-                self.change_caret_xy(Vec2u {
-                    x: 0,
-                    y: self.caret_pos.y.saturating_add(1),
-                });
+        let caret = match granularity {
+            SelectionGranularity::Character => target,
+            SelectionGranularity::Word => {
+                self.get_word_range_at(target)
+                    .map_or(target, |word_range| Vec2u {
+                        x: if is_forward {
+                            word_range.end
+                        } else {
+                            word_range.start
+                        },
+                        y: target.y,
+                    })
+            }
+            SelectionGranularity::Line => {
+                if is_forward {
+                    if target.y.saturating_add(1).to_usize() < self.get_total_lines() {
+                        Vec2u {
+                            x: 0,
+                            y: target.y.saturating_add(1),
+                        }
+                    } else {
+                        Vec2u {
+                            x: self.get_line_len(target.y.to_usize()).to_u64(),
+                            y: target.y,
+                        }
+                    }
+                } else {
+                    Vec2u { x: 0, y: target.y }
+                }
+            }
+        };
+
+        // never shrink the selection to less than the origin
+        let caret = if is_forward {
+            if (caret.y, caret.x) < (origin.end.y, origin.end.x) {
+                origin.end
             } else {
-                self.previous_line_caret_max_x.take();
+                caret
             }
         } else {
-            self.change_caret_x(self.caret_pos.x.saturating_add(1));
-        }
+            caret
+        };
+
+        self.set_selection(anchor, caret);
     }
 
-    pub fn move_cursor_up_one_page(&mut self) {
-        if self.single_line_mode {
-            self.change_caret_y(0);
-        } else {
-            self.change_caret_y(self.caret_pos.y.saturating_sub(self.bounds.size.y.lossy()));
-        }
+    /// Move the caret with `move_caret` (e.g. `Self::move_cursor_left`),
+    /// extending the selection to where it ends up instead of collapsing it.
+    pub fn extend_selection(&mut self, move_caret: impl FnOnce(&mut Self)) {
+        let anchor = self.selection_anchor.unwrap_or(self.caret_pos);
+        move_caret(self);
+        self.selection_anchor = Some(anchor);
     }
 
-    pub fn move_cursor_down_one_page(&mut self) {
-        if self.single_line_mode {
-            self.change_caret_y(0);
-        } else {
-            self.change_caret_y(
-                self.caret_pos
-                    .y
-                    .saturating_add(self.bounds.size.y.lossy())
-                    .clamp(0, self.get_total_lines().to_u64()),
-            );
-        }
+    pub fn selection_mode(&self) -> SelectionMode {
+        self.selection_mode
     }
 
-    pub fn move_cursor_to_start_of_line(&mut self) {
-        self.change_caret_x(0);
+    pub fn set_selection_mode(&mut self, selection_mode: SelectionMode) {
+        self.selection_mode = selection_mode;
     }
 
-    pub fn move_cursor_to_end_of_line(&mut self) {
-        self.change_caret_x(self.get_line_len(self.caret_pos.y.to_usize()).to_u64());
+    /// Returns the lines and the columns of the block selection, or `None`
+    /// if not selecting blocks (or if nothing is selected). The columns are
+    /// empty when the anchor and the caret are on the same column.
+    fn block_selection(&self) -> Option<(Range<usize>, Range<u64>)> {
+        if self.selection_mode != SelectionMode::Block {
+            return None;
+        }
+
+        let range = self.selection_range()?;
+        let anchor_column = self.get_grid_pos_from_caret_pos(self.selection_anchor?).x;
+        let caret_column = self.get_grid_pos_from_caret_pos(self.caret_pos).x;
+        Some((
+            range.start.y.to_usize()..range.end.y.to_usize().saturating_add(1),
+            anchor_column.min(caret_column)..anchor_column.max(caret_column),
+        ))
     }
 
-    pub fn insert_character_at_cursor(
+    /// Replace what `get_fragments` picks on each line of the block selection
+    /// (from the fragments within the block) with `text`. The lines are
+    /// changed from the bottom up, so that the positions of the lines above
+    /// stay valid. The block then becomes empty, right after `text`.
+    ///
+    /// Returns whether any line was changed.
+    fn replace_in_block_selection(
         &mut self,
-        ch: char,
-    ) -> Result<InsertCharResult, InsertCharError> {
-        // TODO: This is not efficient
-        let target_line_render = if self.caret_pos.y == self.contents.total_lines().to_u64() {
-            TextLine::new("")
-        } else {
-            match self.contents.line(self.caret_pos.y.to_usize()) {
-                Some(line) => TextLine::new(line),
-                None => return Err(InsertCharError::InvalidLinePosition),
-            }
+        text: &str,
+        get_fragments: impl Fn(&TextLine, Range<usize>) -> Option<Range<usize>>,
+    ) -> bool {
+        let (Some((lines, columns)), Some(mut anchor)) =
+            (self.block_selection(), self.selection_anchor)
+        else {
+            return false;
         };
+        let mut caret = self.caret_pos;
 
-        // TODO: This logic can be further refactored (it is copied and slightly modified across multiple functions)
-        let buffer_pos = TextBufferPos {
-            line: self.caret_pos.y.to_usize(),
-            byte: match target_line_render
+        let mut is_changed = false;
+        for line_idx in lines.rev() {
+            let Some(line_render) = self.get_line_render(line_idx) else {
+                continue;
+            };
+            let Some(fragments) = get_block_fragment_range(&line_render, &columns)
+                .and_then(|fragments| get_fragments(&line_render, fragments))
+            else {
+                continue;
+            };
+            let (Some(start_byte), Some(end_byte)) = (
+                line_render.get_byte_idx_from_fragment_idx(fragments.start),
+                line_render.get_byte_idx_from_fragment_idx(fragments.end),
+            ) else {
+                continue;
+            };
+            if start_byte == end_byte && text.is_empty() {
+                continue;
+            }
+
+            let start = TextBufferPos {
+                line: line_idx,
+                byte: start_byte,
+            };
+            self.remove_range(
+                start,
+                TextBufferPos {
+                    line: line_idx,
+                    byte: end_byte,
+                },
+            );
+            let Ok(text_end) = self.insert_str(start, text) else {
+                continue;
+            };
+            is_changed = true;
+
+            let text_end_x = self.get_caret_pos_from_buffer_pos(text_end).x;
+            if line_idx.to_u64() == anchor.y {
+                anchor.x = text_end_x;
+            }
+            if line_idx.to_u64() == caret.y {
+                caret.x = text_end_x;
+            }
+        }
+
+        if is_changed {
+            self.is_dirty = true;
+            self.set_selection(anchor, caret);
+        }
+        is_changed
+    }
+
+    pub fn copy_selection(&self) -> Option<String> {
+        if let Some((lines, columns)) = self.block_selection() {
+            return Some(
+                lines
+                    .filter_map(|line_idx| {
+                        let line_render = self
+                            .get_line_render(line_idx)
+                            .unwrap_or_else(|| Rc::new(TextLine::new("", self.render_options)));
+                        let fragments =
+                            get_block_fragment_range(&line_render, &columns).unwrap_or_default();
+                        let line = line_render.to_string();
+                        line.get(
+                            line_render.get_byte_idx_from_fragment_idx(fragments.start)?
+                                ..line_render.get_byte_idx_from_fragment_idx(fragments.end)?,
+                        )
+                        .map(str::to_string)
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            );
+        }
+
+        let range = self.selection_range()?;
+
+        let start = self.get_clamped_buffer_pos_from_caret_pos(range.start)?;
+        let end = self.get_clamped_buffer_pos_from_caret_pos(range.end)?;
+
+        Some(self.get_text_between(start, end))
+    }
+
+    /// Remove the selection, moving the caret to where it started.
+    ///
+    /// Returns what was selected, or `None` if nothing is selected.
+    pub fn cut_selection(&mut self) -> Option<String> {
+        if self.read_only {
+            return None;
+        }
+
+        if self.block_selection().is_some() {
+            let copied = self.copy_selection();
+            self.replace_in_block_selection("", |_, fragments| Some(fragments));
+            return copied;
+        }
+
+        let range = self.selection_buffer_range()?;
+        let removed = self.remove_range(range.start, range.end);
+        self.move_cursor_to_buffer_pos(range.start);
+        Some(removed)
+    }
+
+    /// Insert `text` (which can span multiple lines) at the caret, replacing
+    /// the selection if there is one, and move the caret to the end of it.
+    ///
+    /// Returns where the text was inserted.
+    pub fn paste_at_cursor(&mut self, text: &str) -> Result<Range<TextBufferPos>, InsertCharError> {
+        if self.read_only {
+            return Err(InsertCharError::ReadOnly);
+        }
+        if self.single_line_mode && text.contains('\n') {
+            return Err(InsertCharError::InvalidLinePosition);
+        }
+
+        self.cut_selection();
+        self.insert_str_at_cursor(text)
+    }
+
+    /// Convert a caret position into a position in the buffer.
+    ///
+    /// As the caret is allowed to be one line beyond the last line,
+    /// such positions are clamped back to the end of the last line.
+    fn get_clamped_buffer_pos_from_caret_pos(&self, caret_pos: Vec2u) -> Option<TextBufferPos> {
+        if caret_pos.y.to_usize() >= self.contents.total_lines() {
+            let last_line_idx = self.contents.total_lines().saturating_sub(1);
+            return Some(TextBufferPos {
+                line: last_line_idx,
+                byte: self.contents.line_len(last_line_idx).unwrap_or(0),
+            });
+        }
+
+        let line_render = self.get_line_render(caret_pos.y.to_usize())?;
+
+        Some(TextBufferPos {
+            line: caret_pos.y.to_usize(),
+            byte: line_render.get_byte_idx_from_fragment_idx(caret_pos.x.to_usize())?,
+        })
+    }
+
+    /// The position of the caret in the buffer.
+    pub fn caret_buffer_pos(&self) -> TextBufferPos {
+        self.get_clamped_buffer_pos_from_caret_pos(self.caret_pos)
+            .unwrap_or(TextBufferPos {
+                line: self.caret_pos.y.to_usize(),
+                byte: 0,
+            })
+    }
+
+    /// Convert a position in the buffer into a caret position, clamping
+    /// it to the end of the line (or buffer) if it is beyond it.
+    fn get_caret_pos_from_buffer_pos(&self, pos: TextBufferPos) -> Vec2u {
+        let line_idx = pos.line.min(self.get_total_lines().saturating_sub(1));
+        let x = self.get_line_render(line_idx).map_or(0, |line_render| {
+            line_render
+                .get_fragment_idx_from_byte_idx(pos.byte)
+                .unwrap_or_else(|| line_render.get_line_len())
+        });
+
+        Vec2u {
+            x: x.to_u64(),
+            y: line_idx.to_u64(),
+        }
+    }
+
+    /// The column and visual row (see `Folds`) at the top left of the view.
+    pub fn scroll_offset(&self) -> Vec2u {
+        self.scroll_offset
+    }
+
+    /// Scroll so that `offset` is at the top left of the view, without
+    /// moving the caret. The view does not scroll past the last line.
+    pub fn scroll_to(&mut self, offset: Vec2u) {
+        let last_visual_row = self
+            .folds
+            .get_visual_row(self.get_total_lines().saturating_sub(1).to_u64());
+        self.scroll_offset = Vec2u {
+            x: offset.x,
+            y: offset.y.min(last_visual_row),
+        };
+    }
+
+    /// Scroll by `delta` columns and visual rows (e.g. for the mouse wheel),
+    /// without moving the caret. The caret is scrolled back into view the
+    /// next time that it moves.
+    pub fn scroll_by(&mut self, delta: Vec2i) {
+        self.scroll_to(Vec2u {
+            x: self.scroll_offset.x.saturating_add_signed(delta.x),
+            y: self.scroll_offset.y.saturating_add_signed(delta.y),
+        });
+    }
+
+    /// Record the caret position as the mark `id`, replacing the
+    /// position previously recorded as it.
+    pub fn set_mark(&mut self, id: char) {
+        self.marks.set(id, self.caret_pos);
+    }
+
+    /// Move the caret back to the position recorded as the mark `id`, or as
+    /// near to it as the lines now allow. Returns whether there is such a mark.
+    pub fn jump_to_mark(&mut self, id: char) -> bool {
+        let Some(pos) = self.marks.get(id) else {
+            return false;
+        };
+        let y = pos.y.min(self.get_total_lines().saturating_sub(1).to_u64());
+        let x = pos.x.min(self.get_line_len(y.to_usize()).to_u64());
+        self.change_caret_xy(Vec2u { x, y });
+        true
+    }
+
+    /// Scroll so that the caret's line is in the middle of the view, without
+    /// scrolling above the first line or past the last line.
+    pub fn center_caret_vertically(&mut self) {
+        let height: u64 = self.text_bounds().size.y.lossy();
+        self.scroll_to(Vec2u {
+            x: self.scroll_offset.x,
+            y: self
+                .folds
+                .get_visual_row(self.caret_pos.y)
+                .saturating_sub(height.div_euclid(2)),
+        });
+    }
+
+    /// Move the caret to `pos` in the buffer, clamping it
+    /// to the end of the line (or buffer) if it is beyond it.
+    pub fn move_cursor_to_buffer_pos(&mut self, pos: TextBufferPos) {
+        self.change_caret_xy(self.get_caret_pos_from_buffer_pos(pos));
+    }
+
+    /// Insert `ch` at `pos`, recording it so that it can be undone.
+    fn insert_char_at(&mut self, pos: TextBufferPos, ch: char) -> Result<(), InsertCharError> {
+        if pos.line == self.contents.total_lines()
+            && let Some(last_line_idx) = pos.line.checked_sub(1)
+        {
+            // appending a line is the same as breaking the last line at its end,
+            // which is how it is done so that it is undone the same way
+            let last_line_end = TextBufferPos {
+                line: last_line_idx,
+                byte: self.contents.line_len(last_line_idx).unwrap_or_default(),
+            };
+            self.insert_char_at(last_line_end, '\n')?;
+            return if ch == '\n' {
+                Ok(())
+            } else {
+                self.insert_char_at(pos, ch)
+            };
+        }
+
+        self.contents.insert_character_at_pos(pos, ch)?;
+        let op = if ch == '\n' {
+            EditOp::InsertNewline { pos }
+        } else {
+            EditOp::InsertChar { pos, ch }
+        };
+        self.track_edit_op(&op);
+        self.undo_history.record(op, self.caret_pos);
+        Ok(())
+    }
+
+    /// Remove the character at `pos` (or the line break, if `pos` is at
+    /// the end of its line), recording it so that it can be undone.
+    fn remove_char_at(&mut self, pos: TextBufferPos) -> Result<(), RemoveCharError> {
+        let ch = self
+            .contents
+            .line(pos.line)
+            .and_then(|line| line.get(pos.byte..)?.chars().next());
+        self.contents.remove_character_at_pos(pos)?;
+        let op = match ch {
+            Some(ch) => EditOp::RemoveChar { pos, ch },
+            None => EditOp::JoinLine { pos },
+        };
+        self.track_edit_op(&op);
+        self.undo_history.record(op, self.caret_pos);
+        Ok(())
+    }
+
+    /// Keep track of the lines changed by `op`, which was just
+    /// applied, and forget the counts and renders that are now out of date.
+    fn track_edit_op(&mut self, op: &EditOp) {
+        self.counts.take();
+        match op {
+            EditOp::InsertChar { pos, .. } | EditOp::RemoveChar { pos, .. } => {
+                self.dirty_lines.mark(pos.line);
+                self.changed_lines.mark(pos.line);
+                self.line_renders.mark(pos.line);
+            }
+            EditOp::InsertNewline { pos } => {
+                self.dirty_lines.split_line(pos.line);
+                self.changed_lines.split_line(pos.line);
+                self.line_renders.split_line(pos.line);
+                self.marks.split_line(pos.line);
+                self.folds.split_line(pos.line);
+                self.line_shifts.push(LineShift::Split {
+                    line_idx: pos.line,
+                    new_lines: 1,
+                });
+                self.auto_pair_closers.clear();
+            }
+            EditOp::JoinLine { pos } => {
+                self.dirty_lines.join_line(pos.line);
+                self.changed_lines.join_line(pos.line);
+                self.line_renders.join_line(pos.line);
+                self.marks.join_line(pos.line);
+                self.folds.join_line(pos.line);
+                self.line_shifts.push(LineShift::Join {
+                    line_idx: pos.line,
+                    removed_lines: 1,
+                });
+                self.auto_pair_closers.clear();
+            }
+            EditOp::InsertStr { pos, text } => {
+                self.track_inserted_lines(pos.line, text.matches('\n').count());
+            }
+            EditOp::RemoveStr { start, text } => {
+                self.track_removed_lines(start.line, text.matches('\n').count());
+            }
+        }
+        self.track_auto_pair_closers(op);
+    }
+
+    /// Move the auto-inserted closing characters after the character
+    /// inserted or removed by `op` along with it.
+    fn track_auto_pair_closers(&mut self, op: &EditOp) {
+        match *op {
+            EditOp::InsertChar { pos, ch } => {
+                for closer in &mut self.auto_pair_closers {
+                    if closer.line == pos.line && closer.byte >= pos.byte {
+                        closer.byte = closer.byte.saturating_add(ch.len_utf8());
+                    }
+                }
+            }
+            EditOp::RemoveChar { pos, ch } => {
+                self.auto_pair_closers.retain(|closer| *closer != pos);
+                for closer in &mut self.auto_pair_closers {
+                    if closer.line == pos.line && closer.byte > pos.byte {
+                        closer.byte = closer.byte.saturating_sub(ch.len_utf8());
+                    }
+                }
+            }
+            EditOp::InsertNewline { .. }
+            | EditOp::JoinLine { .. }
+            | EditOp::InsertStr { .. }
+            | EditOp::RemoveStr { .. } => {}
+        }
+    }
+
+    /// Like `Self::track_edit_op`, for text with `new_lines` line
+    /// breaks that was just inserted into `line_idx`.
+    fn track_inserted_lines(&mut self, line_idx: usize, new_lines: usize) {
+        self.counts.take();
+        self.auto_pair_closers.clear();
+        self.dirty_lines.split_lines(line_idx, new_lines);
+        self.changed_lines.split_lines(line_idx, new_lines);
+        self.line_renders.split_lines(line_idx, new_lines);
+        self.marks.split_lines(line_idx, new_lines);
+        self.folds.split_lines(line_idx, new_lines);
+        if new_lines > 0 {
+            self.line_shifts.push(LineShift::Split {
+                line_idx,
+                new_lines,
+            });
+        }
+    }
+
+    /// Like `Self::track_edit_op`, for the `removed_lines` lines
+    /// after `line_idx` that were just joined onto it.
+    fn track_removed_lines(&mut self, line_idx: usize, removed_lines: usize) {
+        self.counts.take();
+        self.auto_pair_closers.clear();
+        self.dirty_lines.join_lines(line_idx, removed_lines);
+        self.changed_lines.join_lines(line_idx, removed_lines);
+        self.line_renders.join_lines(line_idx, removed_lines);
+        self.marks.join_lines(line_idx, removed_lines);
+        self.folds.join_lines(line_idx, removed_lines);
+        if removed_lines > 0 {
+            self.line_shifts.push(LineShift::Join {
+                line_idx,
+                removed_lines,
+            });
+        }
+    }
+
+    /// Apply `op` without recording it, returning whether it was applied.
+    fn apply_edit_op(&mut self, op: &EditOp) -> bool {
+        let is_applied = match op {
+            EditOp::InsertChar { pos, ch } => {
+                self.contents.insert_character_at_pos(*pos, *ch).is_ok()
+            }
+            EditOp::InsertNewline { pos } => {
+                self.contents.insert_character_at_pos(*pos, '\n').is_ok()
+            }
+            EditOp::RemoveChar { pos, .. } | EditOp::JoinLine { pos } => {
+                self.contents.remove_character_at_pos(*pos).is_ok()
+            }
+            EditOp::InsertStr { pos, text } => self.contents.insert_str_at_pos(*pos, text).is_ok(),
+            EditOp::RemoveStr { start, text } => self
+                .contents
+                .remove_range(*start, get_pos_after_str(*start, text))
+                .is_ok(),
+        };
+        if is_applied {
+            self.track_edit_op(op);
+        }
+        is_applied
+    }
+
+    /// End the current group of changes, so that the changes made since
+    /// the last group are undone together. This is usually called after
+    /// each command, consecutive typed characters are grouped regardless.
+    pub fn end_undo_group(&mut self) {
+        self.undo_history.end_group(self.caret_pos);
+    }
+
+    /// Revert the last group of changes, moving the caret back to
+    /// where it was before them. Returns whether there was any.
+    pub fn undo(&mut self) -> bool {
+        if self.read_only {
+            return false;
+        }
+
+        self.end_undo_group();
+        let Some(group) = self.undo_history.undo() else {
+            return false;
+        };
+
+        for op in group.ops.into_iter().rev() {
+            if !self.apply_edit_op(&op.inverse()) {
+                break;
+            }
+        }
+        self.is_dirty = true;
+        self.change_caret_xy(group.caret_before);
+        true
+    }
+
+    /// Apply the last group of changes that was undone again, moving
+    /// the caret to where it was after them. Returns whether there was any.
+    pub fn redo(&mut self) -> bool {
+        if self.read_only {
+            return false;
+        }
+
+        self.end_undo_group();
+        let Some(group) = self.undo_history.redo() else {
+            return false;
+        };
+
+        for op in &group.ops {
+            if !self.apply_edit_op(op) {
+                break;
+            }
+        }
+        self.is_dirty = true;
+        self.change_caret_xy(group.caret_after);
+        true
+    }
+
+    /// Insert `text` at `pos`, returning the position right after it.
+    fn insert_str(
+        &mut self,
+        pos: TextBufferPos,
+        text: &str,
+    ) -> Result<TextBufferPos, InsertCharError> {
+        if text.is_empty() {
+            return Ok(pos);
+        }
+        if pos.line == self.contents.total_lines() && pos.line > 0 {
+            // break the last line first, see `Self::insert_char_at`
+            self.insert_char_at(pos, '\n')?;
+        }
+
+        let end = self.contents.insert_str_at_pos(pos, text)?;
+        self.is_dirty = true;
+        self.track_inserted_lines(pos.line, end.line.saturating_sub(pos.line));
+        self.undo_history.record(
+            EditOp::InsertStr {
+                pos,
+                text: text.to_string(),
+            },
+            self.caret_pos,
+        );
+
+        Ok(end)
+    }
+
+    /// Insert `text` (which can span multiple lines) at the caret, moving
+    /// the caret to the end of the inserted text.
+    ///
+    /// Returns where the text was inserted.
+    pub fn insert_str_at_cursor(
+        &mut self,
+        text: &str,
+    ) -> Result<Range<TextBufferPos>, InsertCharError> {
+        if self.read_only {
+            return Err(InsertCharError::ReadOnly);
+        }
+        if self.single_line_mode && text.contains('\n') {
+            return Err(InsertCharError::InvalidLinePosition);
+        }
+
+        let start = self.caret_buffer_pos();
+        let end = self.insert_str(start, text)?;
+        self.move_cursor_to_buffer_pos(end);
+        Ok(start..end)
+    }
+
+    /// Remove the text from `start` to `end`, returning what was removed.
+    fn remove_range(&mut self, start: TextBufferPos, end: TextBufferPos) -> String {
+        let removed = self.get_text_between(start, end);
+        if removed.is_empty() || self.contents.remove_range(start, end).is_err() {
+            return String::new();
+        }
+
+        self.is_dirty = true;
+        self.track_removed_lines(start.line, end.line.saturating_sub(start.line));
+        self.undo_history.record(
+            EditOp::RemoveStr {
+                start,
+                text: removed.clone(),
+            },
+            self.caret_pos,
+        );
+
+        removed
+    }
+
+    /// Remove the text from the caret to the end of its line. If the
+    /// caret is already at the end, the line break is removed instead,
+    /// joining the next line onto the caret's line.
+    ///
+    /// Returns the removed text, or `None` if there was nothing to remove.
+    pub fn delete_to_end_of_line(&mut self) -> Option<String> {
+        if self.read_only {
+            return None;
+        }
+
+        let start = self.caret_buffer_pos();
+        let line_len = self.contents.line(start.line)?.len();
+
+        let end = if start.byte < line_len {
+            TextBufferPos {
+                line: start.line,
+                byte: line_len,
+            }
+        } else if !self.single_line_mode && start.line.saturating_add(1) < self.get_total_lines() {
+            TextBufferPos {
+                line: start.line.saturating_add(1),
+                byte: 0,
+            }
+        } else {
+            return None;
+        };
+
+        let removed = self.remove_range(start, end);
+        self.previous_line_caret_max_x.take();
+        self.selection_anchor.take();
+        Some(removed)
+    }
+
+    /// Remove the text from the start of the caret's line to the caret.
+    ///
+    /// Returns the removed text, or `None` if there was nothing to remove.
+    pub fn delete_to_start_of_line(&mut self) -> Option<String> {
+        if self.read_only {
+            return None;
+        }
+
+        let end = self.caret_buffer_pos();
+        if end.byte == 0 {
+            return None;
+        }
+
+        let removed = self.remove_range(
+            TextBufferPos {
+                line: end.line,
+                byte: 0,
+            },
+            end,
+        );
+        self.change_caret_x(0);
+        Some(removed)
+    }
+
+    /// Remove the caret's whole line including its line break, moving the
+    /// caret to the start of the next line (or of the previous line, if
+    /// the last line was removed). The only line is emptied instead.
+    ///
+    /// Returns the removed text, or `None` if there was nothing to remove.
+    pub fn delete_current_line(&mut self) -> Option<String> {
+        if self.read_only {
+            return None;
+        }
+
+        let line_idx = self.caret_pos.y.to_usize();
+        let line_len = self.contents.line_len(line_idx)?;
+        let line_end = TextBufferPos {
+            line: line_idx,
+            byte: line_len,
+        };
+
+        let (start, end) = if line_idx.saturating_add(1) < self.get_total_lines() {
+            (
+                TextBufferPos {
+                    line: line_idx,
+                    byte: 0,
+                },
+                TextBufferPos {
+                    line: line_idx.saturating_add(1),
+                    byte: 0,
+                },
+            )
+        } else if let Some(previous_line_idx) = line_idx.checked_sub(1) {
+            (
+                TextBufferPos {
+                    line: previous_line_idx,
+                    byte: self.contents.line_len(previous_line_idx)?,
+                },
+                line_end,
+            )
+        } else if line_len > 0 {
+            (TextBufferPos { line: 0, byte: 0 }, line_end)
+        } else {
+            return None;
+        };
+
+        let removed = self.remove_range(start, end);
+        self.change_caret_xy(Vec2u {
+            x: 0,
+            y: start.line.to_u64(),
+        });
+        Some(removed)
+    }
+
+    /// Remove the text from the caret to the start of the word before it,
+    /// see `Self::move_cursor_word_left`. At the start of a line, the line
+    /// break is removed instead, joining the line onto the previous line.
+    ///
+    /// Returns the removed text, or `None` if there was nothing to remove.
+    pub fn erase_word_before_cursor(&mut self) -> Option<String> {
+        if self.read_only {
+            return None;
+        }
+
+        let end = self.caret_buffer_pos();
+        self.move_cursor_word_left();
+        let start = self.caret_buffer_pos();
+        if start >= end {
+            return None;
+        }
+
+        let removed = self.remove_range(start, end);
+        self.move_cursor_to_buffer_pos(start);
+        Some(removed)
+    }
+
+    /// Remove the text from the caret to the end of the word after it,
+    /// see `Self::move_cursor_word_right`. At the end of a line, the line
+    /// break is removed instead, joining the next line onto the line.
+    ///
+    /// Returns the removed text, or `None` if there was nothing to remove.
+    pub fn erase_word_after_cursor(&mut self) -> Option<String> {
+        if self.read_only {
+            return None;
+        }
+
+        let start = self.caret_buffer_pos();
+        self.move_cursor_word_right();
+        let end = self.caret_buffer_pos();
+        self.move_cursor_to_buffer_pos(start);
+        if end <= start {
+            return None;
+        }
+
+        Some(self.remove_range(start, end))
+    }
+
+    /// Whether `pos` is at the start of a character (or the end of a line)
+    /// in the contents. The start of the line after the last line is also
+    /// valid, as that is where text can be appended to.
+    fn is_valid_buffer_pos(&self, pos: TextBufferPos) -> bool {
+        match self.contents.line(pos.line) {
+            Some(line) => line.is_char_boundary(pos.byte),
+            None => pos.line == self.contents.total_lines() && pos.byte == 0,
+        }
+    }
+
+    /// Apply `edit`, returning the edit that reverts it, or
+    /// `None` (without changing anything) if it is invalid.
+    fn apply_edit(&mut self, edit: Edit) -> Option<Edit> {
+        match edit {
+            Edit::Insert { pos, text } => {
+                if !self.is_valid_buffer_pos(pos) {
+                    return None;
+                }
+
+                let end = self.insert_str(pos, &text).ok()?;
+                Some(Edit::Remove { start: pos, end })
+            }
+            Edit::Remove { start, end } => {
+                if start > end || !self.is_valid_buffer_pos(start) || !self.is_valid_buffer_pos(end)
+                {
+                    return None;
+                }
+
+                let text = self.remove_range(start, end);
+                Some(Edit::Insert { pos: start, text })
+            }
+        }
+    }
+
+    /// Apply `edits` as a single transaction: each edit is applied to the
+    /// contents as left by the edits before it, and if any of them is
+    /// invalid (or the text box is read-only), none of them are applied.
+    ///
+    /// Returns the edits that revert the whole transaction, which
+    /// can be applied as a transaction of their own.
+    pub fn apply_edits(&mut self, edits: Vec<Edit>) -> Result<Vec<Edit>, EditError> {
+        if self.read_only {
+            return Err(EditError::ReadOnly);
+        }
+
+        let caret_pos = self.caret_buffer_pos();
+        // reverting the applied edits would otherwise leave the
+        // text box dirty, with the reverted edits recorded for undoing
+        let is_dirty = self.is_dirty;
+        let dirty_lines = self.dirty_lines.clone();
+        let marks = self.marks.clone();
+        let folds = self.folds.clone();
+        let line_shifts_len = self.line_shifts.len();
+        let auto_pair_closers = self.auto_pair_closers.clone();
+        let pending_undo_len = self.undo_history.pending_len();
+
+        let mut revert_edits = vec![];
+        for (edit_idx, edit) in edits.into_iter().enumerate() {
+            if let Some(revert_edit) = self.apply_edit(edit) {
+                revert_edits.push(revert_edit);
+            } else {
+                for revert_edit in revert_edits.into_iter().rev() {
+                    self.apply_edit(revert_edit);
+                }
+                self.is_dirty = is_dirty;
+                self.dirty_lines = dirty_lines;
+                self.marks = marks;
+                self.folds = folds;
+                self.line_shifts.truncate(line_shifts_len);
+                self.auto_pair_closers = auto_pair_closers;
+                self.undo_history.truncate_pending(pending_undo_len);
+                return Err(EditError::InvalidEdit { edit_idx });
+            }
+        }
+        revert_edits.reverse();
+
+        // the caret stays where it was in the buffer, as far as possible
+        self.move_cursor_to_buffer_pos(caret_pos);
+        Ok(revert_edits)
+    }
+
+    /// Get the selected range in the buffer, see `Self::selection_range`.
+    pub fn selection_buffer_range(&self) -> Option<Range<TextBufferPos>> {
+        let range = self.selection_range()?;
+        Some(
+            self.get_clamped_buffer_pos_from_caret_pos(range.start)?
+                ..self.get_clamped_buffer_pos_from_caret_pos(range.end)?,
+        )
+    }
+
+    /// Select from `anchor` to `caret` in the buffer, moving the
+    /// caret to `caret`. See `Self::move_cursor_to_buffer_pos` for
+    /// how positions beyond the contents are handled.
+    pub fn set_selection_buffer_range(&mut self, anchor: TextBufferPos, caret: TextBufferPos) {
+        self.set_selection(
+            self.get_caret_pos_from_buffer_pos(anchor),
+            self.get_caret_pos_from_buffer_pos(caret),
+        );
+    }
+
+    /// Insert a copy of the selection right after it, and select the copy.
+    /// If nothing is selected, the caret's line is duplicated instead, with
+    /// the caret moving to the copy.
+    ///
+    /// Returns whether anything was duplicated.
+    pub fn duplicate_selection(&mut self) -> bool {
+        if self.read_only {
+            return false;
+        }
+
+        if let Some(range) = self.selection_range() {
+            let (Some(start), Some(end)) = (
+                self.get_clamped_buffer_pos_from_caret_pos(range.start),
+                self.get_clamped_buffer_pos_from_caret_pos(range.end),
+            ) else {
+                return false;
+            };
+
+            let text = self.get_text_between(start, end);
+            let Ok(copy_end) = self.insert_str(end, &text) else {
+                return false;
+            };
+            self.set_selection(
+                self.get_caret_pos_from_buffer_pos(end),
+                self.get_caret_pos_from_buffer_pos(copy_end),
+            );
+            true
+        } else {
+            let line_idx = self.caret_pos.y.to_usize();
+            let Some(line) = self.contents.line(line_idx) else {
+                return false;
+            };
+            if self.single_line_mode {
+                return false;
+            }
+
+            let line_end = TextBufferPos {
+                line: line_idx,
+                byte: line.len(),
+            };
+            if self.insert_str(line_end, &format!("\n{line}")).is_err() {
+                return false;
+            }
+            self.change_caret_y(self.caret_pos.y.saturating_add(1));
+            true
+        }
+    }
+
+    /// Swap `line_idx` with the line after it.
+    ///
+    /// Returns whether the lines were swapped.
+    fn swap_with_next_line(&mut self, line_idx: usize) -> bool {
+        let next_line_idx = line_idx.saturating_add(1);
+        let (Some(line_len), Some(next_line)) = (
+            self.contents.line_len(line_idx),
+            self.contents.line(next_line_idx),
+        ) else {
+            return false;
+        };
+        if self.single_line_mode {
+            return false;
+        }
+
+        // the next line (with the line break before it) is moved before the line
+        self.remove_range(
+            TextBufferPos {
+                line: line_idx,
+                byte: line_len,
+            },
+            TextBufferPos {
+                line: next_line_idx,
+                byte: next_line.len(),
+            },
+        );
+        self.insert_str(
+            TextBufferPos {
+                line: line_idx,
+                byte: 0,
+            },
+            &format!("{next_line}\n"),
+        )
+        .is_ok()
+    }
+
+    /// Swap the caret's line with the line before it, keeping the caret
+    /// on the moved line. Does nothing on the first line.
+    ///
+    /// Returns whether the line was moved.
+    pub fn move_line_up(&mut self) -> bool {
+        if self.read_only {
+            return false;
+        }
+
+        let Some(line_idx) = self.caret_pos.y.to_usize().checked_sub(1) else {
+            return false;
+        };
+        if !self.swap_with_next_line(line_idx) {
+            return false;
+        }
+
+        self.change_caret_xy(Vec2u {
+            x: self.caret_pos.x,
+            y: self.caret_pos.y.saturating_sub(1),
+        });
+        true
+    }
+
+    /// Swap the caret's line with the line after it, keeping the caret
+    /// on the moved line. Does nothing on the last line.
+    ///
+    /// Returns whether the line was moved.
+    pub fn move_line_down(&mut self) -> bool {
+        if self.read_only {
+            return false;
+        }
+
+        if !self.swap_with_next_line(self.caret_pos.y.to_usize()) {
+            return false;
+        }
+
+        self.change_caret_xy(Vec2u {
+            x: self.caret_pos.x,
+            y: self.caret_pos.y.saturating_add(1),
+        });
+        true
+    }
+
+    /// Join the line after the caret's line onto it, replacing the
+    /// indentation of the joined line with a single space (like `J` in Vim).
+    /// No space is added if either side of the join is empty or the caret's
+    /// line already ends with whitespace. The caret is moved to the join.
+    ///
+    /// Returns whether the lines were joined.
+    pub fn join_with_next_line(&mut self) -> bool {
+        if self.read_only || self.single_line_mode {
+            return false;
+        }
+
+        let line_idx = self.caret_pos.y.to_usize();
+        let next_line_idx = line_idx.saturating_add(1);
+        let (Some(line), Some(next_line)) = (
+            self.contents.line(line_idx),
+            self.contents.line(next_line_idx),
+        ) else {
+            return false;
+        };
+
+        let joined_text = next_line.trim_start();
+        let separator =
+            if line.is_empty() || line.ends_with(char::is_whitespace) || joined_text.is_empty() {
+                ""
+            } else {
+                " "
+            };
+        let join_pos = TextBufferPos {
+            line: line_idx,
+            byte: line.len(),
+        };
+        self.remove_range(
+            join_pos,
+            TextBufferPos {
+                line: next_line_idx,
+                byte: next_line.len().saturating_sub(joined_text.len()),
+            },
+        );
+        if self.insert_str(join_pos, separator).is_err() {
+            return false;
+        }
+
+        self.change_caret_xy(self.get_caret_pos_from_buffer_pos(join_pos));
+        true
+    }
+
+    /// The lines covered by the selection, or the caret's line if nothing
+    /// is selected. A selection ending at the start of a line does not
+    /// include that line.
+    pub fn selected_lines(&self) -> Range<usize> {
+        match self.selection_buffer_range() {
+            Some(range) if range.end.byte == 0 && range.end.line > range.start.line => {
+                range.start.line..range.end.line
+            }
+            Some(range) => range.start.line..range.end.line.saturating_add(1),
+            None => {
+                let line_idx = self.caret_pos.y.to_usize();
+                line_idx..line_idx.saturating_add(1)
+            }
+        }
+    }
+
+    /// Insert one level of `indent_style` indentation at the start of
+    /// each of `lines`, except for empty lines. The selection (or the
+    /// caret) stays on the same text.
+    ///
+    /// Returns whether any line was indented.
+    pub fn indent_lines(&mut self, lines: Range<usize>, indent_style: IndentStyle) -> bool {
+        if self.read_only {
+            return false;
+        }
+
+        let anchor = self.selection_anchor_buffer_pos();
+        let caret = self.caret_buffer_pos();
+        let indent_unit = indent_style.indent_unit();
+
+        let mut indented_lines = vec![];
+        for line_idx in lines {
+            if self.contents.line_len(line_idx).unwrap_or_default() == 0 {
+                continue;
+            }
+            let line_start = TextBufferPos {
+                line: line_idx,
+                byte: 0,
+            };
+            if self.insert_str(line_start, &indent_unit).is_ok() {
+                indented_lines.push(line_idx);
+            }
+        }
+
+        self.restore_selection(anchor, caret, |pos| {
+            if pos.byte > 0 && indented_lines.contains(&pos.line) {
+                TextBufferPos {
+                    line: pos.line,
+                    byte: pos.byte.saturating_add(indent_unit.len()),
+                }
+            } else {
+                pos
+            }
+        });
+        !indented_lines.is_empty()
+    }
+
+    /// Remove up to one level of `indent_style` indentation from the start
+    /// of each of `lines`, see `IndentStyle::leading_indent_len`. Only
+    /// leading whitespace is removed. The selection (or the caret) stays
+    /// on the same text.
+    ///
+    /// Returns whether any line was dedented.
+    pub fn dedent_lines(&mut self, lines: Range<usize>, indent_style: IndentStyle) -> bool {
+        if self.read_only {
+            return false;
+        }
+
+        let anchor = self.selection_anchor_buffer_pos();
+        let caret = self.caret_buffer_pos();
+
+        let mut dedented_lines = vec![];
+        for line_idx in lines {
+            let Some(line) = self.contents.line(line_idx) else {
+                continue;
+            };
+            let indent_len = indent_style.leading_indent_len(&line, self.render_options.tab_width);
+            if indent_len == 0 {
+                continue;
+            }
+
+            self.remove_range(
+                TextBufferPos {
+                    line: line_idx,
+                    byte: 0,
+                },
+                TextBufferPos {
+                    line: line_idx,
+                    byte: indent_len,
+                },
+            );
+            dedented_lines.push((line_idx, indent_len));
+        }
+
+        self.restore_selection(anchor, caret, |pos| {
+            match dedented_lines
+                .iter()
+                .find(|(line_idx, _)| *line_idx == pos.line)
+            {
+                Some(&(_, indent_len)) => TextBufferPos {
+                    line: pos.line,
+                    byte: pos.byte.saturating_sub(indent_len),
+                },
+                None => pos,
+            }
+        });
+        !dedented_lines.is_empty()
+    }
+
+    fn selection_anchor_buffer_pos(&self) -> Option<TextBufferPos> {
+        self.selection_anchor
+            .and_then(|anchor| self.get_clamped_buffer_pos_from_caret_pos(anchor))
+    }
+
+    /// Select from `anchor` to `caret` again (or move the caret back to
+    /// `caret`) after the text was changed, with `shift` giving where
+    /// each position's text moved to.
+    fn restore_selection(
+        &mut self,
+        anchor: Option<TextBufferPos>,
+        caret: TextBufferPos,
+        shift: impl Fn(TextBufferPos) -> TextBufferPos,
+    ) {
+        match anchor {
+            Some(anchor) => self.set_selection_buffer_range(shift(anchor), shift(caret)),
+            None => self.move_cursor_to_buffer_pos(shift(caret)),
+        }
+    }
+
+    /// Replace the selection with `text`, and select the inserted text.
+    ///
+    /// Returns what was selected, or `None` (without replacing anything)
+    /// if nothing is selected.
+    pub fn replace_selection(&mut self, text: &str) -> Option<String> {
+        if self.read_only {
+            return None;
+        }
+
+        let range = self.selection_range()?;
+        let start = self.get_clamped_buffer_pos_from_caret_pos(range.start)?;
+        let end = self.get_clamped_buffer_pos_from_caret_pos(range.end)?;
+
+        let removed = self.remove_range(start, end);
+        let text_end = self.insert_str(start, text).unwrap_or(start);
+        self.set_selection(
+            self.get_caret_pos_from_buffer_pos(start),
+            self.get_caret_pos_from_buffer_pos(text_end),
+        );
+
+        Some(removed)
+    }
+
+    fn get_text_between(&self, start: TextBufferPos, end: TextBufferPos) -> String {
+        (start.line..=end.line)
+            .filter_map(|line_idx| {
+                self.contents.line(line_idx).map(|line| {
+                    let start_byte = if line_idx == start.line {
+                        start.byte
+                    } else {
+                        0
+                    };
+                    let end_byte = if line_idx == end.line {
+                        end.byte
+                    } else {
+                        line.len()
+                    };
+
+                    line.get(start_byte..end_byte)
+                        .unwrap_or_default()
+                        .to_string()
+                })
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Returns the indices of the lines that are currently rendered
+    /// by the text box (some of which may be past the end of the buffer).
+    ///
+    /// Lines hidden by a fold within the range are included.
+    pub fn visible_line_range(&self) -> Range<usize> {
+        let end_row = self
+            .scroll_offset
+            .y
+            .saturating_add(self.text_bounds().size.y.lossy());
+        self.folds.get_line(self.scroll_offset.y).to_usize()
+            ..self.folds.get_line(end_row).to_usize()
+    }
+
+    /// Returns the columns that are currently rendered by the text box, where
+    /// a character can take up more than one column (e.g. a tab).
+    pub fn visible_columns(&self) -> Range<u64> {
+        self.scroll_offset.x
+            ..self
+                .scroll_offset
+                .x
+                .saturating_add(self.text_bounds().size.x.lossy())
+    }
+
+    /// Fold the lines in `hidden_lines`, so that they are no longer rendered.
+    /// Instead, a marker is shown after the line before them (the header).
+    ///
+    /// If the caret is on a hidden line, it is moved to the header.
+    pub fn fold_lines(&mut self, hidden_lines: Range<usize>) {
+        if self.single_line_mode {
+            return;
+        }
+
+        let hidden_lines = hidden_lines.start.to_u64()..hidden_lines.end.to_u64();
+        let header_line = hidden_lines.start.saturating_sub(1);
+        self.folds.add(hidden_lines.clone());
+
+        if hidden_lines.contains(&self.caret_pos.y) {
+            self.change_caret_xy(Vec2u {
+                x: self.get_line_len(header_line.to_usize()).to_u64(),
+                y: header_line,
+            });
+        } else {
+            self.adjust_scroll_to_caret_grid_pos();
+        }
+    }
+
+    /// Unfold the fold whose header is `line_idx`, returning
+    /// whether there was such a fold.
+    pub fn unfold_at(&mut self, line_idx: usize) -> bool {
+        let is_unfolded = self.folds.remove_at_header(line_idx.to_u64());
+        self.adjust_scroll_to_caret_grid_pos();
+        is_unfolded
+    }
+
+    /// Returns the ranges of the lines hidden by each fold, in order.
+    pub fn folded_lines(&self) -> Vec<Range<usize>> {
+        self.folds
+            .ranges()
+            .iter()
+            .map(|range| range.start.to_usize()..range.end.to_usize())
+            .collect()
+    }
+
+    pub fn clear_folds(&mut self) {
+        self.folds.clear();
+        self.adjust_scroll_to_caret_grid_pos();
+    }
+
+    /// Returns the line before `line_idx` that is not hidden by a fold.
+    fn get_previous_visible_line(&self, line_idx: u64) -> u64 {
+        let previous_line_idx = line_idx.saturating_sub(1);
+        self.folds
+            .get_containing(previous_line_idx)
+            .map_or(previous_line_idx, |fold| fold.start.saturating_sub(1))
+    }
+
+    /// Returns the line after `line_idx` that is not hidden by a fold.
+    fn get_next_visible_line(&self, line_idx: u64) -> u64 {
+        let next_line_idx = line_idx.saturating_add(1);
+        self.folds
+            .get_containing(next_line_idx)
+            .map_or(next_line_idx, |fold| fold.end)
+    }
+
+    /// Returns the render of `line_idx`, which is reused until the line changes.
+    fn get_line_render(&self, line_idx: usize) -> Option<Rc<TextLine>> {
+        self.line_renders.get_or_render(line_idx, || {
+            Some(TextLine::new(
+                self.contents.line(line_idx)?,
+                self.render_options,
+            ))
+        })
+    }
+
+    /// Render the lines of `line_idxs` that are not rendered yet, getting
+    /// each run of consecutive lines from the buffer at once.
+    fn prepare_line_renders(&self, line_idxs: &[usize]) {
+        let line_idxs: Vec<_> = line_idxs
+            .iter()
+            .copied()
+            .filter(|line_idx| !self.line_renders.contains(*line_idx))
+            .collect();
+        for run in line_idxs
+            .chunk_by(|line_idx, next_line_idx| line_idx.checked_add(1) == Some(*next_line_idx))
+        {
+            let (Some(&start), Some(&last)) = (run.first(), run.last()) else {
+                continue;
+            };
+            let lines = self.contents.lines_range(start..last.saturating_add(1));
+            for (line_idx, line) in (start..).zip(lines) {
+                self.line_renders
+                    .get_or_render(line_idx, || Some(TextLine::new(line, self.render_options)));
+            }
+        }
+    }
+
+    pub fn get_line_len(&self, line_idx: usize) -> usize {
+        self.get_line_render(line_idx)
+            .map_or(0, |line_render| line_render.get_line_len())
+    }
+
+    fn get_grid_pos_from_caret_pos(&self, caret_pos: Vec2u) -> Vec2u {
+        Vec2u {
+            x: self
+                .get_line_render(caret_pos.y.to_usize())
+                .map_or(0, |line_render| {
+                    line_render.get_line_text_width(caret_pos.x.to_usize())
+                }),
+            y: self.folds.get_visual_row(caret_pos.y),
+        }
+    }
+
+    /// Get the caret position that is rendered at `screen_pos`.
+    ///
+    /// Positions beyond the end of a line are clamped to the end of
+    /// that line, and positions below the last line are clamped to
+    /// the last line.
+    pub fn get_caret_pos_from_screen_pos(&self, screen_pos: Vec2f) -> Vec2u {
+        self.get_caret_pos_from_grid_pos(get_grid_pos_from_screen_pos(
+            self.text_bounds(),
+            self.scroll_offset,
+            screen_pos,
+            false,
+        ))
+    }
+
+    fn get_caret_pos_from_grid_pos(&self, grid_pos: Vec2u) -> Vec2u {
+        let y = if self.single_line_mode {
+            0
+        } else {
+            self.folds
+                .get_line(grid_pos.y)
+                .min(self.get_total_lines().saturating_sub(1).to_u64())
+        };
+
+        Vec2u {
+            x: self
+                .get_line_render(y.to_usize())
+                .map_or(0, |line_render| {
+                    line_render.get_fragment_idx_from_line_text_width(grid_pos.x)
+                })
+                .to_u64(),
+            y,
+        }
+    }
+
+    pub fn move_cursor_to_screen_pos(&mut self, screen_pos: Vec2f) {
+        self.change_caret_xy(self.get_caret_pos_from_screen_pos(screen_pos));
+    }
+
+    fn adjust_scroll_to_caret_grid_pos(&mut self) {
+        let mut grid_cursor_pos = self.get_grid_pos_from_caret_pos(self.caret_pos);
+        if self.render_options.sticky_horizontal_scroll
+            && let Some(previous_x) = self.previous_line_caret_max_x
+        {
+            // the caret is held back at the end of a shorter line, so follow
+            // the column it returns to, as if the line went on past its end
+            grid_cursor_pos.x = grid_cursor_pos
+                .x
+                .saturating_add(previous_x.saturating_sub(self.caret_pos.x));
+        }
+
+        if grid_cursor_pos.x < self.scroll_offset.x {
+            self.scroll_offset.x = grid_cursor_pos.x;
+        }
+
+        if grid_cursor_pos.y < self.scroll_offset.y {
+            self.scroll_offset.y = grid_cursor_pos.y;
+        }
+
+        if grid_cursor_pos.x >= self.visible_columns().end {
+            self.scroll_offset.x = grid_cursor_pos
+                .x
+                .saturating_sub(self.text_bounds().size.x.lossy())
+                .saturating_add(1);
+        }
+
+        if grid_cursor_pos.y
+            >= self
+                .scroll_offset
+                .y
+                .saturating_add(self.text_bounds().size.y.lossy())
+        {
+            self.scroll_offset.y = grid_cursor_pos
+                .y
+                .saturating_sub(self.text_bounds().size.y.lossy())
+                .saturating_add(1);
+        }
+    }
+
+    /// See `Self::previous_line_caret_max_x` for more details about the purpose
+    /// of this function.
+    fn adjust_caret_x_on_caret_y_movement(&mut self) {
+        let line_len = self.get_line_len(self.caret_pos.y.to_usize()).to_u64();
+
+        if self.caret_pos.x > line_len {
+            // x is not on a valid position, move it back
+            if self.previous_line_caret_max_x.is_none() {
+                self.previous_line_caret_max_x = Some(self.caret_pos.x);
+            }
+            self.caret_pos.x = line_len;
+        } else {
+            // check to see if we have previous memory of x
+            if let Some(previous_x) = self.previous_line_caret_max_x {
+                if previous_x > line_len {
+                    // previous entry still too far out...
+                    self.caret_pos.x = line_len;
+                } else {
+                    self.caret_pos.x = previous_x;
+                    self.previous_line_caret_max_x = None;
+                }
+            }
+        }
+    }
+
+    // NOTE: all of the change_caret_* functions collapse the selection,
+    // callers that want to keep the selection have to restore the anchor
+    fn change_caret_x(&mut self, new_x: u64) {
+        self.caret_pos.x = new_x;
+        self.previous_line_caret_max_x.take();
+        self.adjust_scroll_to_caret_grid_pos();
+        self.selection_anchor.take();
+    }
+
+    fn change_caret_y(&mut self, new_y: u64) {
+        self.folds.remove_containing(new_y);
+        self.caret_pos.y = new_y;
+        self.adjust_caret_x_on_caret_y_movement();
+        self.adjust_scroll_to_caret_grid_pos();
+        self.selection_anchor.take();
+    }
+
+    fn change_caret_xy(&mut self, new_pos: Vec2u) {
+        self.folds.remove_containing(new_pos.y);
+        self.caret_pos = new_pos;
+        self.previous_line_caret_max_x.take();
+        self.adjust_scroll_to_caret_grid_pos();
+        self.selection_anchor.take();
+    }
+
+    pub fn move_cursor_up(&mut self) {
+        if self.single_line_mode {
+            self.change_caret_y(0);
+        } else {
+            self.change_caret_y(self.get_previous_visible_line(self.caret_pos.y));
+        }
+    }
+
+    pub fn move_cursor_down(&mut self) {
+        if self.single_line_mode {
+            self.change_caret_y(0);
+        } else {
+            self.change_caret_y(
+                self.get_next_visible_line(self.caret_pos.y)
+                    .clamp(0, self.get_total_lines().to_u64()),
+            );
+        }
+    }
+
+    pub fn move_cursor_left(&mut self) {
+        if self.caret_pos.x == 0 {
+            if self.caret_pos.y > 0 && !self.single_line_mode {
+                let previous_line_idx = self.get_previous_visible_line(self.caret_pos.y);
+                self.change_caret_xy(Vec2u {
+                    x: self.get_line_len(previous_line_idx.to_usize()).to_u64(),
+                    y: previous_line_idx,
+                });
+            } else {
+                self.previous_line_caret_max_x.take();
+                self.selection_anchor.take();
+            }
+        } else {
+            self.change_caret_x(self.caret_pos.x.saturating_sub(1));
+        }
+    }
+
+    pub fn move_cursor_right(&mut self) {
+        let line_len = self.get_line_len(self.caret_pos.y.to_usize()).to_u64();
+
+        if self.caret_pos.x == line_len {
+            if self.caret_pos.y < self.get_total_lines().to_u64() && !self.single_line_mode {
+                self.change_caret_xy(Vec2u {
+                    x: 0,
+                    y: self
+                        .get_next_visible_line(self.caret_pos.y)
+                        .min(self.get_total_lines().to_u64()),
+                });
+            } else {
+                self.previous_line_caret_max_x.take();
+                self.selection_anchor.take();
+            }
+        } else {
+            self.change_caret_x(self.caret_pos.x.saturating_add(1));
+        }
+    }
+
+    /// Move the caret to the start of the word before it, skipping any
+    /// whitespace. At the start of a line, it moves to the end of the
+    /// previous line instead.
+    pub fn move_cursor_word_left(&mut self) {
+        if self.caret_pos.x == 0 {
+            self.move_cursor_left();
+            return;
+        }
+
+        let caret_x = self.caret_pos.x.to_usize();
+        let word_start = self
+            .get_line_render(self.caret_pos.y.to_usize())
+            .map(|line_render| line_render.get_words())
+            .and_then(|words| {
+                words
+                    .into_iter()
+                    .rev()
+                    .find(|word| word.range.start < caret_x && !word.is_whitespace)
+            })
+            .map_or(0, |word| word.range.start);
+        self.change_caret_x(word_start.to_u64());
+    }
+
+    /// Move the caret to the end of the word after it, skipping any
+    /// whitespace. At the end of a line, it moves to the start of the
+    /// next line instead.
+    pub fn move_cursor_word_right(&mut self) {
+        let line_len = self.get_line_len(self.caret_pos.y.to_usize());
+        let caret_x = self.caret_pos.x.to_usize();
+        if caret_x >= line_len {
+            self.move_cursor_right();
+            return;
+        }
+
+        let word_end = self
+            .get_line_render(self.caret_pos.y.to_usize())
+            .map(|line_render| line_render.get_words())
+            .and_then(|words| {
+                words
+                    .into_iter()
+                    .find(|word| word.range.end > caret_x && !word.is_whitespace)
+            })
+            .map_or(line_len, |word| word.range.end);
+        self.change_caret_x(word_end.to_u64());
+    }
+
+    /// Move the caret to `visual_row`, scrolling the view by as many rows
+    /// as the caret moves, so that the caret stays on the same row of the
+    /// view unless the view cannot scroll that far.
+    fn page_caret_to_visual_row(&mut self, visual_row: u64) {
+        let caret_row = self.folds.get_visual_row(self.caret_pos.y);
+        self.scroll_to(Vec2u {
+            x: self.scroll_offset.x,
+            y: self
+                .scroll_offset
+                .y
+                .saturating_add(visual_row)
+                .saturating_sub(caret_row),
+        });
+        self.change_caret_y(self.folds.get_line(visual_row));
+    }
+
+    pub fn move_cursor_up_one_page(&mut self) {
+        if self.single_line_mode {
+            self.change_caret_y(0);
+        } else {
+            self.page_caret_to_visual_row(
+                self.folds
+                    .get_visual_row(self.caret_pos.y)
+                    .saturating_sub(self.text_bounds().size.y.lossy()),
+            );
+        }
+    }
+
+    pub fn move_cursor_down_one_page(&mut self) {
+        if self.single_line_mode {
+            self.change_caret_y(0);
+        } else {
+            let last_visual_row = self.folds.get_visual_row(self.get_total_lines().to_u64());
+            self.page_caret_to_visual_row(
+                self.folds
+                    .get_visual_row(self.caret_pos.y)
+                    .saturating_add(self.text_bounds().size.y.lossy())
+                    .min(last_visual_row),
+            );
+        }
+    }
+
+    pub fn move_cursor_to_start_of_line(&mut self) {
+        self.change_caret_x(0);
+    }
+
+    /// Move the caret to the first character of its line that is
+    /// not a space or tab, or to the end of the line if there is none.
+    pub fn move_cursor_to_first_non_whitespace(&mut self) {
+        let first_non_whitespace = self
+            .get_line_render(self.caret_pos.y.to_usize())
+            .map_or(0, |line_render| line_render.get_leading_whitespace_end());
+        self.change_caret_x(first_non_whitespace.to_u64());
+    }
+
+    pub fn move_cursor_to_end_of_line(&mut self) {
+        self.change_caret_x(self.get_line_len(self.caret_pos.y.to_usize()).to_u64());
+    }
+
+    /// If the caret is on a bracket, returns its position and the position
+    /// of the bracket that matches it, if any.
+    ///
+    /// Only the brackets of the same kind are counted for nesting, and
+    /// brackets in strings or comments are counted like any other.
+    pub fn matching_bracket(&self) -> Option<(TextBufferPos, TextBufferPos)> {
+        let pos = self.caret_buffer_pos();
+        let line = self.contents.line(pos.line)?;
+        let bracket = line.get(pos.byte..)?.chars().next()?;
+        let (&(opening, closing), forward) = BRACKET_PAIRS.iter().find_map(|pair| {
+            if pair.0 == bracket {
+                Some((pair, true))
+            } else if pair.1 == bracket {
+                Some((pair, false))
+            } else {
+                None
+            }
+        })?;
+
+        // how many brackets (including the caret's) are still unmatched
+        let mut depth = 0usize;
+        let mut is_match = |ch: char| {
+            if ch == bracket {
+                depth = depth.saturating_add(1);
+            } else if ch == opening || ch == closing {
+                depth = depth.saturating_sub(1);
+            }
+            depth == 0
+        };
+
+        let found = if forward {
+            (pos.line..self.contents.total_lines()).find_map(|line_idx| {
+                let line = self.contents.line(line_idx)?;
+                let start = if line_idx == pos.line { pos.byte } else { 0 };
+                line[start..]
+                    .char_indices()
+                    .find(|(_, ch)| is_match(*ch))
+                    .map(|(byte, _)| TextBufferPos {
+                        line: line_idx,
+                        byte: start.saturating_add(byte),
+                    })
+            })
+        } else {
+            (0..=pos.line).rev().find_map(|line_idx| {
+                let line = self.contents.line(line_idx)?;
+                let end = if line_idx == pos.line {
+                    pos.byte.saturating_add(bracket.len_utf8())
+                } else {
+                    line.len()
+                };
+                line[..end]
+                    .char_indices()
+                    .rev()
+                    .find(|(_, ch)| is_match(*ch))
+                    .map(|(byte, _)| TextBufferPos {
+                        line: line_idx,
+                        byte,
+                    })
+            })
+        };
+
+        found.map(|found| (pos, found))
+    }
+
+    /// Move the caret to the bracket that matches the one it is on,
+    /// see `Self::matching_bracket`. Returns whether it moved.
+    pub fn jump_to_matching_bracket(&mut self) -> bool {
+        let Some((_, found)) = self.matching_bracket() else {
+            return false;
+        };
+
+        self.move_cursor_to_buffer_pos(found);
+        true
+    }
+
+    pub fn move_cursor_to_start_of_buffer(&mut self) {
+        self.change_caret_xy(Vec2u::ZERO);
+    }
+
+    pub fn move_cursor_to_end_of_buffer(&mut self) {
+        if self.single_line_mode {
+            self.move_cursor_to_end_of_line();
+        } else {
+            let last_line_idx = self.get_total_lines().saturating_sub(1);
+            self.change_caret_xy(Vec2u {
+                x: self.get_line_len(last_line_idx).to_u64(),
+                y: last_line_idx.to_u64(),
+            });
+        }
+    }
+
+    pub fn insert_character_at_cursor(
+        &mut self,
+        ch: char,
+    ) -> Result<InsertCharResult, InsertCharError> {
+        if self.read_only {
+            return Err(InsertCharError::ReadOnly);
+        }
+
+        if ch != '\n' && self.block_selection().is_some() {
+            // the same character is typed on each line of the block
+            return Ok(InsertCharResult {
+                line_len_increased: self
+                    .replace_in_block_selection(&ch.to_string(), |_, fragments| Some(fragments)),
+            });
+        }
+
+        let auto_pair_closer = if self.auto_pair && !self.single_line_mode && !self.overwrite_mode {
+            let caret = self.caret_buffer_pos();
+            if self.auto_pair_closers.contains(&caret) && self.char_at(caret) == Some(ch) {
+                // type over the closing character instead of inserting another one
+                self.auto_pair_closers.retain(|closer| *closer != caret);
+                self.change_caret_x(self.caret_pos.x.saturating_add(1));
+                return Ok(InsertCharResult {
+                    line_len_increased: false,
+                });
+            }
+            self.auto_pair_closer_at(caret, ch)
+        } else {
+            None
+        };
+
+        let mut target_line_render = if self.caret_pos.y == self.contents.total_lines().to_u64() {
+            Rc::new(TextLine::new("", self.render_options))
+        } else {
+            match self.get_line_render(self.caret_pos.y.to_usize()) {
+                Some(line_render) => line_render,
+                None => return Err(InsertCharError::InvalidLinePosition),
+            }
+        };
+
+        // TODO: This logic can be further refactored (it is copied and slightly modified across multiple functions)
+        let buffer_pos = TextBufferPos {
+            line: self.caret_pos.y.to_usize(),
+            byte: match target_line_render
+                .get_byte_idx_from_fragment_idx(self.caret_pos.x.to_usize())
+            {
+                Some(column) => column,
+                None => return Err(InsertCharError::InvalidBytePosition),
+            },
+        };
+
+        if self.overwrite_mode
+            && ch != '\n'
+            && let Some(grapheme_end_byte) = target_line_render
+                .get_byte_idx_from_fragment_idx(self.caret_pos.x.saturating_add(1).to_usize())
+        {
+            // remove the entire grapheme under the caret, which may consist of multiple chars
+            let grapheme_chars_count = target_line_render.to_string()
+                [buffer_pos.byte..grapheme_end_byte]
+                .chars()
+                .count();
+            for _ in 0..grapheme_chars_count {
+                self.remove_char_at(buffer_pos)
+                    .map_err(|_| InsertCharError::InvalidBytePosition)?;
+            }
+
+            target_line_render = self
+                .get_line_render(buffer_pos.line)
+                .expect("line to exist since we just modified it");
+        }
+
+        self.insert_char_at(buffer_pos, ch)?;
+        if let Some(closer) = auto_pair_closer {
+            let closer_pos = TextBufferPos {
+                line: buffer_pos.line,
+                byte: buffer_pos.byte.saturating_add(ch.len_utf8()),
+            };
+            self.insert_char_at(closer_pos, closer)?;
+            self.auto_pair_closers.push(closer_pos);
+        }
+        let line_len_increased = self
+            .get_line_render(self.caret_pos.y.to_usize())
+            .expect("line to exist since we just modified it")
+            .get_line_len()
+            > target_line_render.get_line_len();
+
+        self.is_dirty = true;
+        if line_len_increased {
+            self.change_caret_x(self.caret_pos.x.saturating_add(1));
+        }
+
+        Ok(InsertCharResult { line_len_increased })
+    }
+
+    /// The character at `pos`, or `None` if `pos` is at the end of its line.
+    fn char_at(&self, pos: TextBufferPos) -> Option<char> {
+        self.contents
+            .line(pos.line)?
+            .get(pos.byte..)?
+            .chars()
+            .next()
+    }
+
+    /// The closing character to insert after typing `ch` at `pos`, if `ch`
+    /// opens a pair. Nothing is inserted before a word, which is more likely
+    /// to be wrapped by the pair, nor after a quote typed right after a word,
+    /// which is more likely an apostrophe.
+    fn auto_pair_closer_at(&self, pos: TextBufferPos, ch: char) -> Option<char> {
+        let closer = auto_pair_closer(ch)?;
+        let line = self.contents.line(pos.line).unwrap_or_default();
+        let (before, after) = line.split_at_checked(pos.byte)?;
+        if after.chars().next().is_some_and(is_word_char)
+            || (ch == closer && before.chars().next_back().is_some_and(is_word_char))
+        {
+            return None;
+        }
+        Some(closer)
+    }
+
+    fn remove_character(
+        &mut self,
+        line_idx: usize,
+        fragment_idx: usize,
+    ) -> Result<RemoveCharResult, RemoveCharError> {
+        let Some(target_line_render) = self.get_line_render(line_idx) else {
+            return Err(RemoveCharError::InvalidLinePosition);
+        };
+
+        // TODO: This logic can be further refactored (it is copied and slightly modified across multiple functions)
+        let buffer_pos = TextBufferPos {
+            line: line_idx,
+            byte: match target_line_render.get_byte_idx_from_fragment_idx(fragment_idx) {
+                Some(byte) => byte,
+                None => return Err(RemoveCharError::InvalidBytePosition),
+            },
+        };
+
+        self.remove_char_at(buffer_pos)?;
+        self.is_dirty = true;
+
+        if let Some(new_line_render) = self.get_line_render(line_idx) {
+            Ok(RemoveCharResult {
+                line_len_decreased: new_line_render.get_line_len()
+                    < target_line_render.get_line_len(),
+            })
+        } else {
+            Ok(RemoveCharResult {
+                line_len_decreased: true,
+            })
+        }
+    }
+
+    /// Insert one level of `indent_style` indentation at the caret. With
+    /// spaces, only the spaces up to the next indentation stop are
+    /// inserted, so that the text after the caret is aligned to it.
+    ///
+    /// Returns where the indentation was inserted.
+    pub fn insert_indent_at_cursor(
+        &mut self,
+        indent_style: IndentStyle,
+    ) -> Result<Range<TextBufferPos>, InsertCharError> {
+        let column = self.get_grid_pos_from_caret_pos(self.caret_pos).x;
+        self.insert_str_at_cursor(&indent_style.indent_at_column(column))
+    }
+
+    /// When indenting with spaces and there are only spaces before the
+    /// caret on its line, remove the spaces back to the previous
+    /// indentation stop, as if they were a tab.
+    ///
+    /// Returns whether anything was removed, otherwise
+    /// `Self::erase_character_before_cursor` should be used instead.
+    pub fn erase_indent_before_cursor(&mut self, indent_style: IndentStyle) -> bool {
+        if self.read_only {
+            return false;
+        }
+
+        if self.block_selection().is_some() {
+            return false;
+        }
+
+        let caret = self.caret_buffer_pos();
+        let Some(line) = self.contents.line(caret.line) else {
+            return false;
+        };
+        let leading_text = line.get(..caret.byte).unwrap_or_default();
+        if leading_text.is_empty() || leading_text.bytes().any(|byte| byte != b' ') {
+            return false;
+        }
+        let Some(width) = indent_style.dedent_width_at_column(caret.byte.to_u64()) else {
+            return false;
+        };
+
+        let start = TextBufferPos {
+            line: caret.line,
+            byte: caret.byte.saturating_sub(width.to_usize()),
+        };
+        self.remove_range(start, caret);
+        self.move_cursor_to_buffer_pos(start);
+        true
+    }
+
+    pub fn erase_character_before_cursor(&mut self) -> Result<RemoveCharResult, RemoveCharError> {
+        if self.read_only {
+            return Err(RemoveCharError::ReadOnly);
+        }
+
+        if self.block_selection().is_some() {
+            // an empty block erases the character before it on each line
+            return Ok(RemoveCharResult {
+                line_len_decreased: self.replace_in_block_selection("", |_, fragments| {
+                    if fragments.is_empty() {
+                        Some(fragments.start.checked_sub(1)?..fragments.end)
+                    } else {
+                        Some(fragments)
+                    }
+                }),
+            });
+        }
+
+        let caret = self.caret_buffer_pos();
+        if self.auto_pair_closers.contains(&caret)
+            && let Some(line) = self.contents.line(caret.line)
+            && let Some(opener) = line
+                .get(..caret.byte)
+                .and_then(|text| text.chars().next_back())
+            && auto_pair_closer(opener).is_some_and(|closer| self.char_at(caret) == Some(closer))
+        {
+            // the pair is empty, so it is erased as a whole
+            self.remove_char_at(caret)?;
+        }
+
+        if self.caret_pos.x > 0 {
+            let result = self.remove_character(
+                self.caret_pos.y.to_usize(),
+                self.caret_pos.x.saturating_sub(1).to_usize(),
+            );
+
+            if let Ok(result) = &result
+                && result.line_len_decreased
+            {
+                self.change_caret_x(self.caret_pos.x.saturating_sub(1));
+                self.is_dirty = true;
+            }
+
+            self.previous_line_caret_max_x.take();
+            result
+        } else if self.caret_pos.y > 0 && !self.single_line_mode {
+            if self.caret_pos.y == self.contents.total_lines().to_u64() {
+                // TODO: this part exist because right now our cursor can actually go
+                // beyond the last line (in order to allow insert beyond the last line),
+                // but that design will no longer make sense when we introduce vim motions.
+                // At that point, we should revisit this code, and perhaps remove this
+                // special case.
+                self.previous_line_caret_max_x.take();
+                return Ok(RemoveCharResult {
+                    line_len_decreased: false,
+                });
+            }
+
+            let previous_line_fragments_len = self
+                .get_line_len(self.caret_pos.y.saturating_sub(1).to_usize())
+                .to_u64();
+
+            if let Some(previous_line_len) = self
+                .contents
+                .line_len(self.caret_pos.y.saturating_sub(1).to_usize())
+            {
+                self.remove_char_at(TextBufferPos {
+                        line: self.caret_pos.y.saturating_sub(1).to_usize(),
+                        byte: previous_line_len,
+                    }).expect("previous line should exist, and it is legal to remove the pos right after the last character");
+
+                self.change_caret_xy(Vec2u {
+                    x: previous_line_fragments_len,
+                    y: self.caret_pos.y.saturating_sub(1),
+                });
+
+                self.is_dirty = true;
+            }
+
+            self.previous_line_caret_max_x.take();
+
+            Ok(RemoveCharResult {
+                // the (formally) active line was completely wiped out
+                // as it gets absorbed by the line above it
+                line_len_decreased: true,
+            })
+        } else {
+            self.previous_line_caret_max_x.take();
+
+            Ok(RemoveCharResult {
+                line_len_decreased: false,
+            })
+        }
+    }
+
+    pub fn erase_character_after_cursor(&mut self) -> Result<RemoveCharResult, RemoveCharError> {
+        if self.read_only {
+            return Err(RemoveCharError::ReadOnly);
+        }
+
+        if self.block_selection().is_some() {
+            // an empty block erases the character after it on each line
+            return Ok(RemoveCharResult {
+                line_len_decreased: self.replace_in_block_selection(
+                    "",
+                    |line_render, fragments| {
+                        if fragments.is_empty() {
+                            (fragments.start < line_render.get_line_len())
+                                .then(|| fragments.start..fragments.start.saturating_add(1))
+                        } else {
+                            Some(fragments)
+                        }
+                    },
+                ),
+            });
+        }
+
+        if self.caret_pos.x < self.get_line_len(self.caret_pos.y.to_usize()).to_u64() {
+            let result =
+                self.remove_character(self.caret_pos.y.to_usize(), self.caret_pos.x.to_usize());
+
+            if result.is_ok() {
+                self.is_dirty = result.is_ok();
+                self.previous_line_caret_max_x.take();
+            }
+
+            result
+        } else if self.caret_pos.y < self.get_total_lines().to_u64() && !self.single_line_mode {
+            let line_len = self
+                .contents
+                .line_len(self.caret_pos.y.to_usize())
+                .expect("line should exist");
+
+            if self
+                .remove_char_at(TextBufferPos {
+                    line: self.caret_pos.y.to_usize(),
+                    byte: line_len,
+                })
+                .is_ok()
+            {
+                self.is_dirty = true;
+            }
+
+            self.previous_line_caret_max_x.take();
+
+            Ok(RemoveCharResult {
+                // the active line length actually increased / stay the same, it can't decrease
+                line_len_decreased: false,
+            })
+        } else {
+            self.previous_line_caret_max_x.take();
+            Ok(RemoveCharResult {
+                line_len_decreased: false,
+            })
+        }
+    }
+
+    pub fn insert_newline_at_cursor(&mut self) {
+        if self.single_line_mode || self.read_only {
+            return;
+        }
+
+        assert!(self.caret_pos.y <= self.get_total_lines().to_u64());
+
+        let target_line_render = self
+            .get_line_render(self.caret_pos.y.to_usize())
+            .unwrap_or_else(|| Rc::new(TextLine::new("", self.render_options)));
+
+        // TODO: This logic can be further refactored (it is copied and slightly modified across multiple functions)
+        let buffer_pos = TextBufferPos {
+            line: self.caret_pos.y.to_usize(),
+            byte: match target_line_render
                 .get_byte_idx_from_fragment_idx(self.caret_pos.x.to_usize())
             {
-                Some(column) => column,
-                None => return Err(InsertCharError::InvalidBytePosition),
-            },
+                Some(byte) => byte,
+                None => return,
+            },
+        };
+
+        let insert_successful = self.insert_char_at(buffer_pos, '\n').is_ok();
+
+        self.change_caret_xy(Vec2u {
+            x: 0,
+            y: self.caret_pos.y.saturating_add(1),
+        });
+
+        if insert_successful {
+            self.is_dirty = true;
+        }
+    }
+
+    /// Insert a newline at the caret, and then the leading whitespace of
+    /// the caret's line (as far as the caret), so that the new line is
+    /// indented as much as the line it was split from.
+    pub fn insert_newline_with_autoindent(&mut self) {
+        if self.single_line_mode || self.read_only {
+            return;
+        }
+
+        let caret_pos = self.caret_buffer_pos();
+        let indent = self
+            .contents
+            .line(caret_pos.line)
+            .map(|line| {
+                let before_caret = line.get(..caret_pos.byte).unwrap_or(&line);
+                let content = before_caret.trim_start_matches([' ', '\t']);
+                before_caret[..before_caret.len().saturating_sub(content.len())].to_string()
+            })
+            .unwrap_or_default();
+
+        self.insert_newline_at_cursor();
+        if !indent.is_empty() {
+            // cannot fail, as the caret is at the start of the new line
+            self.insert_str_at_cursor(&indent).ok();
+        }
+    }
+
+    // TODO: When we use a backend text object (like ropey), this method shouldn't be here
+    pub fn get_entire_contents_as_string(&self) -> String {
+        self.contents.contents()
+    }
+
+    // TODO: When we use a backend text object (like ropey), this method shouldn't be here
+    pub fn get_raw_line(&self, line_idx: usize) -> Option<String> {
+        self.contents.line(line_idx)
+    }
+
+    /// Guess whether the contents are indented with tabs or spaces
+    /// (and how many), or `None` if there is no indentation to go by.
+    pub fn detect_indentation(&self) -> Option<IndentStyle> {
+        detect_indentation(
+            (0..self.get_total_lines()).filter_map(|line_idx| self.get_raw_line(line_idx)),
+        )
+    }
+
+    // TODO: When we use a backend text object (like ropey), this method shouldn't be here
+    pub fn get_total_lines(&self) -> usize {
+        self.contents.total_lines()
+    }
+
+    fn counts(&self) -> TextCounts {
+        *self.counts.get_or_init(|| {
+            TextCounts::of_lines(
+                (0..self.contents.total_lines())
+                    .filter_map(|line_idx| self.contents.line(line_idx)),
+            )
+        })
+    }
+
+    /// The number of characters in the contents, including line breaks.
+    pub fn char_count(&self) -> usize {
+        self.counts().chars
+    }
+
+    /// The number of graphemes in the contents, including line breaks.
+    pub fn grapheme_count(&self) -> usize {
+        self.counts().graphemes
+    }
+
+    /// The number of words in the contents, as split by Unicode's rules
+    /// (which is why punctuation is not counted as words).
+    pub fn word_count(&self) -> usize {
+        self.counts().words
+    }
+
+    /// Returns where `search` finds a match (and the match itself),
+    /// starting from `start_pos`.
+    fn find_in_contents<F>(&self, start_pos: Vec2u, search: F) -> Option<(Vec2u, FindResult)>
+    where
+        F: FnOnce(&B, TextBufferPos) -> Option<FindResult>,
+    {
+        let target_line_render = if start_pos.y == self.contents.total_lines().to_u64() {
+            Rc::new(TextLine::new("", self.render_options))
+        } else {
+            self.get_line_render(start_pos.y.to_usize())?
+        };
+
+        // TODO: This logic can be further refactored (it is copied and slightly modified across multiple functions)
+        let buffer_pos = TextBufferPos {
+            line: start_pos.y.to_usize(),
+            byte: target_line_render.get_byte_idx_from_fragment_idx(start_pos.x.to_usize())?,
+        };
+
+        search(&self.contents, buffer_pos).map(|result| {
+            let final_line_render = self
+                .get_line_render(result.pos.line)
+                .expect("result should return a valid line");
+            let final_fragment_idx = final_line_render
+                .get_fragment_idx_from_byte_idx(result.pos.byte)
+                .expect("result should return a valid byte index");
+
+            let caret_pos = Vec2u {
+                x: final_fragment_idx.to_u64(),
+                y: result.pos.line.to_u64(),
+            };
+            (caret_pos, result)
+        })
+    }
+
+    pub fn enter_search_mode(&mut self) {
+        self.before_search_caret_pos = Some(self.caret_pos);
+        self.before_search_scroll_offset = Some(self.scroll_offset);
+    }
+
+    pub fn exit_search_mode(&mut self, retain_search_caret_pos: bool) {
+        if !retain_search_caret_pos {
+            self.restore_before_search_pos();
+        }
+        self.before_search_caret_pos.take();
+        self.before_search_scroll_offset.take();
+    }
+
+    /// Move the caret and the view back to where they were before searching,
+    /// while still searching (e.g. when there is nothing to search for).
+    pub fn restore_before_search_pos(&mut self) {
+        self.caret_pos = self.before_search_caret_pos.unwrap_or(self.caret_pos);
+        self.scroll_offset = self
+            .before_search_scroll_offset
+            .unwrap_or(self.scroll_offset);
+    }
+
+    /// Move the caret to the next match of `search`, returning the match if
+    /// there was one. If there is none, a first search goes back to where the
+    /// caret was before searching, while a later search leaves the caret
+    /// where it is.
+    pub fn find<T: AsRef<str>>(
+        &mut self,
+        search: T,
+        first_search: bool,
+        search_direction: SearchDirection,
+        search_options: SearchOptions,
+    ) -> Option<FindResult> {
+        let search = search.as_ref();
+        self.find_with(
+            first_search,
+            search_direction,
+            search.graphemes(true).count().to_u64(),
+            |contents, start_pos| {
+                contents.find(search, start_pos, search_direction, search_options)
+            },
+        )
+    }
+
+    /// Move the caret to the next match of the regular expression `pattern`,
+    /// see `Self::find` (and `TextBuffer::find_regex` for how it matches).
+    pub fn find_regex(
+        &mut self,
+        pattern: &Regex,
+        first_search: bool,
+        search_direction: SearchDirection,
+    ) -> Option<FindResult> {
+        // the length of the current match is not known, but skipping
+        // a single fragment is enough, as the matches do not overlap
+        self.find_with(first_search, search_direction, 1, |contents, start_pos| {
+            contents.find_regex(pattern, start_pos, search_direction)
+        })
+    }
+
+    /// Find the next (or previous) occurrence of `Self::word_under_caret`
+    /// as a whole word, like `*` (or `#`) in Vim, and select it with the
+    /// caret at its start. Returns the word, and where it was found.
+    pub fn find_word_under_caret(
+        &mut self,
+        search_direction: SearchDirection,
+        search_options: SearchOptions,
+    ) -> Option<(String, FindResult)> {
+        let range = self.get_word_byte_range_under_caret()?;
+        let line_idx = self.caret_pos.y.to_usize();
+        let word = self
+            .contents
+            .line(line_idx)?
+            .get(range.clone())?
+            .to_string();
+
+        // from the start of the word, it is skipped when searching forward,
+        // and not found again when searching backward
+        self.clear_selection();
+        self.move_cursor_to_buffer_pos(TextBufferPos {
+            line: line_idx,
+            byte: range.start,
+        });
+        let found = self.find(
+            &word,
+            false,
+            search_direction,
+            SearchOptions {
+                whole_word: true,
+                ..search_options
+            },
+        )?;
+
+        let end = self.get_caret_pos_from_buffer_pos(get_pos_after_str(found.pos, &word));
+        self.set_selection(end, self.caret_pos);
+        Some((word, found))
+    }
+
+    /// The number of matches of `search`, see `Self::get_search_match_ordinal`.
+    pub fn count_matches(&self, search: &str, search_options: SearchOptions) -> usize {
+        self.get_search_match_ordinal(search, search_options).1
+    }
+
+    /// Returns the (1-based) ordinal of the match of `search` that the caret
+    /// is at, and the number of matches, which are counted in a single scan.
+    /// The ordinal is `None` if the caret is not at a match.
+    ///
+    /// Like the highlighted matches, the matches do not overlap.
+    pub fn get_search_match_ordinal(
+        &self,
+        search: &str,
+        search_options: SearchOptions,
+    ) -> (Option<usize>, usize) {
+        let is_multiline = search.contains('\n');
+        if search.is_empty() || (is_multiline && !search_options.multiline) {
+            return (None, 0);
+        }
+
+        let caret = self.caret_buffer_pos();
+        let mut ordinal = None;
+        let mut count = 0usize;
+        let mut count_match = |pos: TextBufferPos| {
+            count = count.saturating_add(1);
+            if pos == caret {
+                ordinal = Some(count);
+            }
+        };
+
+        if is_multiline {
+            // the matches can span lines, so the lines are searched joined
+            let contents = self.contents.contents();
+            let mut pos = TextBufferPos::default();
+            let mut offset = 0;
+            for (match_offset, _) in contents.match_indices(search).filter(|(offset, _)| {
+                !search_options.whole_word || is_whole_word(&contents, *offset, search.len())
+            }) {
+                pos =
+                    get_pos_after_str(pos, contents.get(offset..match_offset).unwrap_or_default());
+                offset = match_offset;
+                count_match(pos);
+            }
+        } else {
+            let lines = self.contents.lines_range(0..self.contents.total_lines());
+            for (line_idx, line) in lines.iter().enumerate() {
+                for (byte, _) in line.match_indices(search).filter(|(byte, _)| {
+                    !search_options.whole_word || is_whole_word(line, *byte, search.len())
+                }) {
+                    count_match(TextBufferPos {
+                        line: line_idx,
+                        byte,
+                    });
+                }
+            }
+        }
+        (ordinal, count)
+    }
+
+    /// See `Self::find`, with `search` finding the match from a position in
+    /// the buffer. A later forward search skips `match_len` fragments past
+    /// the caret, so that the current match is not found again.
+    fn find_with<F>(
+        &mut self,
+        first_search: bool,
+        search_direction: SearchDirection,
+        match_len: u64,
+        search: F,
+    ) -> Option<FindResult>
+    where
+        F: FnOnce(&B, TextBufferPos) -> Option<FindResult>,
+    {
+        let start_pos = if first_search {
+            self.before_search_caret_pos.unwrap_or(self.caret_pos)
+        } else {
+            Vec2u {
+                x: match search_direction {
+                    // skip past the current match, note that the caret
+                    // is in fragments (graphemes), and not bytes
+                    SearchDirection::Forward => self
+                        .caret_pos
+                        .x
+                        .saturating_add(match_len)
+                        .min(self.get_line_len(self.caret_pos.y.to_usize()).to_u64()),
+                    SearchDirection::Backward => self.caret_pos.x,
+                },
+                y: self.caret_pos.y,
+            }
+        };
+
+        if let Some((caret_pos, result)) = self.find_in_contents(start_pos, search) {
+            self.change_caret_xy(caret_pos);
+            Some(result)
+        } else {
+            if first_search && let Some(previous_caret_pos) = self.before_search_caret_pos {
+                self.change_caret_xy(previous_caret_pos);
+            }
+            None
+        }
+    }
+
+    fn render_line(
+        &self,
+        drawer: &mut Drawer,
+        line_idx: usize,
+        screen_pos: Vec2f,
+        text_offset_x: Range<u64>,
+        line_highlight: &TextHighlightLine,
+    ) {
+        match self.get_line_render(line_idx) {
+            Some(line_render) => {
+                let trailing_whitespace = self
+                    .render_options
+                    .highlight_trailing_whitespace
+                    .then_some(self.colors.trailing_whitespace);
+                let is_caret_line = line_idx.to_u64() == self.caret_pos.y;
+                let caret = self
+                    .colors
+                    .caret
+                    .filter(|_| is_caret_line)
+                    .and_then(|color| {
+                        let caret_x = self.caret_pos.x.to_usize();
+                        let start = line_render.get_byte_idx_from_fragment_idx(caret_x)?;
+                        let end = line_render
+                            .get_byte_idx_from_fragment_idx(caret_x.saturating_add(1))?;
+                        Some(TextHighlightBlock {
+                            color,
+                            range: start..end,
+                        })
+                    });
+                let selection = self
+                    .get_selected_byte_range(line_idx, &line_render)
+                    .map(|range| TextHighlightBlock {
+                        color: self.colors.selection,
+                        range,
+                    });
+                let current_line = self.colors.current_line.filter(|_| is_caret_line);
+                if caret.is_none() && selection.is_none() && current_line.is_none() {
+                    line_render.render_line(
+                        drawer,
+                        screen_pos,
+                        text_offset_x,
+                        line_highlight,
+                        trailing_whitespace,
+                    );
+                    return;
+                }
+
+                // the caret and the selection are drawn over the other highlights
+                let mut blocks: Vec<TextHighlightBlock> =
+                    caret.into_iter().chain(selection).collect();
+                match current_line {
+                    Some(background) => {
+                        // the background goes past the end of the text
+                        let width = text_offset_x.end.saturating_sub(text_offset_x.start);
+                        drawer.draw_colored_text(
+                            screen_pos,
+                            " ".repeat(width.to_usize()),
+                            None,
+                            Some(background),
+                        );
+                        blocks.extend(line_highlight.blocks.iter().map(|block| {
+                            TextHighlightBlock {
+                                color: TextColor {
+                                    background: block.color.background.or(Some(background)),
+                                    ..block.color
+                                },
+                                range: block.range.clone(),
+                            }
+                        }));
+                        blocks.push(TextHighlightBlock {
+                            color: TextColor {
+                                foreground: None,
+                                background: Some(background),
+                            },
+                            range: 0..usize::MAX,
+                        });
+                    }
+                    None => blocks.extend(line_highlight.blocks.iter().cloned()),
+                }
+                line_render.render_line(
+                    drawer,
+                    screen_pos,
+                    text_offset_x,
+                    &TextHighlightLine { blocks },
+                    trailing_whitespace,
+                );
+            }
+            None => {
+                if !self.single_line_mode {
+                    drawer.draw_text(screen_pos, "~");
+                }
+            }
+        }
+    }
+
+    /// Returns the bytes of `line_idx` (rendered as `line_render`) that
+    /// are selected, or `None` if none of them are.
+    fn get_selected_byte_range(
+        &self,
+        line_idx: usize,
+        line_render: &TextLine,
+    ) -> Option<Range<usize>> {
+        if let Some((lines, columns)) = self.block_selection() {
+            if !lines.contains(&line_idx) {
+                return None;
+            }
+
+            // an empty block is shown as a column, like the caret
+            let fragments = get_block_fragment_range(line_render, &columns)?;
+            let end = if fragments.is_empty() {
+                fragments.start.saturating_add(1)
+            } else {
+                fragments.end
+            };
+            return Some(
+                line_render.get_byte_idx_from_fragment_idx(fragments.start)?
+                    ..line_render.get_byte_idx_from_fragment_idx(end)?,
+            );
+        }
+
+        let selection = self.selection_range()?;
+        let line_idx = line_idx.to_u64();
+        if line_idx < selection.start.y || line_idx > selection.end.y {
+            return None;
+        }
+
+        let start = if line_idx == selection.start.y {
+            line_render.get_byte_idx_from_fragment_idx(selection.start.x.to_usize())?
+        } else {
+            0
+        };
+        let end = if line_idx == selection.end.y {
+            line_render.get_byte_idx_from_fragment_idx(selection.end.x.to_usize())?
+        } else {
+            line_render.to_string().len()
+        };
+        (start < end).then_some(start..end)
+    }
+
+    /// Returns the width (in columns) of the leading whitespace
+    /// of `line_idx`, or `None` if the line is blank.
+    fn get_indent_width(&self, line_idx: usize) -> Option<u64> {
+        let line = self.contents.line(line_idx)?;
+        let content = line.trim_start();
+        if content.is_empty() {
+            return None;
+        }
+
+        let line_render = self.get_line_render(line_idx)?;
+        let indent_fragments_len =
+            line_render.get_fragment_idx_from_byte_idx(line.len().saturating_sub(content.len()))?;
+        Some(line_render.get_line_text_width(indent_fragments_len))
+    }
+
+    /// Returns the columns that the indent guides of `line_idx` are drawn
+    /// at, which are the tab stops within its leading whitespace. Blank lines
+    /// are treated as being indented as much as the lines around them.
+    fn get_indent_guide_columns(&self, line_idx: usize) -> Vec<u64> {
+        let tab_width = self.render_options.tab_width;
+        if line_idx >= self.get_total_lines() || tab_width == 0 {
+            return vec![];
+        }
+
+        let indent_width = self.get_indent_width(line_idx).unwrap_or_else(|| {
+            let previous_indent_width = (0..line_idx)
+                .rev()
+                .find_map(|line_idx| self.get_indent_width(line_idx));
+            let next_indent_width = (line_idx.saturating_add(1)..self.get_total_lines())
+                .find_map(|line_idx| self.get_indent_width(line_idx));
+            previous_indent_width
+                .unwrap_or_default()
+                .min(next_indent_width.unwrap_or_default())
+        });
+
+        (0..indent_width).step_by(tab_width.to_usize()).collect()
+    }
+
+    fn render_indent_guides(
+        &self,
+        drawer: &mut Drawer,
+        line_idx: usize,
+        screen_pos: Vec2f,
+        text_offset_x: &Range<u64>,
+    ) {
+        self.get_indent_guide_columns(line_idx)
+            .into_iter()
+            .filter(|column| text_offset_x.contains(column))
+            .for_each(|column| {
+                drawer.draw_colored_text(
+                    Vec2f {
+                        x: screen_pos.x + column.saturating_sub(text_offset_x.start).lossy(),
+                        y: screen_pos.y,
+                    },
+                    INDENT_GUIDE,
+                    Some(Color::GRAY),
+                    None,
+                );
+            });
+    }
+
+    /// If `line_idx` is the header of a fold, draws a marker after it,
+    /// ending with the last hidden line (e.g. ` … }`).
+    fn render_fold_marker(
+        &self,
+        drawer: &mut Drawer,
+        line_idx: usize,
+        screen_pos: Vec2f,
+        text_offset_x: Range<u64>,
+    ) {
+        let Some(fold) = self.folds.get_at_header(line_idx.to_u64()) else {
+            return;
+        };
+
+        let header_width = self.get_grid_pos_from_caret_pos(Vec2u {
+            x: self.get_line_len(line_idx).to_u64(),
+            y: line_idx.to_u64(),
+        });
+        let last_hidden_line = self
+            .get_raw_line(fold.end.saturating_sub(1).to_usize())
+            .unwrap_or_default();
+        let marker = format!(" {FOLD_MARKER} {}", last_hidden_line.trim());
+
+        // the marker is made up of single-width characters, except for
+        // whatever the last hidden line contains, which is good enough
+        let visible_marker = marker
+            .chars()
+            .skip(
+                text_offset_x
+                    .start
+                    .saturating_sub(header_width.x)
+                    .to_usize(),
+            )
+            .take(
+                text_offset_x
+                    .end
+                    .saturating_sub(header_width.x.max(text_offset_x.start))
+                    .to_usize(),
+            )
+            .collect::<String>();
+        if !visible_marker.is_empty() {
+            drawer.draw_text(
+                Vec2f {
+                    x: screen_pos.x + header_width.x.saturating_sub(text_offset_x.start).lossy(),
+                    y: screen_pos.y,
+                },
+                visible_marker,
+            );
+        }
+    }
+
+    fn render_scrollbar(&self, drawer: &mut Drawer) {
+        let height: u64 = self.bounds.size.y.lossy();
+        let thumb = get_scrollbar_thumb(
+            self.scroll_offset.y,
+            height,
+            self.folds.get_visual_row(self.get_total_lines().to_u64()),
+        );
+
+        (0..height).for_each(|y| {
+            drawer.draw_text(
+                Vec2f {
+                    x: self.bounds.pos.x + self.bounds.size.x - 1.0,
+                    y: self.bounds.pos.y + y.lossy(),
+                },
+                if thumb.contains(&y) {
+                    SCROLLBAR_THUMB
+                } else {
+                    SCROLLBAR_TRACK
+                },
+            );
+        });
+    }
+
+    /// Returns where the end of `line_idx` is drawn on the screen, or `None`
+    /// if the line is not rendered (e.g. it is scrolled out of view or hidden
+    /// by a fold). If the view is scrolled past the end of the line, this is
+    /// the left edge of the view instead.
+    pub fn line_end_screen_pos(&self, line_idx: usize) -> Option<Vec2f> {
+        if line_idx >= self.get_total_lines()
+            || self.folds.get_containing(line_idx.to_u64()).is_some()
+        {
+            return None;
+        }
+
+        let bounds = self.text_bounds();
+        let grid_pos = self.get_grid_pos_from_caret_pos(Vec2u {
+            x: self.get_line_len(line_idx).to_u64(),
+            y: line_idx.to_u64(),
+        });
+        let row = grid_pos.y.checked_sub(self.scroll_offset.y)?;
+        if row >= bounds.size.y.lossy() {
+            return None;
+        }
+
+        Some(Vec2f {
+            x: bounds.pos.x + grid_pos.x.saturating_sub(self.scroll_offset.x).lossy(),
+            y: bounds.pos.y + row.lossy(),
+        })
+    }
+
+    /// Where the caret is drawn on the screen.
+    pub fn caret_screen_pos(&self) -> Vec2f {
+        let bounds = self.text_bounds();
+        let grid_cursor_pos = self.get_grid_pos_from_caret_pos(self.caret_pos);
+
+        let screen_cursor_pos = Vec2u {
+            x: <f64 as Lossy<u64>>::lossy(&bounds.pos.x)
+                .saturating_add(grid_cursor_pos.x.saturating_sub(self.scroll_offset.x)),
+            y: <f64 as Lossy<u64>>::lossy(&bounds.pos.y)
+                .saturating_add(grid_cursor_pos.y.saturating_sub(self.scroll_offset.y)),
+        };
+
+        Vec2f {
+            x: screen_cursor_pos.x.lossy(),
+            y: screen_cursor_pos.y.lossy(),
+        }
+    }
+
+    pub fn render(&self, drawer: &mut Drawer, highlights: &TextHighlights) {
+        let bounds = self.text_bounds();
+        if bounds.size.x * bounds.size.y > 0.0 {
+            let line_idxs: Vec<_> = (0..bounds.size.y.lossy())
+                .map(|y: u64| {
+                    self.folds
+                        .get_line(self.scroll_offset.y.saturating_add(y))
+                        .to_usize()
+                })
+                .collect();
+            self.prepare_line_renders(&line_idxs);
+
+            line_idxs.into_iter().enumerate().for_each(|(y, line_idx)| {
+                let screen_pos = Vec2f {
+                    x: bounds.pos.x,
+                    y: bounds.pos.y + y.lossy(),
+                };
+                let text_offset_x = self.visible_columns();
+                self.render_line(
+                    drawer,
+                    line_idx,
+                    screen_pos,
+                    text_offset_x.clone(),
+                    highlights
+                        .line_highlight(line_idx)
+                        .unwrap_or(&TextHighlightLine::new()),
+                );
+                if self.render_options.show_indent_guides {
+                    self.render_indent_guides(drawer, line_idx, screen_pos, &text_offset_x);
+                }
+                self.render_fold_marker(drawer, line_idx, screen_pos, text_offset_x);
+            });
+
+            // the caret could have been scrolled out of view
+            let grid_cursor_pos = self.get_grid_pos_from_caret_pos(self.caret_pos);
+            let visible_rows =
+                self.scroll_offset.y..self.scroll_offset.y.saturating_add(bounds.size.y.lossy());
+            if visible_rows.contains(&grid_cursor_pos.y)
+                && self.visible_columns().contains(&grid_cursor_pos.x)
+            {
+                drawer.draw_cursor(self.caret_screen_pos());
+            }
+        }
+
+        if self.render_options.show_scrollbar && self.bounds.size.x * self.bounds.size.y > 0.0 {
+            self.render_scrollbar(drawer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::text_buffer::{RopeTextBuffer, VecTextBuffer};
+
+    use super::*;
+
+    fn new_text_box<T: AsRef<str>>(contents: T) -> TextBox<VecTextBuffer> {
+        let mut text_box = TextBox::new(VecTextBuffer::new());
+        text_box.set_contents(contents);
+        text_box.set_bounds(Bounds2f {
+            pos: Vec2f::ZERO,
+            size: Vec2f { x: 10.0, y: 5.0 },
+        });
+        text_box
+    }
+
+    #[test]
+    fn test_select_all() {
+        // normal
+        {
+            let mut text_box = new_text_box("Hello\nWorld!\n\nThe End");
+            assert_eq!(text_box.selection_range(), None);
+            assert_eq!(text_box.copy_selection(), None);
+
+            text_box.select_all();
+            assert_eq!(
+                text_box.selection_range(),
+                Some(Vec2u { x: 0, y: 0 }..Vec2u { x: 7, y: 3 })
+            );
+            assert_eq!(text_box.caret_pos(), Vec2u { x: 7, y: 3 });
+            assert_eq!(
+                text_box.copy_selection(),
+                Some("Hello\nWorld!\n\nThe End".to_string())
+            );
+
+            // moving the caret collapses the selection
+            text_box.move_cursor_left();
+            assert_eq!(text_box.selection_range(), None);
+        }
+
+        // multi-byte graphemes
+        {
+            let mut text_box = new_text_box("caf\u{e9}\n\u{1f600}!");
+            text_box.select_all();
+            assert_eq!(
+                text_box.selection_range(),
+                Some(Vec2u { x: 0, y: 0 }..Vec2u { x: 2, y: 1 })
+            );
+            assert_eq!(
+                text_box.copy_selection(),
+                Some("caf\u{e9}\n\u{1f600}!".to_string())
+            );
+        }
+
+        // empty
+        {
+            let mut text_box = new_text_box("");
+            text_box.select_all();
+            assert_eq!(text_box.selection_range(), None);
+            assert_eq!(text_box.caret_pos(), Vec2u::ZERO);
+            assert_eq!(text_box.copy_selection(), None);
+        }
+    }
+
+    #[test]
+    fn test_select_line() {
+        let mut text_box = new_text_box("Hello\nWorld!\nThe End");
+
+        text_box.change_caret_xy(Vec2u { x: 3, y: 1 });
+        text_box.select_line();
+        assert_eq!(
+            text_box.selection_range(),
+            Some(Vec2u { x: 0, y: 1 }..Vec2u { x: 0, y: 2 })
+        );
+        assert_eq!(text_box.copy_selection(), Some("World!\n".to_string()));
+
+        // last line has no trailing newline
+        text_box.change_caret_xy(Vec2u { x: 1, y: 2 });
+        text_box.select_line();
+        assert_eq!(
+            text_box.selection_range(),
+            Some(Vec2u { x: 0, y: 2 }..Vec2u { x: 7, y: 2 })
+        );
+        assert_eq!(text_box.copy_selection(), Some("The End".to_string()));
+    }
+
+    #[test]
+    fn test_select_word() {
+        let mut text_box = new_text_box("let x  = foo(bar, baz);");
+
+        let select_word_at = |text_box: &mut TextBox<VecTextBuffer>, x| {
+            text_box.change_caret_xy(Vec2u { x, y: 0 });
+            text_box.select_word();
+            text_box.copy_selection()
+        };
+
+        assert_eq!(select_word_at(&mut text_box, 0), Some("let".to_string()));
+        assert_eq!(select_word_at(&mut text_box, 2), Some("let".to_string()));
+        assert_eq!(select_word_at(&mut text_box, 5), Some("  ".to_string()));
+        assert_eq!(select_word_at(&mut text_box, 9), Some("foo".to_string()));
+        assert_eq!(select_word_at(&mut text_box, 12), Some("(".to_string()));
+        assert_eq!(select_word_at(&mut text_box, 14), Some("bar".to_string()));
+        assert_eq!(select_word_at(&mut text_box, 16), Some(",".to_string()));
+        // end of line
+        assert_eq!(select_word_at(&mut text_box, 23), Some(";".to_string()));
+
+        // repeated select word extends to the next word
+        text_box.change_caret_xy(Vec2u { x: 9, y: 0 });
+        text_box.select_word();
+        assert_eq!(text_box.copy_selection(), Some("foo".to_string()));
+        text_box.select_word();
+        assert_eq!(text_box.copy_selection(), Some("foo(".to_string()));
+        text_box.select_word();
+        assert_eq!(text_box.copy_selection(), Some("foo(bar".to_string()));
+        assert_eq!(
+            text_box.selection_range(),
+            Some(Vec2u { x: 9, y: 0 }..Vec2u { x: 16, y: 0 })
+        );
+
+        // multi-byte graphemes
+        let mut text_box = new_text_box("caf\u{e9} cr\u{e8}me");
+        assert_eq!(
+            select_word_at(&mut text_box, 7),
+            Some("cr\u{e8}me".to_string())
+        );
+        assert_eq!(
+            text_box.selection_range(),
+            Some(Vec2u { x: 5, y: 0 }..Vec2u { x: 10, y: 0 })
+        );
+    }
+
+    #[test]
+    fn test_get_grid_pos_from_screen_pos() {
+        let bounds = Bounds2f {
+            pos: Vec2f { x: 2.0, y: 1.0 },
+            size: Vec2f { x: 10.0, y: 5.0 },
+        };
+        let scroll_offset = Vec2u { x: 3, y: 20 };
+
+        let map = |x, y, scroll_at_edges| {
+            get_grid_pos_from_screen_pos(bounds, scroll_offset, Vec2f { x, y }, scroll_at_edges)
+        };
+
+        // within the bounds
+        assert_eq!(map(2.0, 1.0, false), Vec2u { x: 3, y: 20 });
+        assert_eq!(map(6.0, 3.0, false), Vec2u { x: 7, y: 22 });
+        assert_eq!(map(6.0, 3.0, true), Vec2u { x: 7, y: 22 });
+        assert_eq!(map(11.0, 5.0, false), Vec2u { x: 12, y: 24 });
+        assert_eq!(map(11.0, 5.0, true), Vec2u { x: 12, y: 24 });
+
+        // on the top / left edges
+        assert_eq!(map(2.0, 1.0, true), Vec2u { x: 2, y: 19 });
+        assert_eq!(map(0.0, 0.0, false), Vec2u { x: 3, y: 20 });
+        assert_eq!(map(0.0, 0.0, true), Vec2u { x: 2, y: 19 });
+
+        // beyond the bottom / right edges
+        assert_eq!(map(12.0, 6.0, false), Vec2u { x: 13, y: 25 });
+        assert_eq!(map(12.0, 6.0, true), Vec2u { x: 13, y: 25 });
+        assert_eq!(map(30.0, 30.0, true), Vec2u { x: 13, y: 25 });
+
+        // no scroll left
+        assert_eq!(
+            get_grid_pos_from_screen_pos(bounds, Vec2u::ZERO, Vec2f { x: 2.0, y: 1.0 }, true),
+            Vec2u::ZERO
+        );
+    }
+
+    #[test]
+    fn test_drag_selection_to_screen_pos() {
+        let mut text_box = new_text_box("let x  = foo(bar, baz);\nsecond line\nthird");
+        text_box.set_bounds(Bounds2f {
+            pos: Vec2f::ZERO,
+            size: Vec2f { x: 40.0, y: 5.0 },
+        });
+
+        // character
+        text_box.move_cursor_to_screen_pos(Vec2f { x: 4.0, y: 0.0 });
+        let origin = text_box.caret_pos()..text_box.caret_pos();
+        text_box.drag_selection_to_screen_pos(
+            origin.clone(),
+            Vec2f { x: 3.0, y: 1.0 },
+            SelectionGranularity::Character,
+        );
+        assert_eq!(
+            text_box.copy_selection(),
+            Some("x  = foo(bar, baz);\nsec".to_string())
+        );
+        text_box.drag_selection_to_screen_pos(
+            origin,
+            Vec2f { x: 1.0, y: 0.0 },
+            SelectionGranularity::Character,
+        );
+        assert_eq!(text_box.copy_selection(), Some("et ".to_string()));
+
+        // word
+        text_box.change_caret_xy(Vec2u { x: 14, y: 0 });
+        text_box.select_word();
+        let origin = text_box.selection_range().expect("word is selected");
+        text_box.drag_selection_to_screen_pos(
+            origin.clone(),
+            Vec2f { x: 9.0, y: 1.0 },
+            SelectionGranularity::Word,
+        );
+        assert_eq!(
+            text_box.copy_selection(),
+            Some("bar, baz);\nsecond line".to_string())
+        );
+        text_box.drag_selection_to_screen_pos(
+            origin,
+            Vec2f { x: 10.0, y: 0.0 },
+            SelectionGranularity::Word,
+        );
+        assert_eq!(text_box.copy_selection(), Some("foo(bar".to_string()));
+
+        // line
+        text_box.change_caret_xy(Vec2u { x: 2, y: 1 });
+        text_box.select_line();
+        let origin = text_box.selection_range().expect("line is selected");
+        text_box.drag_selection_to_screen_pos(
+            origin,
+            Vec2f { x: 0.0, y: 4.0 },
+            SelectionGranularity::Line,
+        );
+        assert_eq!(
+            text_box.copy_selection(),
+            Some("second line\nthird".to_string())
+        );
+    }
+
+    #[test]
+    fn test_drag_selection_scrolls_at_edges() {
+        let mut text_box = new_text_box(
+            (0..20)
+                .map(|line| format!("line {line}"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+
+        text_box.move_cursor_to_screen_pos(Vec2f { x: 0.0, y: 2.0 });
+        let origin = text_box.caret_pos()..text_box.caret_pos();
+
+        // dragging below the text box scrolls down one line per drag event
+        text_box.drag_selection_to_screen_pos(
+            origin.clone(),
+            Vec2f { x: 0.0, y: 6.0 },
+            SelectionGranularity::Character,
+        );
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 0, y: 5 });
+        assert_eq!(text_box.scroll_offset.y, 1);
+        text_box.drag_selection_to_screen_pos(
+            origin.clone(),
+            Vec2f { x: 0.0, y: 6.0 },
+            SelectionGranularity::Character,
+        );
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 0, y: 6 });
+        assert_eq!(text_box.scroll_offset.y, 2);
+
+        // dragging on the top edge scrolls back up
+        text_box.drag_selection_to_screen_pos(
+            origin.clone(),
+            Vec2f { x: 3.0, y: 0.0 },
+            SelectionGranularity::Character,
+        );
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 3, y: 1 });
+        assert_eq!(text_box.scroll_offset.y, 1);
+        assert_eq!(
+            text_box.selection_range(),
+            Some(Vec2u { x: 3, y: 1 }..origin.start)
+        );
+    }
+
+    #[test]
+    fn test_find_next_with_multi_byte_search() {
+        let mut text_box = TextBox::new(RopeTextBuffer::new());
+        text_box.set_contents("caf\u{e9} au caf\u{e9}.\ncaf\u{e9}caf\u{e9}!");
+        text_box.set_bounds(Bounds2f {
+            pos: Vec2f::ZERO,
+            size: Vec2f { x: 40.0, y: 5.0 },
+        });
+        let search = "caf\u{e9}";
+
+        text_box.enter_search_mode();
+        text_box.find(
+            search,
+            true,
+            SearchDirection::Forward,
+            SearchOptions::default(),
+        );
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 0, y: 0 });
+
+        let expected_positions = [
+            Vec2u { x: 8, y: 0 },
+            Vec2u { x: 0, y: 1 },
+            Vec2u { x: 4, y: 1 },
+            // wrap around
+            Vec2u { x: 0, y: 0 },
+        ];
+        for expected in expected_positions {
+            text_box.find(
+                search,
+                false,
+                SearchDirection::Forward,
+                SearchOptions::default(),
+            );
+            assert_eq!(text_box.caret_pos(), expected);
+        }
+
+        text_box.find(
+            search,
+            false,
+            SearchDirection::Backward,
+            SearchOptions::default(),
+        );
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 4, y: 1 });
+        text_box.find(
+            search,
+            false,
+            SearchDirection::Backward,
+            SearchOptions::default(),
+        );
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 0, y: 1 });
+    }
+
+    #[test]
+    fn test_move_cursor_to_start_and_end_of_buffer() {
+        let mut text_box = new_text_box(
+            (0..100)
+                .map(|line| format!("line {line}"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+        text_box.change_caret_xy(Vec2u { x: 2, y: 3 });
+        text_box.previous_line_caret_max_x = Some(10);
+
+        text_box.move_cursor_to_end_of_buffer();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 7, y: 99 });
+        assert_eq!(text_box.scroll_offset, Vec2u { x: 0, y: 95 });
+        assert_eq!(text_box.previous_line_caret_max_x, None);
+
+        text_box.move_cursor_to_start_of_buffer();
+        assert_eq!(text_box.caret_pos(), Vec2u::ZERO);
+        assert_eq!(text_box.scroll_offset, Vec2u::ZERO);
+    }
+
+    #[test]
+    fn test_get_search_match_ordinal() {
+        let mut text_box = new_text_box("ab ab\nxyz\n\u{4f60}ab\naab ab");
+        let search_options = SearchOptions::default();
+        assert_eq!(text_box.count_matches("ab", search_options), 5);
+        assert_eq!(text_box.count_matches("nothing", search_options), 0);
+        assert_eq!(text_box.count_matches("", search_options), 0);
+
+        assert_eq!(
+            text_box.get_search_match_ordinal("ab", search_options),
+            (Some(1), 5)
+        );
+        text_box.change_caret_xy(Vec2u { x: 1, y: 0 });
+        assert_eq!(
+            text_box.get_search_match_ordinal("ab", search_options),
+            (None, 5)
+        );
+
+        let find_next = |text_box: &mut TextBox<VecTextBuffer>| {
+            assert!(
+                text_box
+                    .find("ab", false, SearchDirection::Forward, search_options)
+                    .is_some()
+            );
+            text_box.get_search_match_ordinal("ab", search_options)
+        };
+        assert_eq!(find_next(&mut text_box), (Some(2), 5));
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 3, y: 0 });
+        assert_eq!(find_next(&mut text_box), (Some(3), 5));
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 1, y: 2 });
+        assert_eq!(find_next(&mut text_box), (Some(4), 5));
+        assert_eq!(find_next(&mut text_box), (Some(5), 5));
+
+        // back to the first match after wrapping around
+        assert_eq!(find_next(&mut text_box), (Some(1), 5));
+        assert_eq!(text_box.caret_pos(), Vec2u::ZERO);
+
+        // matches spanning lines are only counted with a multiline search
+        assert_eq!(text_box.count_matches("ab\n", search_options), 0);
+        let search_options = SearchOptions {
+            multiline: true,
+            ..SearchOptions::default()
+        };
+        text_box.change_caret_xy(Vec2u { x: 1, y: 2 });
+        assert_eq!(
+            text_box.get_search_match_ordinal("ab\n", search_options),
+            (Some(2), 2)
+        );
+    }
+
+    #[test]
+    fn test_find_word_under_caret() {
+        let mut text_box = new_text_box("cat category\n  bobcat, cat += cat_");
+        let whole_word = SearchOptions {
+            whole_word: true,
+            ..SearchOptions::default()
+        };
+        assert_eq!(text_box.count_matches("cat", SearchOptions::default()), 5);
+        assert_eq!(text_box.count_matches("cat", whole_word), 2);
+
+        // the word that the caret is in, or right after
+        text_box.change_caret_xy(Vec2u { x: 1, y: 0 });
+        assert_eq!(text_box.word_under_caret(), Some("cat".to_string()));
+        text_box.change_caret_xy(Vec2u { x: 3, y: 0 });
+        assert_eq!(text_box.word_under_caret(), Some("cat".to_string()));
+        text_box.change_caret_xy(Vec2u { x: 15, y: 1 });
+        assert_eq!(text_box.word_under_caret(), None);
+
+        // the other occurrences are whole words only, and are selected
+        text_box.change_caret_xy(Vec2u { x: 3, y: 0 });
+        assert_eq!(
+            text_box
+                .find_word_under_caret(SearchDirection::Forward, SearchOptions::default())
+                .map(|(word, found)| (word, found.pos)),
+            Some(("cat".to_string(), TextBufferPos { line: 1, byte: 10 }))
+        );
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 10, y: 1 });
+        assert_eq!(
+            text_box.selection_range(),
+            Some(Vec2u { x: 10, y: 1 }..Vec2u { x: 13, y: 1 })
+        );
+        assert_eq!(
+            text_box.get_search_match_ordinal("cat", whole_word),
+            (Some(2), 2)
+        );
+        assert!(
+            text_box
+                .find_word_under_caret(SearchDirection::Forward, SearchOptions::default())
+                .is_some_and(|(_, found)| found.wrapped)
+        );
+        assert_eq!(text_box.caret_pos(), Vec2u::ZERO);
+        assert!(
+            text_box
+                .find_word_under_caret(SearchDirection::Backward, SearchOptions::default())
+                .is_some_and(|(_, found)| found.wrapped)
+        );
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 10, y: 1 });
+    }
+
+    #[test]
+    fn test_center_caret_vertically() {
+        let mut text_box = new_text_box(
+            (0..100)
+                .map(|line| format!("line {line}"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+
+        // the caret's line is in the middle of the 5 rows of the view
+        text_box.change_caret_xy(Vec2u { x: 0, y: 80 });
+        assert_eq!(text_box.scroll_offset().y, 76);
+        text_box.center_caret_vertically();
+        assert_eq!(text_box.scroll_offset().y, 78);
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 0, y: 80 });
+
+        // but the view does not scroll above the first line
+        text_box.change_caret_xy(Vec2u { x: 0, y: 1 });
+        text_box.center_caret_vertically();
+        assert_eq!(text_box.scroll_offset().y, 0);
+    }
+
+    #[test]
+    fn test_move_cursor_one_page() {
+        let mut text_box = new_text_box(
+            (0..20)
+                .map(|line| format!("line {line}"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+        let screen_row = |text_box: &TextBox<VecTextBuffer>| {
+            text_box
+                .get_grid_pos_from_caret_pos(text_box.caret_pos())
+                .y
+                .saturating_sub(text_box.scroll_offset().y)
+        };
+        text_box.change_caret_xy(Vec2u { x: 0, y: 2 });
+        assert_eq!(screen_row(&text_box), 2);
+
+        // the view scrolls along with the caret
+        text_box.move_cursor_down_one_page();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 0, y: 7 });
+        assert_eq!(text_box.scroll_offset().y, 5);
+        assert_eq!(screen_row(&text_box), 2);
+        text_box.move_cursor_down_one_page();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 0, y: 12 });
+        assert_eq!(screen_row(&text_box), 2);
+        text_box.move_cursor_up_one_page();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 0, y: 7 });
+        assert_eq!(screen_row(&text_box), 2);
+
+        // until it cannot scroll any further at the top
+        text_box.move_cursor_up_one_page();
+        text_box.move_cursor_up_one_page();
+        assert_eq!(text_box.caret_pos(), Vec2u::ZERO);
+        assert_eq!(text_box.scroll_offset().y, 0);
+
+        // or at the bottom
+        text_box.change_caret_xy(Vec2u { x: 0, y: 17 });
+        assert_eq!(text_box.scroll_offset().y, 13);
+        text_box.move_cursor_down_one_page();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 0, y: 20 });
+        assert_eq!(text_box.scroll_offset().y, 16);
+
+        // hidden lines are skipped over like a single row
+        text_box.change_caret_xy(Vec2u { x: 0, y: 1 });
+        text_box.scroll_to(Vec2u::ZERO);
+        text_box.folds.add(3..9);
+        text_box.move_cursor_down_one_page();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 0, y: 12 });
+        assert_eq!(screen_row(&text_box), 1);
+    }
+
+    #[test]
+    fn test_move_cursor_to_first_non_whitespace() {
+        let mut text_box = new_text_box("  \t let x;\n   \n\nx");
+
+        // an indented line
+        text_box.change_caret_xy(Vec2u { x: 7, y: 0 });
+        text_box.move_cursor_to_first_non_whitespace();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 4, y: 0 });
+
+        // a line of only whitespace goes to its end
+        text_box.change_caret_xy(Vec2u { x: 1, y: 1 });
+        text_box.move_cursor_to_first_non_whitespace();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 3, y: 1 });
+
+        // an empty line stays at its start
+        text_box.change_caret_xy(Vec2u { x: 0, y: 2 });
+        text_box.move_cursor_to_first_non_whitespace();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 0, y: 2 });
+
+        // a line that is not indented goes to its start
+        text_box.change_caret_xy(Vec2u { x: 1, y: 3 });
+        text_box.move_cursor_to_first_non_whitespace();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 0, y: 3 });
+    }
+
+    #[test]
+    fn test_auto_pair() {
+        let type_chars = |text_box: &mut TextBox<VecTextBuffer>, chars: &str| {
+            for ch in chars.chars() {
+                assert!(text_box.insert_character_at_cursor(ch).is_ok());
+            }
+        };
+
+        let mut text_box = new_text_box("");
+        assert!(!text_box.is_auto_pair());
+        type_chars(&mut text_box, "(");
+        assert_eq!(text_box.get_entire_contents_as_string(), "(");
+
+        let mut text_box = new_text_box("");
+        text_box.set_auto_pair(true);
+
+        // the closing character is inserted after the caret, nested too
+        type_chars(&mut text_box, "f({\"");
+        assert_eq!(text_box.get_entire_contents_as_string(), "f({\"\"})");
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 4, y: 0 });
+
+        // and typed over instead of typed again
+        type_chars(&mut text_box, "a\"})");
+        assert_eq!(text_box.get_entire_contents_as_string(), "f({\"a\"})");
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 8, y: 0 });
+
+        // a closing character that was typed by hand is typed again
+        type_chars(&mut text_box, ")");
+        assert_eq!(text_box.get_entire_contents_as_string(), "f({\"a\"}))");
+
+        // an empty pair is erased as a whole
+        text_box.set_contents("x = ");
+        text_box.move_cursor_to_end_of_line();
+        type_chars(&mut text_box, "[(");
+        assert_eq!(text_box.get_entire_contents_as_string(), "x = [()]");
+        assert!(text_box.erase_character_before_cursor().is_ok());
+        assert_eq!(text_box.get_entire_contents_as_string(), "x = []");
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 5, y: 0 });
+
+        // but not a pair that was typed by hand
+        text_box.set_contents("()");
+        text_box.change_caret_xy(Vec2u { x: 1, y: 0 });
+        assert!(text_box.erase_character_before_cursor().is_ok());
+        assert_eq!(text_box.get_entire_contents_as_string(), ")");
+
+        // nothing is paired before a word, nor a quote after one
+        text_box.set_contents("word");
+        type_chars(&mut text_box, "(");
+        assert_eq!(text_box.get_entire_contents_as_string(), "(word");
+        text_box.move_cursor_to_end_of_line();
+        type_chars(&mut text_box, "'");
+        assert_eq!(text_box.get_entire_contents_as_string(), "(word'");
+
+        // the closing characters are forgotten once the line is split
+        text_box.set_contents("");
+        type_chars(&mut text_box, "{\n");
+        assert_eq!(text_box.get_entire_contents_as_string(), "{\n}");
+        text_box.change_caret_xy(Vec2u { x: 0, y: 1 });
+        type_chars(&mut text_box, "}");
+        assert_eq!(text_box.get_entire_contents_as_string(), "{\n}}");
+
+        // the pair is undone together
+        text_box.set_contents("");
+        text_box.change_caret_xy(Vec2u::ZERO);
+        type_chars(&mut text_box, "(");
+        assert!(text_box.undo());
+        assert_eq!(text_box.get_entire_contents_as_string(), "");
+
+        // not in overwrite mode, nor in a single line text box
+        text_box.set_overwrite_mode(true);
+        type_chars(&mut text_box, "(");
+        assert_eq!(text_box.get_entire_contents_as_string(), "(");
+
+        let mut text_box = TextBox::new_single_line_text_box(VecTextBuffer::new());
+        text_box.set_auto_pair(true);
+        type_chars(&mut text_box, "(");
+        assert_eq!(text_box.get_entire_contents_as_string(), "(");
+    }
+
+    #[test]
+    fn test_read_only() {
+        let mut text_box = new_text_box("hello\nworld");
+        assert!(!text_box.is_read_only());
+        assert!(text_box.insert_character_at_cursor('a').is_ok());
+        assert!(text_box.is_dirty());
+
+        text_box.set_read_only(true);
+        text_box.set_is_dirty(false);
+
+        // the contents are left as they are, and the text box is not dirty
+        assert_eq!(
+            text_box.insert_character_at_cursor('b').err(),
+            Some(InsertCharError::ReadOnly)
+        );
+        assert_eq!(
+            text_box.erase_character_before_cursor().err(),
+            Some(RemoveCharError::ReadOnly)
+        );
+        assert_eq!(
+            text_box.erase_character_after_cursor().err(),
+            Some(RemoveCharError::ReadOnly)
+        );
+        text_box.insert_newline_at_cursor();
+        text_box.insert_newline_with_autoindent();
+        assert_eq!(
+            text_box.paste_at_cursor("pasted"),
+            Err(InsertCharError::ReadOnly)
+        );
+        assert_eq!(text_box.delete_current_line(), None);
+        assert!(!text_box.undo());
+        text_box.select_all();
+        assert_eq!(text_box.cut_selection(), None);
+        assert_eq!(text_box.replace_selection("replaced"), None);
+        assert!(!text_box.duplicate_selection());
+        assert_eq!(
+            text_box.apply_edits(vec![Edit::Insert {
+                pos: TextBufferPos::default(),
+                text: "edited".to_string(),
+            }]),
+            Err(EditError::ReadOnly)
+        );
+        assert_eq!(text_box.get_entire_contents_as_string(), "ahello\nworld");
+        assert!(!text_box.is_dirty());
+
+        // but the caret can still be moved around
+        text_box.clear_selection();
+        text_box.move_cursor_to_start_of_buffer();
+        text_box.move_cursor_down();
+        text_box.move_cursor_right();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 1, y: 1 });
+        text_box.move_cursor_to_end_of_line();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 5, y: 1 });
+
+        // and the contents searched
+        assert_eq!(text_box.count_matches("o", SearchOptions::default()), 2);
+
+        text_box.set_read_only(false);
+        assert!(text_box.undo());
+        assert_eq!(text_box.get_entire_contents_as_string(), "hello\nworld");
+    }
+
+    #[test]
+    fn test_marks() {
+        let mut text_box = new_text_box("a\nbb\nccc\ndddd");
+        assert!(!text_box.jump_to_mark('a'));
+
+        text_box.move_cursor_to_buffer_pos(TextBufferPos { line: 2, byte: 3 });
+        text_box.set_mark('a');
+
+        // the mark moves down with the line that it is on
+        text_box.move_cursor_to_start_of_buffer();
+        text_box.insert_newline_at_cursor();
+        assert!(text_box.jump_to_mark('a'));
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 3, y: 3 });
+
+        // a mark on a deleted line is moved to the line before it,
+        // and the caret kept within that line
+        text_box.move_cursor_to_end_of_buffer();
+        text_box.set_mark('b');
+        assert_eq!(text_box.delete_current_line(), Some("\ndddd".to_string()));
+        assert!(text_box.jump_to_mark('b'));
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 3, y: 3 });
+
+        // the marks are forgotten along with the contents
+        text_box.set_contents("a\nbb\nccc");
+        assert!(!text_box.jump_to_mark('a'));
+    }
+
+    #[test]
+    fn test_insert_character_at_cursor_overwrite_mode() {
+        let mut text_box = new_text_box("he\u{301}llo\n\u{4f60}\u{597d}");
+        assert!(!text_box.is_overwrite_mode());
+
+        // insert mode
+        text_box.change_caret_xy(Vec2u { x: 1, y: 0 });
+        assert!(text_box.insert_character_at_cursor('a').is_ok());
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "hae\u{301}llo\n\u{4f60}\u{597d}"
+        );
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 2, y: 0 });
+
+        // overwrite mode, combining grapheme is replaced as a single unit
+        text_box.set_overwrite_mode(true);
+        assert!(text_box.insert_character_at_cursor('b').is_ok());
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "habllo\n\u{4f60}\u{597d}"
+        );
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 3, y: 0 });
+
+        // appends at the end of the line
+        text_box.move_cursor_to_end_of_line();
+        assert!(text_box.insert_character_at_cursor('!').is_ok());
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "habllo!\n\u{4f60}\u{597d}"
+        );
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 7, y: 0 });
+
+        // full width grapheme replaced by a half width grapheme
+        text_box.change_caret_xy(Vec2u { x: 0, y: 1 });
+        assert!(text_box.insert_character_at_cursor('x').is_ok());
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "habllo!\nx\u{597d}"
+        );
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 1, y: 1 });
+        assert_eq!(
+            text_box.get_grid_pos_from_caret_pos(text_box.caret_pos()),
+            Vec2u { x: 1, y: 1 }
+        );
+    }
+
+    #[test]
+    fn test_insert_indent_at_cursor() {
+        let mut text_box = new_text_box("ab\tc\n\u{4f60}x");
+
+        // mid-line, the spaces go up to the next tab stop
+        text_box.change_caret_xy(Vec2u { x: 1, y: 0 });
+        assert!(
+            text_box
+                .insert_indent_at_cursor(IndentStyle::Spaces(4))
+                .is_ok()
+        );
+        assert_eq!(text_box.get_raw_line(0).as_deref(), Some("a   b\tc"));
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 4, y: 0 });
+
+        // tabs are counted by their rendered width
+        text_box.set_render_options(TextRenderOptions {
+            show_tabs: true,
+            ..TextRenderOptions::default()
+        });
+        text_box.change_caret_xy(Vec2u { x: 6, y: 0 });
+        assert!(
+            text_box
+                .insert_indent_at_cursor(IndentStyle::Spaces(4))
+                .is_ok()
+        );
+        assert_eq!(text_box.get_raw_line(0).as_deref(), Some("a   b\t    c"));
+
+        // full-width graphemes take two columns
+        text_box.change_caret_xy(Vec2u { x: 1, y: 1 });
+        assert!(
+            text_box
+                .insert_indent_at_cursor(IndentStyle::Spaces(4))
+                .is_ok()
+        );
+        assert_eq!(text_box.get_raw_line(1).as_deref(), Some("\u{4f60}  x"));
+
+        text_box.change_caret_xy(Vec2u { x: 0, y: 1 });
+        assert!(text_box.insert_indent_at_cursor(IndentStyle::Tabs).is_ok());
+        assert_eq!(text_box.get_raw_line(1).as_deref(), Some("\t\u{4f60}  x"));
+    }
+
+    #[test]
+    fn test_erase_indent_before_cursor() {
+        let mut text_box = new_text_box("      a  b\n\t  c");
+
+        // back to the previous indentation stop
+        text_box.change_caret_xy(Vec2u { x: 6, y: 0 });
+        assert!(text_box.erase_indent_before_cursor(IndentStyle::Spaces(4)));
+        assert_eq!(text_box.get_raw_line(0).as_deref(), Some("    a  b"));
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 4, y: 0 });
+        assert!(text_box.erase_indent_before_cursor(IndentStyle::Spaces(4)));
+        assert_eq!(text_box.get_raw_line(0).as_deref(), Some("a  b"));
+        assert!(!text_box.erase_indent_before_cursor(IndentStyle::Spaces(4)));
+
+        // only within the leading whitespace
+        text_box.change_caret_xy(Vec2u { x: 3, y: 0 });
+        assert!(!text_box.erase_indent_before_cursor(IndentStyle::Spaces(4)));
+        text_box.change_caret_xy(Vec2u { x: 3, y: 1 });
+        assert!(!text_box.erase_indent_before_cursor(IndentStyle::Spaces(4)));
+
+        assert!(!text_box.erase_indent_before_cursor(IndentStyle::Tabs));
+        assert_eq!(text_box.get_entire_contents_as_string(), "a  b\n\t  c");
+    }
+
+    #[test]
+    fn test_block_selection() {
+        let mut text_box = new_text_box("let a = 1;\nlet bb = 2;\nx\nlet c = 3;");
+        text_box.set_selection_mode(SelectionMode::Block);
+        assert_eq!(text_box.selection_mode(), SelectionMode::Block);
+
+        // an empty block across the lines, the short line is left alone
+        text_box.set_selection(Vec2u { x: 4, y: 0 }, Vec2u { x: 4, y: 3 });
+        assert!(text_box.insert_character_at_cursor('m').is_ok());
+        assert!(text_box.insert_character_at_cursor('_').is_ok());
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "let m_a = 1;\nlet m_bb = 2;\nx\nlet m_c = 3;"
+        );
+        assert_eq!(
+            text_box.selection_range(),
+            Some(Vec2u { x: 6, y: 0 }..Vec2u { x: 6, y: 3 })
+        );
+
+        assert!(text_box.erase_character_before_cursor().is_ok());
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "let ma = 1;\nlet mbb = 2;\nx\nlet mc = 3;"
+        );
+
+        // typing replaces the block on each line
+        text_box.end_undo_group();
+        text_box.set_selection(Vec2u { x: 4, y: 0 }, Vec2u { x: 6, y: 1 });
+        assert_eq!(text_box.copy_selection().as_deref(), Some("ma\nmb"));
+        assert!(text_box.insert_character_at_cursor('z').is_ok());
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "let z = 1;\nlet zb = 2;\nx\nlet mc = 3;"
+        );
+        assert_eq!(
+            text_box.selection_range(),
+            Some(Vec2u { x: 5, y: 0 }..Vec2u { x: 5, y: 1 })
+        );
+
+        // undone at once
+        text_box.end_undo_group();
+        assert!(text_box.undo());
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "let ma = 1;\nlet mbb = 2;\nx\nlet mc = 3;"
+        );
+
+        // the same columns are drawn as selected on each line
+        text_box.set_selection(Vec2u { x: 4, y: 0 }, Vec2u { x: 6, y: 3 });
+        let get_selected = |line_idx| {
+            let line_render =
+                TextLine::new(text_box.get_raw_line(line_idx)?, text_box.render_options());
+            text_box.get_selected_byte_range(line_idx, &line_render)
+        };
+        assert_eq!(get_selected(1), Some(4..6));
+        assert_eq!(get_selected(2), None);
+        assert_eq!(get_selected(3), Some(4..6));
+
+        // back to normal, the same selection is a stream of text
+        text_box.set_selection_mode(SelectionMode::Normal);
+        text_box.set_selection(Vec2u { x: 4, y: 0 }, Vec2u { x: 6, y: 1 });
+        assert_eq!(
+            text_box.copy_selection().as_deref(),
+            Some("ma = 1;\nlet mb")
+        );
+    }
+
+    #[test]
+    fn test_insert_vs_overwrite_mode() {
+        let type_keys = |overwrite_mode| {
+            let mut text_box = new_text_box("a\u{4f60}cd\nxyz");
+            text_box.set_overwrite_mode(overwrite_mode);
+            text_box.change_caret_xy(Vec2u { x: 1, y: 0 });
+            for ch in "bcde".chars() {
+                assert!(text_box.insert_character_at_cursor(ch).is_ok());
+            }
+            text_box
+        };
+
+        let mut insert_box = type_keys(false);
+        assert_eq!(
+            insert_box.get_entire_contents_as_string(),
+            "abcde\u{4f60}cd\nxyz"
+        );
+        assert_eq!(insert_box.caret_pos(), Vec2u { x: 5, y: 0 });
+
+        let mut overwrite_box = type_keys(true);
+        assert_eq!(overwrite_box.get_entire_contents_as_string(), "abcde\nxyz");
+        assert_eq!(overwrite_box.caret_pos(), Vec2u { x: 5, y: 0 });
+
+        // both are undone back to the original contents
+        for text_box in [&mut insert_box, &mut overwrite_box] {
+            assert!(text_box.undo());
+            assert_eq!(text_box.get_entire_contents_as_string(), "a\u{4f60}cd\nxyz");
+            assert_eq!(text_box.caret_pos(), Vec2u { x: 1, y: 0 });
+        }
+    }
+
+    #[test]
+    fn test_caret_pos_from_screen_pos_with_full_width() {
+        let mut text_box = new_text_box("a\u{4f60}\u{597d}b\nxy");
+        text_box.set_bounds(Bounds2f {
+            pos: Vec2f { x: 10.0, y: 2.0 },
+            size: Vec2f { x: 40.0, y: 5.0 },
+        });
+        let caret_at = |text_box: &TextBox<VecTextBuffer>, x, y| {
+            text_box.get_caret_pos_from_screen_pos(Vec2f { x, y })
+        };
+
+        assert_eq!(caret_at(&text_box, 11.0, 2.0), Vec2u { x: 1, y: 0 });
+        // either column of a full-width grapheme is the same grapheme
+        assert_eq!(caret_at(&text_box, 12.0, 2.0), Vec2u { x: 1, y: 0 });
+        assert_eq!(caret_at(&text_box, 13.0, 2.0), Vec2u { x: 2, y: 0 });
+        assert_eq!(caret_at(&text_box, 15.0, 2.0), Vec2u { x: 3, y: 0 });
+        // past the end of the line, and below the last line
+        assert_eq!(caret_at(&text_box, 30.0, 2.0), Vec2u { x: 4, y: 0 });
+        assert_eq!(caret_at(&text_box, 30.0, 6.0), Vec2u { x: 2, y: 1 });
+
+        // the inverse of where the caret is drawn
+        for x in 0..=4 {
+            let caret_pos = Vec2u { x, y: 0 };
+            let grid_pos = text_box.get_grid_pos_from_caret_pos(caret_pos);
+            let screen_pos = Vec2f {
+                x: 10.0 + grid_pos.x.lossy(),
+                y: 2.0,
+            };
+            assert_eq!(
+                text_box.get_caret_pos_from_screen_pos(screen_pos),
+                caret_pos
+            );
+        }
+
+        text_box.scroll_to(Vec2u { x: 3, y: 0 });
+        assert_eq!(caret_at(&text_box, 10.0, 2.0), Vec2u { x: 2, y: 0 });
+        assert_eq!(caret_at(&text_box, 12.0, 2.0), Vec2u { x: 3, y: 0 });
+    }
+
+    #[test]
+    fn test_control_code_points() {
+        let mut text_box = new_text_box("ab");
+        text_box.set_bounds(Bounds2f {
+            pos: Vec2f::ZERO,
+            size: Vec2f { x: 40.0, y: 5.0 },
+        });
+        text_box.set_render_options(TextRenderOptions {
+            show_control_code_points: true,
+            ..TextRenderOptions::default()
+        });
+
+        text_box.move_cursor_right();
+        assert!(text_box.insert_character_at_cursor('\u{7}').is_ok());
+        assert_eq!(text_box.get_entire_contents_as_string(), "a\u{7}b");
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 2, y: 0 });
+        assert_eq!(
+            text_box.get_grid_pos_from_caret_pos(text_box.caret_pos()),
+            Vec2u { x: 9, y: 0 }
+        );
+        assert_eq!(
+            text_box.get_caret_pos_from_screen_pos(Vec2f { x: 5.0, y: 0.0 }),
+            Vec2u { x: 1, y: 0 }
+        );
+
+        text_box.set_render_options(TextRenderOptions::default());
+        assert_eq!(
+            text_box.get_grid_pos_from_caret_pos(text_box.caret_pos()),
+            Vec2u { x: 2, y: 0 }
+        );
+    }
+
+    #[test]
+    fn test_scrollbar() {
+        assert_eq!(get_scrollbar_thumb(0, 10, 5), 0..10);
+        assert_eq!(get_scrollbar_thumb(0, 10, 40), 0..2);
+        assert_eq!(get_scrollbar_thumb(15, 10, 40), 4..6);
+        assert_eq!(get_scrollbar_thumb(30, 10, 40), 8..10);
+        // the caret can go beyond the last line
+        assert_eq!(get_scrollbar_thumb(35, 10, 40), 8..10);
+
+        let mut text_box = new_text_box(
+            (0..40)
+                .map(|line| format!("line {line} is long"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+        text_box.set_render_options(TextRenderOptions {
+            show_scrollbar: true,
+            ..TextRenderOptions::default()
+        });
+        text_box.set_bounds(Bounds2f {
+            pos: Vec2f::ZERO,
+            size: Vec2f { x: 10.0, y: 10.0 },
+        });
+        (0..24).for_each(|_| text_box.move_cursor_down());
+
+        let mut drawer = Drawer::new();
+        text_box.render(&mut drawer, &TextHighlights::new());
+        let screen = drawer.snapshot(10, 10);
+        assert_eq!(screen[0], "line 15 i░");
+        assert_eq!(screen[3], "line 18 i░");
+        assert_eq!(screen[4], "line 19 i█");
+        assert_eq!(screen[5], "line 20 i█");
+        assert_eq!(screen[6], "line 21 i░");
+    }
+
+    #[test]
+    fn test_visible_range() {
+        let mut text_box = new_text_box(
+            (0..10)
+                .map(|line| format!("{line}abcdefgh"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+        text_box.set_bounds(Bounds2f {
+            pos: Vec2f::ZERO,
+            size: Vec2f { x: 5.0, y: 3.0 },
+        });
+        text_box.scroll_to(Vec2u { x: 2, y: 4 });
+        assert_eq!(text_box.visible_line_range(), 4..7);
+        assert_eq!(text_box.visible_columns(), 2..7);
+
+        let mut drawer = Drawer::new();
+        text_box.render(&mut drawer, &TextHighlights::new());
+        assert_eq!(drawer.snapshot(5, 3), vec!["bcdef", "bcdef", "bcdef"]);
+
+        // the rendered lines are the ones in the range
+        text_box.scroll_to(Vec2u { x: 0, y: 4 });
+        let mut drawer = Drawer::new();
+        text_box.render(&mut drawer, &TextHighlights::new());
+        assert_eq!(drawer.snapshot(5, 3), vec!["4abcd", "5abcd", "6abcd"]);
+    }
+
+    #[test]
+    fn test_counts() {
+        let mut text_box = new_text_box("he\u{301}llo\nworld");
+        assert_eq!(
+            (
+                text_box.char_count(),
+                text_box.grapheme_count(),
+                text_box.word_count()
+            ),
+            (12, 11, 2)
+        );
+
+        // the counts follow edits, including undoing them
+        text_box.move_cursor_to_end_of_buffer();
+        assert!(text_box.insert_str_at_cursor(" and\u{301} more").is_ok());
+        assert_eq!(
+            (
+                text_box.char_count(),
+                text_box.grapheme_count(),
+                text_box.word_count()
+            ),
+            (22, 20, 4)
+        );
+        assert!(text_box.undo());
+        assert_eq!(text_box.char_count(), 12);
+
+        text_box.set_contents("");
+        assert_eq!(
+            (
+                text_box.char_count(),
+                text_box.grapheme_count(),
+                text_box.word_count()
+            ),
+            (0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_take_changed_lines() {
+        let mut text_box = new_text_box("zero\none\ntwo\nthree");
+        assert_eq!(text_box.take_changed_lines(), Some(0..4));
+        assert_eq!(text_box.take_changed_lines(), None);
+
+        text_box.change_caret_xy(Vec2u { x: 1, y: 1 });
+        text_box.insert_newline_at_cursor();
+        assert!(text_box.insert_character_at_cursor('x').is_ok());
+        assert_eq!(text_box.take_changed_lines(), Some(1..3));
+
+        // the last line is now the fifth one, and is moved back up
+        text_box.change_caret_xy(Vec2u { x: 0, y: 4 });
+        assert!(text_box.erase_character_before_cursor().is_ok());
+        assert_eq!(text_box.take_changed_lines(), Some(3..4));
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "zero\no\nxne\ntwothree"
+        );
+    }
+
+    #[test]
+    fn test_take_line_shifts() {
+        let mut text_box = new_text_box("zero\none\ntwo");
+        assert!(text_box.take_line_shifts().is_empty());
+
+        // only the edits that add or remove lines, in order
+        text_box.change_caret_xy(Vec2u { x: 1, y: 1 });
+        text_box.insert_newline_at_cursor();
+        assert!(text_box.insert_character_at_cursor('x').is_ok());
+        assert!(text_box.insert_str_at_cursor("a\nb\nc").is_ok());
+        text_box.change_caret_xy(Vec2u { x: 0, y: 1 });
+        assert!(text_box.erase_character_before_cursor().is_ok());
+        assert_eq!(
+            text_box.take_line_shifts(),
+            vec![
+                LineShift::Split {
+                    line_idx: 1,
+                    new_lines: 1
+                },
+                LineShift::Split {
+                    line_idx: 2,
+                    new_lines: 2
+                },
+                LineShift::Join {
+                    line_idx: 0,
+                    removed_lines: 1
+                },
+            ]
+        );
+        assert!(text_box.take_line_shifts().is_empty());
+
+        // replacing the contents forgets them
+        text_box.insert_newline_at_cursor();
+        text_box.set_contents("zero");
+        assert!(text_box.take_line_shifts().is_empty());
+    }
+
+    #[test]
+    fn test_line_renders() {
+        let mut text_box = new_text_box("zero\none\ntwo");
+        let render = |text_box: &TextBox<VecTextBuffer>, line_idx| {
+            text_box
+                .get_line_render(line_idx)
+                .expect("line to be rendered")
         };
+        let one = render(&text_box, 1);
+        assert!(Rc::ptr_eq(&render(&text_box, 1), &one));
+
+        // the unchanged lines are reused, even after moving down
+        text_box.change_caret_xy(Vec2u { x: 2, y: 0 });
+        text_box.insert_newline_at_cursor();
+        assert!(Rc::ptr_eq(&render(&text_box, 2), &one));
+        assert_eq!(render(&text_box, 0).to_string(), "ze");
+        assert_eq!(render(&text_box, 1).to_string(), "ro");
+
+        // while a changed line is rendered again
+        text_box.change_caret_xy(Vec2u { x: 3, y: 2 });
+        assert!(text_box.insert_character_at_cursor('\u{301}').is_ok());
+        let one_accented = render(&text_box, 2);
+        assert!(!Rc::ptr_eq(&one_accented, &one));
+        assert_eq!(one_accented.to_string(), "one\u{301}");
+        assert_eq!(one_accented.get_line_len(), 3);
+        assert_eq!(text_box.get_line_len(2), 3);
+    }
+
+    #[test]
+    fn test_dirty_lines() {
+        let mut text_box = new_text_box("zero\none\ntwo\nthree\nfour");
+        text_box.set_is_dirty(false);
+        assert_eq!(text_box.dirty_lines(), [false; 5]);
+        assert!(!text_box.has_dirty_lines());
+
+        text_box.change_caret_xy(Vec2u { x: 2, y: 3 });
+        assert!(text_box.insert_character_at_cursor('x').is_ok());
+        assert_eq!(text_box.dirty_lines(), [false, false, false, true, false]);
+        assert!(text_box.has_dirty_lines());
+
+        // the lines after a new line move down with it
+        text_box.change_caret_xy(Vec2u { x: 1, y: 1 });
+        text_box.insert_newline_at_cursor();
+        assert_eq!(
+            text_box.dirty_lines(),
+            [false, true, true, false, true, false]
+        );
+
+        // and back up when lines are joined
+        text_box.change_caret_xy(Vec2u { x: 0, y: 5 });
+        assert!(text_box.erase_character_before_cursor().is_ok());
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "zero\no\nne\ntwo\nthxreefour"
+        );
+        assert_eq!(text_box.dirty_lines(), [false, true, true, false, true]);
+
+        // saving starts over
+        text_box.set_is_dirty(false);
+        assert_eq!(text_box.dirty_lines(), [false; 5]);
+        assert!(!text_box.has_dirty_lines());
+        // undoing the changes changes the lines again
+        assert!(text_box.undo());
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "zero\none\ntwo\nthree\nfour"
+        );
+        assert_eq!(text_box.dirty_lines(), [false, true, false, true, true]);
+    }
+
+    #[test]
+    fn test_scroll_by() {
+        let mut text_box = new_text_box(
+            (0..10)
+                .map(|line| format!("{line}abcdefgh"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+        text_box.set_bounds(Bounds2f {
+            pos: Vec2f::ZERO,
+            size: Vec2f { x: 5.0, y: 3.0 },
+        });
+        text_box.change_caret_xy(Vec2u { x: 1, y: 1 });
+
+        // the caret stays where it is, even though it is scrolled off the view
+        text_box.scroll_by(Vec2i { x: 0, y: 5 });
+        assert_eq!(text_box.scroll_offset(), Vec2u { x: 0, y: 5 });
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 1, y: 1 });
+
+        // not past the last line, nor before the first line
+        text_box.scroll_by(Vec2i { x: 0, y: 100 });
+        assert_eq!(text_box.scroll_offset(), Vec2u { x: 0, y: 9 });
+        text_box.scroll_by(Vec2i { x: 0, y: -100 });
+        assert_eq!(text_box.scroll_offset(), Vec2u::ZERO);
+        text_box.scroll_by(Vec2i { x: -1, y: 0 });
+        assert_eq!(text_box.scroll_offset(), Vec2u::ZERO);
+
+        // moving the caret brings it back into view
+        text_box.scroll_by(Vec2i { x: 0, y: 7 });
+        text_box.move_cursor_down();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 1, y: 2 });
+        assert_eq!(text_box.scroll_offset(), Vec2u { x: 0, y: 2 });
+
+        text_box.scroll_by(Vec2i { x: 4, y: -2 });
+        assert_eq!(text_box.scroll_offset(), Vec2u { x: 4, y: 0 });
+        text_box.move_cursor_left();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 0, y: 2 });
+        assert_eq!(text_box.scroll_offset(), Vec2u { x: 0, y: 0 });
+    }
 
-        self.contents.insert_character_at_pos(buffer_pos, ch)?;
-        let line_len_increased = TextLine::new(
-            self.contents
-                .line(self.caret_pos.y.to_usize())
-                .expect("line to exist since we just modified it"),
-        )
-        .get_line_len()
-            > target_line_render.get_line_len();
+    #[test]
+    fn test_duplicate_selection() {
+        let mut text_box = new_text_box("fn main() {\n    foo();\n}");
 
-        self.is_dirty = true;
-        if line_len_increased {
-            self.change_caret_x(self.caret_pos.x.saturating_add(1));
-        }
+        // the copy is inserted right after the selection, and selected
+        text_box.set_selection(Vec2u { x: 9, y: 0 }, Vec2u { x: 10, y: 1 });
+        assert!(text_box.duplicate_selection());
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "fn main() {\n    foo(); {\n    foo();\n}"
+        );
+        assert_eq!(
+            text_box.selection_range(),
+            Some(Vec2u { x: 10, y: 1 }..Vec2u { x: 10, y: 2 })
+        );
 
-        Ok(InsertCharResult { line_len_increased })
+        // without a selection, the caret's line is duplicated
+        text_box.change_caret_xy(Vec2u { x: 2, y: 0 });
+        assert!(text_box.duplicate_selection());
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "fn main() {\nfn main() {\n    foo(); {\n    foo();\n}"
+        );
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 2, y: 1 });
+        assert_eq!(text_box.selection_range(), None);
     }
 
-    fn remove_character(
-        &mut self,
-        line_idx: usize,
-        fragment_idx: usize,
-    ) -> Result<RemoveCharResult, RemoveCharError> {
-        // TODO: This is not efficient
-        let target_line_render = match self.contents.line(line_idx) {
-            Some(line) => TextLine::new(line),
-            None => return Err(RemoveCharError::InvalidLinePosition),
-        };
+    #[test]
+    fn test_move_line() {
+        let mut text_box = new_text_box("one\ntwo\nthree");
+        text_box.set_is_dirty(false);
 
-        // TODO: This logic can be further refactored (it is copied and slightly modified across multiple functions)
-        let buffer_pos = TextBufferPos {
-            line: line_idx,
-            byte: match target_line_render.get_byte_idx_from_fragment_idx(fragment_idx) {
-                Some(byte) => byte,
-                None => return Err(RemoveCharError::InvalidBytePosition),
-            },
-        };
+        // the first line cannot be moved up
+        text_box.change_caret_xy(Vec2u { x: 2, y: 0 });
+        assert!(!text_box.move_line_up());
+        assert_eq!(text_box.get_entire_contents_as_string(), "one\ntwo\nthree");
 
-        self.contents.remove_character_at_pos(buffer_pos)?;
-        self.is_dirty = true;
+        assert!(text_box.move_line_down());
+        assert_eq!(text_box.get_entire_contents_as_string(), "two\none\nthree");
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 2, y: 1 });
+        assert_eq!(text_box.dirty_lines(), [true, true, false]);
 
-        if let Some(new_line_render) = self.contents.line(line_idx).map(TextLine::new) {
-            Ok(RemoveCharResult {
-                line_len_decreased: new_line_render.get_line_len()
-                    < target_line_render.get_line_len(),
-            })
-        } else {
-            Ok(RemoveCharResult {
-                line_len_decreased: true,
-            })
-        }
+        assert!(text_box.move_line_down());
+        assert_eq!(text_box.get_entire_contents_as_string(), "two\nthree\none");
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 2, y: 2 });
+
+        // the last line cannot be moved down
+        assert!(!text_box.move_line_down());
+        assert_eq!(text_box.get_entire_contents_as_string(), "two\nthree\none");
+
+        text_box.change_caret_xy(Vec2u { x: 5, y: 1 });
+        assert!(text_box.move_line_up());
+        assert_eq!(text_box.get_entire_contents_as_string(), "three\ntwo\none");
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 5, y: 0 });
     }
 
-    pub fn erase_character_before_cursor(&mut self) -> Result<RemoveCharResult, RemoveCharError> {
-        if self.caret_pos.x > 0 {
-            let result = self.remove_character(
-                self.caret_pos.y.to_usize(),
-                self.caret_pos.x.saturating_sub(1).to_usize(),
-            );
+    #[test]
+    fn test_join_with_next_line() {
+        let mut text_box = new_text_box("    if a {\n        b();\n\n    }");
 
-            if let Ok(result) = &result {
-                if result.line_len_decreased {
-                    self.change_caret_x(self.caret_pos.x.saturating_sub(1));
-                    self.is_dirty = true;
-                }
-            }
+        // the indentation of the joined line becomes a single space
+        assert!(text_box.join_with_next_line());
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "    if a { b();\n\n    }"
+        );
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 10, y: 0 });
 
-            self.previous_line_caret_max_x.take();
-            result
-        } else if self.caret_pos.y > 0 && !self.single_line_mode {
-            if self.caret_pos.y == self.contents.total_lines().to_u64() {
-                // TODO: this part exist because right now our cursor can actually go
-                // beyond the last line (in order to allow insert beyond the last line),
-                // but that design will no longer make sense when we introduce vim motions.
-                // At that point, we should revisit this code, and perhaps remove this
-                // special case.
-                self.previous_line_caret_max_x.take();
-                return Ok(RemoveCharResult {
-                    line_len_decreased: false,
-                });
-            }
+        // nothing is added for an empty line
+        assert!(text_box.join_with_next_line());
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "    if a { b();\n    }"
+        );
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 15, y: 0 });
+        // or onto an empty line, which is left without indentation
+        text_box.change_caret_xy(Vec2u { x: 0, y: 1 });
+        text_box.insert_newline_at_cursor();
+        text_box.end_undo_group();
+        text_box.change_caret_xy(Vec2u { x: 0, y: 1 });
+        assert!(text_box.join_with_next_line());
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "    if a { b();\n}"
+        );
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 0, y: 1 });
 
-            let previous_line_fragments_len = self
-                .get_line_len(self.caret_pos.y.saturating_sub(1).to_usize())
-                .to_u64();
+        // the last line has nothing to join
+        assert!(!text_box.join_with_next_line());
 
-            if let Some(previous_line_len) = self
-                .contents
-                .line_len(self.caret_pos.y.saturating_sub(1).to_usize())
-            {
-                self.contents.remove_character_at_pos(TextBufferPos {
-                        line: self.caret_pos.y.saturating_sub(1).to_usize(),
-                        byte: previous_line_len,
-                    }).expect("previous line should exist, and it is legal to remove the pos right after the last character");
+        text_box.end_undo_group();
+        assert!(text_box.undo());
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "    if a { b();\n\n    }"
+        );
+    }
 
-                self.change_caret_xy(Vec2u {
-                    x: previous_line_fragments_len,
-                    y: self.caret_pos.y.saturating_sub(1),
-                });
+    #[test]
+    fn test_indent_lines() {
+        let mut text_box = new_text_box("fn main() {\nfoo();\n\nbar();\n}");
 
-                self.is_dirty = true;
-            }
+        // the selection ends at the start of the last line, which is left alone
+        text_box.set_selection(Vec2u { x: 0, y: 1 }, Vec2u { x: 0, y: 4 });
+        assert_eq!(text_box.selected_lines(), 1..4);
+        assert!(text_box.indent_lines(text_box.selected_lines(), IndentStyle::Spaces(4)));
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "fn main() {\n    foo();\n\n    bar();\n}"
+        );
+        assert_eq!(
+            text_box.selection_range(),
+            Some(Vec2u { x: 0, y: 1 }..Vec2u { x: 0, y: 4 })
+        );
 
-            self.previous_line_caret_max_x.take();
+        // the caret stays on the same text
+        text_box.change_caret_xy(Vec2u { x: 2, y: 0 });
+        assert!(text_box.indent_lines(0..1, IndentStyle::Tabs));
+        assert_eq!(text_box.get_raw_line(0).as_deref(), Some("\tfn main() {"));
+        assert_eq!(
+            text_box.caret_buffer_pos(),
+            TextBufferPos { line: 0, byte: 3 }
+        );
 
-            Ok(RemoveCharResult {
-                // the (formally) active line was completely wiped out
-                // as it gets absorbed by the line above it
-                line_len_decreased: true,
-            })
-        } else {
-            self.previous_line_caret_max_x.take();
+        assert!(!text_box.indent_lines(2..3, IndentStyle::Tabs));
+    }
 
-            Ok(RemoveCharResult {
-                line_len_decreased: false,
-            })
-        }
+    #[test]
+    fn test_dedent_lines() {
+        let mut text_box = new_text_box("      a\n  b\n\t\tc\nd\n     ");
+        text_box.set_selection(Vec2u { x: 0, y: 0 }, Vec2u { x: 1, y: 3 });
+        assert!(text_box.dedent_lines(text_box.selected_lines(), IndentStyle::Spaces(4)));
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "  a\nb\n\tc\nd\n     "
+        );
+        assert_eq!(
+            text_box.selection_range(),
+            Some(Vec2u { x: 0, y: 0 }..Vec2u { x: 1, y: 3 })
+        );
+
+        // only whitespace is removed
+        assert!(text_box.dedent_lines(0..5, IndentStyle::Spaces(4)));
+        assert_eq!(text_box.get_entire_contents_as_string(), "a\nb\nc\nd\n ");
+        assert!(!text_box.dedent_lines(0..4, IndentStyle::Spaces(4)));
+        assert_eq!(text_box.get_entire_contents_as_string(), "a\nb\nc\nd\n ");
     }
 
-    pub fn erase_character_after_cursor(&mut self) -> Result<RemoveCharResult, RemoveCharError> {
-        if self.caret_pos.x < self.get_line_len(self.caret_pos.y.to_usize()).to_u64() {
-            let result =
-                self.remove_character(self.caret_pos.y.to_usize(), self.caret_pos.x.to_usize());
+    #[test]
+    fn test_replace_selection() {
+        let mut text_box = new_text_box("one\ntwo three");
+        assert_eq!(text_box.replace_selection("four"), None);
 
-            if result.is_ok() {
-                self.is_dirty = result.is_ok();
-                self.previous_line_caret_max_x.take();
-            }
+        text_box.set_selection(Vec2u { x: 1, y: 0 }, Vec2u { x: 3, y: 1 });
+        assert_eq!(
+            text_box.replace_selection("ff\nfi").as_deref(),
+            Some("ne\ntwo")
+        );
+        assert_eq!(text_box.get_entire_contents_as_string(), "off\nfi three");
+        assert_eq!(
+            text_box.selection_range(),
+            Some(Vec2u { x: 1, y: 0 }..Vec2u { x: 2, y: 1 })
+        );
+    }
 
-            result
-        } else if self.caret_pos.y < self.get_total_lines().to_u64() && !self.single_line_mode {
-            let line_len = self
-                .contents
-                .line_len(self.caret_pos.y.to_usize())
-                .expect("line should exist");
+    #[test]
+    fn test_indent_guides() {
+        let mut text_box = new_text_box(
+            "mod a {\n    fn b() {\n        if c {\n            d();\n\n            e();\n        }\n    }\n}",
+        );
+        text_box.set_render_options(TextRenderOptions {
+            show_indent_guides: true,
+            ..TextRenderOptions::default()
+        });
+        text_box.set_bounds(Bounds2f {
+            pos: Vec2f::ZERO,
+            size: Vec2f { x: 20.0, y: 9.0 },
+        });
 
-            if self
-                .contents
-                .remove_character_at_pos(TextBufferPos {
-                    line: self.caret_pos.y.to_usize(),
-                    byte: line_len,
-                })
-                .is_ok()
-            {
-                self.is_dirty = true;
-            }
+        let mut drawer = Drawer::new();
+        text_box.render(&mut drawer, &TextHighlights::new());
+        let screen = drawer.snapshot(20, 9);
+        assert_eq!(screen[0].trim_end(), "mod a {");
+        assert_eq!(screen[1].trim_end(), "│   fn b() {");
+        assert_eq!(screen[2].trim_end(), "│   │   if c {");
+        assert_eq!(screen[3].trim_end(), "│   │   │   d();");
+        // blank lines take the indentation of the lines around them
+        assert_eq!(screen[4].trim_end(), "│   │   │");
+        assert_eq!(screen[5].trim_end(), "│   │   │   e();");
+        assert_eq!(screen[6].trim_end(), "│   │   }");
+        assert_eq!(screen[7].trim_end(), "│   }");
+        assert_eq!(screen[8].trim_end(), "}");
+    }
 
-            self.previous_line_caret_max_x.take();
+    #[test]
+    fn test_apply_edits() {
+        let pos = |line, byte| TextBufferPos { line, byte };
+        let mut text_box = new_text_box("let a = 1;\nlet b = 2;");
+        text_box.change_caret_xy(Vec2u { x: 4, y: 1 });
 
-            Ok(RemoveCharResult {
-                // the active line length actually increased / stay the same, it can't decrease
-                line_len_decreased: false,
-            })
-        } else {
-            self.previous_line_caret_max_x.take();
-            Ok(RemoveCharResult {
-                line_len_decreased: false,
-            })
-        }
+        let revert_edits = text_box
+            .apply_edits(vec![
+                Edit::Remove {
+                    start: pos(0, 4),
+                    end: pos(1, 4),
+                },
+                Edit::Insert {
+                    pos: pos(0, 4),
+                    text: "x = 0;\nlet ".to_string(),
+                },
+                Edit::Insert {
+                    pos: pos(1, 10),
+                    text: "\n".to_string(),
+                },
+            ])
+            .expect("edits are valid");
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "let x = 0;\nlet b = 2;\n"
+        );
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 4, y: 1 });
+
+        // the whole transaction is undone at once
+        text_box.end_undo_group();
+        assert!(text_box.undo());
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "let a = 1;\nlet b = 2;"
+        );
+        assert!(text_box.redo());
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "let x = 0;\nlet b = 2;\n"
+        );
+
+        // reverting is a single transaction too
+        text_box
+            .apply_edits(revert_edits)
+            .expect("revert edits are valid");
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "let a = 1;\nlet b = 2;"
+        );
+        text_box.end_undo_group();
+        text_box.set_is_dirty(false);
+
+        // an invalid edit leaves the contents untouched
+        assert_eq!(
+            text_box.apply_edits(vec![
+                Edit::Insert {
+                    pos: pos(0, 0),
+                    text: "// ".to_string(),
+                },
+                Edit::Remove {
+                    start: pos(1, 4),
+                    end: pos(1, 40),
+                },
+            ]),
+            Err(EditError::InvalidEdit { edit_idx: 1 })
+        );
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "let a = 1;\nlet b = 2;"
+        );
+        assert!(!text_box.is_dirty());
+        assert_eq!(text_box.dirty_lines(), [false, false]);
+        // and nothing to undo, so undoing reverts the revert instead
+        text_box.end_undo_group();
+        assert!(text_box.undo());
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "let x = 0;\nlet b = 2;\n"
+        );
     }
 
-    pub fn insert_newline_at_cursor(&mut self) {
-        if self.single_line_mode {
-            return;
-        }
+    #[test]
+    fn test_folds_follow_edits() {
+        let pos = |line, byte| TextBufferPos { line, byte };
+        let mut text_box = new_text_box("a\nfn f() {\n1\n2\n}\nb");
+        text_box.fold_lines(2..4);
 
-        assert!(self.caret_pos.y <= self.get_total_lines().to_u64());
+        // the fold moves with lines added or removed before it
+        text_box
+            .apply_edits(vec![Edit::Insert {
+                pos: pos(0, 0),
+                text: "x\ny\n".to_string(),
+            }])
+            .expect("edits are valid");
+        assert_eq!(text_box.folded_lines(), vec![4..6]);
+        text_box.end_undo_group();
+        assert!(text_box.undo());
+        assert_eq!(text_box.folded_lines(), vec![2..4]);
 
-        // TODO: This is not efficient
-        let target_line_render = match self.contents.line(self.caret_pos.y.to_usize()) {
-            Some(line) => TextLine::new(line),
-            None => TextLine::new(""),
-        };
+        // and is only dropped once its hidden lines are removed
+        text_box
+            .apply_edits(vec![Edit::Remove {
+                start: pos(2, 0),
+                end: pos(3, 0),
+            }])
+            .expect("edits are valid");
+        assert_eq!(text_box.folded_lines(), vec![2..3]);
+        text_box
+            .apply_edits(vec![Edit::Remove {
+                start: pos(1, 8),
+                end: pos(2, 1),
+            }])
+            .expect("edits are valid");
+        assert!(text_box.folded_lines().is_empty());
+    }
 
-        // TODO: This logic can be further refactored (it is copied and slightly modified across multiple functions)
-        let buffer_pos = TextBufferPos {
-            line: self.caret_pos.y.to_usize(),
-            byte: match target_line_render
-                .get_byte_idx_from_fragment_idx(self.caret_pos.x.to_usize())
-            {
-                Some(byte) => byte,
-                None => return,
-            },
-        };
+    #[test]
+    fn test_sticky_horizontal_scroll() {
+        let contents = "0123456789abcdefghij\nshort\n0123456789abcdefghij";
 
-        let insert_successful = self
-            .contents
-            .insert_character_at_pos(buffer_pos, '\n')
-            .is_ok();
+        // by default, the scroll follows the caret to the end of the short line
+        let mut text_box = new_text_box(contents);
+        text_box.move_cursor_to_end_of_line();
+        assert_eq!(text_box.scroll_offset.x, 11);
+        text_box.move_cursor_down();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 5, y: 1 });
+        assert_eq!(text_box.scroll_offset.x, 5);
+        text_box.move_cursor_down();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 20, y: 2 });
+        assert_eq!(text_box.scroll_offset.x, 11);
 
-        self.change_caret_xy(Vec2u {
-            x: 0,
-            y: self.caret_pos.y.saturating_add(1),
+        let mut text_box = new_text_box(contents);
+        text_box.set_render_options(TextRenderOptions {
+            sticky_horizontal_scroll: true,
+            ..TextRenderOptions::default()
         });
+        text_box.move_cursor_to_end_of_line();
+        assert_eq!(text_box.scroll_offset.x, 11);
+        text_box.move_cursor_down();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 5, y: 1 });
+        assert_eq!(text_box.scroll_offset.x, 11);
+        text_box.move_cursor_down();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 20, y: 2 });
+        assert_eq!(text_box.scroll_offset.x, 11);
 
-        if insert_successful {
-            self.is_dirty = true;
-        }
+        // moving along the short line brings the caret back into view
+        text_box.move_cursor_up();
+        text_box.move_cursor_left();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 4, y: 1 });
+        assert_eq!(text_box.scroll_offset.x, 4);
     }
 
-    // TODO: When we use a backend text object (like ropey), this method shouldn't be here
-    pub fn get_entire_contents_as_string(&self) -> String {
-        self.contents.contents()
+    #[test]
+    fn test_delete_to_end_of_line() {
+        let mut text_box = new_text_box("let a = 1;\nlet b = 2;");
+        text_box.move_cursor_right();
+        text_box.move_cursor_right();
+        text_box.move_cursor_right();
+        assert_eq!(text_box.delete_to_end_of_line().as_deref(), Some(" a = 1;"));
+        assert_eq!(text_box.get_entire_contents_as_string(), "let\nlet b = 2;");
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 3, y: 0 });
+
+        // at the end of the line, the next line is joined
+        assert_eq!(text_box.delete_to_end_of_line().as_deref(), Some("\n"));
+        assert_eq!(text_box.get_entire_contents_as_string(), "letlet b = 2;");
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 3, y: 0 });
+
+        text_box.move_cursor_to_end_of_line();
+        assert_eq!(text_box.delete_to_end_of_line(), None);
+        assert_eq!(text_box.get_entire_contents_as_string(), "letlet b = 2;");
     }
 
-    // TODO: When we use a backend text object (like ropey), this method shouldn't be here
-    pub fn get_raw_line(&self, line_idx: usize) -> Option<String> {
-        self.contents.line(line_idx)
+    #[test]
+    fn test_delete_current_line() {
+        let mut text_box = new_text_box("one\ntwo\nthree\nfour");
+        text_box.set_is_dirty(false);
+
+        // first line, the caret moves to the start of the next line
+        text_box.change_caret_xy(Vec2u { x: 2, y: 0 });
+        assert_eq!(text_box.delete_current_line().as_deref(), Some("one\n"));
+        assert_eq!(text_box.get_entire_contents_as_string(), "two\nthree\nfour");
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 0, y: 0 });
+        assert!(text_box.is_dirty());
+
+        // middle line
+        text_box.change_caret_xy(Vec2u { x: 3, y: 1 });
+        assert_eq!(text_box.delete_current_line().as_deref(), Some("three\n"));
+        assert_eq!(text_box.get_entire_contents_as_string(), "two\nfour");
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 0, y: 1 });
+
+        // last line, the caret moves to the start of the previous line
+        assert_eq!(text_box.delete_current_line().as_deref(), Some("\nfour"));
+        assert_eq!(text_box.get_entire_contents_as_string(), "two");
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 0, y: 0 });
+
+        // the only line is emptied
+        assert_eq!(text_box.delete_current_line().as_deref(), Some("two"));
+        assert_eq!(text_box.get_entire_contents_as_string(), "");
+        assert_eq!(text_box.caret_pos(), Vec2u::ZERO);
+        assert_eq!(text_box.delete_current_line(), None);
     }
 
-    // TODO: When we use a backend text object (like ropey), this method shouldn't be here
-    pub fn get_total_lines(&self) -> usize {
-        self.contents.total_lines()
+    #[test]
+    fn test_delete_to_start_of_line() {
+        let mut text_box = new_text_box("let a = 1;\nlet b = 2;");
+        text_box.move_cursor_down();
+        text_box.move_cursor_to_end_of_line();
+        text_box.move_cursor_left();
+        assert_eq!(
+            text_box.delete_to_start_of_line().as_deref(),
+            Some("let b = 2")
+        );
+        assert_eq!(text_box.get_entire_contents_as_string(), "let a = 1;\n;");
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 0, y: 1 });
+
+        assert_eq!(text_box.delete_to_start_of_line(), None);
+        assert_eq!(text_box.get_entire_contents_as_string(), "let a = 1;\n;");
     }
 
-    fn find_in_contents<T: AsRef<str>>(
-        &self,
-        search: T,
-        start_pos: Vec2u,
-        search_direction: SearchDirection,
-    ) -> Option<Vec2u> {
-        // TODO: This is not efficient
-        let target_line_render = if start_pos.y == self.contents.total_lines().to_u64() {
-            TextLine::new("")
-        } else {
-            match self.contents.line(start_pos.y.to_usize()) {
-                Some(line) => TextLine::new(line),
-                None => return None,
+    #[test]
+    fn test_undo_redo() {
+        let mut text_box = new_text_box("ab\ncd");
+        text_box.move_cursor_to_end_of_line();
+        let type_text = |text_box: &mut TextBox<VecTextBuffer>, text: &str| {
+            for ch in text.chars() {
+                text_box
+                    .insert_character_at_cursor(ch)
+                    .expect("able to insert character");
+                text_box.end_undo_group();
             }
         };
 
-        // TODO: This logic can be further refactored (it is copied and slightly modified across multiple functions)
-        let buffer_pos = TextBufferPos {
-            line: start_pos.y.to_usize(),
-            byte: target_line_render.get_byte_idx_from_fragment_idx(start_pos.x.to_usize())?,
-        };
+        // a typed word is undone in one step, apart from the whitespace before the next word
+        type_text(&mut text_box, "xy zw");
+        text_box.insert_newline_at_cursor();
+        text_box.end_undo_group();
+        assert_eq!(text_box.get_entire_contents_as_string(), "abxy zw\n\ncd");
 
-        self.contents
-            .find(search.as_ref(), buffer_pos, search_direction)
-            .map(|result| {
-                let final_line_render = TextLine::new(
-                    self.contents
-                        .line(result.line)
-                        .expect("result should return a valid line"),
-                );
-                let final_fragment_idx = final_line_render
-                    .get_fragment_idx_from_byte_idx(result.byte)
-                    .expect("result should return a valid byte index");
+        assert!(text_box.undo());
+        assert_eq!(text_box.get_entire_contents_as_string(), "abxy zw\ncd");
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 7, y: 0 });
+        assert!(text_box.undo());
+        assert_eq!(text_box.get_entire_contents_as_string(), "abxy\ncd");
+        assert!(text_box.undo());
+        assert_eq!(text_box.get_entire_contents_as_string(), "ab\ncd");
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 2, y: 0 });
+        assert!(!text_box.undo());
 
-                Vec2u {
-                    x: final_fragment_idx.to_u64(),
-                    y: result.line.to_u64(),
-                }
-            })
-    }
+        assert!(text_box.redo());
+        assert_eq!(text_box.get_entire_contents_as_string(), "abxy\ncd");
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 4, y: 0 });
 
-    pub fn enter_search_mode(&mut self) {
-        self.before_search_caret_pos = Some(self.caret_pos);
-        self.before_search_scroll_offset = Some(self.scroll_offset);
+        // joining lines is undone too
+        text_box.move_cursor_down();
+        text_box.move_cursor_to_start_of_line();
+        text_box
+            .erase_character_before_cursor()
+            .expect("able to erase character");
+        text_box.end_undo_group();
+        assert_eq!(text_box.get_entire_contents_as_string(), "abxycd");
+        // a new change cannot be followed by the changes undone before it
+        assert!(!text_box.redo());
+
+        assert!(text_box.undo());
+        assert_eq!(text_box.get_entire_contents_as_string(), "abxy\ncd");
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 0, y: 1 });
+        assert!(text_box.redo());
+        assert_eq!(text_box.get_entire_contents_as_string(), "abxycd");
+
+        // text inserted at once is recorded as a single op
+        text_box.move_cursor_to_start_of_buffer();
+        text_box
+            .paste_at_cursor("one\ntwo\n")
+            .expect("able to paste");
+        assert_eq!(text_box.undo_history.pending_len(), 1);
+        text_box.end_undo_group();
+
+        assert!(text_box.undo());
+        assert_eq!(text_box.get_entire_contents_as_string(), "abxycd");
+        assert_eq!(text_box.dirty_lines().len(), 1);
+        assert!(text_box.redo());
+        assert_eq!(text_box.get_entire_contents_as_string(), "one\ntwo\nabxycd");
+        assert_eq!(text_box.dirty_lines().len(), 3);
+
+        // and so is text removed at once
+        text_box.select_all();
+        assert_eq!(
+            text_box.cut_selection(),
+            Some("one\ntwo\nabxycd".to_string())
+        );
+        assert_eq!(text_box.undo_history.pending_len(), 1);
+        text_box.end_undo_group();
+
+        assert!(text_box.undo());
+        assert_eq!(text_box.get_entire_contents_as_string(), "one\ntwo\nabxycd");
+        assert_eq!(text_box.dirty_lines().len(), 3);
+        assert!(text_box.redo());
+        assert_eq!(text_box.get_entire_contents_as_string(), "");
     }
 
-    pub fn exit_search_mode(&mut self, retain_search_caret_pos: bool) {
-        if retain_search_caret_pos {
-            self.before_search_caret_pos.take();
-            self.before_search_scroll_offset.take();
-        } else {
-            self.caret_pos = self
-                .before_search_caret_pos
-                .take()
-                .unwrap_or(self.caret_pos);
+    #[test]
+    fn test_cut_and_paste_selection() {
+        let mut text_box = new_text_box("Hello\nWorld!\nThe End");
+        text_box.move_cursor_right();
+        text_box.extend_selection(TextBox::move_cursor_down);
+        text_box.extend_selection(TextBox::move_cursor_right);
+        assert_eq!(
+            text_box.selection_range(),
+            Some(Vec2u { x: 1, y: 0 }..Vec2u { x: 2, y: 1 })
+        );
+        assert_eq!(text_box.copy_selection(), Some("ello\nWo".to_string()));
 
-            self.scroll_offset = self
-                .before_search_scroll_offset
-                .take()
-                .unwrap_or(self.scroll_offset);
-        }
+        // the selection can shrink back past the anchor
+        text_box.extend_selection(TextBox::move_cursor_up);
+        text_box.extend_selection(TextBox::move_cursor_left);
+        text_box.extend_selection(TextBox::move_cursor_left);
+        assert_eq!(text_box.copy_selection(), Some("H".to_string()));
+
+        text_box.extend_selection(TextBox::move_cursor_down);
+        assert_eq!(text_box.cut_selection(), Some("ello\n".to_string()));
+        assert_eq!(text_box.get_entire_contents_as_string(), "HWorld!\nThe End");
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 1, y: 0 });
+        assert_eq!(text_box.cut_selection(), None);
+
+        // a multi-line paste replaces the selection
+        text_box.move_cursor_down();
+        text_box.move_cursor_to_start_of_line();
+        text_box.extend_selection(TextBox::move_cursor_to_end_of_line);
+        let pasted = text_box.paste_at_cursor("one\ntwo").expect("able to paste");
+        assert_eq!(
+            pasted,
+            TextBufferPos { line: 1, byte: 0 }..TextBufferPos { line: 2, byte: 3 }
+        );
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "HWorld!\none\ntwo"
+        );
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 3, y: 2 });
+        assert_eq!(text_box.selection_range(), None);
+
+        // the paste is undone in one go
+        text_box.end_undo_group();
+        text_box.move_cursor_to_start_of_buffer();
+        let pasted = text_box.paste_at_cursor("a\nb\nc").expect("able to paste");
+        assert_eq!(
+            pasted,
+            TextBufferPos { line: 0, byte: 0 }..TextBufferPos { line: 2, byte: 1 }
+        );
+        assert_eq!(text_box.get_line_len(2), 8);
+        assert!(text_box.undo());
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "HWorld!\none\ntwo"
+        );
+
+        // and so is a cut across lines
+        text_box.set_selection(Vec2u { x: 2, y: 0 }, Vec2u { x: 1, y: 2 });
+        assert_eq!(text_box.cut_selection(), Some("orld!\none\nt".to_string()));
+        assert_eq!(text_box.get_entire_contents_as_string(), "HWwo");
+        assert_eq!(text_box.get_line_len(0), 4);
+        assert!(text_box.undo());
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "HWorld!\none\ntwo"
+        );
+        assert!(text_box.redo());
+        assert_eq!(text_box.get_entire_contents_as_string(), "HWwo");
     }
 
-    pub fn find<T: AsRef<str>>(
-        &mut self,
-        search: T,
-        first_search: bool,
-        search_direction: SearchDirection,
-    ) {
-        if let Some(caret_pos) = self.find_in_contents(
-            &search,
-            if first_search {
-                self.before_search_caret_pos.unwrap_or(self.caret_pos)
-            } else {
-                Vec2u {
-                    x: match search_direction {
-                        SearchDirection::Forward => self
-                            .caret_pos
-                            .x
-                            .saturating_add(search.as_ref().len().to_u64()),
-                        SearchDirection::Backward => self.caret_pos.x,
-                    },
-                    y: self.caret_pos.y,
-                }
-            },
-            search_direction,
-        ) {
-            self.change_caret_xy(caret_pos);
-        } else if let Some(previous_caret_pos) = self.before_search_caret_pos {
-            self.change_caret_xy(previous_caret_pos);
+    #[test]
+    fn test_move_cursor_by_word() {
+        let mut text_box = new_text_box("let a  = foo, bar();\n\n  end");
+        let mut positions = vec![];
+        for _ in 0..10 {
+            text_box.move_cursor_word_right();
+            positions.push((text_box.caret_pos().x, text_box.caret_pos().y));
         }
+        assert_eq!(
+            positions,
+            vec![
+                (3, 0),
+                (5, 0),
+                // over multiple spaces
+                (8, 0),
+                (12, 0),
+                // punctuation is a word of its own
+                (13, 0),
+                (17, 0),
+                (18, 0),
+                (19, 0),
+                (20, 0),
+                // the end of the line goes to the next line
+                (0, 1),
+            ]
+        );
+
+        // an empty line is crossed one line at a time
+        text_box.move_cursor_word_right();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 0, y: 2 });
+        text_box.move_cursor_word_right();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 5, y: 2 });
+
+        text_box.move_cursor_word_left();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 2, y: 2 });
+        // the leading whitespace is skipped to the start of the line
+        text_box.move_cursor_word_left();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 0, y: 2 });
+        text_box.move_cursor_word_left();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 0, y: 1 });
+        text_box.move_cursor_word_left();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 20, y: 0 });
+        text_box.move_cursor_word_left();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 19, y: 0 });
+        text_box.change_caret_x(9);
+        text_box.move_cursor_word_left();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 7, y: 0 });
+        text_box.move_cursor_word_left();
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 4, y: 0 });
     }
 
-    fn render_line(
-        &self,
-        drawer: &mut Drawer,
-        line_idx: usize,
-        screen_pos: Vec2f,
-        text_offset_x: Range<u64>,
-        line_highlight: &TextHighlightLine,
-    ) {
-        match self.contents.line(line_idx) {
-            Some(line) => {
-                // TODO: This is not efficient
-                let line_render = TextLine::new(line);
-                line_render.render_line(drawer, screen_pos, text_offset_x, line_highlight);
-            }
-            None => {
-                if !self.single_line_mode {
-                    drawer.draw_text(screen_pos, "~");
-                }
-            }
-        }
+    #[test]
+    fn test_erase_word() {
+        let mut text_box = new_text_box("let cafe\u{301} = x;\nnext");
+        text_box.move_cursor_down();
+        text_box.move_cursor_right();
+        text_box.move_cursor_right();
+
+        // within a word, only its start is removed
+        assert_eq!(text_box.erase_word_before_cursor().as_deref(), Some("ne"));
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "let cafe\u{301} = x;\nxt"
+        );
+        // at the start of a line, it is joined onto the previous line
+        assert_eq!(text_box.erase_word_before_cursor().as_deref(), Some("\n"));
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 13, y: 0 });
+
+        text_box.change_caret_x(8);
+        let removed = text_box
+            .erase_word_before_cursor()
+            .expect("a word before the caret");
+        assert_eq!(removed, "cafe\u{301}");
+        assert_eq!(removed.graphemes(true).count(), 4);
+        assert_eq!(text_box.get_entire_contents_as_string(), "let  = x;xt");
+        // the whitespace after the word is removed with it
+        assert_eq!(text_box.erase_word_before_cursor().as_deref(), Some("let "));
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 0, y: 0 });
+        assert_eq!(text_box.erase_word_before_cursor(), None);
+
+        // the whitespace before the word is removed with it
+        assert_eq!(text_box.erase_word_after_cursor().as_deref(), Some(" ="));
+        assert_eq!(text_box.erase_word_after_cursor().as_deref(), Some(" x"));
+        assert_eq!(text_box.erase_word_after_cursor().as_deref(), Some(";"));
+        assert_eq!(text_box.erase_word_after_cursor().as_deref(), Some("xt"));
+        assert_eq!(text_box.erase_word_after_cursor(), None);
+        assert_eq!(text_box.get_entire_contents_as_string(), "");
     }
 
-    pub fn render(&self, drawer: &mut Drawer, highlights: &TextHighlights) {
-        if self.bounds.size.x * self.bounds.size.y > 0.0 {
-            (0..self.bounds.size.y.lossy()).for_each(|y| {
-                let line_idx = self.scroll_offset.y.saturating_add(y).to_usize();
-                self.render_line(
-                    drawer,
-                    line_idx,
-                    Vec2f {
-                        x: self.bounds.pos.x,
-                        y: self.bounds.pos.y + y.lossy(),
-                    },
-                    self.scroll_offset.x
-                        ..(self
-                            .scroll_offset
-                            .x
-                            .saturating_add(self.bounds.size.x.lossy())),
-                    highlights
-                        .line_highlight(line_idx)
-                        .unwrap_or(&TextHighlightLine::new()),
-                );
-            });
+    #[test]
+    fn test_insert_newline_with_autoindent() {
+        let mut text_box = new_text_box("\t  if a {\n    \tb();");
+        text_box.move_cursor_to_end_of_line();
+        text_box.insert_newline_with_autoindent();
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "\t  if a {\n\t  \n    \tb();"
+        );
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 3, y: 1 });
+        assert!(text_box.is_dirty());
 
-            let grid_cursor_pos = self.get_grid_pos_from_caret_pos(self.caret_pos);
+        // only the indentation before the caret is copied
+        text_box.move_cursor_down();
+        text_box.move_cursor_to_start_of_line();
+        text_box.move_cursor_right();
+        text_box.move_cursor_right();
+        text_box.insert_newline_with_autoindent();
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "\t  if a {\n\t  \n  \n    \tb();"
+        );
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 2, y: 3 });
 
-            let screen_cursor_pos = Vec2u {
-                x: <f64 as Lossy<u64>>::lossy(&self.bounds.pos.x)
-                    .saturating_add(grid_cursor_pos.x.saturating_sub(self.scroll_offset.x)),
-                y: <f64 as Lossy<u64>>::lossy(&self.bounds.pos.y)
-                    .saturating_add(grid_cursor_pos.y.saturating_sub(self.scroll_offset.y)),
-            };
+        // within the text of the line, the whole indentation is copied
+        text_box.move_cursor_to_end_of_line();
+        text_box.move_cursor_left();
+        text_box.insert_newline_with_autoindent();
+        assert_eq!(
+            text_box.get_entire_contents_as_string(),
+            "\t  if a {\n\t  \n  \n    \tb()\n    \t;"
+        );
+        assert_eq!(text_box.caret_pos(), Vec2u { x: 5, y: 4 });
 
-            drawer.draw_cursor(Vec2f {
-                x: screen_cursor_pos.x.lossy(),
-                y: screen_cursor_pos.y.lossy(),
-            });
-        }
+        let mut text_box = TextBox::new_single_line_text_box(VecTextBuffer::new());
+        text_box.set_contents("  a");
+        text_box.move_cursor_to_end_of_line();
+        text_box.insert_newline_with_autoindent();
+        assert_eq!(text_box.get_entire_contents_as_string(), "  a");
+    }
+
+    #[test]
+    fn test_jump_to_matching_bracket() {
+        let mut text_box = new_text_box("fn f(a: [u8; 2]) {\n    g((a), b);\n}\n(]");
+        let caret_at = |text_box: &mut TextBox<VecTextBuffer>, line, byte| {
+            text_box.move_cursor_to_buffer_pos(TextBufferPos { line, byte });
+        };
+
+        // nested brackets of the same and other kinds
+        caret_at(&mut text_box, 0, 4);
+        assert!(text_box.jump_to_matching_bracket());
+        assert_eq!(
+            text_box.caret_buffer_pos(),
+            TextBufferPos { line: 0, byte: 15 }
+        );
+        assert!(text_box.jump_to_matching_bracket());
+        assert_eq!(
+            text_box.caret_buffer_pos(),
+            TextBufferPos { line: 0, byte: 4 }
+        );
+
+        caret_at(&mut text_box, 1, 5);
+        assert_eq!(
+            text_box.matching_bracket(),
+            Some((
+                TextBufferPos { line: 1, byte: 5 },
+                TextBufferPos { line: 1, byte: 12 }
+            ))
+        );
+        caret_at(&mut text_box, 1, 8);
+        assert!(text_box.jump_to_matching_bracket());
+        assert_eq!(
+            text_box.caret_buffer_pos(),
+            TextBufferPos { line: 1, byte: 6 }
+        );
+
+        // across lines
+        caret_at(&mut text_box, 2, 0);
+        assert!(text_box.jump_to_matching_bracket());
+        assert_eq!(
+            text_box.caret_buffer_pos(),
+            TextBufferPos { line: 0, byte: 17 }
+        );
+
+        // unbalanced brackets stay where they are
+        caret_at(&mut text_box, 3, 0);
+        assert_eq!(text_box.matching_bracket(), None);
+        assert!(!text_box.jump_to_matching_bracket());
+        assert_eq!(
+            text_box.caret_buffer_pos(),
+            TextBufferPos { line: 3, byte: 0 }
+        );
+        caret_at(&mut text_box, 3, 1);
+        assert!(!text_box.jump_to_matching_bracket());
+
+        // not on a bracket
+        caret_at(&mut text_box, 0, 0);
+        assert_eq!(text_box.matching_bracket(), None);
     }
 }
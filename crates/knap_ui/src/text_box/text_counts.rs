@@ -0,0 +1,55 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The number of characters, graphemes and words in the contents.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TextCounts {
+    pub(crate) chars: usize,
+    pub(crate) graphemes: usize,
+    pub(crate) words: usize,
+}
+
+impl TextCounts {
+    /// Count the text of `lines`. The line breaks between them
+    /// count as a character (and a grapheme) each.
+    pub(crate) fn of_lines<I, S>(lines: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut counts = Self::default();
+        for (line_idx, line) in lines.into_iter().enumerate() {
+            let line = line.as_ref();
+            let line_break = usize::from(line_idx > 0);
+            counts.chars = counts
+                .chars
+                .saturating_add(line.chars().count())
+                .saturating_add(line_break);
+            counts.graphemes = counts
+                .graphemes
+                .saturating_add(line.graphemes(true).count())
+                .saturating_add(line_break);
+            counts.words = counts.words.saturating_add(line.unicode_words().count());
+        }
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_counts() {
+        assert_eq!(TextCounts::of_lines([""]), TextCounts::default());
+
+        // "é" is made of two chars, and each ideograph is a word of its own
+        assert_eq!(
+            TextCounts::of_lines(["he\u{301}llo, world!", "", "\u{4f60}\u{597d} x_y"]),
+            TextCounts {
+                chars: 22,
+                graphemes: 21,
+                words: 5,
+            }
+        );
+    }
+}
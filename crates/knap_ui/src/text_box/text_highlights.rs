@@ -27,6 +27,8 @@ impl TextHighlightLine {
         Self { blocks: vec![] }
     }
 
+    /// Blocks are checked in order, so when blocks overlap, the
+    /// earlier block takes priority.
     pub(crate) fn get_highlight_at(&self, byte_idx: usize) -> Option<TextColor> {
         self.blocks
             .iter()
@@ -41,8 +43,7 @@ pub struct TextHighlightBlock {
     pub range: Range<usize>,
 }
 
-// TODO: This can be part of theme in the future
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct TextColor {
     pub foreground: Option<Color>,
     pub background: Option<Color>,
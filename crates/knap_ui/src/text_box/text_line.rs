@@ -1,55 +1,134 @@
 use std::{cmp::Ordering, fmt::Display, ops::Range};
 
-use knap_base::math::{Lossy, Vec2f};
+use knap_base::math::{Lossy, ToU64, ToUsize, Vec2f};
 use knap_window::drawer::Drawer;
 use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
 
 use super::{TextColor, TextHighlightLine};
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum GraphemeWidth {
-    Half,
-    Full,
+const HALF_WIDTH: u64 = 1;
+const FULL_WIDTH: u64 = 2;
+
+/// Settings that change how a line is rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// each of the bools is an independent toggle, not a state
+#[allow(clippy::struct_excessive_bools)]
+pub struct TextRenderOptions {
+    /// Render control characters as their code point (e.g. `<U+0007>`)
+    /// instead of a single `▯`.
+    pub show_control_code_points: bool,
+    /// Render tabs as a `→` that is padded up to the next tab stop,
+    /// instead of a single blank.
+    pub show_tabs: bool,
+    /// in terms of columns
+    pub tab_width: u64,
+    /// Reserve the rightmost column of a text box for a scrollbar.
+    pub show_scrollbar: bool,
+    /// Draw a faint `│` at every tab stop within the leading
+    /// whitespace of a line.
+    pub show_indent_guides: bool,
+    /// When moving up and down onto a line that is too short for the caret's
+    /// column, keep the horizontal scroll where the column is, instead of
+    /// scrolling back to the end of the shorter line.
+    pub sticky_horizontal_scroll: bool,
+    /// Draw the spaces and tabs at the end of each line with a
+    /// distinct color, over any other highlights.
+    pub highlight_trailing_whitespace: bool,
 }
 
-impl GraphemeWidth {
-    fn width(self) -> u64 {
-        match self {
-            GraphemeWidth::Half => 1,
-            GraphemeWidth::Full => 2,
+impl Default for TextRenderOptions {
+    fn default() -> Self {
+        Self {
+            show_control_code_points: false,
+            show_tabs: false,
+            tab_width: 4,
+            show_scrollbar: false,
+            show_indent_guides: false,
+            sticky_horizontal_scroll: false,
+            highlight_trailing_whitespace: false,
         }
     }
 }
 
 struct TextFragment {
     grapheme: String,
-    rendered_width: GraphemeWidth,
-    replacement: Option<char>,
+    /// in terms of columns
+    rendered_width: u64,
+    replacement: Option<String>,
     start_byte_index: usize,
 }
 
+/// A run of graphemes in a line that forms a single word (as
+/// defined by Unicode word boundaries). Consecutive whitespace
+/// is always treated as a single word.
+pub(crate) struct TextWord {
+    /// in terms of fragment idx
+    pub range: Range<usize>,
+    pub is_whitespace: bool,
+}
+
 // TODO: This now only contains rendering logic, refactor it as such
 pub(crate) struct TextLine {
     fragments: Vec<TextFragment>,
     string: String,
 }
 
-fn get_grapheme_render_replacement<T: AsRef<str>>(grapheme: T) -> Option<(char, GraphemeWidth)> {
+/// Whether `ch` is a combining mark from one of the combining diacritical
+/// marks blocks, which are the ones commonly used to make accented letters.
+fn is_combining_mark(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{0300}'..='\u{036F}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE20}'..='\u{FE2F}'
+    )
+}
+
+/// `column` is where the grapheme starts being rendered.
+fn get_grapheme_render_replacement<T: AsRef<str>>(
+    grapheme: T,
+    column: u64,
+    options: TextRenderOptions,
+) -> Option<(String, u64)> {
     let grapheme = grapheme.as_ref();
 
     match grapheme {
         " " => None,
-        "\t" => Some((' ', GraphemeWidth::Half)),
+        "\t" if options.show_tabs && options.tab_width > 0 => {
+            let width = options
+                .tab_width
+                .saturating_sub(column.checked_rem(options.tab_width).unwrap_or_default());
+            Some((
+                format!("→{}", " ".repeat(width.saturating_sub(1).to_usize())),
+                width,
+            ))
+        }
+        "\t" => Some((" ".to_string(), HALF_WIDTH)),
         _ => {
             if grapheme.trim().is_empty() {
-                Some(('␣', GraphemeWidth::Half))
-            } else if grapheme.chars().next().is_some_and(char::is_control)
+                Some(("␣".to_string(), HALF_WIDTH))
+            } else if let Some(control_char) = grapheme.chars().next().filter(|ch| ch.is_control())
                 && grapheme.chars().nth(1).is_none()
             {
-                Some(('▯', GraphemeWidth::Half))
+                if options.show_control_code_points {
+                    let code_point = format!("<U+{:04X}>", u32::from(control_char));
+                    let width = code_point.len().to_u64();
+                    Some((code_point, width))
+                } else {
+                    Some(("▯".to_string(), HALF_WIDTH))
+                }
             } else if grapheme.width() == 0 {
-                Some(('·', GraphemeWidth::Half))
+                // a combining mark is only on its own if there is nothing for
+                // it to combine with (e.g. at the start of a line), so it is
+                // shown on a dotted circle, like how it is usually depicted
+                if grapheme.chars().next().is_some_and(is_combining_mark) {
+                    Some((format!("◌{grapheme}"), HALF_WIDTH))
+                } else {
+                    Some(("·".to_string(), HALF_WIDTH))
+                }
             } else {
                 None
             }
@@ -57,12 +136,19 @@ fn get_grapheme_render_replacement<T: AsRef<str>>(grapheme: T) -> Option<(char,
     }
 }
 
-fn build_fragments_from_string<T: AsRef<str>>(content: T) -> Vec<TextFragment> {
+fn build_fragments_from_string<T: AsRef<str>>(
+    content: T,
+    options: TextRenderOptions,
+) -> Vec<TextFragment> {
+    let mut column = 0u64;
+
     content
         .as_ref()
         .grapheme_indices(true)
         .map(|(start_byte_index, grapheme)| {
-            if let Some((replacement, rendered_width)) = get_grapheme_render_replacement(grapheme) {
+            let fragment = if let Some((replacement, rendered_width)) =
+                get_grapheme_render_replacement(grapheme, column, options)
+            {
                 TextFragment {
                     grapheme: grapheme.to_string(),
                     rendered_width,
@@ -73,22 +159,25 @@ fn build_fragments_from_string<T: AsRef<str>>(content: T) -> Vec<TextFragment> {
                 TextFragment {
                     grapheme: grapheme.to_string(),
                     rendered_width: if grapheme.width() <= 1 {
-                        GraphemeWidth::Half
+                        HALF_WIDTH
                     } else {
-                        GraphemeWidth::Full
+                        FULL_WIDTH
                     },
                     replacement: None,
                     start_byte_index,
                 }
-            }
+            };
+
+            column = column.saturating_add(fragment.rendered_width);
+            fragment
         })
         .collect()
 }
 
 impl TextLine {
-    pub(crate) fn new<T: AsRef<str>>(content: T) -> Self {
+    pub(crate) fn new<T: AsRef<str>>(content: T, options: TextRenderOptions) -> Self {
         Self {
-            fragments: build_fragments_from_string(&content),
+            fragments: build_fragments_from_string(&content, options),
             string: content.as_ref().to_string(),
         }
     }
@@ -101,110 +190,179 @@ impl TextLine {
         self.fragments
             .iter()
             .take(end_x)
-            .map(|x| x.rendered_width.width())
+            .map(|x| x.rendered_width)
             .sum()
     }
 
-    // TODO: Consider refactoring this in the future, so that we
-    // do not have to disable too_many_lines lint
-    #[allow(clippy::too_many_lines)]
-    pub(crate) fn render_line(
+    /// Get the fragment that is rendered at `text_width`. If `text_width`
+    /// is beyond the end of the line, the line length is returned instead.
+    pub(crate) fn get_fragment_idx_from_line_text_width(&self, text_width: u64) -> usize {
+        let mut current_x = 0u64;
+
+        self.fragments
+            .iter()
+            .position(|fragment| {
+                current_x = current_x.saturating_add(fragment.rendered_width);
+                current_x > text_width
+            })
+            .unwrap_or(self.fragments.len())
+    }
+
+    /// Returns the fragment idx where the leading spaces and tabs of the
+    /// line end, which is the line length if there is nothing else.
+    pub(crate) fn get_leading_whitespace_end(&self) -> usize {
+        self.fragments
+            .iter()
+            .position(|fragment| !matches!(fragment.grapheme.as_str(), " " | "\t"))
+            .unwrap_or(self.fragments.len())
+    }
+
+    /// Returns the fragment idx where the trailing spaces and tabs of the
+    /// line start, which is the line length if there are none.
+    pub(crate) fn get_trailing_whitespace_start(&self) -> usize {
+        self.fragments
+            .iter()
+            .rposition(|fragment| !matches!(fragment.grapheme.as_str(), " " | "\t"))
+            .map_or(0, |fragment_idx| fragment_idx.saturating_add(1))
+    }
+
+    /// Returns the visible part of the line as strings to draw one after
+    /// another, each with its width and color. Consecutive fragments with
+    /// the same color are grouped together. The trailing spaces and tabs
+    /// are drawn with `trailing_whitespace` over the highlights, if given.
+    fn get_segments(
         &self,
-        drawer: &mut Drawer,
-        screen_pos: Vec2f,
         text_offset_x: Range<u64>,
         highlights: &TextHighlightLine,
-    ) {
+        trailing_whitespace: Option<TextColor>,
+    ) -> Vec<(String, u64, Option<TextColor>)> {
+        let trailing_whitespace_start = self.get_trailing_whitespace_start();
         let mut current_x = 0;
-        let mut fragment_iter = self.fragments.iter();
-
         let mut chars_to_render = vec![];
 
-        while current_x < text_offset_x.end {
-            if let Some(current_fragment) = fragment_iter.next() {
-                let next_x = current_x.saturating_add(current_fragment.rendered_width.width());
+        for (fragment_idx, current_fragment) in self.fragments.iter().enumerate() {
+            if current_x >= text_offset_x.end {
+                break;
+            }
 
-                if current_x < text_offset_x.start {
-                    if next_x > text_offset_x.start {
-                        chars_to_render.push(("⋯".to_string(), 1, None));
-                    }
-                } else if next_x > text_offset_x.end {
-                    chars_to_render.push(("⋯".to_string(), 1, None));
-                } else {
-                    chars_to_render.push((
-                        current_fragment
-                            .replacement
-                            .map_or(current_fragment.grapheme.to_string(), |replacement| {
-                                replacement.to_string()
-                            }),
-                        current_fragment.rendered_width.width(),
-                        highlights.get_highlight_at(current_fragment.start_byte_index),
-                    ));
-                }
+            let next_x = current_x.saturating_add(current_fragment.rendered_width);
+            // even when cut off by the scroll, so that a wide tab is still flagged
+            let trailing_color =
+                trailing_whitespace.filter(|_| fragment_idx >= trailing_whitespace_start);
 
-                current_x = next_x;
+            if current_x < text_offset_x.start {
+                if next_x > text_offset_x.start {
+                    chars_to_render.push(("⋯".to_string(), 1, trailing_color));
+                }
+            } else if next_x > text_offset_x.end {
+                chars_to_render.push(("⋯".to_string(), 1, trailing_color));
             } else {
-                // ran out of characters
-                break;
+                chars_to_render.push((
+                    current_fragment
+                        .replacement
+                        .clone()
+                        .unwrap_or_else(|| current_fragment.grapheme.clone()),
+                    current_fragment.rendered_width,
+                    trailing_color
+                        .or_else(|| highlights.get_highlight_at(current_fragment.start_byte_index)),
+                ));
             }
+
+            current_x = next_x;
         }
 
-        if !chars_to_render.is_empty() {
-            let grouped_strings = chars_to_render.into_iter().fold(
-                vec![],
-                |mut acc: Vec<(String, u64, Option<TextColor>)>, current| {
-                    let mut insert_new = true;
-
-                    if let Some(last_entry) = acc.last_mut() {
-                        if last_entry.2 == current.2 {
-                            last_entry.0.push_str(&current.0);
-                            last_entry.1 = last_entry.1.saturating_add(current.1);
-                            insert_new = false;
-                        }
-                    }
+        chars_to_render.into_iter().fold(
+            vec![],
+            |mut acc: Vec<(String, u64, Option<TextColor>)>, current| {
+                let mut insert_new = true;
 
-                    if insert_new {
-                        acc.push(current);
-                    }
+                if let Some(last_entry) = acc.last_mut()
+                    && last_entry.2 == current.2
+                {
+                    last_entry.0.push_str(&current.0);
+                    last_entry.1 = last_entry.1.saturating_add(current.1);
+                    insert_new = false;
+                }
 
-                    acc
-                },
-            );
-            grouped_strings.into_iter().fold(
-                0u64,
-                |x_offset, (string, string_width, highlight_type)| {
-                    let next_x_offset = x_offset.saturating_add(string_width);
-                    let (foreground, background) = match highlight_type {
-                        None => (None, None),
-                        Some(TextColor {
-                            foreground,
-                            background,
-                        }) => (foreground, background),
-                    };
-
-                    drawer.draw_colored_text(
-                        Vec2f {
-                            x: screen_pos.x + x_offset.lossy(),
-                            y: screen_pos.y,
-                        },
-                        string,
+                if insert_new {
+                    acc.push(current);
+                }
+
+                acc
+            },
+        )
+    }
+
+    pub(crate) fn render_line(
+        &self,
+        drawer: &mut Drawer,
+        screen_pos: Vec2f,
+        text_offset_x: Range<u64>,
+        highlights: &TextHighlightLine,
+        trailing_whitespace: Option<TextColor>,
+    ) {
+        self.get_segments(text_offset_x, highlights, trailing_whitespace)
+            .into_iter()
+            .fold(0u64, |x_offset, (string, string_width, highlight_type)| {
+                let next_x_offset = x_offset.saturating_add(string_width);
+                let (foreground, background) = match highlight_type {
+                    None => (None, None),
+                    Some(TextColor {
                         foreground,
                         background,
-                    );
+                    }) => (foreground, background),
+                };
 
-                    next_x_offset
-                },
-            );
-        }
+                drawer.draw_colored_text(
+                    Vec2f {
+                        x: screen_pos.x + x_offset.lossy(),
+                        y: screen_pos.y,
+                    },
+                    string,
+                    foreground,
+                    background,
+                );
+
+                next_x_offset
+            });
+    }
+
+    pub(crate) fn get_words(&self) -> Vec<TextWord> {
+        self.string.split_word_bound_indices().fold(
+            vec![],
+            |mut acc: Vec<TextWord>, (byte_idx, word)| {
+                let start = self
+                    .get_fragment_idx_from_byte_idx(byte_idx)
+                    .expect("word boundaries to be valid byte indices");
+                let end = self
+                    .get_fragment_idx_from_byte_idx(byte_idx.saturating_add(word.len()))
+                    .expect("word boundaries to be valid byte indices");
+                let is_whitespace = word.trim().is_empty();
+
+                match acc.last_mut() {
+                    Some(last_word) if last_word.is_whitespace && is_whitespace => {
+                        last_word.range.end = end;
+                    }
+                    _ => acc.push(TextWord {
+                        range: start..end,
+                        is_whitespace,
+                    }),
+                }
+
+                acc
+            },
+        )
     }
 
     // TODO: Maybe create a FragmentIdx type?
+    /// Returns the fragment that `byte_idx` is in, which is the fragment
+    /// it starts when it is on a fragment boundary.
     pub(crate) fn get_fragment_idx_from_byte_idx(&self, byte_idx: usize) -> Option<usize> {
         match byte_idx.cmp(&self.string.len()) {
             Ordering::Less => self
                 .fragments
                 .iter()
-                .position(|fragment| fragment.start_byte_index >= byte_idx),
+                .rposition(|fragment| fragment.start_byte_index <= byte_idx),
             Ordering::Equal => Some(self.fragments.len()),
             Ordering::Greater => None,
         }
@@ -224,3 +382,156 @@ impl Display for TextLine {
         write!(f, "{}", self.string)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use knap_base::color::Color;
+
+    use super::*;
+
+    #[test]
+    fn test_control_character_replacement() {
+        let line = TextLine::new("a\u{7}b", TextRenderOptions::default());
+        assert_eq!(line.fragments[1].replacement.as_deref(), Some("▯"));
+        assert_eq!(line.get_line_text_width(3), 3);
+
+        let line = TextLine::new(
+            "a\u{7}b",
+            TextRenderOptions {
+                show_control_code_points: true,
+                ..TextRenderOptions::default()
+            },
+        );
+        assert_eq!(line.fragments[1].replacement.as_deref(), Some("<U+0007>"));
+        assert_eq!(line.get_line_text_width(1), 1);
+        assert_eq!(line.get_line_text_width(2), 9);
+        assert_eq!(line.get_line_text_width(3), 10);
+        assert_eq!(line.get_fragment_idx_from_line_text_width(5), 1);
+        assert_eq!(line.get_fragment_idx_from_line_text_width(9), 2);
+    }
+
+    #[test]
+    fn test_tab_replacement() {
+        let line = TextLine::new("\tx", TextRenderOptions::default());
+        assert_eq!(line.fragments[0].replacement.as_deref(), Some(" "));
+        assert_eq!(line.get_line_text_width(1), 1);
+
+        let options = TextRenderOptions {
+            show_tabs: true,
+            tab_width: 4,
+            ..TextRenderOptions::default()
+        };
+
+        let line = TextLine::new("\tx", options);
+        assert_eq!(line.fragments[0].replacement.as_deref(), Some("→   "));
+        assert_eq!(line.get_line_text_width(1), 4);
+        assert_eq!(line.get_line_text_width(2), 5);
+
+        // only expands up to the next tab stop
+        let line = TextLine::new("ab\t\t\u{4f60}\tx", options);
+        assert_eq!(line.fragments[2].replacement.as_deref(), Some("→ "));
+        assert_eq!(line.fragments[3].replacement.as_deref(), Some("→   "));
+        assert_eq!(line.fragments[5].replacement.as_deref(), Some("→ "));
+        assert_eq!(line.get_line_text_width(6), 12);
+        assert_eq!(line.get_fragment_idx_from_line_text_width(5), 3);
+    }
+
+    #[test]
+    fn test_combining_mark_replacement() {
+        // the accent combines with the letter before it
+        let line = TextLine::new("cafe\u{301}", TextRenderOptions::default());
+        assert_eq!(line.get_line_len(), 4);
+        assert_eq!(line.fragments[3].grapheme, "e\u{301}");
+        assert_eq!(line.fragments[3].replacement, None);
+        assert_eq!(line.get_line_text_width(4), 4);
+
+        // nothing for the accent to combine with
+        let line = TextLine::new("\u{301}a", TextRenderOptions::default());
+        assert_eq!(line.get_line_len(), 2);
+        assert_eq!(line.fragments[0].replacement.as_deref(), Some("◌\u{301}"));
+        assert_eq!(line.get_line_text_width(2), 2);
+
+        // other zero-width characters are still shown as a dot
+        let line = TextLine::new("a\u{200b}b", TextRenderOptions::default());
+        assert_eq!(line.fragments[1].replacement.as_deref(), Some("·"));
+    }
+
+    #[test]
+    fn test_fragment_and_byte_idxs() {
+        // ascii, a family emoji (joined with zero width joiners), an
+        // accented letter made of two chars, and a flag
+        let line = TextLine::new(
+            "a\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}e\u{301}\u{1f1ef}\u{1f1f5}z",
+            TextRenderOptions::default(),
+        );
+        assert_eq!(line.get_line_len(), 5);
+        let fragment_starts = [0, 1, 19, 22, 30];
+        for (fragment_idx, byte_idx) in fragment_starts.into_iter().enumerate() {
+            assert_eq!(
+                line.get_byte_idx_from_fragment_idx(fragment_idx),
+                Some(byte_idx)
+            );
+            assert_eq!(
+                line.get_fragment_idx_from_byte_idx(byte_idx),
+                Some(fragment_idx)
+            );
+        }
+
+        // the bytes within a grapheme are in its fragment, not the next one
+        assert_eq!(line.get_fragment_idx_from_byte_idx(5), Some(1));
+        assert_eq!(line.get_fragment_idx_from_byte_idx(18), Some(1));
+        assert_eq!(line.get_fragment_idx_from_byte_idx(20), Some(2));
+        assert_eq!(line.get_fragment_idx_from_byte_idx(26), Some(3));
+
+        // the end of the line, and past it
+        assert_eq!(line.get_byte_idx_from_fragment_idx(5), Some(31));
+        assert_eq!(line.get_fragment_idx_from_byte_idx(31), Some(5));
+        assert_eq!(line.get_byte_idx_from_fragment_idx(6), None);
+        assert_eq!(line.get_fragment_idx_from_byte_idx(32), None);
+    }
+
+    #[test]
+    fn test_trailing_whitespace_segments() {
+        let red = Some(TextColor {
+            foreground: None,
+            background: Some(Color::RED),
+        });
+        let no_highlights = TextHighlightLine { blocks: vec![] };
+
+        let line = TextLine::new("ab \t ", TextRenderOptions::default());
+        assert_eq!(line.get_trailing_whitespace_start(), 2);
+        assert_eq!(
+            line.get_segments(0..10, &no_highlights, red),
+            vec![("ab".to_string(), 2, None), ("   ".to_string(), 3, red)]
+        );
+        assert_eq!(
+            line.get_segments(0..10, &no_highlights, None),
+            vec![("ab   ".to_string(), 5, None)]
+        );
+
+        // a line of only whitespace is flagged entirely
+        let line = TextLine::new("  ", TextRenderOptions::default());
+        assert_eq!(line.get_trailing_whitespace_start(), 0);
+        assert_eq!(line.get_leading_whitespace_end(), 2);
+        assert_eq!(
+            line.get_segments(0..10, &no_highlights, red),
+            vec![("  ".to_string(), 2, red)]
+        );
+
+        // scrolled past the text, with a tab cut off by the scroll
+        let options = TextRenderOptions {
+            show_tabs: true,
+            tab_width: 4,
+            ..TextRenderOptions::default()
+        };
+        let line = TextLine::new("abc  \t", options);
+        assert_eq!(
+            line.get_segments(4..7, &no_highlights, red),
+            vec![(" ⋯".to_string(), 2, red)]
+        );
+        assert_eq!(
+            line.get_segments(1..4, &no_highlights, red),
+            vec![("bc".to_string(), 2, None), (" ".to_string(), 1, red)]
+        );
+    }
+}
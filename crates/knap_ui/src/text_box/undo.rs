@@ -0,0 +1,160 @@
+use knap_base::math::Vec2u;
+
+use crate::text_buffer::TextBufferPos;
+
+/// A change to the contents, recorded so that it can be undone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum EditOp {
+    InsertChar {
+        pos: TextBufferPos,
+        ch: char,
+    },
+    RemoveChar {
+        pos: TextBufferPos,
+        ch: char,
+    },
+    /// A line break was inserted at `pos`, splitting its line.
+    InsertNewline {
+        pos: TextBufferPos,
+    },
+    /// The line break at `pos` (the end of its line) was
+    /// removed, joining the next line onto the line.
+    JoinLine {
+        pos: TextBufferPos,
+    },
+    /// `text` (which can span multiple lines) was inserted at `pos` at once.
+    InsertStr {
+        pos: TextBufferPos,
+        text: String,
+    },
+    /// `text` (which can span multiple lines) was removed from `start` at once.
+    RemoveStr {
+        start: TextBufferPos,
+        text: String,
+    },
+}
+
+impl EditOp {
+    /// The op that reverts this one.
+    pub(crate) fn inverse(self) -> Self {
+        match self {
+            EditOp::InsertChar { pos, ch } => EditOp::RemoveChar { pos, ch },
+            EditOp::RemoveChar { pos, ch } => EditOp::InsertChar { pos, ch },
+            EditOp::InsertNewline { pos } => EditOp::JoinLine { pos },
+            EditOp::JoinLine { pos } => EditOp::InsertNewline { pos },
+            EditOp::InsertStr { pos, text } => EditOp::RemoveStr { start: pos, text },
+            EditOp::RemoveStr { start, text } => EditOp::InsertStr { pos: start, text },
+        }
+    }
+}
+
+/// The ops that are undone (and redone) together, usually
+/// those of a single command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct UndoGroup {
+    pub(crate) ops: Vec<EditOp>,
+    /// where the caret was before the ops, restored when they are undone
+    pub(crate) caret_before: Vec2u,
+    /// where the caret was after the ops, restored when they are redone
+    pub(crate) caret_after: Vec2u,
+}
+
+impl UndoGroup {
+    /// Whether `next` is the typing of a character right after the
+    /// characters typed by this group, so that a word is undone in one
+    /// step. Typing whitespace after a word starts a new group.
+    fn is_continued_by(&self, next: &UndoGroup) -> bool {
+        let Some(EditOp::InsertChar {
+            pos: last_pos,
+            ch: last_ch,
+        }) = self.ops.last()
+        else {
+            return false;
+        };
+        let [EditOp::InsertChar { pos, ch }] = next.ops.as_slice() else {
+            return false;
+        };
+
+        self.ops
+            .iter()
+            .all(|op| matches!(op, EditOp::InsertChar { .. }))
+            && pos.line == last_pos.line
+            && pos.byte == last_pos.byte.saturating_add(last_ch.len_utf8())
+            && (!ch.is_whitespace() || last_ch.is_whitespace())
+    }
+}
+
+/// The changes that can be undone, and those that were undone
+/// and can be redone.
+#[derive(Debug, Default)]
+pub(crate) struct UndoHistory {
+    undo_stack: Vec<UndoGroup>,
+    redo_stack: Vec<UndoGroup>,
+    /// the ops recorded since the last group ended
+    pending: Option<UndoGroup>,
+}
+
+impl UndoHistory {
+    /// Record `op`, which was done with the caret at `caret_pos`.
+    pub(crate) fn record(&mut self, op: EditOp, caret_pos: Vec2u) {
+        self.pending
+            .get_or_insert_with(|| UndoGroup {
+                ops: vec![],
+                caret_before: caret_pos,
+                caret_after: caret_pos,
+            })
+            .ops
+            .push(op);
+    }
+
+    /// The number of ops recorded since the last group ended.
+    pub(crate) fn pending_len(&self) -> usize {
+        self.pending.as_ref().map_or(0, |group| group.ops.len())
+    }
+
+    /// Forget the ops recorded since the last group ended, except the first `len`.
+    pub(crate) fn truncate_pending(&mut self, len: usize) {
+        if len == 0 {
+            self.pending = None;
+        } else if let Some(group) = &mut self.pending {
+            group.ops.truncate(len);
+        }
+    }
+
+    /// Group the ops recorded so far, so that they are undone together.
+    pub(crate) fn end_group(&mut self, caret_pos: Vec2u) {
+        let Some(mut group) = self.pending.take() else {
+            return;
+        };
+        group.caret_after = caret_pos;
+        self.redo_stack.clear();
+
+        match self.undo_stack.last_mut() {
+            Some(last_group) if last_group.is_continued_by(&group) => {
+                last_group.ops.extend(group.ops);
+                last_group.caret_after = group.caret_after;
+            }
+            _ => self.undo_stack.push(group),
+        }
+    }
+
+    /// Returns the group to undo, which can then be redone.
+    pub(crate) fn undo(&mut self) -> Option<UndoGroup> {
+        let group = self.undo_stack.pop()?;
+        self.redo_stack.push(group.clone());
+        Some(group)
+    }
+
+    /// Returns the group to redo, which can then be undone again.
+    pub(crate) fn redo(&mut self) -> Option<UndoGroup> {
+        let group = self.redo_stack.pop()?;
+        self.undo_stack.push(group.clone());
+        Some(group)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.pending = None;
+    }
+}
@@ -1,4 +1,6 @@
-use std::{error::Error, fmt::Display};
+use std::{borrow::Cow, error::Error, fmt::Display, ops::Range};
+
+use regex::Regex;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
 pub struct TextBufferPos {
@@ -11,12 +13,16 @@ pub struct TextBufferPos {
 pub enum InsertCharError {
     InvalidLinePosition,
     InvalidBytePosition,
+    /// the text box is read-only, never returned by the buffers themselves
+    ReadOnly,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum RemoveCharError {
     InvalidLinePosition,
     InvalidBytePosition,
+    /// the text box is read-only, never returned by the buffers themselves
+    ReadOnly,
 }
 
 impl Display for InsertCharError {
@@ -47,6 +53,166 @@ pub enum SearchDirection {
     Backward,
 }
 
+/// A match found by `TextBuffer::find` (or `TextBuffer::find_regex`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FindResult {
+    pub pos: TextBufferPos,
+    /// Whether the search continued from the other end of the text
+    /// buffer to find the match (see `SearchOptions::wrap`).
+    pub wrapped: bool,
+}
+
+impl FindResult {
+    /// The match at `pos`, found by searching from `start_pos` in
+    /// `search_direction`. A search only finds the matches on the other
+    /// side of `start_pos` after it wrapped around.
+    pub(crate) fn new(
+        pos: TextBufferPos,
+        start_pos: TextBufferPos,
+        search_direction: SearchDirection,
+    ) -> Self {
+        Self {
+            pos,
+            wrapped: match search_direction {
+                SearchDirection::Forward => pos < start_pos,
+                SearchDirection::Backward => pos >= start_pos,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SearchOptions {
+    /// Allow a match to span across multiple lines, by
+    /// having newline characters (`\n`) in the search.
+    ///
+    /// When this is false, searches containing newline
+    /// characters will never match anything.
+    pub multiline: bool,
+    /// Continue from the other end of the text buffer when there
+    /// are no more matches in the search direction.
+    ///
+    /// When this is false, the search stops at the end (or start)
+    /// of the text buffer instead. This is true by default.
+    pub wrap: bool,
+    /// Only match the search where it is a whole word, i.e. it is not
+    /// preceded or followed by a word character (a letter, a digit or `_`),
+    /// so that `cat` does not match `category`. This is false by default.
+    pub whole_word: bool,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            multiline: false,
+            wrap: true,
+            whole_word: false,
+        }
+    }
+}
+
+/// Returns the position right after `text`, when it is inserted at `pos`.
+pub(crate) fn get_pos_after_str(pos: TextBufferPos, text: &str) -> TextBufferPos {
+    match text.rsplit_once('\n') {
+        Some((before, last_line)) => TextBufferPos {
+            line: pos
+                .line
+                .saturating_add(before.matches('\n').count())
+                .saturating_add(1),
+            byte: last_line.len(),
+        },
+        None => TextBufferPos {
+            line: pos.line,
+            byte: pos.byte.saturating_add(text.len()),
+        },
+    }
+}
+
+pub(crate) fn is_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+/// Returns whether the `len` bytes of `text` at `byte` are not preceded
+/// or followed by a word character, see `SearchOptions::whole_word`.
+pub fn is_whole_word(text: &str, byte: usize, len: usize) -> bool {
+    let before = text
+        .get(..byte)
+        .and_then(|before| before.chars().next_back());
+    let after = text
+        .get(byte.saturating_add(len)..)
+        .and_then(|after| after.chars().next());
+    !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char)
+}
+
+/// Like `str::find` on `line[from..]` (but returning the byte index within
+/// `line`), skipping the matches that are not whole words if `whole_word`.
+pub(crate) fn find_in_line(
+    line: &str,
+    search: &str,
+    mut from: usize,
+    whole_word: bool,
+) -> Option<usize> {
+    loop {
+        let byte = from.saturating_add(line.get(from..)?.find(search)?);
+        if !whole_word || search.is_empty() || is_whole_word(line, byte, search.len()) {
+            return Some(byte);
+        }
+        // the next match can overlap this one
+        from = byte.saturating_add(line[byte..].chars().next().map_or(1, char::len_utf8));
+    }
+}
+
+/// Like `str::rfind` on `line[..end]`, skipping the matches that are
+/// not whole words if `whole_word`.
+pub(crate) fn rfind_in_line(
+    line: &str,
+    search: &str,
+    mut end: usize,
+    whole_word: bool,
+) -> Option<usize> {
+    loop {
+        let byte = line.get(..end)?.rfind(search)?;
+        if !whole_word || search.is_empty() || is_whole_word(line, byte, search.len()) {
+            return Some(byte);
+        }
+        // the previous match can overlap this one
+        end = line.floor_char_boundary(byte.saturating_add(search.len()).saturating_sub(1));
+    }
+}
+
+/// Find `search` in `contents`, which is the entire contents of a
+/// text buffer (with lines joined by `\n`), starting from the byte
+/// offset `start_offset`.
+///
+/// Like `TextBuffer::find`, a backward search only returns matches
+/// that start before `start_offset`.
+pub(crate) fn find_in_joined_contents(
+    contents: &str,
+    search: &str,
+    start_offset: usize,
+    search_direction: SearchDirection,
+    search_options: SearchOptions,
+) -> Option<usize> {
+    let matches = || {
+        contents
+            .match_indices(search)
+            .map(|(offset, _)| offset)
+            .filter(|offset| {
+                !search_options.whole_word || is_whole_word(contents, *offset, search.len())
+            })
+    };
+
+    match search_direction {
+        SearchDirection::Forward => matches()
+            .find(|offset| *offset >= start_offset)
+            .or_else(|| search_options.wrap.then(|| matches().next()).flatten()),
+        SearchDirection::Backward => matches()
+            .take_while(|offset| *offset < start_offset)
+            .last()
+            .or_else(|| search_options.wrap.then(|| matches().last()).flatten()),
+    }
+}
+
 /// A type that can be used to store and manipulate text.
 pub trait TextBuffer {
     /// Get the entire contents of the text buffer.
@@ -71,6 +237,15 @@ pub trait TextBuffer {
     /// a contiguous block of memory.
     fn line(&self, line_idx: usize) -> Option<String>;
 
+    /// Get the contents of the lines in `range` (the lines past the end
+    /// of the text buffer are left out), borrowing the lines that are
+    /// in a contiguous block of memory instead of copying them.
+    fn lines_range(&self, range: Range<usize>) -> Vec<Cow<'_, str>> {
+        range
+            .map_while(|line_idx| self.line(line_idx).map(Cow::Owned))
+            .collect()
+    }
+
     /// Get the length of a specific line.
     fn line_len(&self, line_idx: usize) -> Option<usize>;
 
@@ -88,6 +263,17 @@ pub trait TextBuffer {
         ch: char,
     ) -> Result<(), InsertCharError>;
 
+    /// Insert `text` (which can span multiple lines) at a specific
+    /// position in one go, which is much faster than inserting it a
+    /// character at a time.
+    ///
+    /// Returns the position right after the inserted text.
+    fn insert_str_at_pos(
+        &mut self,
+        pos: TextBufferPos,
+        text: &str,
+    ) -> Result<TextBufferPos, InsertCharError>;
+
     /// Remove a character at a specific position.
     ///
     /// If the position is directly after the last non-newline character of a line,
@@ -95,6 +281,15 @@ pub trait TextBuffer {
     /// the line will be joined with the next line.
     fn remove_character_at_pos(&mut self, pos: TextBufferPos) -> Result<(), RemoveCharError>;
 
+    /// Remove everything between `start` and `end` (which can be on
+    /// different lines, and in either order) in one go, joining the
+    /// lines that they are on.
+    fn remove_range(
+        &mut self,
+        start: TextBufferPos,
+        end: TextBufferPos,
+    ) -> Result<(), RemoveCharError>;
+
     /// Find a substring in the text buffer.
     ///
     /// This function will search for the first occurrence of `search`
@@ -102,14 +297,73 @@ pub trait TextBuffer {
     ///
     /// `search_direction` specifies the direction of the search.
     ///
-    /// Note that if `search` contains a newline the behavior is undefined
-    /// (text buffer implementations are not required to honor the newline).
+    /// See `SearchOptions` for how `search_options` affects the search
+    /// (e.g. whether `search` can contain newlines).
     fn find(
         &self,
         search: &str,
         start_pos: TextBufferPos,
         search_direction: SearchDirection,
-    ) -> Option<TextBufferPos>;
+        search_options: SearchOptions,
+    ) -> Option<FindResult>;
+
+    /// Find a match of the regular expression `pattern` in the text buffer,
+    /// starting from `start_pos` in `search_direction`, and continuing from
+    /// the other end of the text buffer if there are no more matches.
+    ///
+    /// The buffer is searched line by line, so a match never spans lines,
+    /// and anchors like `^` and `$` match at the start and end of each line.
+    /// Empty matches are skipped, as there is nothing to move to.
+    fn find_regex(
+        &self,
+        pattern: &Regex,
+        start_pos: TextBufferPos,
+        search_direction: SearchDirection,
+    ) -> Option<FindResult> {
+        let total_lines = self.total_lines();
+        if start_pos.line >= total_lines {
+            return None;
+        }
+
+        let matches_in_line = |line_idx: usize| -> Vec<TextBufferPos> {
+            self.line(line_idx)
+                .map(|line| {
+                    pattern
+                        .find_iter(&line)
+                        .filter(|found| !found.is_empty())
+                        .map(|found| TextBufferPos {
+                            line: line_idx,
+                            byte: found.start(),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        // the start line is searched again last, for the matches
+        // on the other side of `start_pos`
+        match search_direction {
+            SearchDirection::Forward => matches_in_line(start_pos.line)
+                .into_iter()
+                .find(|pos| *pos >= start_pos)
+                .or_else(|| {
+                    (start_pos.line.saturating_add(1)..total_lines)
+                        .chain(0..=start_pos.line)
+                        .find_map(|line_idx| matches_in_line(line_idx).first().copied())
+                }),
+            SearchDirection::Backward => matches_in_line(start_pos.line)
+                .into_iter()
+                .rev()
+                .find(|pos| *pos < start_pos)
+                .or_else(|| {
+                    (0..start_pos.line)
+                        .rev()
+                        .chain((start_pos.line..total_lines).rev())
+                        .find_map(|line_idx| matches_in_line(line_idx).last().copied())
+                }),
+        }
+        .map(|pos| FindResult::new(pos, start_pos, search_direction))
+    }
 }
 
 #[cfg(test)]
@@ -141,6 +395,52 @@ pub(crate) mod buffer_tests {
         }
     }
 
+    fn test_lines_range<B, F>(new_buffer_fn: F)
+    where
+        B: TextBuffer,
+        F: Fn() -> B,
+    {
+        let mut buffer = new_buffer_fn();
+        // long enough for the lines to be split up in some buffers
+        let contents = (0..2000)
+            .map(|line_idx| format!("line {line_idx} \u{e9}\u{4f60}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        buffer.set_contents(&contents);
+
+        let lines_range = |range: Range<usize>| {
+            buffer
+                .lines_range(range)
+                .into_iter()
+                .map(Cow::into_owned)
+                .collect::<Vec<_>>()
+        };
+        let lines = |range: Range<usize>| {
+            range
+                .filter_map(|line_idx| buffer.line(line_idx))
+                .collect::<Vec<_>>()
+        };
+        for range in [
+            0..2000,
+            5..6,
+            1500..1900,
+            1990..2010,
+            7..7,
+            2000..2001,
+            2500..2600,
+        ] {
+            assert_eq!(lines_range(range.clone()), lines(range));
+        }
+        assert_eq!(
+            lines_range(1..3),
+            vec!["line 1 \u{e9}\u{4f60}", "line 2 \u{e9}\u{4f60}"]
+        );
+
+        let mut buffer = new_buffer_fn();
+        buffer.set_contents("one\n\nthree");
+        assert_eq!(buffer.lines_range(0..5), vec!["one", "", "three"]);
+    }
+
     fn test_line_len<B, F>(new_buffer_fn: F)
     where
         B: TextBuffer,
@@ -288,6 +588,64 @@ pub(crate) mod buffer_tests {
         assert_eq!(buffer.total_lines(), 6);
     }
 
+    fn test_insert_str_at_pos<B, F>(new_buffer_fn: F)
+    where
+        B: TextBuffer,
+        F: Fn() -> B,
+    {
+        let mut buffer = new_buffer_fn();
+        buffer.set_contents("first\nhello world\nlast");
+
+        // three lines in the middle of a line
+        let result = buffer.insert_str_at_pos(
+            TextBufferPos { line: 1, byte: 6 },
+            "one\ntwo \u{e9}\nthree ",
+        );
+        assert_eq!(result, Ok(TextBufferPos { line: 3, byte: 6 }));
+        assert_eq!(
+            buffer.contents(),
+            "first\nhello one\ntwo \u{e9}\nthree world\nlast"
+        );
+        assert_eq!(buffer.total_lines(), 5);
+
+        // a single line
+        let result = buffer.insert_str_at_pos(TextBufferPos { line: 4, byte: 4 }, "ly \u{e9}");
+        assert_eq!(result, Ok(TextBufferPos { line: 4, byte: 9 }));
+        assert_eq!(buffer.line(4), Some("lastly \u{e9}".to_string()));
+
+        // ending with a line break
+        let result = buffer.insert_str_at_pos(TextBufferPos { line: 0, byte: 0 }, "zeroth\n");
+        assert_eq!(result, Ok(TextBufferPos { line: 1, byte: 0 }));
+        assert_eq!(buffer.line(0), Some("zeroth".to_string()));
+        assert_eq!(buffer.line(1), Some("first".to_string()));
+
+        // nothing
+        let result = buffer.insert_str_at_pos(TextBufferPos { line: 1, byte: 2 }, "");
+        assert_eq!(result, Ok(TextBufferPos { line: 1, byte: 2 }));
+        assert_eq!(buffer.total_lines(), 6);
+
+        // invalid positions
+        let result = buffer.insert_str_at_pos(TextBufferPos { line: 1, byte: 6 }, "x");
+        assert_eq!(result, Err(InsertCharError::InvalidBytePosition));
+        let result = buffer.insert_str_at_pos(TextBufferPos { line: 7, byte: 0 }, "x");
+        assert_eq!(result, Err(InsertCharError::InvalidLinePosition));
+
+        // the same as inserting each character
+        let mut buffer = new_buffer_fn();
+        let mut char_buffer = new_buffer_fn();
+        buffer.set_contents("ab\ncd");
+        char_buffer.set_contents("ab\ncd");
+        let text = "1\n\n2\u{4f60}\n";
+        let mut pos = TextBufferPos { line: 1, byte: 1 };
+        for ch in text.chars() {
+            assert_eq!(char_buffer.insert_character_at_pos(pos, ch), Ok(()));
+            pos = get_pos_after_str(pos, &ch.to_string());
+        }
+        let result = buffer.insert_str_at_pos(TextBufferPos { line: 1, byte: 1 }, text);
+        assert_eq!(result, Ok(pos));
+        assert_eq!(buffer.contents(), char_buffer.contents());
+    }
+
     fn test_remove_character_at_pos<B, F>(new_buffer_fn: F)
     where
         B: TextBuffer,
@@ -337,6 +695,77 @@ pub(crate) mod buffer_tests {
         assert_eq!(buffer.contents(), "");
     }
 
+    fn test_remove_range<B, F>(new_buffer_fn: F)
+    where
+        B: TextBuffer,
+        F: Fn() -> B,
+    {
+        let mut buffer = new_buffer_fn();
+        buffer.set_contents("first\nh\u{e9}llo world\nthird\nlast");
+
+        // within a line
+        let result = buffer.remove_range(
+            TextBufferPos { line: 1, byte: 1 },
+            TextBufferPos { line: 1, byte: 7 },
+        );
+        assert_eq!(result, Ok(()));
+        assert_eq!(buffer.contents(), "first\nhworld\nthird\nlast");
+
+        // across lines, in either order
+        let result = buffer.remove_range(
+            TextBufferPos { line: 2, byte: 2 },
+            TextBufferPos { line: 0, byte: 3 },
+        );
+        assert_eq!(result, Ok(()));
+        assert_eq!(buffer.contents(), "firird\nlast");
+        assert_eq!(buffer.total_lines(), 2);
+
+        // just the line break
+        let result = buffer.remove_range(
+            TextBufferPos { line: 0, byte: 6 },
+            TextBufferPos { line: 1, byte: 0 },
+        );
+        assert_eq!(result, Ok(()));
+        assert_eq!(buffer.contents(), "firirdlast");
+
+        // nothing
+        let result = buffer.remove_range(
+            TextBufferPos { line: 0, byte: 2 },
+            TextBufferPos { line: 0, byte: 2 },
+        );
+        assert_eq!(result, Ok(()));
+        assert_eq!(buffer.contents(), "firirdlast");
+
+        // invalid positions
+        let result = buffer.remove_range(
+            TextBufferPos { line: 0, byte: 0 },
+            TextBufferPos { line: 0, byte: 11 },
+        );
+        assert_eq!(result, Err(RemoveCharError::InvalidBytePosition));
+        let result = buffer.remove_range(
+            TextBufferPos { line: 0, byte: 0 },
+            TextBufferPos { line: 1, byte: 0 },
+        );
+        assert_eq!(result, Err(RemoveCharError::InvalidLinePosition));
+        buffer.set_contents("\u{e9}");
+        let result = buffer.remove_range(
+            TextBufferPos { line: 0, byte: 0 },
+            TextBufferPos { line: 0, byte: 1 },
+        );
+        assert_eq!(result, Err(RemoveCharError::InvalidBytePosition));
+        assert_eq!(buffer.contents(), "\u{e9}");
+
+        // the whole buffer
+        buffer.set_contents("one\ntwo\nthree");
+        let result = buffer.remove_range(
+            TextBufferPos { line: 0, byte: 0 },
+            TextBufferPos { line: 2, byte: 5 },
+        );
+        assert_eq!(result, Ok(()));
+        assert_eq!(buffer.contents(), "");
+        assert_eq!(buffer.total_lines(), 1);
+    }
+
     // this is a test, so it is ok to have a lot of lines
     #[allow(clippy::too_many_lines)]
     fn test_find<B, F>(new_buffer_fn: F)
@@ -348,183 +777,910 @@ pub(crate) mod buffer_tests {
         buffer.set_contents("this is a text and this is his cat.\nthe next line contains the history.\nand this is the last line.");
 
         // normal search on the same line
-        let result = buffer.find(
-            "this",
-            TextBufferPos { line: 0, byte: 0 },
-            SearchDirection::Forward,
-        );
+        let result = buffer
+            .find(
+                "this",
+                TextBufferPos { line: 0, byte: 0 },
+                SearchDirection::Forward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
         assert_eq!(result, Some(TextBufferPos { line: 0, byte: 0 }));
 
-        let result = buffer.find(
-            "this",
-            TextBufferPos { line: 0, byte: 1 },
-            SearchDirection::Forward,
-        );
+        let result = buffer
+            .find(
+                "this",
+                TextBufferPos { line: 0, byte: 1 },
+                SearchDirection::Forward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
         assert_eq!(result, Some(TextBufferPos { line: 0, byte: 19 }));
 
-        let result = buffer.find(
-            "this",
-            TextBufferPos { line: 0, byte: 18 },
-            SearchDirection::Forward,
-        );
+        let result = buffer
+            .find(
+                "this",
+                TextBufferPos { line: 0, byte: 18 },
+                SearchDirection::Forward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
         assert_eq!(result, Some(TextBufferPos { line: 0, byte: 19 }));
 
-        let result = buffer.find(
-            "this",
-            TextBufferPos { line: 0, byte: 19 },
-            SearchDirection::Forward,
-        );
+        let result = buffer
+            .find(
+                "this",
+                TextBufferPos { line: 0, byte: 19 },
+                SearchDirection::Forward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
         assert_eq!(result, Some(TextBufferPos { line: 0, byte: 19 }));
 
-        let result = buffer.find(
-            "this",
-            TextBufferPos { line: 0, byte: 21 },
-            SearchDirection::Backward,
-        );
+        let result = buffer
+            .find(
+                "this",
+                TextBufferPos { line: 0, byte: 21 },
+                SearchDirection::Backward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
         assert_eq!(result, Some(TextBufferPos { line: 0, byte: 19 }));
 
-        let result = buffer.find(
-            "this",
-            TextBufferPos { line: 0, byte: 20 },
-            SearchDirection::Backward,
-        );
+        let result = buffer
+            .find(
+                "this",
+                TextBufferPos { line: 0, byte: 20 },
+                SearchDirection::Backward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
         assert_eq!(result, Some(TextBufferPos { line: 0, byte: 19 }));
 
-        let result = buffer.find(
-            "this",
-            TextBufferPos { line: 0, byte: 19 },
-            SearchDirection::Backward,
-        );
+        let result = buffer
+            .find(
+                "this",
+                TextBufferPos { line: 0, byte: 19 },
+                SearchDirection::Backward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
         assert_eq!(result, Some(TextBufferPos { line: 0, byte: 0 }));
 
-        let result = buffer.find(
-            "this",
-            TextBufferPos { line: 0, byte: 18 },
-            SearchDirection::Backward,
-        );
+        let result = buffer
+            .find(
+                "this",
+                TextBufferPos { line: 0, byte: 18 },
+                SearchDirection::Backward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
         assert_eq!(result, Some(TextBufferPos { line: 0, byte: 0 }));
 
         // jump to a different line
-        let result = buffer.find(
-            "this",
-            TextBufferPos { line: 0, byte: 20 },
-            SearchDirection::Forward,
-        );
+        let result = buffer
+            .find(
+                "this",
+                TextBufferPos { line: 0, byte: 20 },
+                SearchDirection::Forward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
         assert_eq!(result, Some(TextBufferPos { line: 2, byte: 4 }));
 
-        let result = buffer.find(
-            "this",
-            TextBufferPos { line: 2, byte: 4 },
-            SearchDirection::Backward,
-        );
+        let result = buffer
+            .find(
+                "this",
+                TextBufferPos { line: 2, byte: 4 },
+                SearchDirection::Backward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
         assert_eq!(result, Some(TextBufferPos { line: 0, byte: 19 }));
 
         // wrap around
-        let result = buffer.find(
-            "is",
-            TextBufferPos { line: 2, byte: 10 },
-            SearchDirection::Forward,
-        );
+        let result = buffer
+            .find(
+                "is",
+                TextBufferPos { line: 2, byte: 10 },
+                SearchDirection::Forward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
         assert_eq!(result, Some(TextBufferPos { line: 0, byte: 2 }));
 
-        let result = buffer.find(
-            "is",
-            TextBufferPos { line: 0, byte: 2 },
-            SearchDirection::Backward,
-        );
+        let result = buffer
+            .find(
+                "is",
+                TextBufferPos { line: 0, byte: 2 },
+                SearchDirection::Backward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
         assert_eq!(result, Some(TextBufferPos { line: 2, byte: 9 }));
 
+        // backward across multi-byte characters, including a match
+        // that ends right after the start
+        let mut non_ascii_buffer = new_buffer_fn();
+        non_ascii_buffer.set_contents("\u{e9}t\u{e9} \u{e9}t\u{e9}");
+        let result = non_ascii_buffer
+            .find(
+                "\u{e9}t\u{e9}",
+                TextBufferPos { line: 0, byte: 6 },
+                SearchDirection::Backward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, Some(TextBufferPos { line: 0, byte: 0 }));
+        let result = non_ascii_buffer
+            .find(
+                "\u{e9}",
+                TextBufferPos { line: 0, byte: 3 },
+                SearchDirection::Backward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, Some(TextBufferPos { line: 0, byte: 0 }));
+        let result = non_ascii_buffer
+            .find(
+                "\u{e9}",
+                TextBufferPos { line: 0, byte: 0 },
+                SearchDirection::Backward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, Some(TextBufferPos { line: 0, byte: 9 }));
+
         // non-existent search
-        let result = buffer.find(
-            "cannot be found",
-            TextBufferPos { line: 0, byte: 0 },
-            SearchDirection::Forward,
-        );
+        let result = buffer
+            .find(
+                "cannot be found",
+                TextBufferPos { line: 0, byte: 0 },
+                SearchDirection::Forward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
         assert_eq!(result, None);
 
-        let result = buffer.find(
-            "cannot be found",
-            TextBufferPos { line: 2, byte: 5 },
-            SearchDirection::Backward,
-        );
+        let result = buffer
+            .find(
+                "cannot be found",
+                TextBufferPos { line: 2, byte: 5 },
+                SearchDirection::Backward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
         assert_eq!(result, None);
 
         // search in empty buffer
         let empty_buffer = new_buffer_fn();
-        let result = empty_buffer.find(
-            "nothing to search",
-            TextBufferPos { line: 0, byte: 0 },
-            SearchDirection::Forward,
-        );
+        let result = empty_buffer
+            .find(
+                "nothing to search",
+                TextBufferPos { line: 0, byte: 0 },
+                SearchDirection::Forward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
         assert_eq!(result, None);
-        let result = empty_buffer.find(
-            "nothing to search",
-            TextBufferPos { line: 0, byte: 0 },
-            SearchDirection::Backward,
-        );
+        let result = empty_buffer
+            .find(
+                "nothing to search",
+                TextBufferPos { line: 0, byte: 0 },
+                SearchDirection::Backward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
         assert_eq!(result, None);
 
         // illegal cursor position
-        let result = empty_buffer.find(
-            "nothing to search",
-            TextBufferPos { line: 0, byte: 1 },
-            SearchDirection::Forward,
-        );
+        let result = empty_buffer
+            .find(
+                "nothing to search",
+                TextBufferPos { line: 0, byte: 1 },
+                SearchDirection::Forward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
         assert_eq!(result, None);
-        let result = empty_buffer.find(
-            "nothing to search",
-            TextBufferPos { line: 0, byte: 2 },
-            SearchDirection::Forward,
-        );
+        let result = empty_buffer
+            .find(
+                "nothing to search",
+                TextBufferPos { line: 0, byte: 2 },
+                SearchDirection::Forward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
         assert_eq!(result, None);
-        let result = empty_buffer.find(
-            "nothing to search",
-            TextBufferPos { line: 0, byte: 1 },
-            SearchDirection::Backward,
-        );
+        let result = empty_buffer
+            .find(
+                "nothing to search",
+                TextBufferPos { line: 0, byte: 1 },
+                SearchDirection::Backward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
         assert_eq!(result, None);
-        let result = empty_buffer.find(
-            "nothing to search",
-            TextBufferPos { line: 0, byte: 2 },
-            SearchDirection::Backward,
-        );
+        let result = empty_buffer
+            .find(
+                "nothing to search",
+                TextBufferPos { line: 0, byte: 2 },
+                SearchDirection::Backward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
         assert_eq!(result, None);
-        let result = empty_buffer.find(
-            "nothing to search",
-            TextBufferPos { line: 1, byte: 0 },
-            SearchDirection::Forward,
-        );
+        let result = empty_buffer
+            .find(
+                "nothing to search",
+                TextBufferPos { line: 1, byte: 0 },
+                SearchDirection::Forward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
         assert_eq!(result, None);
-        let result = empty_buffer.find(
-            "nothing to search",
-            TextBufferPos { line: 2, byte: 0 },
-            SearchDirection::Forward,
-        );
+        let result = empty_buffer
+            .find(
+                "nothing to search",
+                TextBufferPos { line: 2, byte: 0 },
+                SearchDirection::Forward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
         assert_eq!(result, None);
-        let result = empty_buffer.find(
-            "nothing to search",
-            TextBufferPos { line: 1, byte: 0 },
-            SearchDirection::Backward,
-        );
+        let result = empty_buffer
+            .find(
+                "nothing to search",
+                TextBufferPos { line: 1, byte: 0 },
+                SearchDirection::Backward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
         assert_eq!(result, None);
-        let result = empty_buffer.find(
-            "nothing to search",
-            TextBufferPos { line: 2, byte: 0 },
-            SearchDirection::Backward,
-        );
+        let result = empty_buffer
+            .find(
+                "nothing to search",
+                TextBufferPos { line: 2, byte: 0 },
+                SearchDirection::Backward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, None);
+    }
+
+    fn test_find_multiline<B, F>(new_buffer_fn: F)
+    where
+        B: TextBuffer,
+        F: Fn() -> B,
+    {
+        let mut buffer = new_buffer_fn();
+        buffer.set_contents("the quick brown\nfox jumps over\nthe lazy dog.\nquick brown\nfox");
+        let multiline = SearchOptions {
+            multiline: true,
+            ..SearchOptions::default()
+        };
+
+        // not allowed unless it is a multiline search
+        let result = buffer
+            .find(
+                "brown\nfox",
+                TextBufferPos { line: 0, byte: 0 },
+                SearchDirection::Forward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, None);
+
+        // match that straddles a newline
+        let result = buffer
+            .find(
+                "brown\nfox",
+                TextBufferPos { line: 0, byte: 0 },
+                SearchDirection::Forward,
+                multiline,
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, Some(TextBufferPos { line: 0, byte: 10 }));
+
+        let result = buffer
+            .find(
+                "brown\nfox",
+                TextBufferPos { line: 0, byte: 11 },
+                SearchDirection::Forward,
+                multiline,
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, Some(TextBufferPos { line: 3, byte: 6 }));
+
+        // match that spans more than two lines
+        let result = buffer
+            .find(
+                "over\nthe lazy dog.\nquick",
+                TextBufferPos { line: 0, byte: 0 },
+                SearchDirection::Forward,
+                multiline,
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, Some(TextBufferPos { line: 1, byte: 10 }));
+
+        // backward
+        let result = buffer
+            .find(
+                "brown\nfox",
+                TextBufferPos { line: 3, byte: 6 },
+                SearchDirection::Backward,
+                multiline,
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, Some(TextBufferPos { line: 0, byte: 10 }));
+
+        // wrap around
+        let result = buffer
+            .find(
+                "brown\nfox",
+                TextBufferPos { line: 3, byte: 7 },
+                SearchDirection::Forward,
+                multiline,
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, Some(TextBufferPos { line: 0, byte: 10 }));
+
+        let result = buffer
+            .find(
+                "brown\nfox",
+                TextBufferPos { line: 0, byte: 10 },
+                SearchDirection::Backward,
+                multiline,
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, Some(TextBufferPos { line: 3, byte: 6 }));
+
+        // single line searches still work
+        let result = buffer
+            .find(
+                "lazy",
+                TextBufferPos { line: 0, byte: 0 },
+                SearchDirection::Forward,
+                multiline,
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, Some(TextBufferPos { line: 2, byte: 4 }));
+
+        // non-existent search
+        let result = buffer
+            .find(
+                "dog\nthe",
+                TextBufferPos { line: 0, byte: 0 },
+                SearchDirection::Forward,
+                multiline,
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, None);
+    }
+
+    fn test_find_without_wrap<B, F>(new_buffer_fn: F)
+    where
+        B: TextBuffer,
+        F: Fn() -> B,
+    {
+        let mut buffer = new_buffer_fn();
+        buffer.set_contents("this is a text and this is his cat.\nthe next line contains the history.\nand this is the last line.");
+        let no_wrap = SearchOptions {
+            wrap: false,
+            ..SearchOptions::default()
+        };
+
+        // matches that do not need wrapping are still found
+        let result = buffer
+            .find(
+                "this",
+                TextBufferPos { line: 0, byte: 20 },
+                SearchDirection::Forward,
+                no_wrap,
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, Some(TextBufferPos { line: 2, byte: 4 }));
+
+        let result = buffer
+            .find(
+                "this",
+                TextBufferPos { line: 2, byte: 4 },
+                SearchDirection::Backward,
+                no_wrap,
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, Some(TextBufferPos { line: 0, byte: 19 }));
+
+        // past the last match
+        let result = buffer
+            .find(
+                "this",
+                TextBufferPos { line: 2, byte: 5 },
+                SearchDirection::Forward,
+                no_wrap,
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, None);
+        let result = buffer
+            .find(
+                "this",
+                TextBufferPos { line: 2, byte: 5 },
+                SearchDirection::Forward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, Some(TextBufferPos { line: 0, byte: 0 }));
+
+        // before the first match
+        let result = buffer
+            .find(
+                "this",
+                TextBufferPos { line: 0, byte: 0 },
+                SearchDirection::Backward,
+                no_wrap,
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, None);
+        let result = buffer
+            .find(
+                "this",
+                TextBufferPos { line: 0, byte: 0 },
+                SearchDirection::Backward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, Some(TextBufferPos { line: 2, byte: 4 }));
+
+        // multiline searches do not wrap either
+        let result = buffer
+            .find(
+                "cat.\nthe",
+                TextBufferPos { line: 1, byte: 0 },
+                SearchDirection::Forward,
+                SearchOptions {
+                    multiline: true,
+                    wrap: false,
+                    whole_word: false,
+                },
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, None);
+    }
+
+    fn test_find_from_end_of_line<B, F>(new_buffer_fn: F)
+    where
+        B: TextBuffer,
+        F: Fn() -> B,
+    {
+        let mut buffer = new_buffer_fn();
+        buffer.set_contents("one two\n\nthree two");
+
+        // the search continues onto the next lines
+        let result = buffer
+            .find(
+                "two",
+                TextBufferPos { line: 0, byte: 7 },
+                SearchDirection::Forward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, Some(TextBufferPos { line: 2, byte: 6 }));
+        let result = buffer
+            .find(
+                "two",
+                TextBufferPos { line: 1, byte: 0 },
+                SearchDirection::Forward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, Some(TextBufferPos { line: 2, byte: 6 }));
+
+        // or the rest of the line, when searching backward
+        let result = buffer
+            .find(
+                "two",
+                TextBufferPos { line: 0, byte: 7 },
+                SearchDirection::Backward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, Some(TextBufferPos { line: 0, byte: 4 }));
+        let result = buffer
+            .find(
+                "two",
+                TextBufferPos { line: 1, byte: 0 },
+                SearchDirection::Backward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, Some(TextBufferPos { line: 0, byte: 4 }));
+
+        // wrapping around to the only match, on the first line
+        let result = buffer
+            .find(
+                "one",
+                TextBufferPos { line: 2, byte: 9 },
+                SearchDirection::Forward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, Some(TextBufferPos { line: 0, byte: 0 }));
+        let result = buffer
+            .find(
+                "one",
+                TextBufferPos { line: 0, byte: 7 },
+                SearchDirection::Forward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, Some(TextBufferPos { line: 0, byte: 0 }));
+        let result = buffer
+            .find(
+                "one",
+                TextBufferPos { line: 2, byte: 9 },
+                SearchDirection::Forward,
+                SearchOptions {
+                    wrap: false,
+                    ..SearchOptions::default()
+                },
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, None);
+
+        // past the end of the line is still invalid
+        let result = buffer
+            .find(
+                "two",
+                TextBufferPos { line: 0, byte: 8 },
+                SearchDirection::Forward,
+                SearchOptions::default(),
+            )
+            .map(|found| found.pos);
         assert_eq!(result, None);
     }
 
+    fn test_find_regex<B, F>(new_buffer_fn: F)
+    where
+        B: TextBuffer,
+        F: Fn() -> B,
+    {
+        let mut buffer = new_buffer_fn();
+        buffer.set_contents("fn main() {\n    let a1 = 10;\n}\nfn b2() {}");
+
+        // anchors match at the start and end of each line
+        let pattern = Regex::new("^fn").expect("valid regex");
+        let result = buffer
+            .find_regex(
+                &pattern,
+                TextBufferPos { line: 0, byte: 1 },
+                SearchDirection::Forward,
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, Some(TextBufferPos { line: 3, byte: 0 }));
+
+        let pattern = Regex::new(r"\{$").expect("valid regex");
+        let result = buffer
+            .find_regex(
+                &pattern,
+                TextBufferPos { line: 1, byte: 0 },
+                SearchDirection::Forward,
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, Some(TextBufferPos { line: 0, byte: 10 }));
+
+        // character classes
+        let pattern = Regex::new("[a-z][0-9]+").expect("valid regex");
+        let result = buffer
+            .find_regex(
+                &pattern,
+                TextBufferPos { line: 0, byte: 0 },
+                SearchDirection::Forward,
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, Some(TextBufferPos { line: 1, byte: 8 }));
+
+        let result = buffer
+            .find_regex(
+                &pattern,
+                TextBufferPos { line: 1, byte: 8 },
+                SearchDirection::Forward,
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, Some(TextBufferPos { line: 1, byte: 8 }));
+
+        let result = buffer
+            .find_regex(
+                &pattern,
+                TextBufferPos { line: 1, byte: 9 },
+                SearchDirection::Forward,
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, Some(TextBufferPos { line: 3, byte: 3 }));
+
+        // backward search only finds matches before the start, wrapping around
+        let result = buffer
+            .find_regex(
+                &pattern,
+                TextBufferPos { line: 3, byte: 3 },
+                SearchDirection::Backward,
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, Some(TextBufferPos { line: 1, byte: 8 }));
+
+        let result = buffer
+            .find_regex(
+                &pattern,
+                TextBufferPos { line: 1, byte: 8 },
+                SearchDirection::Backward,
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, Some(TextBufferPos { line: 3, byte: 3 }));
+
+        // empty matches are skipped
+        let pattern = Regex::new(r"^\s*$").expect("valid regex");
+        let result = buffer
+            .find_regex(
+                &pattern,
+                TextBufferPos { line: 0, byte: 0 },
+                SearchDirection::Forward,
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, None);
+
+        // no match
+        let pattern = Regex::new("^[0-9]").expect("valid regex");
+        let result = buffer
+            .find_regex(
+                &pattern,
+                TextBufferPos { line: 0, byte: 0 },
+                SearchDirection::Forward,
+            )
+            .map(|found| found.pos);
+        assert_eq!(result, None);
+    }
+
+    fn test_find_whole_word<B, F>(new_buffer_fn: F)
+    where
+        B: TextBuffer,
+        F: Fn() -> B,
+    {
+        let mut buffer = new_buffer_fn();
+        buffer.set_contents("category cat\nbobcat cat_ cat.\ncat");
+        let whole_word = SearchOptions {
+            whole_word: true,
+            ..SearchOptions::default()
+        };
+        let find = |start_pos: TextBufferPos,
+                    search_direction: SearchDirection,
+                    search_options: SearchOptions| {
+            buffer
+                .find("cat", start_pos, search_direction, search_options)
+                .map(|found| found.pos)
+        };
+        let start_pos = TextBufferPos { line: 0, byte: 0 };
+
+        // a substring of a word matches unless only whole words do
+        assert_eq!(
+            find(
+                start_pos,
+                SearchDirection::Forward,
+                SearchOptions::default()
+            ),
+            Some(TextBufferPos { line: 0, byte: 0 })
+        );
+        assert_eq!(
+            find(start_pos, SearchDirection::Forward, whole_word),
+            Some(TextBufferPos { line: 0, byte: 9 })
+        );
+        assert_eq!(
+            find(
+                TextBufferPos { line: 0, byte: 10 },
+                SearchDirection::Forward,
+                whole_word
+            ),
+            Some(TextBufferPos { line: 1, byte: 12 })
+        );
+        assert_eq!(
+            find(
+                TextBufferPos { line: 1, byte: 12 },
+                SearchDirection::Backward,
+                SearchOptions::default()
+            ),
+            Some(TextBufferPos { line: 1, byte: 7 })
+        );
+        assert_eq!(
+            find(
+                TextBufferPos { line: 1, byte: 12 },
+                SearchDirection::Backward,
+                whole_word
+            ),
+            Some(TextBufferPos { line: 0, byte: 9 })
+        );
+        assert_eq!(
+            find(start_pos, SearchDirection::Backward, whole_word),
+            Some(TextBufferPos { line: 2, byte: 0 })
+        );
+
+        // across lines too
+        let multiline = SearchOptions {
+            multiline: true,
+            ..whole_word
+        };
+        assert_eq!(
+            buffer
+                .find("cat.\nca", start_pos, SearchDirection::Forward, multiline)
+                .map(|found| found.pos),
+            None
+        );
+        assert_eq!(
+            buffer
+                .find("cat.\ncat", start_pos, SearchDirection::Forward, multiline)
+                .map(|found| found.pos),
+            Some(TextBufferPos { line: 1, byte: 12 })
+        );
+
+        // the words that contain the search are skipped in either direction
+        buffer.set_contents("this history is\nthis");
+        let find_is = |start_pos: TextBufferPos, search_direction: SearchDirection| {
+            buffer
+                .find("is", start_pos, search_direction, whole_word)
+                .map(|found| found.pos)
+        };
+        assert_eq!(
+            find_is(start_pos, SearchDirection::Forward),
+            Some(TextBufferPos { line: 0, byte: 13 })
+        );
+        assert_eq!(
+            find_is(
+                TextBufferPos { line: 1, byte: 4 },
+                SearchDirection::Backward
+            ),
+            Some(TextBufferPos { line: 0, byte: 13 })
+        );
+        assert_eq!(
+            buffer
+                .find(
+                    "is",
+                    start_pos,
+                    SearchDirection::Forward,
+                    SearchOptions::default()
+                )
+                .map(|found| found.pos),
+            Some(TextBufferPos { line: 0, byte: 2 })
+        );
+    }
+
+    fn test_find_wrapped<B, F>(new_buffer_fn: F)
+    where
+        B: TextBuffer,
+        F: Fn() -> B,
+    {
+        let mut buffer = new_buffer_fn();
+        buffer.set_contents("one two\nthree one\ntwo");
+        let find = |search: &str, start_pos: TextBufferPos, search_direction: SearchDirection| {
+            buffer.find(
+                search,
+                start_pos,
+                search_direction,
+                SearchOptions::default(),
+            )
+        };
+
+        // the matches after the start (including at the start) are found first
+        let start_pos = TextBufferPos { line: 1, byte: 6 };
+        assert_eq!(
+            find("one", start_pos, SearchDirection::Forward),
+            Some(FindResult {
+                pos: TextBufferPos { line: 1, byte: 6 },
+                wrapped: false,
+            })
+        );
+        assert_eq!(
+            find("two", start_pos, SearchDirection::Forward),
+            Some(FindResult {
+                pos: TextBufferPos { line: 2, byte: 0 },
+                wrapped: false,
+            })
+        );
+        assert_eq!(
+            find("three", start_pos, SearchDirection::Forward),
+            Some(FindResult {
+                pos: TextBufferPos { line: 1, byte: 0 },
+                wrapped: true,
+            })
+        );
+
+        // and the matches before the start when searching backward
+        assert_eq!(
+            find("one", start_pos, SearchDirection::Backward),
+            Some(FindResult {
+                pos: TextBufferPos { line: 0, byte: 0 },
+                wrapped: false,
+            })
+        );
+        assert_eq!(
+            find(
+                "one",
+                TextBufferPos { line: 0, byte: 0 },
+                SearchDirection::Backward
+            ),
+            Some(FindResult {
+                pos: TextBufferPos { line: 1, byte: 6 },
+                wrapped: true,
+            })
+        );
+
+        // across lines too
+        let search_options = SearchOptions {
+            multiline: true,
+            ..SearchOptions::default()
+        };
+        assert_eq!(
+            buffer.find(
+                "one\ntwo",
+                start_pos,
+                SearchDirection::Forward,
+                search_options
+            ),
+            Some(FindResult {
+                pos: TextBufferPos { line: 1, byte: 6 },
+                wrapped: false,
+            })
+        );
+        assert_eq!(
+            buffer.find(
+                "two\nthree",
+                start_pos,
+                SearchDirection::Forward,
+                search_options
+            ),
+            Some(FindResult {
+                pos: TextBufferPos { line: 0, byte: 4 },
+                wrapped: true,
+            })
+        );
+
+        // and with regular expressions
+        let pattern = Regex::new("t[a-z]+").expect("valid regex");
+        assert_eq!(
+            buffer.find_regex(&pattern, start_pos, SearchDirection::Forward),
+            Some(FindResult {
+                pos: TextBufferPos { line: 2, byte: 0 },
+                wrapped: false,
+            })
+        );
+        assert_eq!(
+            buffer.find_regex(
+                &pattern,
+                TextBufferPos { line: 2, byte: 1 },
+                SearchDirection::Forward
+            ),
+            Some(FindResult {
+                pos: TextBufferPos { line: 0, byte: 4 },
+                wrapped: true,
+            })
+        );
+    }
+
     pub(crate) fn do_standard_text_buffer_tests<B, F>(new_buffer_fn: &F)
     where
         B: TextBuffer,
         F: Fn() -> B,
     {
         test_line(new_buffer_fn);
+        test_lines_range(new_buffer_fn);
         test_line_len(new_buffer_fn);
         test_total_lines(new_buffer_fn);
         test_insert_character_at_pos(new_buffer_fn);
+        test_insert_str_at_pos(new_buffer_fn);
         test_remove_character_at_pos(new_buffer_fn);
+        test_remove_range(new_buffer_fn);
         test_find(new_buffer_fn);
+        test_find_multiline(new_buffer_fn);
+        test_find_without_wrap(new_buffer_fn);
+        test_find_from_end_of_line(new_buffer_fn);
+        test_find_wrapped(new_buffer_fn);
+        test_find_whole_word(new_buffer_fn);
+        test_find_regex(new_buffer_fn);
     }
 }
@@ -1,8 +1,11 @@
-use std::cmp::Ordering;
+use std::{borrow::Cow, cmp::Ordering, ops::Range};
 
 use ropey::Rope;
 
-use super::{InsertCharError, RemoveCharError, SearchDirection, TextBuffer, TextBufferPos};
+use super::{
+    FindResult, InsertCharError, RemoveCharError, SearchDirection, SearchOptions, TextBuffer,
+    TextBufferPos, get_pos_after_str, is_word_char,
+};
 
 /// A text buffer that is stored in a rope.
 ///
@@ -21,6 +24,50 @@ impl RopeTextBuffer {
         Self { rope: Rope::new() }
     }
 
+    /// Returns the char idx that inserting at `pos` (a position
+    /// within the existing lines) inserts at.
+    fn insert_char_idx(&self, pos: TextBufferPos) -> Result<usize, InsertCharError> {
+        if pos.line >= self.rope.len_lines() {
+            return Err(InsertCharError::InvalidLinePosition);
+        }
+
+        let line_byte = self.rope.line_to_byte(pos.line);
+        let char_byte = line_byte.saturating_add(pos.byte);
+
+        if char_byte > self.rope.len_bytes() {
+            return Err(InsertCharError::InvalidLinePosition);
+        }
+
+        let char_idx = self.rope.byte_to_char(char_byte);
+
+        if char_idx >= self.rope.line_to_char(pos.line.saturating_add(1))
+            && pos.line.saturating_add(1) != self.rope.len_lines()
+        {
+            return Err(InsertCharError::InvalidBytePosition);
+        }
+
+        Ok(char_idx)
+    }
+
+    /// Returns the char idx of `pos`, which has to be
+    /// on a character boundary of an existing line.
+    fn checked_char_idx(&self, pos: TextBufferPos) -> Result<usize, RemoveCharError> {
+        let line_len = self
+            .line_len(pos.line)
+            .ok_or(RemoveCharError::InvalidLinePosition)?;
+        if pos.byte > line_len {
+            return Err(RemoveCharError::InvalidBytePosition);
+        }
+
+        let char_idx = self.char_idx(pos);
+        let line_byte = self.rope.line_to_byte(pos.line);
+        if self.rope.char_to_byte(char_idx) == line_byte.saturating_add(pos.byte) {
+            Ok(char_idx)
+        } else {
+            Err(RemoveCharError::InvalidBytePosition)
+        }
+    }
+
     fn char_idx(&self, buffer_pos: TextBufferPos) -> usize {
         let line_byte = self.rope.line_to_byte(buffer_pos.line);
         let char_byte = line_byte.saturating_add(buffer_pos.byte);
@@ -52,6 +99,26 @@ impl TextBuffer for RopeTextBuffer {
         }
     }
 
+    fn lines_range(&self, range: Range<usize>) -> Vec<Cow<'_, str>> {
+        let start = range.start.min(self.rope.len_lines());
+        self.rope
+            .lines_at(start)
+            .take(range.end.saturating_sub(start))
+            .map(|line| {
+                if let Some(line) = line.as_str() {
+                    return Cow::Borrowed(line.strip_suffix('\n').unwrap_or(line));
+                }
+
+                // the line is split across chunks of the rope
+                let mut line = line.to_string();
+                if line.ends_with('\n') {
+                    line.pop();
+                }
+                Cow::Owned(line)
+            })
+            .collect()
+    }
+
     fn line_len(&self, line_idx: usize) -> Option<usize> {
         if line_idx < self.rope.len_lines() {
             let last_char_pos = self
@@ -93,27 +160,30 @@ impl TextBuffer for RopeTextBuffer {
                 }
             }
             Ordering::Less => {
-                let line_byte = self.rope.line_to_byte(pos.line);
-                let char_byte = line_byte.saturating_add(pos.byte);
-
-                if char_byte > self.rope.len_bytes() {
-                    return Err(InsertCharError::InvalidLinePosition);
-                }
-
-                let char_idx = self.rope.byte_to_char(char_byte);
-
-                if char_idx >= self.rope.line_to_char(pos.line.saturating_add(1))
-                    && pos.line.saturating_add(1) != self.rope.len_lines()
-                {
-                    return Err(InsertCharError::InvalidBytePosition);
-                }
-
+                let char_idx = self.insert_char_idx(pos)?;
                 self.rope.insert_char(char_idx, ch);
                 Ok(())
             }
         }
     }
 
+    fn insert_str_at_pos(
+        &mut self,
+        pos: TextBufferPos,
+        text: &str,
+    ) -> Result<TextBufferPos, InsertCharError> {
+        if pos.line == self.rope.len_lines() {
+            if pos.byte != 0 {
+                return Err(InsertCharError::InvalidBytePosition);
+            }
+            self.rope.insert_char(self.rope.len_chars(), '\n');
+        }
+
+        let char_idx = self.insert_char_idx(pos)?;
+        self.rope.insert(char_idx, text);
+        Ok(get_pos_after_str(pos, text))
+    }
+
     fn remove_character_at_pos(&mut self, pos: TextBufferPos) -> Result<(), RemoveCharError> {
         if pos.line >= self.rope.len_lines() {
             return Err(RemoveCharError::InvalidLinePosition);
@@ -136,41 +206,84 @@ impl TextBuffer for RopeTextBuffer {
         Ok(())
     }
 
+    fn remove_range(
+        &mut self,
+        start: TextBufferPos,
+        end: TextBufferPos,
+    ) -> Result<(), RemoveCharError> {
+        let start_char_idx = self.checked_char_idx(start)?;
+        let end_char_idx = self.checked_char_idx(end)?;
+        self.rope
+            .remove(start_char_idx.min(end_char_idx)..start_char_idx.max(end_char_idx));
+        Ok(())
+    }
+
     fn find(
         &self,
         search: &str,
         start_pos: TextBufferPos,
         search_direction: SearchDirection,
-    ) -> Option<TextBufferPos> {
-        // check that the start_pos is not an invalid position
+        search_options: SearchOptions,
+    ) -> Option<FindResult> {
+        // matching across lines is natively supported by the rope,
+        // so only need to reject them if they are not allowed
+        if search.contains('\n') && !search_options.multiline {
+            return None;
+        }
+
+        // check that the start_pos is not an invalid position (the end
+        // of a line is valid, the search then continues onto the next line)
         if start_pos.line >= self.total_lines() {
             return None;
         }
-        if start_pos.byte >= self.line_len(start_pos.line).unwrap_or(0) {
+        if start_pos.byte > self.line_len(start_pos.line).unwrap_or(0) {
             return None;
         }
 
         let search_chars_len = search.chars().count();
         let start_char_idx = self.char_idx(start_pos);
 
+        let is_word_char_at = |char_idx: Option<usize>| {
+            char_idx
+                .and_then(|char_idx| self.rope.get_char(char_idx))
+                .is_some_and(is_word_char)
+        };
         let substring_matches_search = |char_idx: &usize| {
-            self.rope
-                .slice(char_idx..&char_idx.saturating_add(search_chars_len))
-                == search
+            let end_char_idx = char_idx.saturating_add(search_chars_len);
+            self.rope.slice(char_idx..&end_char_idx) == search
+                && (!search_options.whole_word
+                    || !(is_word_char_at(char_idx.checked_sub(1))
+                        || is_word_char_at(Some(end_char_idx))))
         };
 
-        let mut after_start_pos =
-            start_char_idx..self.rope.len_chars().saturating_sub(search_chars_len);
-        let mut before_start_pos = 0..start_char_idx;
+        // (exclusive) end of the char idxs where a match can still fit in the rope
+        let last_possible_char_idx = self
+            .rope
+            .len_chars()
+            .checked_sub(search_chars_len)
+            .map_or(0, |char_idx| char_idx.saturating_add(1));
+
+        let mut after_start_pos = start_char_idx..last_possible_char_idx;
+        let mut before_start_pos = 0..start_char_idx.min(last_possible_char_idx);
 
         match search_direction {
-            SearchDirection::Forward => after_start_pos
-                .find(substring_matches_search)
-                .or_else(|| before_start_pos.find(substring_matches_search)),
+            SearchDirection::Forward => {
+                after_start_pos.find(substring_matches_search).or_else(|| {
+                    search_options
+                        .wrap
+                        .then(|| before_start_pos.find(substring_matches_search))
+                        .flatten()
+                })
+            }
             SearchDirection::Backward => before_start_pos
                 .rev()
                 .find(substring_matches_search)
-                .or_else(|| after_start_pos.rev().find(substring_matches_search)),
+                .or_else(|| {
+                    search_options
+                        .wrap
+                        .then(|| after_start_pos.rev().find(substring_matches_search))
+                        .flatten()
+                }),
         }
         .map(|result_char_idx| {
             let line = self.rope.char_to_line(result_char_idx);
@@ -178,7 +291,7 @@ impl TextBuffer for RopeTextBuffer {
                 .rope
                 .char_to_byte(result_char_idx)
                 .saturating_sub(self.rope.line_to_byte(line));
-            TextBufferPos { line, byte }
+            FindResult::new(TextBufferPos { line, byte }, start_pos, search_direction)
         })
     }
 }
@@ -206,6 +319,48 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_find_at_end_of_rope() {
+        let mut buffer = RopeTextBuffer::new();
+        buffer.set_contents("ab\ncd");
+        let start_pos = TextBufferPos { line: 0, byte: 0 };
+
+        // a match that ends at the end of the rope is found
+        assert_eq!(
+            buffer
+                .find(
+                    "cd",
+                    start_pos,
+                    SearchDirection::Forward,
+                    SearchOptions::default()
+                )
+                .map(|found| found.pos),
+            Some(TextBufferPos { line: 1, byte: 0 })
+        );
+        assert_eq!(
+            buffer
+                .find(
+                    "d",
+                    start_pos,
+                    SearchDirection::Backward,
+                    SearchOptions::default()
+                )
+                .map(|found| found.pos),
+            Some(TextBufferPos { line: 1, byte: 1 })
+        );
+
+        // and a search that cannot fit before the end is not tried there
+        assert_eq!(
+            buffer.find(
+                "dxy",
+                TextBufferPos { line: 1, byte: 1 },
+                SearchDirection::Backward,
+                SearchOptions::default()
+            ),
+            None
+        );
+    }
+
     #[test]
     fn test_standard_text_buffer_tests() {
         use crate::text_buffer::buffer_tests::do_standard_text_buffer_tests;
@@ -1,7 +1,9 @@
-use std::cmp::Ordering;
+use std::{borrow::Cow, cmp::Ordering, ops::Range};
 
 use super::{
-    InsertCharError, JoinLineResult, RemoveCharError, SearchDirection, TextBuffer, TextBufferPos,
+    FindResult, InsertCharError, JoinLineResult, RemoveCharError, SearchDirection, SearchOptions,
+    TextBuffer, TextBufferPos, find_in_joined_contents, find_in_line, get_pos_after_str,
+    rfind_in_line,
 };
 
 /// A text buffer that is stored in a vector of strings.
@@ -47,12 +49,12 @@ impl VecTextBuffer {
     fn join_line_with_below_line(&mut self, line: usize) -> JoinLineResult {
         let mut new_line_string = None;
 
-        if let Some(first_line) = self.text.get(line) {
-            if let Some(second_line) = self.text.get(line.saturating_add(1)) {
-                let mut final_string = first_line.to_string();
-                final_string.push_str(&second_line.to_string());
-                new_line_string = Some(final_string);
-            }
+        if let Some(first_line) = self.text.get(line)
+            && let Some(second_line) = self.text.get(line.saturating_add(1))
+        {
+            let mut final_string = first_line.clone();
+            final_string.push_str(second_line);
+            new_line_string = Some(final_string);
         }
 
         if let Some(new_line) = new_line_string {
@@ -66,6 +68,80 @@ impl VecTextBuffer {
             JoinLineResult::NotJoined
         }
     }
+
+    /// Check that `pos` is on a character boundary of an existing line.
+    fn check_pos(&self, pos: TextBufferPos) -> Result<(), RemoveCharError> {
+        let line = self
+            .text
+            .get(pos.line)
+            .ok_or(RemoveCharError::InvalidLinePosition)?;
+        if line.is_char_boundary(pos.byte) {
+            Ok(())
+        } else {
+            Err(RemoveCharError::InvalidBytePosition)
+        }
+    }
+
+    /// Convert `pos` into a byte offset of `Self::contents()`.
+    fn byte_offset(&self, pos: TextBufferPos) -> Option<usize> {
+        if pos.byte > self.text.get(pos.line)?.len() {
+            return None;
+        }
+
+        Some(
+            self.text
+                .iter()
+                .take(pos.line)
+                .map(|line| line.len().saturating_add("\n".len()))
+                .sum::<usize>()
+                .saturating_add(pos.byte),
+        )
+    }
+
+    /// Convert a byte offset of `Self::contents()` into a position.
+    fn pos_from_byte_offset(&self, byte_offset: usize) -> TextBufferPos {
+        let mut remaining = byte_offset;
+
+        for (line_idx, line) in self.text.iter().enumerate() {
+            if remaining <= line.len() {
+                return TextBufferPos {
+                    line: line_idx,
+                    byte: remaining,
+                };
+            }
+            remaining = remaining.saturating_sub(line.len().saturating_add("\n".len()));
+        }
+
+        TextBufferPos {
+            line: self.text.len().saturating_sub(1),
+            byte: self.text.last().map_or(0, String::len),
+        }
+    }
+
+    fn find_multiline(
+        &self,
+        search: &str,
+        start_pos: TextBufferPos,
+        search_direction: SearchDirection,
+        search_options: SearchOptions,
+    ) -> Option<FindResult> {
+        let start_offset = self.byte_offset(start_pos)?;
+
+        find_in_joined_contents(
+            &self.contents(),
+            search,
+            start_offset,
+            search_direction,
+            search_options,
+        )
+        .map(|offset| {
+            FindResult::new(
+                self.pos_from_byte_offset(offset),
+                start_pos,
+                search_direction,
+            )
+        })
+    }
 }
 
 impl TextBuffer for VecTextBuffer {
@@ -85,6 +161,16 @@ impl TextBuffer for VecTextBuffer {
         self.text.get(line_idx).map(ToString::to_string)
     }
 
+    fn lines_range(&self, range: Range<usize>) -> Vec<Cow<'_, str>> {
+        let end = range.end.min(self.text.len());
+        self.text
+            .get(range.start.min(end)..end)
+            .unwrap_or_default()
+            .iter()
+            .map(|line| Cow::Borrowed(line.as_str()))
+            .collect()
+    }
+
     fn line_len(&self, line_idx: usize) -> Option<usize> {
         self.text.get(line_idx).map(String::len)
     }
@@ -122,6 +208,38 @@ impl TextBuffer for VecTextBuffer {
         }
     }
 
+    fn insert_str_at_pos(
+        &mut self,
+        pos: TextBufferPos,
+        text: &str,
+    ) -> Result<TextBufferPos, InsertCharError> {
+        if pos.line == self.text.len() {
+            if pos.byte != 0 {
+                return Err(InsertCharError::InvalidBytePosition);
+            }
+            self.text.push(String::new());
+        }
+
+        let line = self
+            .text
+            .get_mut(pos.line)
+            .ok_or(InsertCharError::InvalidLinePosition)?;
+        if !line.is_char_boundary(pos.byte) {
+            return Err(InsertCharError::InvalidBytePosition);
+        }
+
+        // the rest of the line goes after the last inserted line
+        let rest_of_line = line.split_off(pos.byte);
+        let mut inserted_lines = text.split('\n');
+        line.push_str(inserted_lines.next().unwrap_or_default());
+        let mut new_lines: Vec<String> = inserted_lines.map(ToString::to_string).collect();
+        new_lines.last_mut().unwrap_or(line).push_str(&rest_of_line);
+        let new_lines_idx = pos.line.saturating_add(1);
+        self.text.splice(new_lines_idx..new_lines_idx, new_lines);
+
+        Ok(get_pos_after_str(pos, text))
+    }
+
     fn remove_character_at_pos(&mut self, pos: TextBufferPos) -> Result<(), RemoveCharError> {
         match self.text.get_mut(pos.line) {
             Some(line) => match pos.byte.cmp(&line.len()) {
@@ -139,43 +257,84 @@ impl TextBuffer for VecTextBuffer {
         }
     }
 
+    fn remove_range(
+        &mut self,
+        start: TextBufferPos,
+        end: TextBufferPos,
+    ) -> Result<(), RemoveCharError> {
+        let (start, end) = (start.min(end), start.max(end));
+        self.check_pos(start)?;
+        self.check_pos(end)?;
+
+        let rest_of_end_line = self
+            .text
+            .get(end.line)
+            .and_then(|line| line.get(end.byte..))
+            .unwrap_or_default()
+            .to_string();
+        let removed_lines = start.line.saturating_add(1)..end.line.saturating_add(1);
+        self.text.drain(removed_lines);
+        if let Some(line) = self.text.get_mut(start.line) {
+            line.truncate(start.byte);
+            line.push_str(&rest_of_end_line);
+        }
+        Ok(())
+    }
+
     fn find(
         &self,
         search: &str,
         start_pos: TextBufferPos,
         search_direction: SearchDirection,
-    ) -> Option<TextBufferPos> {
-        if let Some(first_line) = self.text.get(start_pos.line) {
-            if start_pos.byte < first_line.len() {
-                let first_line_result = match search_direction {
-                    SearchDirection::Forward => {
-                        first_line[start_pos.byte..]
-                            .find(search)
-                            .map(|byte| TextBufferPos {
-                                line: start_pos.line,
-                                byte: start_pos.byte.saturating_add(byte),
-                            })
-                    }
-                    SearchDirection::Backward => {
-                        let end_byte = start_pos
-                            .byte
-                            .saturating_add(search.len().saturating_sub(1))
-                            .clamp(0, first_line.len());
-                        first_line[..end_byte]
-                            .rfind(search)
-                            .map(|byte| TextBufferPos {
-                                line: start_pos.line,
-                                byte,
-                            })
-                    }
-                };
+        search_options: SearchOptions,
+    ) -> Option<FindResult> {
+        if search.contains('\n') {
+            return if search_options.multiline {
+                self.find_multiline(search, start_pos, search_direction, search_options)
+            } else {
+                None
+            };
+        }
 
-                if first_line_result.is_some() {
-                    return first_line_result;
-                }
+        let whole_word = search_options.whole_word;
+        // the end of a line is a valid start, the search then
+        // continues onto the next line
+        let first_line = self
+            .text
+            .get(start_pos.line)
+            .filter(|line| start_pos.byte <= line.len())?;
+        let first_line_result = match search_direction {
+            SearchDirection::Forward => {
+                find_in_line(first_line, search, start_pos.byte, whole_word).map(|byte| {
+                    TextBufferPos {
+                        line: start_pos.line,
+                        byte,
+                    }
+                })
+            }
+            SearchDirection::Backward => {
+                // a match has to start before `start_pos`, but it can end after it
+                let end_byte = first_line.floor_char_boundary(
+                    start_pos
+                        .byte
+                        .saturating_add(search.len().saturating_sub(1)),
+                );
+                rfind_in_line(first_line, search, end_byte, whole_word).map(|byte| TextBufferPos {
+                    line: start_pos.line,
+                    byte,
+                })
             }
+        };
+
+        if let Some(pos) = first_line_result {
+            return Some(FindResult {
+                pos,
+                wrapped: false,
+            });
         }
 
+        // when wrapping, the search ends on the first line again, for the
+        // matches on the other side of `start_pos`
         match search_direction {
             SearchDirection::Forward => self
                 .text
@@ -183,9 +342,15 @@ impl TextBuffer for VecTextBuffer {
                 .enumerate()
                 .cycle()
                 .skip(start_pos.line.saturating_add(1))
-                .take(self.text.len().saturating_sub(1))
+                .take(if search_options.wrap {
+                    self.text.len()
+                } else {
+                    self.text
+                        .len()
+                        .saturating_sub(start_pos.line.saturating_add(1))
+                })
                 .find_map(|(line_idx, line)| {
-                    line.find(search).map(|byte| TextBufferPos {
+                    find_in_line(line, search, 0, whole_word).map(|byte| TextBufferPos {
                         line: line_idx,
                         byte,
                     })
@@ -197,14 +362,19 @@ impl TextBuffer for VecTextBuffer {
                 .rev()
                 .cycle()
                 .skip(self.text.len().saturating_sub(start_pos.line))
-                .take(self.text.len().saturating_sub(1))
+                .take(if search_options.wrap {
+                    self.text.len()
+                } else {
+                    start_pos.line
+                })
                 .find_map(|(line_idx, line)| {
-                    line.rfind(search).map(|byte| TextBufferPos {
+                    rfind_in_line(line, search, line.len(), whole_word).map(|byte| TextBufferPos {
                         line: line_idx,
                         byte,
                     })
                 }),
         }
+        .map(|pos| FindResult::new(pos, start_pos, search_direction))
     }
 }
 
@@ -245,4 +415,54 @@ mod tests {
 
         do_standard_text_buffer_tests(&|| VecTextBuffer::new());
     }
+
+    #[test]
+    fn test_find_same_as_rope() {
+        use crate::text_buffer::RopeTextBuffer;
+
+        let contents = "h\u{e9}h\u{e9} \u{4f60}\u{597d}h\u{e9}\nh\u{e9}h\u{e9}h\u{e9}\n\u{1f600}";
+        let mut vec_buffer = VecTextBuffer::new();
+        vec_buffer.set_contents(contents);
+        let mut rope_buffer = RopeTextBuffer::new();
+        rope_buffer.set_contents(contents);
+
+        for (line_idx, line) in contents.split('\n').enumerate() {
+            let end_of_line = line.len();
+            for byte in line
+                .char_indices()
+                .map(|(byte, _)| byte)
+                .chain([end_of_line])
+            {
+                let start_pos = TextBufferPos {
+                    line: line_idx,
+                    byte,
+                };
+                for search in ["h\u{e9}", "\u{e9}h\u{e9}", "\u{597d}", "\u{1f600}", "x"] {
+                    for search_direction in [SearchDirection::Forward, SearchDirection::Backward] {
+                        for wrap in [true, false] {
+                            let search_options = SearchOptions {
+                                wrap,
+                                ..SearchOptions::default()
+                            };
+                            assert_eq!(
+                                vec_buffer.find(
+                                    search,
+                                    start_pos,
+                                    search_direction,
+                                    search_options
+                                ),
+                                rope_buffer.find(
+                                    search,
+                                    start_pos,
+                                    search_direction,
+                                    search_options
+                                ),
+                                "{search:?} {search_direction:?} from {start_pos:?} (wrap: {wrap})"
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
 }
@@ -1,3 +1,5 @@
+use std::ops::Range;
+
 use anyhow::Result;
 use knap_base::{
     color::Color,
@@ -6,6 +8,21 @@ use knap_base::{
 
 use crate::terminal::{self, TerminalPos};
 
+/// The glyph used for vertical separators, e.g. between split panes.
+pub const VERTICAL_LINE: &str = "│";
+/// The glyph used for horizontal separators, e.g. between split panes.
+pub const HORIZONTAL_LINE: &str = "─";
+
+/// The colors of a popup, see `Drawer::draw_popup`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PopupColors {
+    pub foreground: Color,
+    pub background: Color,
+    /// the colors of the selected item instead
+    pub selected_foreground: Color,
+    pub selected_background: Color,
+}
+
 pub struct Drawer {
     queue: Vec<DrawCommand>,
 }
@@ -68,10 +85,105 @@ impl Drawer {
         });
     }
 
+    /// Draws a vertical line in column `x`, covering the rows in `y_range`.
+    pub fn draw_vertical_line(&mut self, x: f64, y_range: Range<f64>) {
+        let mut y = y_range.start;
+        while y < y_range.end {
+            self.draw_text(Vec2f { x, y }, VERTICAL_LINE);
+            y += 1.0;
+        }
+    }
+
+    /// Draws a horizontal line in row `y`, covering the columns in `x_range`.
+    pub fn draw_horizontal_line(&mut self, y: f64, x_range: Range<f64>) {
+        let width = <f64 as Lossy<usize>>::lossy(&(x_range.end - x_range.start).max(0.0));
+        self.draw_text(
+            Vec2f {
+                x: x_range.start,
+                y,
+            },
+            HORIZONTAL_LINE.repeat(width),
+        );
+    }
+
+    /// Draws `items` one per row, starting with the top left at `pos`, on a
+    /// background that is as wide as the widest item. The `selected` item
+    /// is drawn with the selected colors of `colors` instead.
+    pub fn draw_popup<T: AsRef<str>>(
+        &mut self,
+        pos: Vec2f,
+        items: &[T],
+        selected: Option<usize>,
+        colors: PopupColors,
+    ) {
+        let width = items
+            .iter()
+            .map(|item| item.as_ref().chars().count())
+            .max()
+            .unwrap_or(0);
+
+        for (idx, item) in items.iter().enumerate() {
+            let (foreground, background) = if selected == Some(idx) {
+                (colors.selected_foreground, colors.selected_background)
+            } else {
+                (colors.foreground, colors.background)
+            };
+            self.draw_colored_text(
+                Vec2f {
+                    x: pos.x,
+                    y: pos.y + idx.lossy(),
+                },
+                format!(" {:<width$} ", item.as_ref()),
+                Some(foreground),
+                Some(background),
+            );
+        }
+    }
+
     pub fn draw_cursor(&mut self, pos: Vec2f) {
         self.queue.push(DrawCommand::Cursor { pos });
     }
 
+    /// Renders what has been queued so far onto a grid of `width` by
+    /// `height` cells, without colors or the cursor, and returns each
+    /// row as a string. Every character is assumed to occupy one cell.
+    ///
+    /// This is meant for inspecting what would be drawn, e.g. in tests.
+    pub fn snapshot(&self, width: usize, height: usize) -> Vec<String> {
+        let mut grid = vec![vec![' '; width]; height];
+
+        for command in &self.queue {
+            let (pos, text) = match command {
+                DrawCommand::Text { pos, text } | DrawCommand::ColoredText { pos, text, .. } => {
+                    (convert_vec2f_to_terminal_pos(*pos), text)
+                }
+                DrawCommand::Cursor { .. } => continue,
+            };
+
+            let Some(row) = grid.get_mut(usize::from(pos.y)) else {
+                continue;
+            };
+            let mut chars = text.chars();
+            let mut x = usize::from(pos.x);
+            while let Some(ch) = chars.next() {
+                if ch == '\x1b' {
+                    // skip escape sequences, e.g. for reversing the colors
+                    chars.by_ref().skip(1).find(char::is_ascii_alphabetic);
+                    continue;
+                }
+
+                if let Some(cell) = row.get_mut(x) {
+                    *cell = ch;
+                }
+                x = x.saturating_add(1);
+            }
+        }
+
+        grid.into_iter()
+            .map(|row| row.into_iter().collect())
+            .collect()
+    }
+
     pub fn clear(&mut self) {
         self.queue.clear();
     }
@@ -116,3 +228,57 @@ impl Drawer {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use knap_base::math::Bounds2f;
+
+    use super::*;
+
+    #[test]
+    fn test_draw_vertical_line() {
+        let mut drawer = Drawer::new();
+        drawer.draw_text(Vec2f { x: 0.0, y: 0.0 }, "abcdefgh");
+        drawer.draw_vertical_line(2.0, 0.0..2.0);
+        assert_eq!(drawer.snapshot(5, 3), vec!["ab│de", "  │  ", "     "]);
+    }
+
+    #[test]
+    fn test_draw_popup() {
+        let mut drawer = Drawer::new();
+        drawer.draw_text(Vec2f::ZERO, "abcdefgh");
+        let colors = PopupColors {
+            foreground: Color::BLACK,
+            background: Color::GRAY,
+            selected_foreground: Color::WHITE,
+            selected_background: Color::BLUE,
+        };
+        drawer.draw_popup(Vec2f { x: 1.0, y: 1.0 }, &["one", "three"], Some(1), colors);
+        assert_eq!(
+            drawer.snapshot(9, 4),
+            vec!["abcdefgh ", "  one    ", "  three  ", "         "]
+        );
+    }
+
+    #[test]
+    fn test_split_with_separator() {
+        let window = Bounds2f {
+            pos: Vec2f::ZERO,
+            size: Vec2f { x: 9.0, y: 3.0 },
+        };
+        let (left, separator_x, right) = window.split_vertically();
+        assert_eq!(left.size, Vec2f { x: 4.0, y: 3.0 });
+        assert!((separator_x - 4.0).abs() < f64::EPSILON);
+        assert_eq!(right.pos, Vec2f { x: 5.0, y: 0.0 });
+        assert_eq!(right.size, Vec2f { x: 4.0, y: 3.0 });
+
+        let mut drawer = Drawer::new();
+        drawer.draw_text(left.pos, "left");
+        drawer.draw_text(right.pos, "right");
+        drawer.draw_vertical_line(separator_x, window.pos.y..window.size.y);
+        assert_eq!(
+            drawer.snapshot(9, 3),
+            vec!["left│righ", "    │    ", "    │    "]
+        );
+    }
+}
@@ -2,7 +2,7 @@ use std::io::{self, Write};
 
 use anyhow::Result;
 use crossterm::{
-    cursor, queue,
+    cursor, event, queue,
     style::{self, Color},
     terminal,
 };
@@ -19,12 +19,14 @@ pub(crate) fn init_terminal() -> Result<()> {
 
     queue!(io::stdout(), terminal::EnterAlternateScreen)?;
     queue!(io::stdout(), terminal::DisableLineWrap)?;
+    queue!(io::stdout(), event::EnableMouseCapture)?;
     io::stdout().flush()?;
 
     Ok(())
 }
 
 pub(crate) fn end_terminal() -> Result<()> {
+    queue!(io::stdout(), event::DisableMouseCapture)?;
     queue!(io::stdout(), terminal::EnableLineWrap)?;
     queue!(io::stdout(), terminal::LeaveAlternateScreen)?;
     io::stdout().flush()?;